@@ -0,0 +1,37 @@
+//! Time the programs in `benches/programs`
+//!
+//! Pass part of a program's name to only run matching programs.
+
+use std::{fs, path::Path};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use uiua::bench::bench;
+
+fn programs(c: &mut Criterion) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/programs");
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ua"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let code = fs::read_to_string(&path).unwrap();
+        // Only the runs are timed, not setting up the interpreter for each one
+        c.bench_function(&name, |b| {
+            b.iter_custom(|runs| match bench(&code, runs as usize) {
+                Ok(times) => times.times().iter().sum(),
+                Err(e) => panic!("{name} failed: {}", e.report()),
+            })
+        });
+    }
+}
+
+criterion_group! {
+    name = benches;
+    // Each program takes long enough that a few samples give a stable time
+    config = Criterion::default().sample_size(10);
+    targets = programs
+}
+criterion_main!(benches);
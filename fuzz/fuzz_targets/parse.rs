@@ -0,0 +1,11 @@
+//! Lex, parse, and format arbitrary text
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use uiua::format::{format_str, FormatConfig};
+
+fuzz_target!(|input: &str| {
+    _ = uiua::parse(input, None);
+    _ = format_str(input, &FormatConfig::default());
+});
@@ -0,0 +1,45 @@
+//! Run random programs built from the language's tokens
+//!
+//! Arbitrary bytes rarely form code that gets past the parser, so this target
+//! builds programs from primitives, literals, and syntax instead.
+
+#![no_main]
+
+use std::time::Duration;
+
+use libfuzzer_sys::{arbitrary::Arbitrary, fuzz_target};
+use uiua::{NullSys, Primitive, Uiua};
+
+const SYNTAX: &[&str] = &[
+    "0", "1", "2", "¯1", "0.5", "∞", "1_2", "2_3", "[1_2 3_4]", "[]", "\"\"", "\"ab\"", "@a",
+    "[", "]", "(", ")", "{", "}", "|", "|2 ", "|1.1 ", "⋄", "'", "!", "^", "_", "\n", "X ← ",
+    "X", "F ← ", "F", "---\n",
+];
+
+#[derive(Debug, Arbitrary)]
+enum Token {
+    Primitive(u8),
+    Syntax(u8),
+}
+
+fuzz_target!(|tokens: Vec<Token>| {
+    let primitives: Vec<Primitive> = Primitive::non_deprecated().collect();
+    let mut code = String::new();
+    for token in tokens {
+        match token {
+            Token::Primitive(i) => {
+                let prim = primitives[i as usize % primitives.len()];
+                // System functions are skipped because the backend does not support them anyway
+                if prim.name().starts_with('&') {
+                    continue;
+                }
+                code.push(' ');
+                code.push_str(&prim.ascii_name());
+                code.push(' ');
+            }
+            Token::Syntax(i) => code.push_str(SYNTAX[i as usize % SYNTAX.len()]),
+        }
+    }
+    let mut env = Uiua::with_backend(NullSys).with_execution_limit(Duration::from_millis(100));
+    _ = env.load_str(&code);
+});
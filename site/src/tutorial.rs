@@ -158,6 +158,9 @@ fn TutorialBasic() -> impl IntoView {
         <p>"Try formatting the lines below by clicking "<strong>"Run"</strong>"."</p>
         <Editor examples=&{["max ", "sqrt ", "10 ", "mod ", "10 ", "pow ", "2 ", "8"]}/>
         <Editor example="abs +`1 `2"/>
+        <p>"Every built-in function with a glyph has a name made of ASCII letters. Names with spaces are typed without them."</p>
+        <Editor example="lessorequal 2 notequals 3 [1 2 3]"/>
+        <p>"If your system can't display the glyphs, "<code>"AsciiNames ← 1"</code>" in a "<code>".fmt.ua"</code>" file makes the native formatter write these names instead."</p>
         <p>"You don't have to type the whole name, just enough to to disambiguate it from others."</p>
         <Editor example="cei 1.5\nceil 1.5\nceili 1.5\nceilin 1.5\nceiling 1.5"/>
         <p>"You don't even have to remove spaces between built-in function names. The formatter will figure it out!"</p>
@@ -214,6 +217,8 @@ fn TutorialBasic() -> impl IntoView {
         <p>"It also attaches line and column numbers."</p>
         <p>"This is useful for debugging by inpecting the stack."</p>
         <Editor example="+1 ⸮ ×4 trace ×. -3 5"/>
+        <p>"To print something about a value instead of the value itself, use "<Prim prim=Inspect/>". It prints the result of calling its function on the top item, labeled with the code of the function, and leaves the stack as it was."</p>
+        <Editor example="/+ inspect△ ⇡10"/>
     }
 }
 
@@ -343,6 +348,10 @@ fn TutorialArrays() -> impl IntoView {
 [[1 2 3]
  [4 5 6]
  [7 8 9]]"/>
+        <p>"Inside "<code>"[]"</code>"s, "<code>"|"</code>"s separate the rows of an array, so matrices can be written without nesting."</p>
+        <Editor example="[1 2 3 | 4 5 6 | 7 8 9]"/>
+        <p>"Each row is made from all the values between its "<code>"|"</code>"s, just like an array of its own."</p>
+        <Editor example="[[1 2 | 3 4] [5 6 | 7 8]]"/>
 
         <h2 id="shape-len"><Prim prim=Shape/>" and "<Prim prim=Len/></h2>
         <p>"Other than their data, arrays also have a property called their "<strong>"shape"</strong>". Shape is a list of non-negative integers that describes the array's size along each of its axes."</p>
@@ -453,6 +462,8 @@ fn TutorialTypes() -> impl IntoView {
         <h2 id="numbers">"Numbers"</h2>
         <p>"Numbers are decimal numbers with floating precision. They use a 64-bit floating-point representation."</p>
         <Editor example="[5 0 3.2 ¯1.1 π ∞]"/>
+        <p>"Number literals can also be written in scientific notation, or in hexadecimal or binary with a "<code>"0x"</code>" or "<code>"0b"</code>" prefix."</p>
+        <Editor example="[1e6 2.5e¯3 0xFF ¯0x10 0b1010]"/>
         <p>"Most math operations can only be applied to numbers."</p>
         <p>"Even though numbers can have a fractional part, many built-in functions require whole numbers. These functions will return an error if given a non-whole number."</p>
         <p>"One such example is "<Prim prim=Pick/>"."</p>
@@ -539,6 +550,10 @@ fn TutorialBindings() -> impl IntoView {
         <Editor example="part = 5" help={&["", "Run to format and reveal why this does not work"]}/>
         <p>"Binding names longer than 2 characters should be TitleCase to avoid this issue."</p>
         <Editor example="Part = 5\n*2 Part"/>
+        <p>"With "<code>"←"</code>", binding a name that starts with the name of a built-in function is an error."</p>
+        <Editor example="part ← 5"/> // Should fail
+        <p>"A name refers to the closest binding of it. Names bound inside a function with "<A href="/docs/functions#local-bindings">"local bindings"</A>" come first, then bindings in the current "<A href="/docs/testing#test-scopes">"scope"</A>", then bindings in the scope around it. Built-in constants like "<code>"e"</code>" are bound outside of all of these, so a binding can shadow one, but this gives a warning."</p>
+        <Editor example="e ← 5\n+1 e"/>
         <p>"Bindings run the code to the right of the "<code>"←"</code>", then pop the top value off the stack and bind it to the name on the left."</p>
         <p>"Note, though, that an empty right side is perfectly valid! This means you can bind values that were create on previous lines."</p>
         <Editor example="×6 7\nAnswer ←\n[Answer]"/>
@@ -599,9 +614,26 @@ X ← (
 )
 X 5"/>
 
-        <h2 id="local-bindings">"A Note on Local Bindings"</h2>
-        <p>"Bindings in Uiua can "<em>"only"</em>" be global. There is no way to give a name to a value within an inline function. A "<code>"←"</code>" inside "<code>"()"</code>"s is a syntax error."</p>
-        <p>"This is a deliberate design decision. It forces you to write tacit code, a.k.a. code with functions that do not mention their arguments. Uiua is designed to make writing tacit code as workable as possible. "<em>"How"</em>" it does this will be discussed in "<A href="/docs/advancedstack">"later"</A>" "<A href="/docs/advancedarray">"sections"</A>"."</p>
+        <h2 id="local-bindings">"Local Bindings"</h2>
+        <p>"A line in an inline function that starts with a name and a "<code>"←"</code>" creates a "<em>"local"</em>" binding. Like a global binding, it runs the code to its right and pops the top value off the stack. The name is only visible in the rest of that function, including any functions nested inside it."</p>
+        <Editor example="Hyp ← (\n  A ← ×.\n  B ← ×.\n  √+A B\n)\nHyp 3 4"/>
+        <p>"Local bindings always hold values, never functions. Inside "<code>"()"</code>"s, only "<code>"←"</code>" creates a binding. "<code>"="</code>" is still "<Prim prim=Eq/>"."</p>
+        <h2 id="destructuring">"Destructuring"</h2>
+        <p>"Putting several names in "<code>"[]"</code>"s before the "<code>"←"</code>" binds each row of the resulting array to its own name. The array must have exactly as many rows as there are names."</p>
+        <Editor example="[A B C] ← ↯3_2⇡6\nB"/>
+        <p>"This works for local bindings as well."</p>
+        <Editor example="Spread ← (\n  [Lo Hi] ← ⊏[0 ¯1]⊏⍏.\n  -Lo Hi\n)\nSpread [5 2 9 10 4]"/>
+
+        <p>"Without the "<code>"[]"</code>"s, several names each take a value off the stack. The first name gets the value on top."</p>
+        <Editor example="Q R ← ⌊÷5 17 ◿5 17\nQ\nR"/>
+        <Editor example="Dist ← (\n  X Y ←\n  √+×.X×.Y\n)\nDist 3 4"/>
+
+        <h2 id="mutable-bindings">"Mutable Bindings"</h2>
+        <p>"A binding made with "<code>"↩"</code>" instead of "<code>"←"</code>" is "<em>"mutable"</em>". Binding the same name with "<code>"↩"</code>" again, even from inside a function, replaces its value instead of creating a new binding. Names bound with "<code>"←"</code>" cannot be reassigned this way."</p>
+        <Editor example="Count ↩ 0\nTick ← (Count ↩ +1 Count)\nTick Tick Tick\nCount"/>
+        <p>"Inside a function, "<code>"↩"</code>" can also reassign a local binding of an enclosing function."</p>
+        <Editor example="Total ← (\n  S ← 0\n  ;∵(S ↩ +S.)\n  S\n)\nTotal [1 2 3 4]"/>
+        <p>"Use local bindings sparingly. Uiua is designed to make writing tacit code, a.k.a. code with functions that do not mention their arguments, as workable as possible. "<em>"How"</em>" it does this will be discussed in "<A href="/docs/advancedstack">"later"</A>" "<A href="/docs/advancedarray">"sections"</A>"."</p>
 
         <h2 id="format-strings">"Format Strings"</h2>
         <p>"Prefixing a string with a "<code>"$"</code>" creates a format string. A format string is a special kind of function. It takes an argument for each "<code>"_"</code>" in the string and replaces it with the stringified version."</p>
@@ -613,6 +645,16 @@ X 5"/>
         <Editor example="$\"\\__\\_\" 27"/>
         <p>"Multi-line strings are implicitly format strings."</p>
         <Editor example="+,, 1 2\n&p $ What are two numbers that add up to _?\n   $ _ and _ do!"/>
+        <p>"Lines that start with "<code>"$$ "</code>" are "<em>"raw"</em>". Escapes, "<code>"_"</code>"s, and interpolations in them are left as they are, which is handy for regexes and embedded data. Raw and formatted lines can be mixed in one string."</p>
+        <Editor example="$$ {\"name\": \"Uiua\",\n$$  \"tags\": [\"a_b\", \"\\n\"]}"/>
+
+        <h2 id="interpolation">"Interpolation"</h2>
+        <p>"Any string can have code in it between "<code>"\\{"</code>" and "<code>"}"</code>". The code is run when the string is made, and its result is formatted into the string. Interpolated code must take no arguments and produce exactly one value, so it usually refers to bindings."</p>
+        <Editor example="Name ← \"world\"\nN ← 3\n\"Hello, \\{Name}! \\{×2 N} is twice \\{N}.\""/>
+        <p>"In format strings, interpolations can be mixed with "<code>"_"</code>"s, which still take their values from the stack."</p>
+        <Editor example="X ← 10\n$\"_ + \\{X} = _\" ⊃∘(+X) 5"/>
+        <p>"Strings also support the escapes "<code>"\\n"</code>", "<code>"\\t"</code>", "<code>"\\\""</code>", "<code>"\\\\"</code>", "<code>"\\xHH"</code>", "<code>"\\uHHHH"</code>", and "<code>"\\u{H…}"</code>" for any unicode character."</p>
+        <Editor example="\"tab\\there\\u{1F600}\""/>
 
         <h2 id="stack-signatures">"Stack Signatures"</h2>
         <p>"Bindings and inline functions can have a "<em>"stack signature"</em>" declared with a "<code>"|"</code>" followed by 1 or 2 numbers seperated by a "<code>"."</code>". The first number is the number of arguments the function pops from the stack. The second number is the number of values the function pushes to the stack."</p>
@@ -627,6 +669,22 @@ X 5"/>
         <Editor example="≡(|2 ↻.) 1_2_3 ↯3_3⇡9"/> // Should fail
         <p>"If the compiler cannot derive the stack signature of a function and you give it one which is "<em>"wrong"</em>", the function will throw an error at runtime."</p>
         <Editor example="f = |1.2 /∘\nf [1 2 3]"/> // Should fail
+
+        <h2 id="annotations">"Annotations"</h2>
+        <p>"Instead of a signature, a binding can have an "<em>"annotation"</em>" that describes the types and shapes of its arguments and outputs. Annotations are written between two "<code>"|"</code>"s. Patterns before the "<code>"."</code>" are for arguments, starting with the top of the stack. Patterns after it are for outputs. If there is no "<code>"."</code>", the function has 1 output that can be anything."</p>
+        <p>"A pattern can have a type, "<code>"Num"</code>", "<code>"Char"</code>", or "<code>"Box"</code>", and a shape in "<code>"[]"</code>"s. A "<code>"_"</code>" matches anything. In a shape, "<code>"_"</code>" matches any length, "<code>"*"</code>" matches any number of axes, and a name must have the same length everywhere it appears in the annotation."</p>
+        <Editor example="Dot ← |[N] [N].Num[]| /+×\nDot [1 2 3] [4 5 6]"/>
+        <p>"Annotations are checked when the function is called."</p>
+        <Editor example="Dot ← |[N] [N].Num[]| /+×\nDot [1 2 3] [4 5]"/> // Should fail
+        <p>"Mutable bindings can annotate their value. It is checked every time the binding is reassigned."</p>
+        <Editor example="Count ↩ |.Num[]| 0\nCount ↩ \"many\""/> // Should fail
+        <p>"The native interpreter can also check shapes before running. "<code>"uiua check --check-shapes"</code>" and "<code>"uiua run --check-shapes"</code>" follow the shapes of constants and annotated arguments through code and warn about arrays that will not fit, like adding arrays of different lengths."</p>
+
+        <h2 id="documentation-comments">"Documentation Comments"</h2>
+        <p>"Comments on the lines just before a binding "<em>"document"</em>" it. The lines are joined, and an empty comment line starts a new paragraph. The experimental "<Prim prim=Help/>" modifier gets the documentation of a function as a string."</p>
+        <Editor example="# Experimental!\n# Add 1 to a number\nInc ← +1\nhelp Inc"/>
+        <p>"Imported functions keep their documentation, so "<Prim prim=Help/>" is a quick way to find out what a function from another file does."</p>
+        <Editor example="# Experimental!\nSqr ← &i \"example.ua\" \"Square\"\nhelp Sqr"/>
     }
 }
 
@@ -636,7 +694,7 @@ fn TutorialAdvancedStack() -> impl IntoView {
     view! {
         <Title text="Advanced Stack Manipulation - Uiua Docs"/>
         <h1>"Advanced Stack Manipulation"</h1>
-        <p>"Uiua does not have local variables. With only "<Prim prim=Dup/>", "<Prim prim=Flip/>", and "<Prim prim=Over/>", how do you work with more than 2 values at a time?"</p>
+        <p>"Local bindings aside, tacit Uiua code does not name its values. With only "<Prim prim=Dup/>", "<Prim prim=Flip/>", and "<Prim prim=Over/>", how do you work with more than 2 values at a time?"</p>
 
         <h2 id="fork"><Prim prim=Fork/></h2>
         <p><Prim prim=Fork/>" is a dyadic modifier that takes 2 functions and calls them both on the same set of arguments. The number of arguments used is the maximum of the two functions."</p>
@@ -733,10 +791,24 @@ splitArray([1, 2, 3, 7, 2, 4, 5])"</code>
         <Editor example="⁅[⍥⚂5]"/>
         <p>"You can loop forever by using "<Prim prim=Infinity/>". You can break out of an infinite (or finite) loop with "<Prim prim=Break/>"."</p>
         <Editor example="⍥(⎋>1000.×2)∞ 1"/>
-        <p>"This requires a signature declaration if done in a function."</p>
-        <Editor example="f ← |1 ⍥(⎋>1000.×2)∞\nf 5"/>
+        <p>"The function must break at the same stack height that it would otherwise end at. If it does, its signature can still be inferred."</p>
+        <Editor example="f ← ⍥(⎋>1000.×2)∞\nf 5"/>
         <p><Prim prim=Repeat/>"'s glyph is a combination of a circle, representing a loop, and the 𝄇 symbol from musical notation."</p>
 
+        <h2 id="do">"Looping with "<Prim prim=Do/></h2>
+        <p>"When you do not know ahead of time how many times a loop should run, use "<Prim prim=Do/>". It takes two functions: a loop body and a condition. The condition is called before each iteration and must leave a boolean on the stack. The loop continues while that boolean is "<code>"1"</code>"."</p>
+        <Editor example="⍢(×2)(<1000.) 1"/>
+        <p>"The condition usually uses "<Prim prim=Dup/>" or "<Prim prim=Over/>" so that the values it checks are still there for the body."</p>
+        <Editor example="⍢(↘1)(>3⧻.) ⇡10"/>
+
+        <h2 id="break">"Breaking out of loops"</h2>
+        <p><Prim prim=Break/>" works the same way in all loops: "<Prim prim=Reduce/>", "<Prim prim=Fold/>", "<Prim prim=Scan/>", "<Prim prim=Each/>", "<Prim prim=Rows/>", "<Prim prim=Repeat/>", and "<Prim prim=Do/>". Whatever the loop's function left on the stack when it broke is kept, so it is easy to break with a value."</p>
+        <Editor example="/(⎋>10.+) ⇡8"/>
+        <p>"When "<Prim prim=Each/>" or "<Prim prim=Rows/>" break, the rows that were not yet visited are left as they were."</p>
+        <Editor example="∵(⎋=6.×2) [1 2 3 4 5]"/>
+        <p>"Breaking with a number greater than "<code>"1"</code>" breaks out of that many nested loops."</p>
+        <Editor example="⍥(⍥(⎋×2>10.+1)∞)∞ 0"/>
+
         <h2 id="try">"Catching errors with "<Prim prim=Try/></h2>
         <p>"The "<Prim prim=Try/>" modifier takes two functions. If the first function throws an error, the second function is called with the same arguments plus an error message."</p>
         <p>"We can see how this works by using it with "<Prim prim=Parse/>"."</p>
@@ -889,6 +961,20 @@ F!!!+×⊂ [1 2 3][4 5 6]"/>
 ReduceAll! ← ⍥/^2⧻△.
 ReduceAll!+[1_2_3 4_5_6]"/>
 
+        <h2 id="code-macros">"Code Macros"</h2>
+        <p>"Sometimes a modifier needs to do more than call its functions. If a modifier's "<code>"←"</code>" is followed by a "<code>"^"</code>", it becomes a "<em>"code macro"</em>"."</p>
+        <p>"A code macro is run when the code that uses it is compiled. It is passed a list of boxed strings of the code of its functions, and it must return a string of code, which is compiled in its place. Functions written in parentheses are passed without them."</p>
+        <Editor example="\
+Twice! ←^ $\"_ _\".⊔⊢
+Twice!(⊂1) []"/>
+        <p>"The generated code can use other code macros, including the one that generated it."</p>
+        <Editor example="\
+Twice! ←^ $\"_ _\".⊔⊢
+Quad! ←^ $\"Twice!Twice!(_)\"⊔⊢
+Quad!(×2) 1"/>
+        <p>"Because code macros run at compile time, they cannot use system functions like "<Prim prim=Sys(SysOp::Print)/>"."</p>
+        <p>"The generated code is compiled where the macro is used, so names in it refer to the bindings that are visible there, not the ones that are visible where the macro is defined. A macro that generates code using a binding should only be used where that binding is in scope."</p>
+
         <br/>
         <br/>
         <hr/>
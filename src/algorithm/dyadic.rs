@@ -4,11 +4,12 @@ use std::{
     borrow::Cow,
     cmp::Ordering,
     collections::{HashMap, HashSet},
+    fmt,
     iter::repeat,
     mem::take,
 };
 
-use ecow::EcoVec;
+use ecow::{eco_vec, EcoVec};
 use tinyvec::tiny_vec;
 
 use crate::{
@@ -947,16 +948,25 @@ impl Value {
             let last_axis_len = *index_shape.last().unwrap();
             if last_axis_len == 0 {
                 if index_shape[..index_shape.len() - 1].iter().any(|&n| n > 1) {
-                    return Err(env.error("Cannot undo pick with duplicate indices"));
+                    return Err(env.error("Cannot undo pick with duplicate index []"));
                 }
             } else {
-                let mut sorted_indices = Vec::with_capacity(index_data.len() / last_axis_len);
-                for index in index_data.chunks(last_axis_len) {
-                    sorted_indices.push(index);
-                }
+                // Normalize negative indices so that aliases of the same element are caught
+                let into_shape = into.shape();
+                let mut sorted_indices: Vec<Vec<isize>> = index_data
+                    .chunks(last_axis_len)
+                    .map(|index| {
+                        (index.iter().zip(into_shape))
+                            .map(|(&i, &s)| if i < 0 { i + s as isize } else { i })
+                            .collect()
+                    })
+                    .collect();
                 sorted_indices.sort_unstable();
-                if sorted_indices.windows(2).any(|w| w[0] == w[1]) {
-                    return Err(env.error("Cannot undo pick with duplicate indices"));
+                if let Some(w) = sorted_indices.windows(2).find(|w| w[0] == w[1]) {
+                    return Err(env.error(format!(
+                        "Cannot undo pick with duplicate index {}",
+                        FormatIndex(&w[0])
+                    )));
                 }
             }
         }
@@ -1034,7 +1044,9 @@ impl<T: ArrayValue> Array<T> {
                 }
                 return Err(env
                     .error(format!(
-                        "Index {i} is out of bounds of length {s} (dimension {d}) in shape {}",
+                        "Index {} is out of bounds of shape {} \
+                        (axis {d} has length {s})",
+                        FormatIndex(index),
                         self.format_shape()
                     ))
                     .fill());
@@ -1122,9 +1134,6 @@ impl<T: ArrayValue> Array<T> {
 
 impl Value {
     pub fn take(self, from: Self, env: &Uiua) -> UiuaResult<Self> {
-        if from.rank() == 0 {
-            return Err(env.error("Cannot take from scalar"));
-        }
         let index = self.as_indices(env, "Index must be a list of integers")?;
         Ok(match from {
             Value::Num(a) => Value::Num(a.take(&index, env)?),
@@ -1189,6 +1198,13 @@ impl Value {
 
 impl<T: ArrayValue> Array<T> {
     pub fn take(mut self, index: &[isize], env: &Uiua) -> UiuaResult<Self> {
+        if self.rank() == 0 {
+            if T::get_fill(env).is_none() {
+                return Err(env.error("Cannot take from scalar").fill());
+            }
+            // With a fill, a scalar is taken from like an array with a single element
+            self.shape = index.iter().map(|_| 1).collect();
+        }
         Ok(match index {
             [] => self,
             &[taking] => {
@@ -1278,7 +1294,7 @@ impl<T: ArrayValue> Array<T> {
                     for row in self.rows().take(abs_taking) {
                         new_rows.push(row.take(sub_index, env)?);
                     }
-                    let mut arr = Array::from_row_arrays_infallible(new_rows);
+                    let mut arr = self.taken_rows(new_rows, sub_index, env);
                     // Extend with fill values if necessary
                     if abs_taking > arr.row_count() {
                         if let Some(fill) = T::get_fill(env) {
@@ -1305,7 +1321,7 @@ impl<T: ArrayValue> Array<T> {
                     for row in self.rows().skip(start) {
                         new_rows.push(row.take(sub_index, env)?);
                     }
-                    let mut arr = Array::from_row_arrays_infallible(new_rows);
+                    let mut arr = self.taken_rows(new_rows, sub_index, env);
                     // Prepend with fill values if necessary
                     if abs_taking > arr.row_count() {
                         if let Some(fill) = T::get_fill(env) {
@@ -1334,6 +1350,25 @@ impl<T: ArrayValue> Array<T> {
             }
         })
     }
+    /// Combine rows that were each taken from, even if there are none
+    fn taken_rows(&self, rows: Vec<Self>, sub_index: &[isize], env: &Uiua) -> Self {
+        if !rows.is_empty() {
+            return Array::from_row_arrays_infallible(rows);
+        }
+        let filled = T::get_fill(env).is_some();
+        let mut shape: Shape = tiny_vec![0];
+        for (i, &taking) in sub_index.iter().enumerate() {
+            let len = self.shape[i + 1];
+            let abs_taking = taking.unsigned_abs();
+            shape.push(if filled {
+                abs_taking
+            } else {
+                abs_taking.min(len)
+            });
+        }
+        shape.extend_from_slice(&self.shape[1 + sub_index.len()..]);
+        Array::new(shape, [])
+    }
     pub fn drop(mut self, index: &[isize], env: &Uiua) -> UiuaResult<Self> {
         Ok(match index {
             [] => self,
@@ -1698,6 +1733,91 @@ impl<T: ArrayValue> Array<T> {
     }
 }
 
+impl Value {
+    /// Replace the rows of an array at some indices
+    pub fn amend(self, indices: Self, into: Self, env: &Uiua) -> UiuaResult<Self> {
+        let (ind_shape, ind) = indices.as_shaped_indices(env)?;
+        if ind_shape.len() > 1 {
+            return Err(env.error(format!(
+                "Amend indices must be a single index or a list of indices, \
+                but their shape is {}",
+                FormatShape(ind_shape)
+            )));
+        }
+        let single = ind_shape.is_empty();
+        Ok(match (self, into) {
+            (Value::Num(a), Value::Num(b)) => a.amend(single, &ind, b, env)?.into(),
+            (Value::Byte(a), Value::Byte(b)) => a.amend(single, &ind, b, env)?.into(),
+            (Value::Char(a), Value::Char(b)) => a.amend(single, &ind, b, env)?.into(),
+            (Value::Box(a), Value::Box(b)) => a.amend(single, &ind, b, env)?.into(),
+            (Value::Num(a), Value::Byte(b)) => a.amend(single, &ind, b.convert(), env)?.into(),
+            (Value::Byte(a), Value::Num(b)) => a.convert().amend(single, &ind, b, env)?.into(),
+            (a, b) => {
+                return Err(env.error(format!(
+                    "Cannot amend {} array with {}",
+                    b.type_name(),
+                    a.type_name_plural()
+                )))
+            }
+        })
+    }
+}
+
+impl<T: ArrayValue> Array<T> {
+    fn amend(
+        &self,
+        single: bool,
+        indices: &[isize],
+        mut into: Self,
+        env: &Uiua,
+    ) -> UiuaResult<Self> {
+        if into.rank() == 0 {
+            return Err(env.error("Cannot amend a scalar"));
+        }
+        // The new rows are either one row for every index or a row for each index
+        let one_row = self.shape[..] == into.shape[1..];
+        if !one_row
+            && (single
+                || self.rank() != into.rank()
+                || self.shape[1..] != into.shape[1..]
+                || self.row_count() != indices.len())
+        {
+            return Err(env.error(format!(
+                "Cannot amend rows of shape {} at {} {} with an array of shape {}",
+                FormatShape(&into.shape[1..]),
+                indices.len(),
+                if indices.len() == 1 {
+                    "index"
+                } else {
+                    "indices"
+                },
+                self.format_shape()
+            )));
+        }
+        let row_len = into.row_len();
+        let row_count = into.row_count();
+        // Only copies the array if it is shared
+        let into_data = into.data.as_mut_slice();
+        for (j, &i) in indices.iter().enumerate() {
+            let row = if one_row {
+                self.data.as_slice()
+            } else {
+                self.row_slice(j)
+            };
+            let i = if i >= 0 { i } else { row_count as isize + i };
+            if i < 0 || i as usize >= row_count {
+                return Err(env.error(format!(
+                    "Index {} is out of bounds of length {}",
+                    indices[j], row_count
+                )));
+            }
+            let start = i as usize * row_len;
+            into_data[start..start + row_len].clone_from_slice(row);
+        }
+        Ok(into)
+    }
+}
+
 impl Value {
     pub fn windows(&self, from: &Self, env: &Uiua) -> UiuaResult<Self> {
         let size_spec = self.as_naturals(env, "Window size must be a list of natural numbers")?;
@@ -1814,6 +1934,15 @@ impl<T: ArrayValue> Array<T> {
             )));
         }
 
+        // Sequences of whole rows are found without comparing every window
+        if self.rank() > 0
+            && self.rank() == searched.rank()
+            && self.flat_len() > 0
+            && self.shape[1..] == searched.shape[1..]
+        {
+            return Ok(self.find_rows(searched));
+        }
+
         // Pad the shape of the searched-for array
         let mut searched_for_shape = self.shape.clone();
         while searched_for_shape.len() < searched.shape.len() {
@@ -1889,6 +2018,117 @@ impl<T: ArrayValue> Array<T> {
             break Ok(arr);
         }
     }
+    /// Find a sequence of rows in an array whose rows are the same shape
+    ///
+    /// This uses the Knuth-Morris-Pratt algorithm, so it takes time linear in the number of rows.
+    fn find_rows(&self, searched: &Self) -> Array<u8> {
+        let needle: Vec<_> = self.row_slices().map(ArrayCmpSlice).collect();
+        // The length of the longest proper prefix of each prefix of the needle that is also its suffix
+        let mut prefix_lens = vec![0; needle.len()];
+        let mut k = 0;
+        for i in 1..needle.len() {
+            while k > 0 && needle[i] != needle[k] {
+                k = prefix_lens[k - 1];
+            }
+            if needle[i] == needle[k] {
+                k += 1;
+            }
+            prefix_lens[i] = k;
+        }
+        let mut data = eco_vec![0; searched.row_count() + 1 - needle.len()];
+        let slice = data.make_mut();
+        let mut k = 0;
+        for (i, row) in searched.row_slices().map(ArrayCmpSlice).enumerate() {
+            while k > 0 && row != needle[k] {
+                k = prefix_lens[k - 1];
+            }
+            if row == needle[k] {
+                k += 1;
+            }
+            if k == needle.len() {
+                slice[i + 1 - k] = 1;
+                k = prefix_lens[k - 1];
+            }
+        }
+        let mut shape = searched.shape.clone();
+        shape[0] = data.len();
+        for dim in &mut shape[1..] {
+            *dim = 1;
+        }
+        Array::new(shape, data)
+    }
+}
+
+impl Value {
+    /// Split an array into boxed segments separated by a delimiter
+    pub fn split(&self, haystack: &Self, env: &Uiua) -> UiuaResult<Self> {
+        Ok(match (self, haystack) {
+            (Value::Num(a), Value::Num(b)) => a.split(b, env)?.into(),
+            (Value::Byte(a), Value::Byte(b)) => a.split(b, env)?.into(),
+            (Value::Char(a), Value::Char(b)) => a.split(b, env)?.into(),
+            (Value::Box(a), Value::Box(b)) => a.split(b, env)?.into(),
+            (Value::Num(a), Value::Byte(b)) => a.split(&b.clone().convert(), env)?.into(),
+            (Value::Byte(a), Value::Num(b)) => a.clone().convert().split(b, env)?.into(),
+            (a, b) => {
+                return Err(env.error(format!(
+                    "Cannot split {} array by {}",
+                    b.type_name(),
+                    a.type_name_plural(),
+                )))
+            }
+        })
+    }
+}
+
+impl<T: ArrayValue> Array<T> {
+    /// Split an array into boxed segments separated by a delimiter
+    pub fn split(&self, haystack: &Self, env: &Uiua) -> UiuaResult<Array<Boxed>>
+    where
+        Value: From<Array<T>>,
+    {
+        // A delimiter can be a single row or a sequence of rows
+        let delim_rows = if haystack.rank() > 0 && self.shape[..] == haystack.shape[1..] {
+            1
+        } else if haystack.rank() > 0
+            && self.rank() == haystack.rank()
+            && self.shape[1..] == haystack.shape[1..]
+        {
+            self.row_count()
+        } else {
+            return Err(env.error(format!(
+                "Cannot split array of shape {} by delimiter of shape {}",
+                haystack.format_shape(),
+                self.format_shape()
+            )));
+        };
+        if delim_rows == 0 {
+            return Err(env.error("Cannot split by an empty delimiter"));
+        }
+        let row_len = haystack.row_len();
+        let row_count = haystack.row_count();
+        let delim = self.data.as_slice();
+        let segment = |start: usize, end: usize| {
+            let mut shape = haystack.shape.clone();
+            shape[0] = end - start;
+            let data = haystack.data.slice(start * row_len..end * row_len);
+            Boxed(Array::new(shape, data).into())
+        };
+        let mut segments = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i + delim_rows <= row_count {
+            let window = &haystack.data[i * row_len..(i + delim_rows) * row_len];
+            if ArrayCmpSlice(window) == ArrayCmpSlice(delim) {
+                segments.push(segment(start, i));
+                i += delim_rows;
+                start = i;
+            } else {
+                i += 1;
+            }
+        }
+        segments.push(segment(start, row_count));
+        Ok(segments.into_iter().collect())
+    }
 }
 
 impl Value {
@@ -2039,3 +2279,19 @@ impl<T: ArrayValue> Array<T> {
         })
     }
 }
+
+/// Formats the coordinates of a multi-dimensional index for error messages
+struct FormatIndex<'a>(&'a [isize]);
+
+impl<'a> fmt::Display for FormatIndex<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, index) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{index}")?;
+        }
+        write!(f, "]")
+    }
+}
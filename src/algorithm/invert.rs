@@ -12,13 +12,13 @@ use crate::{
 
 impl Function {
     pub fn inverse(&self) -> Option<Self> {
-        Function::new_inferred(self.id.clone(), invert_instrs(&self.instrs)?).ok()
+        Function::new_inferred(self.id.clone(), invert_instrs(&self.unpooled_instrs())?).ok()
     }
     pub fn under(&self, g_sig: Signature) -> Option<(Self, Self)> {
         if let Some(f) = self.inverse() {
             Some((self.clone(), f))
         } else {
-            let (befores, afters) = under_instrs(&self.instrs, g_sig)?;
+            let (befores, afters) = under_instrs(&self.unpooled_instrs(), g_sig)?;
             let before = Function::new_inferred(self.id.clone(), befores).ok()?;
             let after = Function::new_inferred(self.id.clone(), afters).ok()?;
             Some((before, after))
@@ -82,6 +82,7 @@ fn prim_inverse(prim: Primitive, span: usize) -> Option<Instr> {
         Box => Instr::Prim(Unbox, span),
         Unbox => Instr::Prim(Box, span),
         Where => Instr::ImplPrim(InvWhere, span),
+        RunLength => Instr::Prim(Keep, span),
         Utf => Instr::ImplPrim(InvUtf, span),
         _ => return None,
     })
@@ -487,7 +488,7 @@ fn invert_invert_pattern(input: &[Instr]) -> Option<(&[Instr], Vec<Instr>)> {
     let [Instr::PushFunc(func), Instr::Prim(Primitive::Invert, _), input @ ..] = input else {
         return None;
     };
-    Some((input, func.instrs.clone()))
+    Some((input, func.unpooled_instrs().into_owned()))
 }
 
 fn under_from_inverse_pattern(input: &[Instr], _: Signature) -> Option<(&[Instr], Under)> {
@@ -530,7 +531,7 @@ fn under_both_pattern(input: &[Instr], g_sig: Signature) -> Option<(&[Instr], Un
     let [Instr::PushFunc(func), Instr::Prim(Primitive::Both, span), input @ ..] = input else {
         return None;
     };
-    let (befores, afters) = under_instrs(&func.instrs, g_sig)?;
+    let (befores, afters) = under_instrs(&func.unpooled_instrs(), g_sig)?;
     let (befores, afters) = match (g_sig.args, g_sig.outputs) {
         (2, 1) => {
             let before_func = Function::new(func.id.clone(), befores, func.signature());
@@ -80,6 +80,22 @@ pub fn repeat(env: &mut Uiua) -> UiuaResult {
     Ok(())
 }
 
+pub fn do_while(env: &mut Uiua) -> UiuaResult {
+    crate::profile_function!();
+    let body = env.pop_function()?;
+    let cond = env.pop_function()?;
+    loop {
+        env.call_error_on_break(cond.clone(), "break is not allowed in do's condition")?;
+        let keep_going = env
+            .pop("do condition")?
+            .as_bool(env, "Do's condition must be a boolean")?;
+        if !keep_going || env.call_catch_break(body.clone())? {
+            break;
+        }
+    }
+    Ok(())
+}
+
 pub fn partition(env: &mut Uiua) -> UiuaResult {
     crate::profile_function!();
     collapse_groups(
@@ -7,7 +7,7 @@ use std::{
 };
 
 use crate::{
-    array::{Array, ArrayValue, Shape},
+    array::{Array, ArrayValue, FormatShape, Shape},
     value::Value,
     Uiua, UiuaError, UiuaResult,
 };
@@ -20,6 +20,7 @@ mod monadic;
 pub mod pervade;
 pub mod reduce;
 pub mod table;
+mod tolerance;
 pub mod zip;
 
 fn max_shape(a: &[usize], b: &[usize]) -> Shape {
@@ -43,6 +44,13 @@ fn max_shape(a: &[usize], b: &[usize]) -> Shape {
 pub trait FillContext {
     type Error;
     fn error(&self, msg: impl ToString) -> Self::Error;
+    fn shape_mismatch(&self, a: &[usize], b: &[usize]) -> Self::Error {
+        self.error(format!(
+            "Shapes {} and {} do not match",
+            FormatShape(a),
+            FormatShape(b)
+        ))
+    }
     fn pack_boxes(&self) -> bool;
     fn fill<T: ArrayValue>(&self) -> Option<T>;
     fn fill_error(error: Self::Error) -> Self::Error;
@@ -54,6 +62,9 @@ impl FillContext for Uiua {
     fn error(&self, msg: impl ToString) -> Self::Error {
         self.error(msg)
     }
+    fn shape_mismatch(&self, a: &[usize], b: &[usize]) -> Self::Error {
+        self.shape_mismatch(a, b)
+    }
     fn pack_boxes(&self) -> bool {
         self.pack_boxes()
     }
@@ -177,11 +188,7 @@ where
                 }
             }
             if !shape_prefixes_match(&a.shape, &b.shape) {
-                return Err(C::fill_error(ctx.error(format!(
-                    "Shapes {} and {} do not match",
-                    a.format_shape(),
-                    b.format_shape()
-                ))));
+                return Err(C::fill_error(ctx.shape_mismatch(&a.shape, &b.shape)));
             }
         }
     }
@@ -204,7 +204,10 @@ impl Value {
             Array::transpose,
             Array::transpose,
             Array::transpose,
-        )
+        );
+        if self.axes().is_some() {
+            self.meta_mut(|meta| meta.axes.rotate_left(1));
+        }
     }
     pub fn inv_transpose(&mut self) {
         self.generic_mut_deep(
@@ -212,7 +215,39 @@ impl Value {
             Array::inv_transpose,
             Array::inv_transpose,
             Array::inv_transpose,
-        )
+        );
+        if self.axes().is_some() {
+            self.meta_mut(|meta| meta.axes.rotate_right(1));
+        }
+    }
+    /// Move the axes with the given names to the front, in the given order
+    ///
+    /// The other axes keep their order after them.
+    pub fn orient(&mut self, names: &[String], env: &Uiua) -> UiuaResult {
+        let Some(axes) = self.axes() else {
+            return Err(env.error("Cannot orient an array whose axes are not named"));
+        };
+        let mut perm = Vec::with_capacity(axes.len());
+        for name in names {
+            let Some(i) = (axes.iter()).position(|axis| axis.as_deref() == Some(name)) else {
+                return Err(env.error(format!("The array has no axis named `{name}`")));
+            };
+            if perm.contains(&i) {
+                return Err(env.error(format!("Axis `{name}` is named more than once")));
+            }
+            perm.push(i);
+        }
+        let rest: Vec<usize> = (0..axes.len()).filter(|i| !perm.contains(i)).collect();
+        perm.extend(rest);
+        let axes: Vec<Option<String>> = perm.iter().map(|&i| axes[i].clone()).collect();
+        match self {
+            Value::Num(arr) => arr.permute_axes(&perm),
+            Value::Byte(arr) => arr.permute_axes(&perm),
+            Value::Char(arr) => arr.permute_axes(&perm),
+            Value::Box(arr) => arr.permute_axes(&perm),
+        }
+        self.set_axes(axes);
+        Ok(())
     }
 }
 
@@ -226,9 +261,14 @@ impl<T: ArrayValue> Array<T> {
             self.shape.rotate_left(1);
             return;
         }
-        let mut temp = EcoVec::with_capacity(self.data.len());
         let row_len = self.row_len();
         let row_count = self.row_count();
+        if self.data.is_unique() {
+            transpose_in_place(self.data.as_mut_slice(), row_count, row_len);
+            self.shape.rotate_left(1);
+            return;
+        }
+        let mut temp = EcoVec::with_capacity(self.data.len());
         for j in 0..row_len {
             for i in 0..row_count {
                 temp.push(self.data[i * row_len + j].clone());
@@ -246,9 +286,14 @@ impl<T: ArrayValue> Array<T> {
             self.shape.rotate_right(1);
             return;
         }
-        let mut temp = EcoVec::with_capacity(self.data.len());
         let col_len = *self.shape.last().unwrap();
         let col_count: usize = self.shape.iter().rev().skip(1).product();
+        if self.data.is_unique() {
+            transpose_in_place(self.data.as_mut_slice(), col_count, col_len);
+            self.shape.rotate_right(1);
+            return;
+        }
+        let mut temp = EcoVec::with_capacity(self.data.len());
         for j in 0..col_len {
             for i in 0..col_count {
                 temp.push(self.data[i * col_len + j].clone());
@@ -259,6 +304,118 @@ impl<T: ArrayValue> Array<T> {
     }
 }
 
+impl<T: ArrayValue> Array<T> {
+    /// Reorder the axes so that the new axis `i` is the old axis `perm[i]`
+    fn permute_axes(&mut self, perm: &[usize]) {
+        let mut strides = vec![1; self.shape.len()];
+        for i in (1..self.shape.len()).rev() {
+            strides[i - 1] = strides[i] * self.shape[i];
+        }
+        let shape: Shape = perm.iter().map(|&i| self.shape[i]).collect();
+        let mut data = EcoVec::with_capacity(self.data.len());
+        let mut index = vec![0; shape.len()];
+        for _ in 0..self.data.len() {
+            let src: usize = (index.iter().zip(perm))
+                .map(|(&j, &axis)| j * strides[axis])
+                .sum();
+            data.push(self.data[src].clone());
+            for (j, &len) in index.iter_mut().zip(&shape).rev() {
+                *j += 1;
+                if *j < len {
+                    break;
+                }
+                *j = 0;
+            }
+        }
+        self.data = data.into();
+        self.shape = shape;
+    }
+}
+
+/// Transpose a row-major `rows × cols` matrix without allocating a new buffer
+fn transpose_in_place<T>(data: &mut [T], rows: usize, cols: usize) {
+    if rows == cols {
+        for i in 0..rows {
+            for j in i + 1..cols {
+                data.swap(i * cols + j, j * rows + i);
+            }
+        }
+        return;
+    }
+    // Follow each cycle of the permutation, carrying its elements through its first position
+    let len = data.len();
+    let dest = |k: usize| {
+        if k == len - 1 {
+            k
+        } else {
+            k * rows % (len - 1)
+        }
+    };
+    let mut visited = vec![false; len];
+    for start in 0..len {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut next = dest(start);
+        while next != start {
+            data.swap(start, next);
+            visited[next] = true;
+            next = dest(next);
+        }
+    }
+}
+
+#[test]
+fn transpose_in_place_test() {
+    for (rows, cols) in [(1, 1), (2, 2), (3, 3), (2, 3), (3, 2), (1, 4), (4, 5)] {
+        let mut data: Vec<usize> = (0..rows * cols).collect();
+        transpose_in_place(&mut data, rows, cols);
+        for i in 0..rows {
+            for j in 0..cols {
+                assert_eq!(data[j * rows + i], i * cols + j, "{rows}×{cols}");
+            }
+        }
+    }
+}
+
+#[test]
+fn unique_arrays_are_changed_in_place() {
+    let env = Uiua::with_native_sys();
+    let array = || {
+        Array::<f64>::new(
+            tiny_vec![2, 3],
+            (0..6).map(|i| i as f64).collect::<CowSlice<_>>(),
+        )
+    };
+    let ptr = |arr: &Array<f64>| arr.data.as_slice().as_ptr();
+    let mut arr = array();
+    let before = ptr(&arr);
+    arr.reverse();
+    arr.transpose();
+    arr.inv_transpose();
+    assert_eq!(ptr(&arr), before);
+    let Value::Num(arr) = Value::Num(arr).neg(&env).unwrap() else {
+        panic!("negate changed the type")
+    };
+    assert_eq!(ptr(&arr), before);
+    let Value::Num(arr) = Value::Num(arr).add(Value::Num(array()), &env).unwrap() else {
+        panic!("add changed the type")
+    };
+    assert_eq!(ptr(&arr), before);
+    let Value::Num(arr) = Value::from(1.0).add(Value::Num(arr), &env).unwrap() else {
+        panic!("add changed the type")
+    };
+    assert_eq!(ptr(&arr), before);
+    // Shared arrays are not changed
+    let shared = array();
+    let mut copy = shared.clone();
+    copy.transpose();
+    assert_eq!(shared.shape.as_slice(), [2, 3]);
+    assert_eq!(copy.data.as_slice(), [0.0, 3.0, 1.0, 4.0, 2.0, 5.0]);
+    assert_eq!(shared.data.as_slice(), [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+}
+
 impl Value {
     pub fn rise(&self, env: &Uiua) -> UiuaResult<Self> {
         self.generic_ref_env_deep(Array::rise, Array::rise, Array::rise, Array::rise, env)
@@ -269,6 +426,9 @@ impl Value {
             .map(Self::from_iter)
     }
     pub fn classify(&self, env: &Uiua) -> UiuaResult<Self> {
+        if let Some(classes) = self.classify_tolerant(env) {
+            return Ok(Self::from_iter(classes));
+        }
         self.generic_ref_env_deep(
             Array::classify,
             Array::classify,
@@ -278,7 +438,10 @@ impl Value {
         )
         .map(Self::from_iter)
     }
-    pub fn deduplicate(&mut self) {
+    pub fn deduplicate(&mut self, env: &Uiua) {
+        if self.deduplicate_tolerant(env) {
+            return;
+        }
         self.generic_mut_deep(
             Array::deduplicate,
             Array::deduplicate,
@@ -286,6 +449,32 @@ impl Value {
             Array::deduplicate,
         )
     }
+    /// Get the lengths of runs of equal rows and the row of each run
+    pub fn run_length(&self, env: &Uiua) -> UiuaResult<(Array<f64>, Self)> {
+        Ok(match self {
+            Value::Num(arr) => {
+                let (counts, rows) = arr.run_length(env)?;
+                (counts, rows.into())
+            }
+            Value::Byte(arr) => {
+                let (counts, rows) = arr.run_length(env)?;
+                (counts, rows.into())
+            }
+            Value::Char(arr) => {
+                let (counts, rows) = arr.run_length(env)?;
+                (counts, rows.into())
+            }
+            Value::Box(arr) => {
+                let (counts, rows) = arr.run_length(env)?;
+                (counts, rows.into())
+            }
+        })
+    }
+}
+
+/// About how many comparisons it takes to sort some number of rows
+fn sort_steps(len: usize) -> usize {
+    len * (usize::BITS - len.leading_zeros()) as usize
 }
 
 impl<T: ArrayValue> Array<T> {
@@ -293,11 +482,15 @@ impl<T: ArrayValue> Array<T> {
         if self.rank() == 0 {
             return Err(env.error("Cannot rise a scalar"));
         }
-        if self.flat_len() == 0 {
+        if self.row_count() == 0 {
             return Ok(Vec::new());
         }
         let mut indices = (0..self.row_count()).collect::<Vec<_>>();
+        let progress = env.progress("rise", sort_steps(indices.len()));
         indices.par_sort_by(|&a, &b| {
+            if !progress.step() {
+                return Ordering::Equal;
+            }
             self.row_slice(a)
                 .iter()
                 .zip(self.row_slice(b))
@@ -305,17 +498,22 @@ impl<T: ArrayValue> Array<T> {
                 .find(|x| x != &Ordering::Equal)
                 .unwrap_or(Ordering::Equal)
         });
+        progress.finish(env)?;
         Ok(indices)
     }
     pub fn fall(&self, env: &Uiua) -> UiuaResult<Vec<usize>> {
         if self.rank() == 0 {
             return Err(env.error("Cannot fall a scalar"));
         }
-        if self.flat_len() == 0 {
+        if self.row_count() == 0 {
             return Ok(Vec::new());
         }
         let mut indices = (0..self.row_count()).collect::<Vec<_>>();
+        let progress = env.progress("fall", sort_steps(indices.len()));
         indices.par_sort_by(|&a, &b| {
+            if !progress.step() {
+                return Ordering::Equal;
+            }
             self.row_slice(a)
                 .iter()
                 .zip(self.row_slice(b))
@@ -323,6 +521,7 @@ impl<T: ArrayValue> Array<T> {
                 .find(|x| x != &Ordering::Equal)
                 .unwrap_or(Ordering::Equal)
         });
+        progress.finish(env)?;
         Ok(indices)
     }
     pub fn classify(&self, env: &Uiua) -> UiuaResult<Vec<usize>> {
@@ -354,6 +553,29 @@ impl<T: ArrayValue> Array<T> {
         self.data = deduped;
         self.shape[0] = new_len;
     }
+    pub fn run_length(&self, env: &Uiua) -> UiuaResult<(Array<f64>, Self)> {
+        if self.rank() == 0 {
+            return Err(env.error("Cannot get the run lengths of a scalar"));
+        }
+        let mut counts: Vec<f64> = Vec::new();
+        let mut data = CowSlice::new();
+        let mut prev: Option<&[T]> = None;
+        for row in self.row_slices() {
+            match (prev, counts.last_mut()) {
+                (Some(prev), Some(count)) if ArrayCmpSlice(prev) == ArrayCmpSlice(row) => {
+                    *count += 1.0
+                }
+                _ => {
+                    counts.push(1.0);
+                    data.extend_from_slice(row);
+                    prev = Some(row);
+                }
+            }
+        }
+        let mut shape = self.shape.clone();
+        shape[0] = counts.len();
+        Ok((counts.into_iter().collect(), Array::new(shape, data)))
+    }
 }
 
 impl Value {
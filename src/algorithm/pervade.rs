@@ -205,7 +205,7 @@ where
             for a in a_data {
                 *a = f(*a, *a);
             }
-        } else if b.data.is_unique() {
+        } else if !a.data.is_unique() && b.data.is_unique() {
             let a_data = a.data.as_slice();
             let b_data = b.data.as_mut_slice();
             for (a, b) in a_data.iter().zip(b_data) {
@@ -869,11 +869,7 @@ fn bin_pervade_recursive_generic<A: PervasiveInput, B: PervasiveInput, C>(
             let a_cells = a_shape[0];
             let b_cells = b_shape[0];
             if a_cells != b_cells {
-                return Err(env.error(format!(
-                    "Shapes {} and {} do not match",
-                    FormatShape(a_shape),
-                    FormatShape(b_shape)
-                )));
+                return Err(env.shape_mismatch(a_shape, b_shape));
             }
             let a_chunk_size = a.len() / a_cells;
             let b_chunk_size = b.len() / b_cells;
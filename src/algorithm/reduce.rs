@@ -11,7 +11,7 @@ use crate::{
     },
     array::{Array, ArrayValue, Shape},
     cowslice::cowslice,
-    function::{Function, Signature},
+    function::{Function, Instr, Signature},
     value::Value,
     Primitive, Uiua, UiuaResult,
 };
@@ -21,7 +21,7 @@ pub fn reduce(env: &mut Uiua) -> UiuaResult {
     let f = env.pop_function()?;
     let xs = env.pop(1)?;
 
-    match (f.as_flipped_primitive(), xs) {
+    match (env.fast_primitive(&f, &[&xs]), xs) {
         (Some((Primitive::Join, false)), mut xs) if !env.pack_boxes() => {
             if xs.rank() < 2 {
                 env.push(xs);
@@ -106,6 +106,99 @@ where
     }
 }
 
+/// Reduce an array along an axis without splitting it into subarrays,
+/// if the function is a reduction by a primitive that has a fast implementation, like `/+`
+///
+/// This lets [`rows`](Primitive::Rows) and [`level`](Primitive::Level) reduce along later axes
+/// without copying every row. The array is given back if there is no fast implementation.
+pub(crate) fn fast_reduce_axis(
+    f: &Function,
+    xs: Value,
+    axis: usize,
+    env: &Uiua,
+) -> Result<Value, Value> {
+    let reduced = match f.instrs.as_slice() {
+        [Instr::PushFunc(g), Instr::Prim(Primitive::Reduce, _)] => env.fast_primitive(g, &[&xs]),
+        _ => None,
+    };
+    let Some((prim, flipped)) = reduced else {
+        return Err(xs);
+    };
+    let fast = matches!(
+        prim,
+        Primitive::Add
+            | Primitive::Sub
+            | Primitive::Mul
+            | Primitive::Div
+            | Primitive::Max
+            | Primitive::Min
+    );
+    // Empty outer axes give the same shape as the unoptimized path
+    if !fast || axis >= xs.rank() || xs.shape()[..axis].contains(&0) {
+        return Err(xs);
+    }
+    let nums = match xs {
+        Value::Num(nums) => nums,
+        Value::Byte(bytes) => bytes.convert(),
+        xs => return Err(xs),
+    };
+    Ok(match prim {
+        Primitive::Add => fast_reduce_along(nums, axis, 0.0, add::num_num),
+        Primitive::Sub if flipped => fast_reduce_along(nums, axis, 0.0, flip(sub::num_num)),
+        Primitive::Sub => fast_reduce_along(nums, axis, 0.0, sub::num_num),
+        Primitive::Mul => fast_reduce_along(nums, axis, 1.0, mul::num_num),
+        Primitive::Div if flipped => fast_reduce_along(nums, axis, 1.0, flip(div::num_num)),
+        Primitive::Div => fast_reduce_along(nums, axis, 1.0, div::num_num),
+        Primitive::Max => fast_reduce_along(nums, axis, f64::NEG_INFINITY, max::num_num),
+        Primitive::Min => fast_reduce_along(nums, axis, f64::INFINITY, min::num_num),
+        _ => unreachable!("{prim} has no fast reduction"),
+    }
+    .into())
+}
+
+/// Like [`fast_reduce`], but along any axis
+fn fast_reduce_along<T>(arr: Array<T>, axis: usize, identity: T, f: impl Fn(T, T) -> T) -> Array<T>
+where
+    T: ArrayValue + Copy,
+{
+    if axis == 0 {
+        return fast_reduce(arr, identity, f);
+    }
+    let mut shape = arr.shape.clone();
+    let axis_len = shape.remove(axis);
+    let outer: usize = shape[..axis].iter().product();
+    let inner: usize = shape[axis..].iter().product();
+    let mut data = EcoVec::with_capacity(outer * inner);
+    if axis_len == 0 {
+        data.extend((0..outer * inner).map(|_| identity));
+    } else if inner > 0 {
+        for block in arr.data.chunks_exact(axis_len * inner) {
+            let start = data.len();
+            data.extend_from_slice(&block[..inner]);
+            let acc = &mut data.make_mut()[start..];
+            for row in block[inner..].chunks_exact(inner) {
+                for (a, b) in acc.iter_mut().zip(row) {
+                    *a = f(*a, *b);
+                }
+            }
+        }
+    }
+    Array::new(shape, data)
+}
+
+/// Get the identity element of a primitive's reduction, which is what reducing an empty array gives
+///
+/// Primitives without one cannot reduce empty arrays.
+pub(crate) fn reduce_identity(prim: Primitive) -> Option<f64> {
+    Some(match prim {
+        Primitive::Add | Primitive::Sub | Primitive::Ne => 0.0,
+        Primitive::Mul | Primitive::Div | Primitive::Eq | Primitive::Pow => 1.0,
+        Primitive::Max => f64::NEG_INFINITY,
+        Primitive::Min => f64::INFINITY,
+        _ => return None,
+    })
+}
+
 fn generic_fold_right_1(
     f: Arc<Function>,
     xs: Value,
@@ -138,10 +231,22 @@ fn generic_fold_right_1(
             }
         }
         2 => {
+            if init.is_none() && xs.row_count() == 0 {
+                let prim = f.as_flipped_primitive().map(|(prim, _)| prim);
+                let Some(identity) = prim.and_then(reduce_identity) else {
+                    let who = prim.map_or_else(|| "the function".into(), |prim| prim.to_string());
+                    return Err(env.error(format!(
+                        "Cannot reduce empty array because {who} has no identity element. \
+                        Use fold with an initial value instead."
+                    )));
+                };
+                let shape = Shape::from(&xs.shape()[1..]);
+                let len = shape.iter().product();
+                env.push(Array::new(shape, vec![identity; len].as_slice()));
+                return Ok(());
+            }
             let mut rows = xs.into_rows();
-            let mut acc = init
-                .or_else(|| rows.next())
-                .ok_or_else(|| env.error("Cannot reduce empty array"))?;
+            let mut acc = init.or_else(|| rows.next()).unwrap();
             for row in rows {
                 env.push(row);
                 env.push(acc);
@@ -169,7 +274,7 @@ pub fn scan(env: &mut Uiua) -> UiuaResult {
     if xs.rank() == 0 {
         return Err(env.error("Cannot scan rank 0 array"));
     }
-    match (f.as_flipped_primitive(), xs) {
+    match (env.fast_primitive(&f, &[&xs]), xs) {
         (Some((prim, flipped)), Value::Num(nums)) => {
             let arr = match prim {
                 Primitive::Add => fast_scan(nums, add::num_num),
@@ -23,7 +23,7 @@ pub fn table(env: &mut Uiua) -> UiuaResult {
     let f = env.pop_function()?;
     let xs = env.pop(1)?;
     let ys = env.pop(2)?;
-    match (f.as_flipped_primitive(), xs, ys) {
+    match (env.fast_primitive(&f, &[&xs, &ys]), xs, ys) {
         (Some((prim, flipped)), Value::Num(xs), Value::Num(ys)) => {
             if let Err((xs, ys)) = table_nums(prim, flipped, xs, ys, env) {
                 return generic_table(f, Value::Num(xs), Value::Num(ys), env);
@@ -57,7 +57,7 @@ pub fn table(env: &mut Uiua) -> UiuaResult {
             Primitive::Sub => env.push(fast_table(xs, ys, sub::byte_byte)),
             Primitive::Mul => env.push(fast_table(xs, ys, mul::byte_byte)),
             Primitive::Div if flipped => env.push(fast_table(xs, ys, flip(div::byte_byte))),
-            Primitive::Div => env.push(fast_table(xs, ys, flip(div::byte_byte))),
+            Primitive::Div => env.push(fast_table(xs, ys, div::byte_byte)),
             Primitive::Min => env.push(fast_table(xs, ys, min::byte_byte)),
             Primitive::Max => env.push(fast_table(xs, ys, max::byte_byte)),
             Primitive::Join | Primitive::Couple => {
@@ -94,7 +94,7 @@ fn table_nums(
         Primitive::Sub => env.push(fast_table(xs, ys, sub::num_num)),
         Primitive::Mul => env.push(fast_table(xs, ys, mul::num_num)),
         Primitive::Div if flipped => env.push(fast_table(xs, ys, flip(div::num_num))),
-        Primitive::Div => env.push(fast_table(xs, ys, flip(div::num_num))),
+        Primitive::Div => env.push(fast_table(xs, ys, div::num_num)),
         Primitive::Min => env.push(fast_table(xs, ys, min::num_num)),
         Primitive::Max => env.push(fast_table(xs, ys, max::num_num)),
         Primitive::Join | Primitive::Couple => env.push(fast_table_join_or_couple(xs, ys, flipped)),
@@ -152,6 +152,7 @@ fn generic_table(f: Arc<Function>, xs: Value, ys: Value, env: &mut Uiua) -> Uiua
     let mut new_shape = Shape::from(xs.shape());
     new_shape.extend_from_slice(ys.shape());
     let mut items = Value::builder(xs.flat_len() * ys.flat_len());
+    let progress = env.progress("table", xs.flat_len() * ys.flat_len());
     let y_values = ys.into_flat_values().collect::<Vec<_>>();
     for x in xs.into_flat_values() {
         for y in y_values.iter().cloned() {
@@ -161,8 +162,12 @@ fn generic_table(f: Arc<Function>, xs: Value, ys: Value, env: &mut Uiua) -> Uiua
             let item = env.pop("tabled function result")?;
             item.validate_shape();
             items.add_row(item, env)?;
+            if !progress.step() {
+                break;
+            }
         }
     }
+    progress.finish(env)?;
     let mut tabled = items.finish();
     new_shape.extend_from_slice(&tabled.shape()[1..]);
     *tabled.shape_mut() = new_shape;
@@ -182,6 +187,32 @@ pub fn cross(env: &mut Uiua) -> UiuaResult {
             "Cross's function's signature must be |2.1, but it is {sig}"
         )));
     }
+    let (xs, ys) = match (env.fast_primitive(&f, &[&xs, &ys]), xs, ys) {
+        // Rows of the same shape can be combined by arithmetic without splitting the arrays
+        (Some((prim, flipped)), xs, ys)
+            if xs.row_count() > 0
+                && ys.row_count() > 0
+                && xs.rank() > 0
+                && ys.rank() > 0
+                && xs.shape()[1..] == ys.shape()[1..] =>
+        {
+            let as_nums = |val: Value| match val {
+                Value::Byte(bytes) => Value::Num(bytes.convert()),
+                val => val,
+            };
+            match (as_nums(xs), as_nums(ys)) {
+                (Value::Num(xs), Value::Num(ys)) => match cross_nums(prim, flipped, xs, ys) {
+                    Ok(crossed) => {
+                        env.push(crossed);
+                        return Ok(());
+                    }
+                    Err((xs, ys)) => (Value::Num(xs), Value::Num(ys)),
+                },
+                vals => vals,
+            }
+        }
+        (_, xs, ys) => (xs, ys),
+    };
     let mut new_shape = tiny_vec![xs.row_count(), ys.row_count()];
     let mut items = Value::builder(xs.row_count() * ys.row_count());
     let y_rows = ys.into_rows().collect::<Vec<_>>();
@@ -203,6 +234,48 @@ pub fn cross(env: &mut Uiua) -> UiuaResult {
     Ok(())
 }
 
+#[allow(clippy::result_large_err)]
+fn cross_nums(
+    prim: Primitive,
+    flipped: bool,
+    xs: Array<f64>,
+    ys: Array<f64>,
+) -> Result<Array<f64>, (Array<f64>, Array<f64>)> {
+    Ok(match prim {
+        Primitive::Add => fast_cross(xs, ys, add::num_num),
+        Primitive::Sub if flipped => fast_cross(xs, ys, flip(sub::num_num)),
+        Primitive::Sub => fast_cross(xs, ys, sub::num_num),
+        Primitive::Mul => fast_cross(xs, ys, mul::num_num),
+        Primitive::Div if flipped => fast_cross(xs, ys, flip(div::num_num)),
+        Primitive::Div => fast_cross(xs, ys, div::num_num),
+        Primitive::Min => fast_cross(xs, ys, min::num_num),
+        Primitive::Max => fast_cross(xs, ys, max::num_num),
+        _ => return Err((xs, ys)),
+    })
+}
+
+/// Combine each pair of rows of two arrays whose rows have the same shape
+fn fast_cross<A: ArrayValue, B: ArrayValue, C: ArrayValue>(
+    a: Array<A>,
+    b: Array<B>,
+    f: impl Fn(A, B) -> C,
+) -> Array<C> {
+    let row_len = a.row_len();
+    let mut new_data = EcoVec::with_capacity(a.row_count() * b.row_count() * row_len);
+    if row_len > 0 {
+        for x_row in a.data.chunks_exact(row_len) {
+            for y_row in b.data.chunks_exact(row_len) {
+                for (x, y) in x_row.iter().zip(y_row) {
+                    new_data.push(f(x.clone(), y.clone()));
+                }
+            }
+        }
+    }
+    let mut new_shape = tiny_vec![a.row_count(), b.row_count()];
+    new_shape.extend_from_slice(&a.shape[1..]);
+    Array::new(new_shape, new_data)
+}
+
 pub fn combinate(env: &mut Uiua) -> UiuaResult {
     crate::profile_function!();
     let ns = rank_list("Combinate", env)?;
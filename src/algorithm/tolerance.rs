@@ -0,0 +1,179 @@
+//! Comparing numbers with a tolerance
+//!
+//! These are only used when a comparison tolerance is set, so the exact algorithms stay fast.
+
+use std::{borrow::Cow, cmp::Ordering};
+
+use crate::{
+    algorithm::pervade::{bin_pervade, InfalliblePervasiveFn},
+    array::*,
+    cowslice::CowSlice,
+    primitive::PervasiveDyadic,
+    value::Value,
+    Uiua, UiuaResult,
+};
+
+/// Compare two numbers, treating them as equal if they are at most the tolerance apart
+fn tolerant_cmp(a: f64, b: f64, tolerance: f64) -> Ordering {
+    if (a - b).abs() <= tolerance {
+        Ordering::Equal
+    } else {
+        a.array_cmp(&b)
+    }
+}
+
+fn rows_eq(a: &[f64], b: &[f64], tolerance: f64) -> bool {
+    a.len() == b.len()
+        && (a.iter().zip(b)).all(|(a, b)| tolerant_cmp(*a, *b, tolerance) == Ordering::Equal)
+}
+
+macro_rules! tolerant_cmps {
+    ($($name:ident, $exact:ident, $f:expr);* $(;)?) => {
+        impl Value {
+            $(
+                #[allow(clippy::wrong_self_convention)]
+                pub(crate) fn $name(self, other: Self, env: &Uiua) -> UiuaResult<Self> {
+                    self.cmp_tolerant(other, env, Value::$exact, $f)
+                }
+            )*
+        }
+    };
+}
+
+tolerant_cmps!(
+    is_eq_tolerant, is_eq, Ordering::is_eq;
+    is_ne_tolerant, is_ne, Ordering::is_ne;
+    is_lt_tolerant, is_lt, Ordering::is_lt;
+    is_le_tolerant, is_le, Ordering::is_le;
+    is_gt_tolerant, is_gt, Ordering::is_gt;
+    is_ge_tolerant, is_ge, Ordering::is_ge;
+);
+
+impl Value {
+    /// Get the value's numbers and the comparison tolerance, if there is one
+    fn tolerant_nums(&self, env: &Uiua) -> Option<(Cow<'_, Array<f64>>, f64)> {
+        let tolerance = env.tolerance();
+        if tolerance <= 0.0 {
+            return None;
+        }
+        let nums = match self {
+            Value::Num(arr) => Cow::Borrowed(arr),
+            Value::Byte(arr) => Cow::Owned(arr.convert_ref()),
+            _ => return None,
+        };
+        Some((nums, tolerance))
+    }
+    /// Compare two values with a comparison primitive's function,
+    /// or with the comparison tolerance if there is one and they are both numbers
+    fn cmp_tolerant(
+        self,
+        other: Self,
+        env: &Uiua,
+        exact: PervasiveDyadic,
+        f: fn(Ordering) -> bool,
+    ) -> UiuaResult<Self> {
+        let tolerance = env.tolerance();
+        let (a, b) = match (self, other) {
+            (a, b) if tolerance <= 0.0 => return exact(a, b, env),
+            (Value::Num(a), Value::Num(b)) => (a, b),
+            (Value::Num(a), Value::Byte(b)) => (a, b.convert()),
+            (Value::Byte(a), Value::Num(b)) => (a.convert(), b),
+            (Value::Byte(a), Value::Byte(b)) => (a.convert(), b.convert()),
+            (a, b) => return exact(a, b, env),
+        };
+        let cmp = InfalliblePervasiveFn::new(move |a: f64, b: f64| {
+            u8::from(f(tolerant_cmp(b, a, tolerance)))
+        });
+        Ok(bin_pervade(a, b, env, cmp)?.into())
+    }
+    /// Check if two values match, treating numbers that are at most the comparison tolerance apart as equal
+    pub(crate) fn matches(&self, other: &Self, env: &Uiua) -> bool {
+        match (self.tolerant_nums(env), other.tolerant_nums(env)) {
+            (Some((a, tolerance)), Some((b, _))) => {
+                a.shape == b.shape && rows_eq(&a.data, &b.data, tolerance)
+            }
+            _ => self == other,
+        }
+    }
+    /// Classify the value's rows with the comparison tolerance, if there is one
+    pub(crate) fn classify_tolerant(&self, env: &Uiua) -> Option<Vec<usize>> {
+        if self.rank() == 0 {
+            return None;
+        }
+        let (nums, tolerance) = self.tolerant_nums(env)?;
+        Some(nums.classify_tolerant(tolerance))
+    }
+    /// Deduplicate the value's rows with the comparison tolerance, if there is one
+    ///
+    /// Returns `false` if there is no tolerance.
+    pub(crate) fn deduplicate_tolerant(&mut self, env: &Uiua) -> bool {
+        let Some(classes) = self.classify_tolerant(env) else {
+            return false;
+        };
+        match self {
+            Value::Num(arr) => arr.keep_first_of_classes(&classes),
+            Value::Byte(arr) => arr.keep_first_of_classes(&classes),
+            Value::Char(arr) => arr.keep_first_of_classes(&classes),
+            Value::Box(arr) => arr.keep_first_of_classes(&classes),
+        }
+        true
+    }
+}
+
+impl Array<f64> {
+    /// Classify the rows of an array, treating rows whose numbers are all at most the tolerance apart as equal
+    ///
+    /// Each row is compared with the first row of each class, so this is slower than classifying exactly.
+    fn classify_tolerant(&self, tolerance: f64) -> Vec<usize> {
+        let mut firsts: Vec<&[f64]> = Vec::new();
+        (self.row_slices())
+            .map(|row| {
+                (firsts.iter())
+                    .position(|first| rows_eq(first, row, tolerance))
+                    .unwrap_or_else(|| {
+                        firsts.push(row);
+                        firsts.len() - 1
+                    })
+            })
+            .collect()
+    }
+}
+
+impl<T: ArrayValue> Array<T> {
+    /// Keep only the first row of each class
+    fn keep_first_of_classes(&mut self, classes: &[usize]) {
+        let mut kept = CowSlice::new();
+        let mut new_len = 0;
+        for (row, &class) in self.row_slices().zip(classes) {
+            if class == new_len {
+                kept.extend_from_slice(row);
+                new_len += 1;
+            }
+        }
+        self.data = kept;
+        self.shape[0] = new_len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::run_with;
+
+    #[test]
+    fn comparison_tolerance() {
+        // The modifier's behavior is covered by tests/tolerance.ua
+        let run = |code: &str| {
+            let env = Uiua::with_native_sys().with_comparison_tolerance(1e-9);
+            let mut stack = run_with(env, code).unwrap();
+            stack.pop().unwrap().show()
+        };
+        assert_eq!(run("= 0.3 +0.1 0.2"), "1");
+        assert_eq!(run("≍ [0.3 1] [+0.1 0.2 1]"), "1");
+        assert_eq!(run("⊝ [0.3 +0.1 0.2 1]"), "[0.3 1]");
+        assert_eq!(run("⊛ [0.3 +0.1 0.2 1]"), "[0 0 1]");
+        assert_eq!(run("> 1 +1 1e-12"), "0");
+        // The modifier overrides the runtime's tolerance
+        assert_eq!(run("tolerance0(= 0.3 +0.1 0.2)"), "0");
+    }
+}
@@ -2,17 +2,41 @@
 
 use std::sync::Arc;
 
+use tinyvec::tiny_vec;
+
 use crate::{
     algorithm::{
         loops::{rank_list, rank_to_depth},
         pervade::bin_pervade_generic,
+        reduce::fast_reduce_axis,
     },
-    array::{FormatShape, Shape},
+    array::{Array, FormatShape, Shape},
     function::Function,
     value::Value,
     Uiua, UiuaResult,
 };
 
+/// Find the result of calling a function on arrays with no rows
+///
+/// The function is called once on proxy rows of default elements to find the shape and type of the
+/// rows of the result. If the function fails on them, the result is an empty list of numbers.
+fn empty_result(f: &Arc<Function>, proxies: Vec<Value>, shape: Shape, env: &mut Uiua) -> Value {
+    let height = env.stack_size();
+    for proxy in proxies.into_iter().rev() {
+        env.push(proxy);
+    }
+    env.proxy_depth += 1;
+    let res = env
+        .call_error_on_break(f.clone(), "break is not allowed on proxy rows")
+        .and_then(|_| env.pop("proxy result"));
+    env.proxy_depth -= 1;
+    env.truncate_stack(height);
+    match res {
+        Ok(row) => row.into_empty(shape),
+        Err(_) => Array::<f64>::new(shape, []).into(),
+    }
+}
+
 pub fn each(env: &mut Uiua) -> UiuaResult {
     crate::profile_function!();
     let f = env.pop_function()?;
@@ -61,6 +85,12 @@ pub fn each(env: &mut Uiua) -> UiuaResult {
 }
 
 fn each1_1(f: Arc<Function>, xs: Value, env: &mut Uiua) -> UiuaResult {
+    if xs.flat_len() == 0 {
+        let proxy = xs.proxy(Shape::new());
+        let eached = empty_result(&f, vec![proxy], xs.shape().into(), env);
+        env.push(eached);
+        return Ok(());
+    }
     let mut new_values = Vec::with_capacity(xs.flat_len());
     let mut new_shape = Shape::from(xs.shape());
     let mut old_values = xs.into_flat_values();
@@ -96,6 +126,19 @@ fn each1_0(f: Arc<Function>, xs: Value, env: &mut Uiua) -> UiuaResult {
 fn each2_1(f: Arc<Function>, xs: Value, ys: Value, env: &mut Uiua) -> UiuaResult {
     let xs_shape = xs.shape().to_vec();
     let ys_shape = ys.shape().to_vec();
+    if xs.flat_len() == 0 || ys.flat_len() == 0 {
+        let shape = if xs_shape.len() >= ys_shape.len() {
+            &xs_shape
+        } else {
+            &ys_shape
+        };
+        if xs_shape.iter().zip(&ys_shape).all(|(a, b)| a == b) {
+            let proxies = vec![xs.proxy(Shape::new()), ys.proxy(Shape::new())];
+            let eached = empty_result(&f, proxies, shape.as_slice().into(), env);
+            env.push(eached);
+            return Ok(());
+        }
+    }
     let xs_values: Vec<_> = xs.into_flat_values().collect();
     let ys_values: Vec<_> = ys.into_flat_values().collect();
     let (mut shape, values) = bin_pervade_generic(
@@ -151,6 +194,12 @@ fn eachn_1(f: Arc<Function>, args: Vec<Value>, env: &mut Uiua) -> UiuaResult {
         }
     }
     let elem_count = args[0].flat_len();
+    if elem_count == 0 {
+        let proxies = args.iter().map(|arg| arg.proxy(Shape::new())).collect();
+        let eached = empty_result(&f, proxies, args[0].shape().into(), env);
+        env.push(eached);
+        return Ok(());
+    }
     let mut arg_elems: Vec<_> = args.into_iter().map(|v| v.into_flat_values()).collect();
     let mut new_values = Vec::new();
     for _ in 0..elem_count {
@@ -235,6 +284,19 @@ pub fn rows(env: &mut Uiua) -> UiuaResult {
 }
 
 fn rows1_1(f: Arc<Function>, xs: Value, env: &mut Uiua) -> UiuaResult {
+    let xs = match fast_reduce_axis(&f, xs, 1, env) {
+        Ok(reduced) => {
+            env.push(reduced);
+            return Ok(());
+        }
+        Err(xs) => xs,
+    };
+    if xs.row_count() == 0 {
+        let proxy = xs.proxy(xs.shape()[1..].into());
+        let rowed = empty_result(&f, vec![proxy], tiny_vec![0], env);
+        env.push(rowed);
+        return Ok(());
+    }
     let mut new_rows = Value::builder(xs.row_count());
     let mut old_rows = xs.into_rows();
     for row in old_rows.by_ref() {
@@ -271,6 +333,15 @@ fn rows2_1(f: Arc<Function>, xs: Value, ys: Value, env: &mut Uiua) -> UiuaResult
             ys.row_count()
         )));
     }
+    if xs.row_count() == 0 {
+        let proxies = vec![
+            xs.proxy(xs.shape()[1..].into()),
+            ys.proxy(ys.shape()[1..].into()),
+        ];
+        let rowed = empty_result(&f, proxies, tiny_vec![0], env);
+        env.push(rowed);
+        return Ok(());
+    }
     let mut new_rows = Vec::with_capacity(xs.row_count());
     let x_rows = xs.into_rows();
     let y_rows = ys.into_rows();
@@ -314,6 +385,14 @@ fn rowsn_1(f: Arc<Function>, args: Vec<Value>, env: &mut Uiua) -> UiuaResult {
         }
     }
     let row_count = args[0].row_count();
+    if row_count == 0 {
+        let proxies = (args.iter())
+            .map(|arg| arg.proxy(arg.shape()[1..].into()))
+            .collect();
+        let rowed = empty_result(&f, proxies, tiny_vec![0], env);
+        env.push(rowed);
+        return Ok(());
+    }
     let mut arg_elems: Vec<_> = args.into_iter().map(|v| v.into_rows()).collect();
     let mut new_values = Vec::new();
     for _ in 0..row_count {
@@ -543,6 +622,13 @@ pub fn level(env: &mut Uiua) -> UiuaResult {
                 Some(_) => {}
             }
             let n = rank_to_depth(n, xs.rank());
+            let xs = match fast_reduce_axis(&f, xs, n, env) {
+                Ok(reduced) => {
+                    env.push(reduced);
+                    return Ok(());
+                }
+                Err(xs) => xs,
+            };
             let res = monadic_level_recursive(f, xs, n, env)?;
             env.push(res);
         }
@@ -0,0 +1,199 @@
+//! Runtime checks of binding [`Annotation`]s
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use crate::{
+    array::FormatShape,
+    ast::{Annotation, DimPattern, ValuePattern, ValueType},
+    function::{DynamicFunction, Function, Instr, Signature},
+    lex::Sp,
+    value::Value,
+    Ident, Uiua, UiuaResult,
+};
+
+/// Make sure the patterns of an annotation make sense
+pub(crate) fn validate(annotation: &Annotation) -> UiuaResult {
+    let patterns = (annotation.args.iter()).chain(annotation.outputs.iter().flatten());
+    for pattern in patterns {
+        let shape = pattern.value.shape.iter().flatten();
+        if shape.filter(|dim| **dim == DimPattern::Rest).count() > 1 {
+            return Err(pattern
+                .span
+                .clone()
+                .sp("A shape pattern may have at most one `*`".into())
+                .into());
+        }
+    }
+    Ok(())
+}
+
+/// Wrap a function so that its arguments and outputs are checked against an annotation
+pub(crate) fn annotated(name: Ident, annotation: Annotation, f: Arc<Function>) -> Function {
+    let sig = f.signature();
+    let id = {
+        let mut hasher = DefaultHasher::new();
+        annotation.hash(&mut hasher);
+        f.hash(&mut hasher);
+        hasher.finish()
+    };
+    let id_fn = f.id.clone();
+    let check = move |env: &mut Uiua| {
+        let mut dims = HashMap::new();
+        check_stack(env, &name, "Argument", &annotation.args, &mut dims)?;
+        env.call(f.clone())?;
+        if let Some(outputs) = &annotation.outputs {
+            let kind = if annotation.args.is_empty() && outputs.len() == 1 {
+                "Value"
+            } else {
+                "Output"
+            };
+            check_stack(env, &name, kind, outputs, &mut dims)?;
+        }
+        Ok(())
+    };
+    Function::new(
+        id_fn,
+        vec![Instr::Dynamic(Box::new(DynamicFunction {
+            id,
+            f: Arc::new(check),
+            signature: sig,
+        }))],
+        sig,
+    )
+}
+
+/// An instruction that checks the value on top of the stack before it is assigned to a binding
+///
+/// Errors point to the name where it is assigned.
+pub(crate) fn value_check(name: Sp<Ident>, pattern: Sp<ValuePattern>) -> Instr {
+    let id = {
+        let mut hasher = DefaultHasher::new();
+        name.hash(&mut hasher);
+        pattern.hash(&mut hasher);
+        hasher.finish()
+    };
+    Instr::Dynamic(Box::new(DynamicFunction {
+        id,
+        f: Arc::new(move |env| {
+            let Some(value) = env.stack.last() else {
+                return Ok(());
+            };
+            check(&pattern.value, value, &mut HashMap::new()).map_err(|e| {
+                (name.span.clone())
+                    .sp(format!("Value of {} {e}", name.value))
+                    .into()
+            })
+        }),
+        signature: Signature::new(1, 1),
+    }))
+}
+
+/// Check the values on top of the stack against some patterns, starting with the top
+fn check_stack(
+    env: &Uiua,
+    name: &Ident,
+    kind: &str,
+    patterns: &[Sp<ValuePattern>],
+    dims: &mut HashMap<Ident, usize>,
+) -> UiuaResult {
+    for (i, pattern) in patterns.iter().enumerate() {
+        // Missing values are reported by whatever needs them
+        let Some(value) = (env.stack.len().checked_sub(i + 1)).map(|j| &env.stack[j]) else {
+            break;
+        };
+        if let Err(e) = check(&pattern.value, value, dims) {
+            let which = if kind == "Value" {
+                String::new()
+            } else {
+                format!(" {}", i + 1)
+            };
+            return Err(env.error(format!("{kind}{which} of {name} {e}")));
+        }
+    }
+    Ok(())
+}
+
+fn check(
+    pattern: &ValuePattern,
+    value: &Value,
+    dims: &mut HashMap<Ident, usize>,
+) -> Result<(), String> {
+    if let Some(ty) = pattern.ty {
+        let (matches, expected) = match ty {
+            ValueType::Num => (matches!(value, Value::Num(_) | Value::Byte(_)), "numbers"),
+            ValueType::Char => (matches!(value, Value::Char(_)), "characters"),
+            ValueType::Box => (matches!(value, Value::Box(_)), "boxes"),
+        };
+        if !matches {
+            return Err(format!(
+                "must be {expected}, but it is {}",
+                value.type_name_plural()
+            ));
+        }
+    }
+    let Some(dim_patterns) = &pattern.shape else {
+        return Ok(());
+    };
+    let shape = value.shape();
+    let mismatch = || {
+        let expected = ValuePattern {
+            ty: None,
+            shape: pattern.shape.clone(),
+        };
+        format!(
+            "must have shape {expected}, but it has shape {}",
+            FormatShape(shape)
+        )
+    };
+    let rest = dim_patterns.iter().position(|dim| *dim == DimPattern::Rest);
+    let (front, back) = match rest {
+        Some(i) => (&dim_patterns[..i], &dim_patterns[i + 1..]),
+        None => (dim_patterns.as_slice(), &[][..]),
+    };
+    let fits = match rest {
+        Some(_) => shape.len() >= front.len() + back.len(),
+        None => shape.len() == front.len(),
+    };
+    if !fits {
+        return Err(mismatch());
+    }
+    let pairs = (front.iter().zip(shape)).chain(back.iter().rev().zip(shape.iter().rev()));
+    for (dim, &len) in pairs {
+        match dim {
+            DimPattern::Any | DimPattern::Rest => {}
+            DimPattern::Len(n) => {
+                if *n != len {
+                    return Err(mismatch());
+                }
+            }
+            DimPattern::Named(name) => match dims.get(name) {
+                Some(&bound) if bound != len => {
+                    return Err(format!("{}, and {name} is {bound}", mismatch()));
+                }
+                Some(_) => {}
+                None => {
+                    dims.insert(*name, len);
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::run;
+
+    #[test]
+    fn annotations() {
+        // Errors when running annotated code are covered by tests/annotations.ua
+        let fails = |code: &str| run(code).unwrap_err().to_string();
+        assert!(fails("V ← |.[2]| [1 2 3]").contains("Value of V"));
+        assert!(fails("F ← |[* *]| ⧻").contains("at most one `*`"));
+        assert!(fails("C ↩ |[]| 0").contains("can only annotate its value"));
+    }
+}
@@ -1,7 +1,9 @@
 use std::{
     cmp::Ordering,
+    collections::BTreeMap,
     fmt::{self, Debug, Display},
     hash::{Hash, Hasher},
+    sync::Arc,
 };
 
 use ecow::EcoVec;
@@ -12,7 +14,7 @@ use crate::{
     cowslice::{cowslice, CowSlice},
     grid_fmt::GridFmt,
     value::Value,
-    Uiua,
+    Uiua, Unit,
 };
 
 /// Uiua's array type
@@ -20,6 +22,34 @@ use crate::{
 pub struct Array<T> {
     pub(crate) shape: Shape,
     pub(crate) data: CowSlice<T>,
+    /// A label and other metadata, if any have been attached
+    pub(crate) meta: Option<Arc<ArrayMeta>>,
+}
+
+/// Metadata attached to an array
+///
+/// Metadata does not change how arrays are compared or hashed.
+#[derive(Debug, Clone, Default)]
+pub struct ArrayMeta {
+    /// A name for the array, which is shown when it is displayed and in errors about its shape
+    pub label: Option<String>,
+    /// Names for the array's axes, from the first axis
+    ///
+    /// `None` is an axis without a name. The names are ignored if there are not as many as the array's rank.
+    pub axes: Vec<Option<String>>,
+    /// The unit of measure of the array's numbers
+    pub unit: Option<Unit>,
+    /// Other metadata, for use by embedders
+    pub map: BTreeMap<String, Value>,
+}
+
+impl ArrayMeta {
+    fn is_empty(&self) -> bool {
+        self.label.is_none()
+            && self.axes.iter().all(Option::is_none)
+            && self.unit.is_none()
+            && self.map.is_empty()
+    }
 }
 
 pub type Shape = TinyVec<[usize; 3]>;
@@ -29,6 +59,7 @@ impl<T: ArrayValue> Default for Array<T> {
         Self {
             shape: tiny_vec![0],
             data: CowSlice::new(),
+            meta: None,
         }
     }
 }
@@ -79,12 +110,33 @@ fn validate_shape<T>(shape: &[usize], data: &[T]) {
 }
 
 impl<T> Array<T> {
+    /// Get the metadata attached to the array
+    pub fn meta(&self) -> Option<&ArrayMeta> {
+        self.meta.as_deref()
+    }
+    /// Get the names of the array's axes, if any are named
+    pub fn axes(&self) -> Option<&[Option<String>]> {
+        let axes = &self.meta()?.axes;
+        (axes.len() == self.shape.len() && axes.iter().any(Option::is_some)).then_some(axes)
+    }
+    /// Change the metadata attached to the array
+    pub fn meta_mut(&mut self, f: impl FnOnce(&mut ArrayMeta)) {
+        let mut meta = self.meta.take().unwrap_or_default();
+        f(Arc::make_mut(&mut meta));
+        if !meta.is_empty() {
+            self.meta = Some(meta);
+        }
+    }
     #[track_caller]
     pub fn new(shape: impl Into<Shape>, data: impl Into<CowSlice<T>>) -> Self {
         let shape = shape.into();
         let data = data.into();
         validate_shape(&shape, &data);
-        Self { shape, data }
+        Self {
+            shape,
+            data,
+            meta: None,
+        }
     }
     #[track_caller]
     #[inline(always)]
@@ -160,7 +212,20 @@ impl<T: ArrayValue> Array<T> {
         let row_len = self.row_len();
         let start = row * row_len;
         let end = start + row_len;
-        Self::new(&self.shape[1..], self.data.slice(start..end))
+        let mut row = Self::new(&self.shape[1..], self.data.slice(start..end));
+        row.meta = self.row_meta();
+        row
+    }
+    /// Get the metadata that the array's rows share with it
+    ///
+    /// A unit of measure applies to every number in the array, so rows keep it.
+    /// Labels and axis names describe the whole array, so they are not kept.
+    pub(crate) fn row_meta(&self) -> Option<Arc<ArrayMeta>> {
+        let unit = self.meta()?.unit.clone()?;
+        Some(Arc::new(ArrayMeta {
+            unit: Some(unit),
+            ..ArrayMeta::default()
+        }))
     }
     pub fn convert<U>(self) -> Array<U>
     where
@@ -173,6 +238,7 @@ impl<T: ArrayValue> Array<T> {
         Array {
             shape: self.shape,
             data: self.data.into_iter().map(f).collect(),
+            meta: self.meta,
         }
     }
     pub fn try_convert_with<U: Clone, E>(
@@ -182,6 +248,7 @@ impl<T: ArrayValue> Array<T> {
         Ok(Array {
             shape: self.shape,
             data: self.data.into_iter().map(f).collect::<Result<_, _>>()?,
+            meta: self.meta,
         })
     }
     pub fn convert_ref<U>(&self) -> Array<U>
@@ -195,6 +262,7 @@ impl<T: ArrayValue> Array<T> {
         Array {
             shape: self.shape.clone(),
             data: self.data.iter().cloned().map(f).collect(),
+            meta: self.meta.clone(),
         }
     }
     pub fn into_rows(self) -> impl ExactSizeIterator<Item = Self> {
@@ -205,12 +273,15 @@ impl<T: ArrayValue> Array<T> {
         } else {
             row_shape.remove(0)
         };
+        let meta = self.row_meta();
         let mut data = self.data.into_iter();
         (0..row_count).map(move |_| {
-            Array::new(
+            let mut row = Array::new(
                 row_shape.clone(),
                 data.by_ref().take(row_len).collect::<CowSlice<_>>(),
-            )
+            );
+            row.meta = meta.clone();
+            row
         })
     }
     pub fn into_rows_rev(self) -> impl Iterator<Item = Self> {
@@ -221,10 +292,13 @@ impl<T: ArrayValue> Array<T> {
         } else {
             row_shape.remove(0)
         };
+        let meta = self.row_meta();
         let mut data = self.data.into_iter().rev();
         (0..row_count).map(move |_| {
             let row: CowSlice<_> = data.by_ref().take(row_len).rev().collect();
-            Array::new(row_shape.clone(), row)
+            let mut row = Array::new(row_shape.clone(), row);
+            row.meta = meta.clone();
+            row
         })
     }
     pub(crate) fn first_dim_zero(&self) -> Self {
@@ -489,3 +563,40 @@ impl<'a> fmt::Display for FormatShape<'a> {
         write!(f, "]")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::run;
+
+    #[test]
+    fn labels() {
+        let stack = run("×2 label \"prices\" [1 2 3]").unwrap();
+        assert_eq!(stack[0].label(), Some("prices"));
+        assert_eq!(stack[0].show(), "prices: [2 4 6]");
+        let stack = run("P ← label \"prices\" [1 2 3]\nlabel \"\" P").unwrap();
+        assert_eq!(stack[0].label(), None);
+        let mut value = Value::from(5);
+        value.meta_mut(|meta| _ = meta.map.insert("unit".into(), "m".into()));
+        assert_eq!(value.meta().unwrap().map["unit"], Value::from("m"));
+        assert_eq!(value.label(), None);
+    }
+
+    #[test]
+    fn named_axes() {
+        let names = |code: &str| {
+            let stack = run(code).unwrap();
+            (stack[0].axes().unwrap().iter())
+                .map(|name| name.clone().unwrap_or_default())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names("⍉ +1 axes {\"time\" \"\"} [1_2_3 4_5_6]"),
+            ["", "time"]
+        );
+        assert_eq!(
+            names("orient {\"c\" \"a\"} axes {\"a\" \"b\" \"c\"} ↯2_3_4 ⇡24"),
+            ["c", "a", "b"]
+        );
+    }
+}
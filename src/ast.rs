@@ -1,3 +1,8 @@
+//! The syntax tree of Uiua code
+//!
+//! Use [`parse`](crate::parse) to get the [`Item`]s of some code,
+//! and a [`Visitor`] to walk them.
+
 use std::fmt;
 
 use crate::{
@@ -7,29 +12,186 @@ use crate::{
     Ident, Primitive,
 };
 
+/// A top-level item
 #[derive(Debug, Clone)]
 pub enum Item {
+    /// Items between a pair of `---`
     TestScope(Vec<Item>),
+    /// A line of code that is not a binding
     Words(Vec<Sp<Word>>),
     Binding(Binding),
+    Destructure(Destructure),
     ExtraNewlines(CodeSpan),
 }
 
+/// A binding of a single name
 #[derive(Debug, Clone)]
 pub struct Binding {
     pub name: Sp<Ident>,
     pub arrow_span: CodeSpan,
+    /// Whether the binding was made with `↩`
+    pub mutable: bool,
+    /// Whether the binding is a code macro, marked with `^` after the arrow
+    pub code_macro: bool,
     pub signature: Option<Sp<Signature>>,
+    /// Patterns for the values the binding takes and returns, given in place of a signature
+    pub annotation: Option<Sp<Annotation>>,
     pub words: Vec<Sp<Word>>,
+    /// The comments on the lines just before the binding, joined
+    pub doc: Option<String>,
+}
+
+impl Binding {
+    /// The span of the whole binding, from its name to the end of its body
+    pub fn span(&self) -> CodeSpan {
+        let end = (self.words.last().map(|word| &word.span))
+            .or(self.annotation.as_ref().map(|annotation| &annotation.span))
+            .or(self.signature.as_ref().map(|sig| &sig.span))
+            .unwrap_or(&self.arrow_span);
+        self.name.span.clone().merge(end.clone())
+    }
+}
+
+/// Patterns that the values a binding takes and returns must match, like `|[N] [N].[]|`
+///
+/// These are checked at runtime when the binding is called or assigned.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Annotation {
+    /// Patterns for the arguments, starting with the top of the stack
+    pub args: Vec<Sp<ValuePattern>>,
+    /// Patterns for the outputs, given after a `.`
+    pub outputs: Option<Vec<Sp<ValuePattern>>>,
+}
+
+impl Annotation {
+    /// The signature implied by the number of patterns
+    pub fn signature(&self) -> Signature {
+        let outputs = self.outputs.as_ref().map_or(1, Vec::len);
+        Signature::new(self.args.len(), outputs)
+    }
+    /// The pattern of the value if the annotation only describes a single value, like `|.[3]|`
+    pub fn value_pattern(&self) -> Option<&Sp<ValuePattern>> {
+        match (self.args.as_slice(), self.outputs.as_deref()) {
+            ([], Some([pattern])) => Some(pattern),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", arg.value)?;
+        }
+        if let Some(outputs) = &self.outputs {
+            write!(f, ".")?;
+            for (i, output) in outputs.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " ")?;
+                }
+                write!(f, "{}", output.value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A pattern for the type and shape of a value, like `Char[_ 3]`
+///
+/// `_` alone matches any value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ValuePattern {
+    pub ty: Option<ValueType>,
+    /// The patterns for each axis, if the shape is constrained
+    pub shape: Option<Vec<DimPattern>>,
+}
+
+impl fmt::Display for ValuePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.ty, &self.shape) {
+            (None, None) => write!(f, "_"),
+            (ty, shape) => {
+                if let Some(ty) = ty {
+                    write!(f, "{ty}")?;
+                }
+                if let Some(shape) = shape {
+                    write!(f, "[")?;
+                    for (i, dim) in shape.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, " ")?;
+                        }
+                        write!(f, "{dim}")?;
+                    }
+                    write!(f, "]")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The type of a [`ValuePattern`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    Num,
+    Char,
+    Box,
+}
+
+impl ValueType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "Num" => ValueType::Num,
+            "Char" => ValueType::Char,
+            "Box" => ValueType::Box,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A pattern for the length of one axis of a [`ValuePattern`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DimPattern {
+    /// `_`, any length
+    Any,
+    /// An exact length
+    Len(usize),
+    /// A name, which must have the same length everywhere it appears in an [`Annotation`]
+    Named(Ident),
+    /// `*`, any number of axes of any length
+    Rest,
+}
+
+impl fmt::Display for DimPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DimPattern::Any => write!(f, "_"),
+            DimPattern::Len(n) => write!(f, "{n}"),
+            DimPattern::Named(name) => write!(f, "{name}"),
+            DimPattern::Rest => write!(f, "*"),
+        }
+    }
 }
 
+/// A word of code
+///
+/// Words in a line are stored in the order they are written, not the order they are run.
 #[derive(Clone)]
 pub enum Word {
     Number(String, f64),
     Char(String),
     String(String),
-    FormatString(Vec<String>),
-    MultilineString(Vec<Sp<Vec<String>>>),
+    FormatString(FormatString),
+    MultilineString(Vec<Sp<FormatString>>),
     Ident(Ident),
     Strand(Vec<Sp<Word>>),
     Array(Arr),
@@ -39,8 +201,12 @@ pub enum Word {
     Primitive(Primitive),
     Modified(Box<Modified>),
     Placeholder(Signature),
+    Local(Box<LocalBinding>),
+    Destructure(Box<Destructure>),
     Comment(String),
     Spaces,
+    /// A `|` between the rows of an array literal
+    RowBreak,
 }
 
 impl Word {
@@ -55,23 +221,10 @@ impl fmt::Debug for Word {
             Word::Number(s, _) => write!(f, "{s:?}"),
             Word::Char(char) => write!(f, "{char:?}"),
             Word::String(string) => write!(f, "{string:?}"),
-            Word::FormatString(parts) => {
-                write!(f, "$\"")?;
-                for part in parts {
-                    let escaped = format!("{part:?}");
-                    let part = &escaped[1..escaped.len() - 1];
-                    write!(f, "{part}")?;
-                }
-                write!(f, "\"")
-            }
+            Word::FormatString(fs) => write!(f, "$\"{fs:?}\""),
             Word::MultilineString(lines) => {
                 for line in lines {
-                    write!(f, "$ ")?;
-                    for part in &line.value {
-                        let escaped = format!("{part:?}");
-                        let part = &escaped[1..escaped.len() - 1];
-                        write!(f, "{part}")?;
-                    }
+                    write!(f, "$ {:?}", line.value)?;
                 }
                 Ok(())
             }
@@ -84,18 +237,77 @@ impl fmt::Debug for Word {
             Word::Primitive(prim) => prim.fmt(f),
             Word::Modified(modified) => modified.fmt(f),
             Word::Spaces => write!(f, "' '"),
+            Word::RowBreak => write!(f, "|"),
             Word::Comment(comment) => write!(f, "# {comment}"),
             Word::Placeholder(sig) => write!(f, "^{}.{}", sig.args, sig.outputs),
+            Word::Local(local) => local.fmt(f),
+            Word::Destructure(des) => des.fmt(f),
+        }
+    }
+}
+
+/// The fragments of a format string and the arguments between them
+#[derive(Clone)]
+pub struct FormatString {
+    pub frags: Vec<String>,
+    /// One argument for each gap between fragments
+    pub args: Vec<FormatArg>,
+}
+
+/// What fills a gap in a [`FormatString`]
+#[derive(Clone)]
+pub enum FormatArg {
+    /// A `_`, filled by a value from the stack
+    Stack,
+    /// A `\{…}`, filled by the value of the code inside it
+    Code(Sp<Vec<Sp<Word>>>),
+}
+
+impl FormatString {
+    /// The number of arguments taken from the stack
+    pub fn stack_args(&self) -> usize {
+        (self.args.iter())
+            .filter(|arg| matches!(arg, FormatArg::Stack))
+            .count()
+    }
+}
+
+impl fmt::Debug for FormatString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, frag) in self.frags.iter().enumerate() {
+            if i > 0 {
+                match &self.args[i - 1] {
+                    FormatArg::Stack => write!(f, "_")?,
+                    FormatArg::Code(words) => {
+                        write!(f, "\\{{")?;
+                        for word in &words.value {
+                            write!(f, "{:?}", word.value)?;
+                        }
+                        write!(f, "}}")?;
+                    }
+                }
+            }
+            let escaped = format!("{frag:?}");
+            write!(f, "{}", &escaped[1..escaped.len() - 1])?;
         }
+        Ok(())
     }
 }
 
 #[derive(Clone)]
 pub struct Arr {
     pub lines: Vec<Vec<Sp<Word>>>,
+    /// Whether the array is a box array written with `{}`
     pub constant: bool,
 }
 
+impl Arr {
+    /// Whether the rows of the array are separated with `|`s
+    pub fn has_row_breaks(&self) -> bool {
+        (self.lines.iter().flatten()).any(|word| matches!(word.value, Word::RowBreak))
+    }
+}
+
 impl fmt::Debug for Arr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut d = f.debug_tuple("arr");
@@ -128,6 +340,66 @@ impl fmt::Debug for Func {
     }
 }
 
+/// A binding of a value to a name that is only visible in the rest of a function
+#[derive(Clone)]
+pub struct LocalBinding {
+    pub name: Sp<Ident>,
+    pub arrow_span: CodeSpan,
+    /// Whether the binding was made with `↩`, reassigning an existing name
+    pub mutable: bool,
+    pub words: Vec<Sp<Word>>,
+}
+
+impl fmt::Debug for LocalBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_tuple("local");
+        d.field(&self.name.value);
+        for word in &self.words {
+            d.field(&word.value);
+        }
+        d.finish()
+    }
+}
+
+/// A binding of several names at once
+///
+/// With brackets, each row of an array is bound to its own name.
+/// Without them, each name takes a value from the stack.
+#[derive(Clone)]
+pub struct Destructure {
+    pub names: Vec<Sp<Ident>>,
+    pub brackets: Option<(CodeSpan, CodeSpan)>,
+    pub arrow_span: CodeSpan,
+    pub words: Vec<Sp<Word>>,
+}
+
+impl Destructure {
+    /// The span of the opening bracket, or of the first name if there are no brackets
+    pub fn span(&self) -> CodeSpan {
+        match &self.brackets {
+            Some((open, _)) => open.clone(),
+            None => self.names[0].span.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for Destructure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_tuple("destructure");
+        d.field(
+            &self
+                .names
+                .iter()
+                .map(|name| &name.value)
+                .collect::<Vec<_>>(),
+        );
+        for word in &self.words {
+            d.field(&word.value);
+        }
+        d.finish()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Switch {
     pub branches: Vec<Sp<Func>>,
@@ -172,3 +444,154 @@ impl Modifier {
         }
     }
 }
+
+/// A visitor over the syntax tree
+///
+/// Each method's default implementation visits the node's children with the matching `walk_*` function.
+/// Override the methods for the nodes you are interested in,
+/// and call the `walk_*` function from them to keep visiting children.
+///
+/// ```
+/// use uiua::{ast::Visitor, parse, CodeSpan, Ident};
+///
+/// #[derive(Default)]
+/// struct Idents(Vec<String>);
+///
+/// impl Visitor for Idents {
+///     fn visit_ident(&mut self, ident: &Ident, span: &CodeSpan) {
+///         self.0.push(format!("{ident} at {}", span.start));
+///     }
+/// }
+///
+/// let (items, _, _) = parse("F ← +1\nF (F 2)", None);
+/// let mut idents = Idents::default();
+/// idents.visit_items(&items);
+/// assert_eq!(idents.0, ["F at 2:1", "F at 2:4"]);
+/// ```
+pub trait Visitor {
+    fn visit_items(&mut self, items: &[Item]) {
+        for item in items {
+            self.visit_item(item);
+        }
+    }
+    fn visit_item(&mut self, item: &Item) {
+        walk_item(self, item);
+    }
+    fn visit_binding(&mut self, binding: &Binding) {
+        walk_binding(self, binding);
+    }
+    fn visit_destructure(&mut self, des: &Destructure) {
+        walk_destructure(self, des);
+    }
+    fn visit_words(&mut self, words: &[Sp<Word>]) {
+        for word in words {
+            self.visit_word(word);
+        }
+    }
+    fn visit_word(&mut self, word: &Sp<Word>) {
+        walk_word(self, word);
+    }
+    fn visit_func(&mut self, func: &Func, span: &CodeSpan) {
+        let _ = span;
+        walk_func(self, func);
+    }
+    fn visit_modified(&mut self, modified: &Modified) {
+        walk_modified(self, modified);
+    }
+    /// Visit a reference to a binding, including one used as a modifier
+    fn visit_ident(&mut self, ident: &Ident, span: &CodeSpan) {
+        let _ = (ident, span);
+    }
+    /// Visit a primitive, including one used as a modifier or in an ocean
+    fn visit_primitive(&mut self, prim: Primitive, span: &CodeSpan) {
+        let _ = (prim, span);
+    }
+}
+
+/// Visit the children of an [`Item`]
+pub fn walk_item<V: Visitor + ?Sized>(visitor: &mut V, item: &Item) {
+    match item {
+        Item::TestScope(items) => visitor.visit_items(items),
+        Item::Words(words) => visitor.visit_words(words),
+        Item::Binding(binding) => visitor.visit_binding(binding),
+        Item::Destructure(des) => visitor.visit_destructure(des),
+        Item::ExtraNewlines(_) => {}
+    }
+}
+
+/// Visit the words of a [`Binding`]
+pub fn walk_binding<V: Visitor + ?Sized>(visitor: &mut V, binding: &Binding) {
+    visitor.visit_words(&binding.words);
+}
+
+/// Visit the words of a [`Destructure`]
+pub fn walk_destructure<V: Visitor + ?Sized>(visitor: &mut V, des: &Destructure) {
+    visitor.visit_words(&des.words);
+}
+
+/// Visit the children of a [`Word`]
+pub fn walk_word<V: Visitor + ?Sized>(visitor: &mut V, word: &Sp<Word>) {
+    match &word.value {
+        Word::Ident(ident) => visitor.visit_ident(ident, &word.span),
+        Word::Primitive(prim) => visitor.visit_primitive(*prim, &word.span),
+        Word::Ocean(prims) => {
+            for prim in prims {
+                visitor.visit_primitive(prim.value, &prim.span);
+            }
+        }
+        Word::Strand(items) => visitor.visit_words(items),
+        Word::Array(arr) => {
+            for line in &arr.lines {
+                visitor.visit_words(line);
+            }
+        }
+        Word::Func(func) => visitor.visit_func(func, &word.span),
+        Word::Switch(sw) => {
+            for branch in &sw.branches {
+                visitor.visit_func(&branch.value, &branch.span);
+            }
+        }
+        Word::Modified(modified) => visitor.visit_modified(modified),
+        Word::Local(local) => visitor.visit_words(&local.words),
+        Word::Destructure(des) => visitor.visit_destructure(des),
+        Word::FormatString(fs) => walk_format_str(visitor, fs),
+        Word::MultilineString(lines) => {
+            for line in lines {
+                walk_format_str(visitor, &line.value);
+            }
+        }
+        Word::Number(..)
+        | Word::Char(_)
+        | Word::String(_)
+        | Word::Placeholder(_)
+        | Word::Comment(_)
+        | Word::Spaces
+        | Word::RowBreak => {}
+    }
+}
+
+/// Visit the code interpolated into a [`FormatString`]
+pub fn walk_format_str<V: Visitor + ?Sized>(visitor: &mut V, fs: &FormatString) {
+    for arg in &fs.args {
+        if let FormatArg::Code(words) = arg {
+            visitor.visit_words(&words.value);
+        }
+    }
+}
+
+/// Visit the lines of a [`Func`]
+pub fn walk_func<V: Visitor + ?Sized>(visitor: &mut V, func: &Func) {
+    for line in &func.lines {
+        visitor.visit_words(line);
+    }
+}
+
+/// Visit the modifier and operands of a [`Modified`]
+pub fn walk_modified<V: Visitor + ?Sized>(visitor: &mut V, modified: &Modified) {
+    let span = &modified.modifier.span;
+    match &modified.modifier.value {
+        Modifier::Primitive(prim) => visitor.visit_primitive(*prim, span),
+        Modifier::Ident(ident) => visitor.visit_ident(ident, span),
+    }
+    visitor.visit_words(&modified.operands);
+}
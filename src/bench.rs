@@ -0,0 +1,86 @@
+//! Timing Uiua programs
+//!
+//! This is what the benchmarks in `benches/` use to time the programs in `benches/programs`.
+
+use std::{fmt, time::Duration};
+
+use instant::Instant;
+
+use crate::{NullSys, Uiua, UiuaResult};
+
+/// The times of several runs of a program, from fastest to slowest
+#[derive(Debug, Clone)]
+pub struct BenchTimes {
+    times: Vec<Duration>,
+}
+
+impl BenchTimes {
+    /// The time of each run, from fastest to slowest
+    pub fn times(&self) -> &[Duration] {
+        &self.times
+    }
+    pub fn min(&self) -> Duration {
+        self.times.first().copied().unwrap_or_default()
+    }
+    pub fn max(&self) -> Duration {
+        self.times.last().copied().unwrap_or_default()
+    }
+    pub fn median(&self) -> Duration {
+        self.times
+            .get(self.times.len() / 2)
+            .copied()
+            .unwrap_or_default()
+    }
+    pub fn mean(&self) -> Duration {
+        if self.times.is_empty() {
+            return Duration::ZERO;
+        }
+        self.times.iter().sum::<Duration>() / self.times.len() as u32
+    }
+}
+
+impl fmt::Display for BenchTimes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "median {:.2?} (min {:.2?}, mean {:.2?}, max {:.2?}) over {} runs",
+            self.median(),
+            self.min(),
+            self.mean(),
+            self.max(),
+            self.times.len()
+        )
+    }
+}
+
+/// Run some code several times and time each run
+///
+/// Each run compiles and runs the code from scratch with a [`NullSys`] backend,
+/// so the times do not depend on the environment. Any error stops the benchmark.
+pub fn bench(code: &str, runs: usize) -> UiuaResult<BenchTimes> {
+    let mut times = Vec::with_capacity(runs);
+    for _ in 0..runs {
+        let mut env = Uiua::with_backend(NullSys);
+        let start = Instant::now();
+        env.load_str(code)?;
+        times.push(start.elapsed());
+    }
+    times.sort_unstable();
+    Ok(BenchTimes { times })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bench_programs() {
+        for entry in std::fs::read_dir("benches/programs").unwrap() {
+            let path = entry.unwrap().path();
+            let code = std::fs::read_to_string(&path).unwrap();
+            if let Err(e) = bench(&code, 1) {
+                panic!("Bench program {} failed:\n{}", path.display(), e.report());
+            }
+        }
+    }
+}
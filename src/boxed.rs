@@ -3,12 +3,26 @@ use std::{
     fmt,
 };
 
-use crate::value::Value;
+use crate::{value::Value, Uiua, UiuaResult};
+
+/// The maximum number of boxes that can be nested inside each other
+///
+/// Formatting and dropping values recurses through boxes, so deeper nesting could overflow the stack.
+pub(crate) const MAX_BOX_DEPTH: usize = 256;
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct Boxed(pub Value);
 
 impl Boxed {
+    /// Box a value, making sure that boxes are not nested too deeply
+    pub(crate) fn checked(value: Value, env: &Uiua) -> UiuaResult<Self> {
+        if value.box_depth() >= MAX_BOX_DEPTH {
+            return Err(env.error(format!(
+                "Boxes cannot be nested more than {MAX_BOX_DEPTH} deep"
+            )));
+        }
+        Ok(Boxed(value))
+    }
     pub fn as_value(&self) -> &Value {
         &self.0
     }
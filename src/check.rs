@@ -24,8 +24,13 @@ pub(crate) fn instrs_signature(instrs: &[Instr]) -> Result<Signature, String> {
         function_stack: Vec::new(),
         array_stack: Vec::new(),
         min_height: START_HEIGHT,
+        break_heights: Vec::new(),
     };
     env.instrs(instrs)?;
+    // Breaking or continuing early must leave the stack as the end of the function would
+    if env.break_heights.iter().any(|&h| h != env.stack.len()) {
+        return Err("break or continue changes the stack height".into());
+    }
     let args = START_HEIGHT.saturating_sub(env.min_height);
     let outputs = env.stack.len() - env.min_height;
     // println!("Checked {:?} -> {}/{}", instrs, args, outputs);
@@ -38,6 +43,8 @@ struct VirtualEnv<'a> {
     function_stack: Vec<Cow<'a, Function>>,
     array_stack: Vec<usize>,
     min_height: usize,
+    /// The stack height at each break or continue
+    break_heights: Vec<usize>,
 }
 
 #[derive(Debug, Clone)]
@@ -87,12 +94,16 @@ impl<'a> VirtualEnv<'a> {
         use Primitive::*;
         match instr {
             Instr::Push(val) => self.stack.push(BasicValue::from_val(val)),
+            // Signatures are inferred before constants are pooled
+            Instr::PushConst(_) => self.stack.push(BasicValue::Unknown),
             Instr::BeginArray => self.array_stack.push(self.stack.len()),
             Instr::EndArray { .. } => {
                 let bottom = self
                     .array_stack
                     .pop()
                     .ok_or("EndArray without BeginArray")?;
+                // The array's code may have taken values from below where it started
+                let bottom = bottom.min(self.stack.len());
                 let mut items: Vec<_> = self.stack.drain(bottom..).collect();
                 items.reverse();
                 self.stack.push(BasicValue::Arr(items));
@@ -146,7 +157,10 @@ impl<'a> VirtualEnv<'a> {
                 }
             }
             Instr::Dynamic(f) => self.handle_sig(f.signature)?,
-            Instr::DropTempInline { .. } => {}
+            Instr::DropTempInline { .. } | Instr::InitLocals { .. } => {}
+            Instr::SetLocal { .. } | Instr::SetGlobal { .. } => self.handle_args_outputs(1, 0)?,
+            Instr::GetLocal { .. } | Instr::GetGlobal { .. } => self.handle_args_outputs(0, 1)?,
+            Instr::Unpack { count, .. } => self.handle_args_outputs(1, *count)?,
             Instr::Prim(prim, _) => match prim {
                 Reduce | Scan => {
                     let sig = self.pop_func()?.signature();
@@ -192,15 +206,32 @@ impl<'a> VirtualEnv<'a> {
                     };
                     self.handle_args_outputs(args, outputs)?;
                 }
+                Memo => {
+                    let sig = self.pop_func()?.signature();
+                    self.handle_sig(sig)?;
+                }
                 Spawn => {
                     let sig = self.pop_func()?.signature();
                     self.handle_args_outputs(sig.args, 1)?;
                 }
+                Break | Continue => {
+                    // Leaving more than one loop cannot be checked
+                    if let BasicValue::Num(n) = self.pop()? {
+                        if n > 1.0 {
+                            return Err(format!("{} present", prim.name()));
+                        }
+                    }
+                    self.set_min_height();
+                    self.break_heights.push(self.stack.len());
+                }
                 Repeat => {
                     let f = self.pop_func()?;
                     let n = self.pop()?;
-                    // Break anywhere but the end of the function prevents signature checking.
-                    if instrs_contain_break(&f.instrs) {
+                    // Breaking early changes the number of iterations,
+                    // which only matters if each iteration changes the stack height.
+                    if f.signature().args != f.signature().outputs
+                        && instrs_contain_break(&f.instrs)
+                    {
                         return Err("break present".into());
                     }
                     if let BasicValue::Num(n) = n {
@@ -254,6 +285,26 @@ impl<'a> VirtualEnv<'a> {
                         }
                     }
                 }
+                Do => {
+                    let body = self.pop_func()?;
+                    let cond = self.pop_func()?;
+                    let body_sig = body.signature();
+                    let cond_sig = cond.signature();
+                    if body_sig.args != body_sig.outputs {
+                        return Err(format!(
+                            "do's body must have the same number of arguments and outputs, \
+                            but its signature is {body_sig}"
+                        ));
+                    }
+                    if cond_sig.outputs != cond_sig.args + 1 {
+                        return Err(format!(
+                            "do's condition must have one more output than arguments, \
+                            but its signature is {cond_sig}"
+                        ));
+                    }
+                    let args = body_sig.args.max(cond_sig.args);
+                    self.handle_args_outputs(args, args)?;
+                }
                 Bind => {
                     let f = self.pop_func()?;
                     let g = self.pop_func()?;
@@ -372,7 +423,7 @@ impl<'a> VirtualEnv<'a> {
                         // but the "no inverse found" error is more useful.
                     }
                 }
-                Fill => {
+                Fill | Tolerance => {
                     let fill = self.pop_func()?;
                     self.handle_sig(fill.signature())?;
                     let _fill_value = self.pop()?;
@@ -421,6 +472,11 @@ impl<'a> VirtualEnv<'a> {
                     self.set_min_height();
                     self.handle_sig(f.signature())?;
                 }
+                TimeIt => {
+                    let f = self.pop_func()?;
+                    self.handle_sig(f.signature())?;
+                    self.stack.push(BasicValue::Other);
+                }
                 Join => {
                     let a = self.pop()?;
                     let b = self.pop()?;
@@ -571,7 +627,8 @@ mod test {
                 push(1),
                 EndArray {
                     span: 0,
-                    boxed: false
+                    boxed: false,
+                    row_spans: None,
                 }
             ])
         );
@@ -584,10 +641,27 @@ mod test {
                 push(1),
                 EndArray {
                     span: 0,
-                    boxed: false
+                    boxed: false,
+                    row_spans: None,
                 },
                 Prim(Add, 0)
             ])
         );
+
+        // Break must not change the stack height
+        assert_eq!(
+            Ok(sig(1, 1)),
+            check(&[
+                push(2),
+                Prim(Mul, 0),
+                Prim(Dup, 0),
+                push(10),
+                Prim(Gt, 0),
+                Prim(Break, 0)
+            ])
+        );
+        assert!(check(&[push(1), Prim(Break, 0), push(2), Prim(Mul, 0)]).is_ok());
+        assert!(check(&[push(1), Prim(Break, 0), push(2)]).is_err());
+        assert!(check(&[push(2), Prim(Break, 0)]).is_err());
     }
 }
@@ -1,22 +1,26 @@
 use std::{
-    collections::hash_map::DefaultHasher,
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
     hash::{Hash, Hasher},
+    iter::once,
+    mem::take,
     sync::Arc,
 };
 
 use crate::{
     algorithm::invert::under_instrs,
+    annotation,
     array::Array,
     ast::*,
     boxed::Boxed,
     check::instrs_signature,
     function::*,
     lex::{CodeSpan, Sp, Span},
-    parse::{count_placeholders, ident_modifier_args},
-    primitive::{ImplPrimitive, Primitive},
-    run::{Global, RunMode},
+    parse::{count_placeholders, ident_modifier_args, parse},
+    primitive::{ImplPrimitive, Primitive, CONSTANTS},
+    run::{BindingRef, BindingUsage, Global, RunMode},
+    shape_check,
     value::Value,
-    Diagnostic, DiagnosticKind, Ident, SysOp, UiuaError, UiuaResult,
+    Diagnostic, DiagnosticKind, Ident, UiuaError, UiuaResult, UnknownIdent,
 };
 
 use crate::Uiua;
@@ -32,7 +36,7 @@ impl Uiua {
         fn words_have_import(words: &[Sp<Word>]) -> bool {
             words
                 .iter()
-                .any(|w| matches!(w.value, Word::Primitive(Primitive::Sys(SysOp::Import))))
+                .any(|w| matches!(w.value, Word::Primitive(Primitive::Sys(op)) if op.is_import()))
         }
         fn words_are_export(words: &[Sp<Word>]) -> bool {
             let [word] = words else {
@@ -59,7 +63,11 @@ impl Uiua {
         }
         match item {
             Item::TestScope(items) => {
-                self.in_scope(|env| env.items(items, true))?;
+                self.in_scope(|env| {
+                    env.items(items, true)?;
+                    env.warn_unused_bindings();
+                    Ok(())
+                })?;
             }
             Item::Words(words) => {
                 let can_run = match self.mode {
@@ -67,7 +75,11 @@ impl Uiua {
                     RunMode::Test => in_test,
                     RunMode::All => true,
                 };
-                if can_run || words_have_import(&words) || words_are_export(&words) {
+                if self.compile_only
+                    || can_run
+                    || words_have_import(&words)
+                    || words_are_export(&words)
+                {
                     let span = words
                         .first()
                         .unwrap()
@@ -80,7 +92,21 @@ impl Uiua {
                             .into());
                     }
                     let instrs = self.compile_words(words, true)?;
-                    self.exec_global_instrs(instrs)?;
+                    if self.lints {
+                        self.lint_instrs(&instrs);
+                    }
+                    self.check_shapes(&instrs, None);
+                    if !self.compile_only {
+                        let args = (self.leak_checks)
+                            .then(|| instrs_signature(&instrs).map_or(0, |sig| sig.args));
+                        let height = self.stack.len();
+                        self.exec_global_instrs(instrs)?;
+                        if let Some(args) = args {
+                            self.check_line_leaks(span, height, args);
+                        }
+                    }
+                } else {
+                    self.mark_words_used(&words);
                 }
             }
             Item::Binding(binding) => {
@@ -88,8 +114,28 @@ impl Uiua {
                     RunMode::Normal => !in_test,
                     RunMode::All | RunMode::Test => true,
                 };
-                if can_run || words_have_import(&binding.words) {
+                if self.compile_only || can_run || words_have_import(&binding.words) {
+                    let name = binding.name.value;
+                    let doc = binding.doc.clone();
                     self.binding(binding)?;
+                    if let Some((doc, &index)) = doc.zip(self.scope.names.get(&name)) {
+                        self.binding_docs.lock().insert(index, doc);
+                    }
+                } else {
+                    let prev_binding = self.current_binding.replace(binding.name.span);
+                    self.mark_words_used(&binding.words);
+                    self.current_binding = prev_binding;
+                }
+            }
+            Item::Destructure(des) => {
+                let can_run = match self.mode {
+                    RunMode::Normal => !in_test,
+                    RunMode::All | RunMode::Test => true,
+                };
+                if self.compile_only || can_run || words_have_import(&des.words) {
+                    self.destructure(des)?;
+                } else {
+                    self.mark_words_used(&des.words);
                 }
             }
             Item::ExtraNewlines(_) => {}
@@ -97,6 +143,12 @@ impl Uiua {
         Ok(())
     }
     fn binding(&mut self, binding: Binding) -> UiuaResult {
+        if binding.mutable {
+            return self.mutable_binding(binding);
+        }
+        if binding.code_macro {
+            return self.code_macro_binding(binding);
+        }
         let name = binding.name.value;
         let span = &binding.name.span;
 
@@ -115,18 +167,28 @@ impl Uiua {
                     }
                 }
             }
-            Function::new(FunctionId::Named(name.clone()), instrs, sig)
+            Function::new(FunctionId::Named(name), instrs, sig)
         };
 
         let placeholder_count = count_placeholders(&binding.words);
         // Compile the body
-        let mut instrs = self.compile_words(binding.words, true)?;
+        let prev_binding = self.current_binding.replace(span.clone());
+        let instrs = self.compile_words(binding.words, true);
+        self.current_binding = prev_binding;
+        let mut instrs = instrs?;
+        if self.lints {
+            self.lint_instrs(&instrs);
+        }
         // Handle placeholders
         if placeholder_count > 0 {
             increment_placeholders(&mut instrs);
             instrs.insert(0, Instr::PushTempFunctions(placeholder_count));
             instrs.push(Instr::PopTempFunctions(placeholder_count));
         }
+        if let Some(annotation) = &binding.annotation {
+            annotation::validate(&annotation.value)?;
+        }
+        self.check_shapes(&instrs, binding.annotation.as_ref().map(|a| (&name, a)));
         // Resolve signature
         match instrs_signature(&instrs) {
             Ok(mut sig) => {
@@ -142,16 +204,44 @@ impl Uiua {
                         )));
                     }
                 }
+                if let Some(annotation) = binding.annotation {
+                    instrs = self.annotate_instrs(&name, span, annotation.value, instrs, sig);
+                }
                 if let [Instr::PushFunc(f)] = instrs.as_slice() {
                     self.bind_function(name, f.clone(), span)?;
                 } else if sig.args == 0
                     && (sig.outputs > 0 || instrs.is_empty())
                     && placeholder_count == 0
                 {
+                    if self.compile_only {
+                        // Bind the code itself so that uses of the binding can still be checked
+                        let func = make_fn(instrs, sig, self);
+                        self.bind_function(name, func.into(), span)?;
+                        let index = self.scope.names[&name];
+                        if let Some(binding) = self.scope.bindings.get_mut(&index) {
+                            binding.eager = true;
+                        }
+                        return Ok(());
+                    }
+                    // Reuse the value from a previous run if nothing it depends on has changed
+                    let key = (sig == Signature::new(0, 1))
+                        .then(|| self.binding_key(&instrs))
+                        .flatten();
+                    if let Some([value]) = key.and_then(|key| self.memo_file_get(key)).as_deref() {
+                        return self.bind_value(name, value.clone(), span);
+                    }
                     self.exec_global_instrs(instrs)?;
                     if let Some(f) = self.function_stack.pop() {
                         self.bind_function(name, f, span)?;
+                        let index = self.scope.names[&name];
+                        if let Some(binding) = self.scope.bindings.get_mut(&index) {
+                            binding.eager = true;
+                        }
                     } else if let Some(value) = self.stack.pop() {
+                        // Metadata is not persisted, so values that have it are always recomputed
+                        if let Some(key) = key.filter(|_| value.meta().is_none()) {
+                            self.memo_file_insert(key, vec![value.clone()]);
+                        }
                         self.bind_value(name, value, span)?;
                     } else {
                         let func = make_fn(Vec::new(), sig, self);
@@ -164,6 +254,10 @@ impl Uiua {
             }
             Err(e) => {
                 if let Some(sig) = binding.signature {
+                    if let Some(annotation) = binding.annotation {
+                        instrs =
+                            self.annotate_instrs(&name, span, annotation.value, instrs, sig.value);
+                    }
                     let func = make_fn(instrs, sig.value, self);
                     self.bind_function(name, func.into(), span)?;
                 } else {
@@ -175,13 +269,232 @@ impl Uiua {
         }
         Ok(())
     }
+    /// Wrap the instructions of a binding so that they check its annotation
+    fn annotate_instrs(
+        &mut self,
+        name: &Ident,
+        span: &CodeSpan,
+        annotation: Annotation,
+        mut instrs: Vec<Instr>,
+        sig: Signature,
+    ) -> Vec<Instr> {
+        match (instrs.as_slice(), annotation.value_pattern()) {
+            ([Instr::PushFunc(f)], _) => {
+                let f = annotation::annotated(*name, annotation.clone(), f.clone());
+                self.register_annotated(name, annotation, &f);
+                vec![Instr::push_func(f)]
+            }
+            // A value is checked once, where it is bound
+            (_, Some(pattern)) if sig == Signature::new(0, 1) => {
+                let name = span.clone().sp(*name);
+                instrs.push(annotation::value_check(name, pattern.clone()));
+                instrs
+            }
+            (..) => {
+                let f = Function::new(FunctionId::Named(*name), instrs, sig);
+                let f = annotation::annotated(*name, annotation.clone(), f.into());
+                self.register_annotated(name, annotation, &f);
+                f.unpooled_instrs().into_owned()
+            }
+        }
+    }
+    /// Remember the annotation of an annotated function so that calls to it can be shape checked
+    fn register_annotated(&mut self, name: &Ident, annotation: Annotation, f: &Function) {
+        if let [Instr::Dynamic(check)] = f.instrs.as_slice() {
+            self.annotated_fns.insert(check.id, (*name, annotation));
+        }
+    }
+    /// Report arrays whose shapes are certain not to fit, if shape checks are enabled
+    fn check_shapes(&mut self, instrs: &[Instr], binding: Option<(&Ident, &Sp<Annotation>)>) {
+        if !self.shape_checks {
+            return;
+        }
+        let problems = shape_check::check_shapes(
+            instrs,
+            binding.map(|(name, annotation)| (name, &annotation.value)),
+            &self.annotated_fns,
+        );
+        for problem in problems {
+            let span = match (problem.span, binding) {
+                (Some(span), _) => self.get_span(span),
+                (None, Some((_, annotation))) => annotation.span.clone().into(),
+                (None, None) => continue,
+            };
+            let diagnostic = Diagnostic::new(problem.message, span, DiagnosticKind::Warning);
+            self.diagnostics.insert(diagnostic);
+        }
+        self.flush_diagnostics();
+    }
+    fn mutable_binding(&mut self, binding: Binding) -> UiuaResult {
+        let name = binding.name.value;
+        let span = binding.name.span;
+        if count_placeholders(&binding.words) > 0 {
+            return Err(span
+                .sp(format!("Mutable binding {name} cannot use placeholders"))
+                .into());
+        }
+        let mut instrs = self.compile_words(binding.words, true)?;
+        let index = if let Some(index) = self.find_global(&name) {
+            if !matches!(self.globals.lock()[index], Global::Var(_)) {
+                return Err(span
+                    .sp(format!(
+                        "{name} was bound with `←` and cannot be reassigned. \
+                        Bind it with `↩` to make it mutable."
+                    ))
+                    .into());
+            }
+            self.record_binding_ref(index, &name, &span, false);
+            index
+        } else {
+            self.validate_binding_name(&name, &[], &span)?;
+            let mut globals = self.globals.lock();
+            let index = globals.len();
+            globals.push(Global::Var(Value::default()));
+            drop(globals);
+            self.declare_binding(name, index, &span, true);
+            index
+        };
+        if let Some(annotation) = binding.annotation {
+            annotation::validate(&annotation.value)?;
+            let Some(pattern) = annotation.value.value_pattern() else {
+                return Err(annotation
+                    .span
+                    .sp(format!(
+                        "Mutable binding {name} can only annotate its value, like `|.[3]|`"
+                    ))
+                    .into());
+            };
+            self.var_annotations.insert(index, pattern.clone());
+        }
+        if let Some(pattern) = self.var_annotations.get(&index) {
+            instrs.push(annotation::value_check(
+                span.clone().sp(name),
+                pattern.clone(),
+            ));
+        }
+        let span = self.add_span(span);
+        instrs.push(Instr::SetGlobal { index, span });
+        if self.compile_only {
+            return Ok(());
+        }
+        self.exec_global_instrs(instrs)
+    }
+    fn code_macro_binding(&mut self, binding: Binding) -> UiuaResult {
+        let name = binding.name.value;
+        let span = binding.name.span;
+        if ident_modifier_args(&name) == 0 {
+            return Err(span
+                .sp(format!(
+                    "Code macro {name} must have a name that ends in `!`"
+                ))
+                .into());
+        }
+        if count_placeholders(&binding.words) > 0 {
+            return Err(span
+                .sp(format!("Code macro {name} cannot use placeholders"))
+                .into());
+        }
+        let prev_binding = self.current_binding.replace(span.clone());
+        let instrs = self.compile_words(binding.words, true);
+        self.current_binding = prev_binding;
+        let instrs = instrs?;
+        if self.lints {
+            self.lint_instrs(&instrs);
+        }
+        let sig = match (instrs_signature(&instrs), binding.signature) {
+            (Ok(sig), Some(declared)) if sig != declared.value => {
+                return Err(UiuaError::Run(Span::Code(declared.span).sp(format!(
+                    "Function signature mismatch:  declared {} but inferred {}",
+                    declared.value, sig
+                ))))
+            }
+            (Ok(sig), _) => sig,
+            (Err(_), Some(declared)) => declared.value,
+            (Err(e), None) => {
+                return Err(span
+                    .sp(format!("Cannot infer function signature: {e}"))
+                    .into())
+            }
+        };
+        if sig != (1, 1) {
+            return Err(span
+                .sp(format!(
+                    "Code macros must take 1 argument and return 1 value, \
+                    but {name}'s signature is {sig}"
+                ))
+                .into());
+        }
+        let func = Function::new(FunctionId::Named(name), instrs, sig);
+        let mut globals = self.globals.lock();
+        let index = globals.len();
+        globals.push(Global::Macro(func.into()));
+        drop(globals);
+        self.declare_binding(name, index, &span, false);
+        Ok(())
+    }
+    fn destructure(&mut self, des: Destructure) -> UiuaResult {
+        let instrs = self.destructure_instrs(&des)?;
+        if self.compile_only {
+            // The values are unknown, so bind functions that stand in for them
+            for name in des.names {
+                let id = FunctionId::Named(name.value);
+                let func = Function::new(id, Vec::new(), Signature::new(0, 1));
+                self.bind_function(name.value, func.into(), &name.span)?;
+                let index = self.scope.names[&name.value];
+                if let Some(binding) = self.scope.bindings.get_mut(&index) {
+                    binding.eager = true;
+                }
+            }
+            return Ok(());
+        }
+        self.exec_global_instrs(instrs)?;
+        for name in des.names {
+            let value = (self.stack.pop()).ok_or_else(|| {
+                (name.span.clone()).sp(format!("No value to bind to {}", name.value))
+            })?;
+            self.bind_value(name.value, value, &name.span)?;
+        }
+        Ok(())
+    }
+    /// Compile the words of a multiple binding
+    ///
+    /// The values to bind are left on the stack with the first name's on top
+    fn destructure_instrs(&mut self, des: &Destructure) -> UiuaResult<Vec<Instr>> {
+        for name in &des.names {
+            if ident_modifier_args(&name.value) > 0 {
+                return Err((name.span.clone())
+                    .sp(format!(
+                        "{} cannot take modifier arguments because \
+                        it is bound with other names",
+                        name.value
+                    ))
+                    .into());
+            }
+        }
+        if count_placeholders(&des.words) > 0 {
+            return Err(des
+                .span()
+                .sp("Bindings of multiple names cannot use placeholders".into())
+                .into());
+        }
+        let mut instrs = self.compile_words(des.words.clone(), true)?;
+        if des.brackets.is_some() {
+            let span = self.add_span(des.span());
+            instrs.push(Instr::Unpack {
+                count: des.names.len(),
+                span,
+            });
+        }
+        Ok(instrs)
+    }
     fn bind_value(&mut self, name: Ident, mut value: Value, span: &CodeSpan) -> UiuaResult {
         self.validate_binding_name(&name, &[], span)?;
         value.compress();
         let mut globals = self.globals.lock();
         let idx = globals.len();
         globals.push(Global::Val(value));
-        self.scope.names.insert(name, idx);
+        drop(globals);
+        self.declare_binding(name, idx, span, true);
         Ok(())
     }
     fn bind_function(
@@ -194,9 +507,246 @@ impl Uiua {
         let mut globals = self.globals.lock();
         let idx = globals.len();
         globals.push(Global::Func(function));
-        self.scope.names.insert(name, idx);
+        drop(globals);
+        self.declare_binding(name, idx, span, false);
         Ok(())
     }
+    /// Add a name to the current scope, warning if it shadows an earlier binding or a constant
+    fn declare_binding(&mut self, name: Ident, index: usize, span: &CodeSpan, eager: bool) {
+        if self.lints && self.find_global(&name).is_some_and(|i| i < CONSTANTS.len()) {
+            let message = format!("{name} shadows the built-in constant of the same name");
+            self.diagnostic_with_span(message, DiagnosticKind::Warning, span.clone());
+        }
+        let shadowed = (self.scope.names.get(&name))
+            .filter(|_| self.lints)
+            .and_then(|prev| self.scope.bindings.get(prev))
+            // Rebinding a name from an earlier REPL line or cell is fine
+            .filter(|prev| Arc::ptr_eq(&prev.span.input, &span.input));
+        if let Some(prev) = shadowed {
+            let message = format!(
+                "{name} shadows the binding of the same name on line {}",
+                prev.span.start.line
+            );
+            self.diagnostic_with_span(message, DiagnosticKind::Warning, span.clone());
+        }
+        self.record_binding_ref(index, &name, span, true);
+        self.scope.bindings.insert(
+            index,
+            BindingUsage {
+                name,
+                span: span.clone(),
+                users: Vec::new(),
+                eager,
+            },
+        );
+        self.scope.names.insert(name, index);
+    }
+    /// Record where a global is defined or referenced, if recording is enabled
+    fn record_binding_ref(
+        &mut self,
+        index: usize,
+        name: &Ident,
+        span: &CodeSpan,
+        definition: bool,
+    ) {
+        let top_level = self.higher_scopes.is_empty();
+        if let Some(refs) = &mut self.binding_refs {
+            refs.push(BindingRef {
+                index,
+                name: *name,
+                span: span.clone(),
+                definition,
+                top_level,
+            });
+        }
+    }
+    /// Mark a global as used by the binding currently being compiled
+    fn mark_used(&mut self, index: usize) {
+        let scopes = once(&mut self.scope).chain(self.higher_scopes.last_mut());
+        for scope in scopes {
+            if let Some(binding) = scope.bindings.get_mut(&index) {
+                binding.users.push(self.current_binding.clone());
+            }
+        }
+    }
+    /// Mark the globals referenced by words that are not being run as used
+    fn mark_words_used(&mut self, words: &[Sp<Word>]) {
+        for word in words {
+            match &word.value {
+                Word::Ident(ident) => {
+                    if let Some(index) = self.find_global(ident) {
+                        self.mark_used(index);
+                    }
+                }
+                Word::Strand(words) => self.mark_words_used(words),
+                Word::Array(arr) => arr.lines.iter().for_each(|l| self.mark_words_used(l)),
+                Word::Func(func) => func.lines.iter().for_each(|l| self.mark_words_used(l)),
+                Word::Switch(sw) => {
+                    for branch in &sw.branches {
+                        branch
+                            .value
+                            .lines
+                            .iter()
+                            .for_each(|l| self.mark_words_used(l));
+                    }
+                }
+                Word::Modified(m) => {
+                    if let Modifier::Ident(ident) = &m.modifier.value {
+                        if let Some(index) = self.find_global(ident) {
+                            self.mark_used(index);
+                        }
+                    }
+                    self.mark_words_used(&m.operands);
+                }
+                Word::Local(local) => self.mark_words_used(&local.words),
+                Word::Destructure(des) => self.mark_words_used(&des.words),
+                _ => {}
+            }
+        }
+    }
+    /// Warn about bindings in the current scope that are never used by code that runs
+    pub(crate) fn warn_unused_bindings(&mut self) {
+        let bindings = take(&mut self.scope.bindings);
+        if !self.lints {
+            return;
+        }
+        let by_span: HashMap<&CodeSpan, &BindingUsage> =
+            bindings.values().map(|b| (&b.span, b)).collect();
+        // A binding is live if it is used by code outside of any binding,
+        // by a binding in another scope, or by a live or eagerly run binding
+        let mut live: HashSet<&CodeSpan> = HashSet::new();
+        loop {
+            let newly_live: Vec<&CodeSpan> = (bindings.values())
+                .filter(|b| !live.contains(&b.span))
+                .filter(|b| {
+                    b.users.iter().any(|user| match user {
+                        None => true,
+                        Some(user) => match by_span.get(user) {
+                            Some(u) => u.eager || live.contains(user),
+                            None => true,
+                        },
+                    })
+                })
+                .map(|b| &b.span)
+                .collect();
+            if newly_live.is_empty() {
+                break;
+            }
+            live.extend(newly_live);
+        }
+        let mut diagnostics = Vec::new();
+        for binding in bindings.values().filter(|b| !live.contains(&b.span)) {
+            let message = if binding.users.is_empty() {
+                format!("{} is never used", binding.name)
+            } else {
+                format!(
+                    "{} is only used by bindings that are never used",
+                    binding.name
+                )
+            };
+            diagnostics.push(Diagnostic::new(
+                message,
+                binding.span.clone(),
+                DiagnosticKind::Warning,
+            ));
+        }
+        self.diagnostics.extend(diagnostics);
+        self.flush_diagnostics();
+    }
+    /// Warn about a top-level line that took values left by earlier lines or left too many values
+    fn check_line_leaks(&mut self, span: CodeSpan, height: usize, args: usize) {
+        let taken = args.min(height);
+        if taken > 0 {
+            let values = if taken == 1 { "value" } else { "values" };
+            let message = format!("This line takes {taken} {values} left by earlier lines");
+            self.diagnostic_with_span(message, DiagnosticKind::Warning, span.clone());
+        }
+        let left = self.stack.len().saturating_sub(height - taken);
+        if left > 1 {
+            let message = format!("This line leaves {left} values on the stack");
+            self.diagnostic_with_span(message, DiagnosticKind::Warning, span);
+        }
+        self.flush_diagnostics();
+    }
+    /// Warn about code that can never run
+    fn lint_instrs(&mut self, instrs: &[Instr]) {
+        fn constant_nat(instr: &Instr) -> Option<usize> {
+            let Instr::Push(val) = instr else {
+                return None;
+            };
+            let n = match &**val {
+                Value::Num(arr) if arr.rank() == 0 => arr.data[0],
+                Value::Byte(arr) if arr.rank() == 0 => arr.data[0] as f64,
+                _ => return None,
+            };
+            (n >= 0.0 && n.fract() == 0.0).then_some(n as usize)
+        }
+        let pushed_funcs = |i: usize, count: usize| {
+            i >= count
+                && instrs[i - count..i]
+                    .iter()
+                    .all(|instr| matches!(instr, Instr::PushFunc(_)))
+        };
+        for (i, instr) in instrs.iter().enumerate() {
+            match instr {
+                Instr::Prim(Primitive::If, span) if pushed_funcs(i, 2) => {
+                    let Some(cond) = i.checked_sub(3).and_then(|j| constant_nat(&instrs[j])) else {
+                        continue;
+                    };
+                    let message = match cond {
+                        0 => "This condition is always false, so the first branch is never run",
+                        1 => "This condition is always true, so the second branch is never run",
+                        _ => continue,
+                    };
+                    let span = self.get_span(*span);
+                    self.diagnostic_with_span(message, DiagnosticKind::Warning, span);
+                }
+                Instr::Switch { count, span } if pushed_funcs(i, *count) => {
+                    let Some(index) =
+                        (i.checked_sub(count + 1)).and_then(|j| constant_nat(&instrs[j]))
+                    else {
+                        continue;
+                    };
+                    if index < *count {
+                        let span = self.get_span(*span);
+                        self.diagnostic_with_span(
+                            format!("This switch always runs branch {index}"),
+                            DiagnosticKind::Warning,
+                            span,
+                        );
+                    }
+                }
+                Instr::Prim(prim @ (Primitive::Break | Primitive::Continue), span) => {
+                    let breaks = (i.checked_sub(1)).and_then(|j| constant_nat(&instrs[j]));
+                    let code_after = instrs[i + 1..].iter().any(|instr| {
+                        matches!(
+                            instr,
+                            Instr::Push(_)
+                                | Instr::PushFunc(_)
+                                | Instr::Prim(..)
+                                | Instr::ImplPrim(..)
+                                | Instr::Call(_)
+                                | Instr::GetLocal { .. }
+                                | Instr::GetGlobal { .. }
+                        )
+                    });
+                    if breaks.is_some_and(|n| n > 0) && code_after {
+                        let span = self.get_span(*span);
+                        self.diagnostic_with_span(
+                            format!("Code after this {} is never run", prim.name()),
+                            DiagnosticKind::Warning,
+                            span,
+                        );
+                    }
+                }
+                // Named functions are checked when they are bound
+                Instr::PushFunc(f) if matches!(f.id, FunctionId::Anonymous(_)) => {
+                    self.lint_instrs(&f.unpooled_instrs())
+                }
+                _ => {}
+            }
+        }
+    }
     fn validate_binding_name(&self, name: &Ident, instrs: &[Instr], span: &CodeSpan) -> UiuaResult {
         let temp_function_count = count_temp_functions(instrs);
         let name_marg_count = ident_modifier_args(name) as usize;
@@ -213,29 +763,84 @@ impl Uiua {
         }
         Ok(())
     }
-    fn compile_words(&mut self, words: Vec<Sp<Word>>, call: bool) -> UiuaResult<Vec<Instr>> {
+    pub(crate) fn compile_words(
+        &mut self,
+        words: Vec<Sp<Word>>,
+        call: bool,
+    ) -> UiuaResult<Vec<Instr>> {
         self.new_functions.push(Vec::new());
-        self.words(words, call)?;
+        let res = self.words(words, call);
+        let instrs = self.new_functions.pop();
+        res?;
         self.flush_diagnostics();
-        Ok(self.new_functions.pop().unwrap())
+        instrs.ok_or_else(|| self.bug("a function was compiled more than once"))
     }
-    fn flush_diagnostics(&mut self) {
-        if self.print_diagnostics {
+    /// Compile the lines of an array, and find the span of the word that pushes each row
+    ///
+    /// The spans are empty if they cannot be known before the array is built.
+    fn array_words(
+        &mut self,
+        lines: Vec<Vec<Sp<Word>>>,
+    ) -> UiuaResult<(Vec<Instr>, Vec<CodeSpan>)> {
+        self.new_functions.push(Vec::new());
+        let mut words = Vec::new();
+        let mut res = Ok(());
+        for word in lines
+            .into_iter()
+            .rev()
+            .flat_map(|line| line.into_iter().rev())
+        {
+            let start = self.new_functions.last().map_or(0, Vec::len);
+            words.push((start, word.span.clone()));
+            res = self.word(word, true);
+            if res.is_err() {
+                break;
+            }
+        }
+        let instrs = self.new_functions.pop();
+        res?;
+        self.flush_diagnostics();
+        let instrs = instrs.ok_or_else(|| self.bug("a function was compiled more than once"))?;
+        let row_spans = row_spans(&instrs, &words).unwrap_or_default();
+        Ok((instrs, row_spans))
+    }
+    /// Add the spans of the words that push an array's rows, so they can be used if the array fails to build
+    fn row_span_indices(&mut self, row_spans: Vec<CodeSpan>) -> Option<Arc<[usize]>> {
+        if row_spans.is_empty() {
+            return None;
+        }
+        Some(
+            row_spans
+                .into_iter()
+                .map(|span| self.add_span(span))
+                .collect(),
+        )
+    }
+    pub(crate) fn flush_diagnostics(&mut self) {
+        if self.print_diagnostics && !self.diagnostics.is_empty() {
+            _ = self.backend.flush_stdout();
             for diagnostic in self.take_diagnostics() {
                 eprintln!("{}", diagnostic.report());
             }
         }
     }
-    fn compile_operand_words(
+    pub(crate) fn compile_operand_words(
         &mut self,
         words: Vec<Sp<Word>>,
     ) -> UiuaResult<(Vec<Instr>, Result<Signature, String>)> {
+        let span = words.first().map(|word| word.span.clone());
         let mut instrs = self.compile_words(words, true)?;
         let mut sig = None;
         // Extract function instrs if possible
         if let [Instr::PushFunc(f)] = instrs.as_slice() {
             sig = Some(f.signature());
-            instrs = f.instrs.clone();
+            if let (Some(Instr::InitLocals { .. }), Some(span)) = (f.instrs.first(), span) {
+                // Functions with local bindings need their own frame
+                let span = self.add_span(span);
+                instrs.push(Instr::Call(span));
+            } else {
+                instrs = f.unpooled_instrs().into_owned();
+            }
         }
         let sig = if let Some(sig) = sig {
             Ok(sig)
@@ -341,76 +946,10 @@ impl Uiua {
                     )));
                 }
             }
-            Word::FormatString(frags) => {
-                let signature = Signature::new(frags.len() - 1, 1);
-                let f = Function::new(
-                    FunctionId::Anonymous(word.span.clone()),
-                    vec![Instr::Dynamic(DynamicFunction {
-                        id: {
-                            let mut hasher = DefaultHasher::new();
-                            frags.hash(&mut hasher);
-                            hasher.finish()
-                        },
-                        f: Arc::new(move |env| {
-                            let mut formatted = String::new();
-                            for (i, frag) in frags.iter().enumerate() {
-                                if i > 0 {
-                                    let val = env.pop(format!("format argument {i}"))?;
-                                    formatted.push_str(&format!("{}", val));
-                                }
-                                formatted.push_str(frag);
-                            }
-                            env.push(formatted);
-                            Ok(())
-                        }),
-                        signature,
-                    })],
-                    signature,
-                );
-                self.push_instr(Instr::push_func(f));
-                if call {
-                    let span = self.add_span(word.span);
-                    self.push_instr(Instr::Call(span));
-                }
-            }
+            Word::FormatString(fs) => self.format_string(vec![fs], word.span, call)?,
             Word::MultilineString(lines) => {
-                let signature = Signature::new(lines.iter().map(|l| l.value.len() - 1).sum(), 1);
-                let f = Function::new(
-                    FunctionId::Anonymous(word.span.clone()),
-                    vec![Instr::Dynamic(DynamicFunction {
-                        id: {
-                            let mut hasher = DefaultHasher::new();
-                            lines.hash(&mut hasher);
-                            hasher.finish()
-                        },
-                        f: Arc::new(move |env| {
-                            let mut formatted = String::new();
-                            let mut i = 0;
-                            for (j, line) in lines.iter().enumerate() {
-                                if j > 0 {
-                                    formatted.push('\n');
-                                }
-                                for (k, frag) in line.value.iter().enumerate() {
-                                    if k > 0 {
-                                        let val = env.pop(format!("format argument {i}"))?;
-                                        formatted.push_str(&format!("{}", val));
-                                    }
-                                    formatted.push_str(frag);
-                                    i += 1;
-                                }
-                            }
-                            env.push(formatted);
-                            Ok(())
-                        }),
-                        signature,
-                    })],
-                    signature,
-                );
-                self.push_instr(Instr::push_func(f));
-                if call {
-                    let span = self.add_span(word.span);
-                    self.push_instr(Instr::Call(span));
-                }
+                let lines = lines.into_iter().map(|line| line.value).collect();
+                self.format_string(lines, word.span, call)?
             }
             Word::Ident(ident) => self.ident(ident, word.span, call)?,
             Word::Strand(items) => {
@@ -418,7 +957,7 @@ impl Uiua {
                     self.new_functions.push(Vec::new());
                 }
                 self.push_instr(Instr::BeginArray);
-                let inner = self.compile_words(items, true)?;
+                let (inner, row_spans) = self.array_words(vec![items])?;
                 let span = self.add_span(word.span.clone());
                 let instrs = self.new_functions.last_mut().unwrap();
                 if call && inner.iter().all(|instr| matches!(instr, Instr::Push(_))) {
@@ -429,13 +968,20 @@ impl Uiua {
                         _ => unreachable!(),
                     });
                     self.push_span(span, None);
-                    let val = Value::from_row_values(values, self)?;
+                    let val = self.array_from_rows(values.collect(), |i| {
+                        row_spans.get(i).cloned().map(Span::Code)
+                    })?;
                     self.pop_span();
                     self.push_instr(Instr::push(val));
                 } else {
                     // Normal case
                     instrs.extend(inner);
-                    self.push_instr(Instr::EndArray { span, boxed: false });
+                    let row_spans = self.row_span_indices(row_spans);
+                    self.push_instr(Instr::EndArray {
+                        span,
+                        boxed: false,
+                        row_spans,
+                    });
                     if !call {
                         let instrs = self.new_functions.pop().unwrap();
                         let sig =
@@ -445,15 +991,16 @@ impl Uiua {
                     }
                 }
             }
-            Word::Array(arr) => {
+            Word::Array(mut arr) => {
+                if arr.has_row_breaks() {
+                    let words = take(&mut arr.lines).into_iter().flatten();
+                    arr.lines = vec![array_rows(words, &word.span)];
+                }
                 if !call {
                     self.new_functions.push(Vec::new());
                 }
                 self.push_instr(Instr::BeginArray);
-                let mut inner = Vec::new();
-                for lines in arr.lines.into_iter().rev() {
-                    inner.extend(self.compile_words(lines, true)?);
-                }
+                let (inner, row_spans) = self.array_words(arr.lines)?;
                 let span = self.add_span(word.span.clone());
                 let instrs = self.new_functions.last_mut().unwrap();
                 if call && inner.iter().all(|instr| matches!(instr, Instr::Push(_))) {
@@ -475,15 +1022,19 @@ impl Uiua {
                             )?
                         }
                     } else {
-                        Value::from_row_values(values, self)?
+                        self.array_from_rows(values.collect(), |i| {
+                            row_spans.get(i).cloned().map(Span::Code)
+                        })?
                     };
                     self.pop_span();
                     self.push_instr(Instr::push(val));
                 } else {
                     instrs.extend(inner);
+                    let row_spans = self.row_span_indices(row_spans);
                     self.push_instr(Instr::EndArray {
                         span,
                         boxed: arr.constant,
+                        row_spans,
                     });
                     if !call {
                         let instrs = self.new_functions.pop().unwrap();
@@ -510,19 +1061,53 @@ impl Uiua {
                     self.push_instr(Instr::Call(span));
                 }
             }
+            Word::Local(local) => {
+                return Err(local
+                    .name
+                    .span
+                    .sp("Local bindings must be on their own line in a function".into())
+                    .into())
+            }
+            Word::Destructure(_) => {
+                return Err(word
+                    .span
+                    .sp("Local bindings must be on their own line in a function".into())
+                    .into())
+            }
             Word::Spaces | Word::Comment(_) => {}
+            Word::RowBreak => {
+                return Err(word
+                    .span
+                    .sp("`|` can only separate the rows of an array".into())
+                    .into())
+            }
         }
         Ok(())
     }
     fn ident(&mut self, ident: Ident, span: CodeSpan, call: bool) -> UiuaResult {
-        if let Some(idx) = self
-            .scope
-            .names
-            .get(&ident)
-            .or_else(|| self.higher_scopes.last()?.names.get(&ident))
-        {
+        if let Some((up, index)) = self.find_local(&ident) {
+            // Name is a local binding
+            let span_i = self.add_span(span.clone());
+            let instr = Instr::GetLocal {
+                up,
+                index,
+                frame: None,
+                span: span_i,
+            };
+            if call {
+                self.push_instr(instr);
+            } else {
+                self.push_instr(Instr::push_func(Function::new(
+                    FunctionId::Anonymous(span),
+                    vec![instr],
+                    Signature::new(0, 1),
+                )));
+            }
+        } else if let Some(idx) = self.find_global(&ident) {
             // Name exists in scope
-            let global = self.globals.lock()[*idx].clone();
+            self.mark_used(idx);
+            self.record_binding_ref(idx, &ident, &span, false);
+            let global = self.globals.lock()[idx].clone();
             match global {
                 Global::Val(val) if call => self.push_instr(Instr::push(val)),
                 Global::Val(val) => {
@@ -539,9 +1124,37 @@ impl Uiua {
                         self.push_instr(Instr::Call(span));
                     }
                 }
+                Global::Macro(_) => {
+                    return Err(span
+                        .sp(format!(
+                            "{ident} is a code macro and can only be used as a modifier"
+                        ))
+                        .into())
+                }
+                Global::Var(_) => {
+                    // Mutable bindings must be looked up when they are used
+                    let span_i = self.add_span(span.clone());
+                    let instr = Instr::GetGlobal {
+                        index: idx,
+                        span: span_i,
+                    };
+                    if call {
+                        self.push_instr(instr);
+                    } else {
+                        self.push_instr(Instr::push_func(Function::new(
+                            FunctionId::Anonymous(span),
+                            vec![instr],
+                            Signature::new(0, 1),
+                        )));
+                    }
+                }
             }
         } else {
-            return Err(span.sp(format!("Unknown identifier `{ident}`")).into());
+            return Err(UiuaError::UnknownIdent(Box::new(UnknownIdent {
+                suggestions: self.similar_names(&ident),
+                name: ident,
+                span: span.into(),
+            })));
         }
         Ok(())
     }
@@ -567,10 +1180,177 @@ impl Uiua {
         }
         Ok(())
     }
+    /// Run a code macro on the code of its operands and compile the code it returns
+    ///
+    /// The generated code is compiled as if it were written where the macro is used.
+    fn expand_code_macro(
+        &mut self,
+        f: Arc<Function>,
+        modified: Modified,
+        call: bool,
+    ) -> UiuaResult {
+        const MAX_MACRO_DEPTH: usize = 100;
+        let span = modified.modifier.span;
+        if self.macro_depth >= MAX_MACRO_DEPTH {
+            return Err(span
+                .sp(format!("Expanding {} recursed too deeply", f.id))
+                .into());
+        }
+        // Generate the code
+        let operands = (modified.operands.iter())
+            .filter(|word| word.value.is_code())
+            .map(quoted_code);
+        let call_span = self.add_span(span.clone());
+        let id = f.id.clone();
+        let running_macro = self.running_macro.replace(id.clone());
+        let res = self.exec_global_instrs(vec![
+            Instr::push(Array::<Boxed>::from_iter(operands)),
+            Instr::push_func(f),
+            Instr::Call(call_span),
+        ]);
+        self.running_macro = running_macro;
+        res?;
+        let code =
+            (self.pop("generated code")?).as_string(self, "Code macros must return a string")?;
+        // Parse and compile it
+        let (items, errors, _) = parse(&code, None);
+        if !errors.is_empty() {
+            let error = UiuaError::from(errors);
+            return Err(span
+                .sp(format!("{id} generated invalid code: {error}"))
+                .into());
+        }
+        let mut lines = Vec::new();
+        for item in items {
+            match item {
+                Item::Words(words) => lines.push(words),
+                Item::ExtraNewlines(_) => {}
+                _ => {
+                    return Err(span
+                        .sp(format!("{id} generated code with bindings or test scopes"))
+                        .into())
+                }
+            }
+        }
+        let func = Func {
+            id: FunctionId::Anonymous(span.clone()),
+            signature: None,
+            lines,
+        };
+        self.macro_depth += 1;
+        let res = self.func(func, span.clone());
+        self.macro_depth -= 1;
+        res?;
+        if call {
+            let span = self.add_span(span);
+            self.push_instr(Instr::Call(span));
+        }
+        Ok(())
+    }
+    /// Compile the lines of a format string, joined by newlines
+    fn format_string(
+        &mut self,
+        lines: Vec<FormatString>,
+        span: CodeSpan,
+        call: bool,
+    ) -> UiuaResult {
+        let mut compiled = Vec::with_capacity(lines.len());
+        for line in lines {
+            let signature = Signature::new(line.stack_args(), 1);
+            let mut args = Vec::with_capacity(line.args.len());
+            for arg in line.args {
+                args.push(match arg {
+                    FormatArg::Stack => None,
+                    FormatArg::Code(words) => Some(self.interpolation(words)?),
+                });
+            }
+            compiled.push((line.frags, args, signature));
+        }
+        let signature = Signature::new(compiled.iter().map(|(.., sig)| sig.args).sum(), 1);
+        let f = Function::new(
+            FunctionId::Anonymous(span.clone()),
+            vec![Instr::Dynamic(Box::new(DynamicFunction {
+                id: {
+                    let mut hasher = DefaultHasher::new();
+                    for (frags, args, _) in &compiled {
+                        frags.hash(&mut hasher);
+                        args.hash(&mut hasher);
+                    }
+                    hasher.finish()
+                },
+                f: Arc::new(move |env| {
+                    let mut formatted = String::new();
+                    let mut i = 0;
+                    for (j, (frags, args, _)) in compiled.iter().enumerate() {
+                        if j > 0 {
+                            formatted.push('\n');
+                        }
+                        for (k, frag) in frags.iter().enumerate() {
+                            if k > 0 {
+                                let val = match &args[k - 1] {
+                                    Some(f) => {
+                                        env.call(f.clone())?;
+                                        env.pop("interpolated value")?
+                                    }
+                                    None => {
+                                        i += 1;
+                                        env.pop(format!("format argument {i}"))?
+                                    }
+                                };
+                                formatted.push_str(&format!("{}", val));
+                            }
+                            formatted.push_str(frag);
+                        }
+                    }
+                    env.push(formatted);
+                    Ok(())
+                }),
+                signature,
+            }))],
+            signature,
+        );
+        self.push_instr(Instr::push_func(f));
+        if call {
+            let span = self.add_span(span);
+            self.push_instr(Instr::Call(span));
+        }
+        Ok(())
+    }
+    /// Compile the code of a string interpolation, which must produce a single value
+    fn interpolation(&mut self, words: Sp<Vec<Sp<Word>>>) -> UiuaResult<Arc<Function>> {
+        let (instrs, sig) = self.compile_operand_words(words.value)?;
+        match sig {
+            Ok(sig) if sig == (0, 1) => Ok(Arc::new(Function::new(
+                FunctionId::Anonymous(words.span),
+                instrs,
+                sig,
+            ))),
+            Ok(sig) => Err(words
+                .span
+                .sp(format!(
+                    "Interpolated code must have signature |0.1, but its signature is {sig}"
+                ))
+                .into()),
+            Err(e) => Err(words
+                .span
+                .sp(format!(
+                    "Cannot infer the signature of interpolated code: {e}"
+                ))
+                .into()),
+        }
+    }
     fn func(&mut self, func: Func, span: CodeSpan) -> UiuaResult {
-        let mut instrs = Vec::new();
-        for line in func.lines {
-            instrs.extend(self.compile_words(line, true)?);
+        let has_locals = (func.lines.iter().flatten()).any(|word| match &word.value {
+            Word::Local(local) => !local.mutable,
+            Word::Destructure(_) => true,
+            _ => false,
+        });
+        self.local_names.push(has_locals.then(Vec::new));
+        let instrs = self.func_lines(func.lines);
+        let local_names = self.local_names.pop().unwrap();
+        let mut instrs = instrs?;
+        if let Some(names) = local_names {
+            instrs.insert(0, Instr::InitLocals { count: names.len() });
         }
 
         // Validate signature
@@ -613,12 +1393,163 @@ impl Uiua {
         self.push_instr(Instr::push_func(function));
         Ok(())
     }
+    fn func_lines(&mut self, lines: Vec<Vec<Sp<Word>>>) -> UiuaResult<Vec<Instr>> {
+        let mut instrs = Vec::new();
+        for mut line in lines {
+            if let [Sp {
+                value: Word::Local(_),
+                ..
+            }] = line.as_slice()
+            {
+                let Word::Local(local) = line.pop().unwrap().value else {
+                    unreachable!()
+                };
+                instrs.extend(self.local_binding(*local)?);
+            } else if let [Sp {
+                value: Word::Destructure(_),
+                ..
+            }] = line.as_slice()
+            {
+                let Word::Destructure(des) = line.pop().unwrap().value else {
+                    unreachable!()
+                };
+                instrs.extend(self.local_destructure(*des)?);
+            } else {
+                instrs.extend(self.compile_words(line, true)?);
+            }
+        }
+        Ok(instrs)
+    }
+    fn local_binding(&mut self, local: LocalBinding) -> UiuaResult<Vec<Instr>> {
+        let name = local.name.value;
+        if ident_modifier_args(&name) > 0 {
+            return Err(local
+                .name
+                .span
+                .sp(format!(
+                    "Local binding {name} cannot take modifier arguments"
+                ))
+                .into());
+        }
+        let mut instrs = self.compile_words(local.words, true)?;
+        if local.mutable {
+            let index = self.find_local(&name);
+            let span_i = self.add_span(local.name.span.clone());
+            let set = if let Some((up, index)) = index {
+                Instr::SetLocal {
+                    up,
+                    index,
+                    frame: None,
+                    span: span_i,
+                }
+            } else if let Some(index) = (self.find_global(&name))
+                .filter(|&index| matches!(self.globals.lock()[index], Global::Var(_)))
+            {
+                self.record_binding_ref(index, &name, &local.name.span, false);
+                if let Some(pattern) = self.var_annotations.get(&index) {
+                    let name = local.name.span.clone().sp(name);
+                    instrs.push(annotation::value_check(name, pattern.clone()));
+                }
+                Instr::SetGlobal {
+                    index,
+                    span: span_i,
+                }
+            } else {
+                return Err(local
+                    .name
+                    .span
+                    .sp(format!(
+                        "Cannot reassign {name} because it is \
+                        not a local or mutable binding"
+                    ))
+                    .into());
+            };
+            instrs.push(set);
+            return Ok(instrs);
+        }
+        let index = self.declare_local(name, &local.name.span)?;
+        let span = self.add_span(local.name.span);
+        instrs.push(Instr::SetLocal {
+            up: 0,
+            index,
+            frame: None,
+            span,
+        });
+        Ok(instrs)
+    }
+    fn local_destructure(&mut self, des: Destructure) -> UiuaResult<Vec<Instr>> {
+        let mut instrs = self.destructure_instrs(&des)?;
+        for name in des.names {
+            let index = self.declare_local(name.value, &name.span)?;
+            let span = self.add_span(name.span);
+            instrs.push(Instr::SetLocal {
+                up: 0,
+                index,
+                frame: None,
+                span,
+            });
+        }
+        Ok(instrs)
+    }
+    /// Add a local binding to the function under construction, returning its slot index
+    fn declare_local(&mut self, name: Ident, span: &CodeSpan) -> UiuaResult<usize> {
+        let Some(names) = self.local_names.last_mut().and_then(Option::as_mut) else {
+            return Err(span
+                .clone()
+                .sp(format!(
+                    "Local binding {name} can only be made inside a function"
+                ))
+                .into());
+        };
+        names.push(name);
+        Ok(names.len() - 1)
+    }
+    /// Find the global index of a name in scope
+    fn find_global(&self, ident: &Ident) -> Option<usize> {
+        (self.scope.names.get(ident))
+            .or_else(|| self.higher_scopes.last()?.names.get(ident))
+            .copied()
+    }
+    /// Find bound names that are similar to an unknown one
+    fn similar_names(&self, ident: &Ident) -> Vec<Ident> {
+        let lower = ident.to_lowercase();
+        let len = lower.chars().count();
+        let max_distance = if len < 2 { 0 } else { (len / 3).max(1) };
+        let locals = self.local_names.iter().flatten().flatten();
+        let globals = (self.scope.names.keys()).chain(
+            self.higher_scopes
+                .last()
+                .into_iter()
+                .flat_map(|s| s.names.keys()),
+        );
+        let mut names: Vec<Ident> = locals
+            .chain(globals)
+            .filter(|name| edit_distance(&name.to_lowercase(), &lower) <= max_distance)
+            .cloned()
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+    /// Find the frame distance and slot index of a local binding
+    fn find_local(&self, ident: &Ident) -> Option<(u32, usize)> {
+        for (up, names) in self.local_names.iter().rev().flatten().enumerate() {
+            if let Some(index) = names.iter().rposition(|name| name == ident) {
+                return Some((up as u32, index));
+            }
+        }
+        None
+    }
     fn switch(&mut self, sw: Switch, span: CodeSpan, call: bool) -> UiuaResult {
         let count = sw.branches.len();
         if !call {
             self.new_functions.push(Vec::new());
         }
         for branch in sw.branches {
+            // Branches are counted separately so that ones that never run are reported
+            if let (Some(_), FunctionId::Anonymous(span)) = (&self.coverage, &branch.value.id) {
+                self.add_span(span.clone());
+            }
             self.func(branch.value, branch.span)?;
         }
         let span_idx = self.add_span(span.clone());
@@ -642,6 +1573,17 @@ impl Uiua {
         Ok(())
     }
     fn modified(&mut self, modified: Modified, call: bool) -> UiuaResult {
+        if let Modifier::Ident(ident) = &modified.modifier.value {
+            if let Some(index) = self.find_global(ident) {
+                let global = self.globals.lock()[index].clone();
+                if let Global::Macro(f) = global {
+                    let ident = *ident;
+                    self.mark_used(index);
+                    self.record_binding_ref(index, &ident, &modified.modifier.span, false);
+                    return self.expand_code_macro(f, modified, call);
+                }
+            }
+        }
         if let Modifier::Primitive(prim) = modified.modifier.value {
             // Give advice about redundancy
             match prim {
@@ -675,9 +1617,28 @@ impl Uiua {
             }
             // Handle deprecation
             self.handle_primitive_deprecation(prim, &modified.modifier.span);
+            self.check_experimental(prim, &modified.modifier.span)?;
 
             // Inlining
             match prim {
+                Primitive::Quote => {
+                    let code = (modified.operands.iter())
+                        .find(|word| word.value.is_code())
+                        .map(quoted_code)
+                        .unwrap_or_default();
+                    return if call {
+                        self.push_instr(Instr::push(code));
+                        Ok(())
+                    } else {
+                        let span = modified.modifier.span;
+                        self.push_instr(Instr::push_func(Function::new(
+                            FunctionId::Anonymous(span),
+                            vec![Instr::push(code)],
+                            Signature::new(0, 1),
+                        )));
+                        Ok(())
+                    };
+                }
                 Primitive::Bind => {
                     let instrs = self.compile_words(modified.operands, true)?;
                     return if call {
@@ -895,9 +1856,41 @@ impl Uiua {
                 DiagnosticKind::Warning,
             ));
         }
+        // Spellings from before a file's declared version are not deprecated for it
+        let alias = Primitive::from_alias(span.as_str())
+            .filter(|alias| !matches!(self.language_version, Some(v) if v < alias.since));
+        if let Some(alias) = alias {
+            let current = match prim.glyph() {
+                Some(glyph) => format!("{} {glyph}", prim.name()),
+                None => prim.name().into(),
+            };
+            self.diagnostics.insert(Diagnostic::new(
+                format!(
+                    "Warning: `{}` was renamed to {current} in version {}. \
+                    Formatting the code will update it.",
+                    alias.spelling, alias.since
+                ),
+                span.clone(),
+                DiagnosticKind::Warning,
+            ));
+        }
+    }
+    fn check_experimental(&self, prim: Primitive, span: &CodeSpan) -> UiuaResult {
+        if prim.is_experimental() && !self.experimental {
+            return Err(span
+                .clone()
+                .sp(format!(
+                    "{} is experimental. To use it, add `# Experimental!` \
+                    to the top of the file.",
+                    prim.name()
+                ))
+                .into());
+        }
+        Ok(())
     }
     fn primitive(&mut self, prim: Primitive, span: CodeSpan, call: bool) -> UiuaResult {
         self.handle_primitive_deprecation(prim, &span);
+        self.check_experimental(prim, &span)?;
         let span_i = self.add_span(span.clone());
         if call {
             self.push_instr(Instr::Prim(prim, span_i));
@@ -935,7 +1928,7 @@ fn words_look_pervasive(words: &[Sp<Word>]) -> bool {
 }
 
 fn increment_placeholders(instrs: &mut [Instr]) {
-    let mut curr = 0;
+    let mut curr = 0u32;
     for instr in instrs {
         if let Instr::GetTempFunction { offset, .. } = instr {
             *offset = curr;
@@ -957,3 +1950,182 @@ fn count_temp_functions(instrs: &[Instr]) -> usize {
     }
     count
 }
+
+/// Split the words of an array literal at its `|`s, making an array of each row
+fn array_rows(words: impl IntoIterator<Item = Sp<Word>>, span: &CodeSpan) -> Vec<Sp<Word>> {
+    let mut rows = Vec::new();
+    let mut row: Vec<Sp<Word>> = Vec::new();
+    for word in words
+        .into_iter()
+        .chain(once(span.clone().sp(Word::RowBreak)))
+    {
+        if !matches!(word.value, Word::RowBreak) {
+            row.push(word);
+            continue;
+        }
+        let mut code = row.iter().filter(|word| word.value.is_code());
+        let row_span = match (code.next(), code.next_back()) {
+            (Some(first), Some(last)) => first.span.clone().merge(last.span.clone()),
+            (Some(only), None) => only.span.clone(),
+            _ => word.span,
+        };
+        rows.push(row_span.sp(Word::Array(Arr {
+            lines: vec![take(&mut row)],
+            constant: false,
+        })));
+    }
+    rows
+}
+
+/// Find the span of the word that pushes each row of an array
+///
+/// `words` are the index of the first instruction of each word, in the order they are run, and the word's span.
+/// Returns `None` if a word's signature is unknown or its instructions were optimized together with another word's.
+fn row_spans(instrs: &[Instr], words: &[(usize, CodeSpan)]) -> Option<Vec<CodeSpan>> {
+    let mut origins: Vec<&CodeSpan> = Vec::new();
+    for (i, (start, span)) in words.iter().enumerate() {
+        let end = words.get(i + 1).map_or(instrs.len(), |(end, _)| *end);
+        let sig = instrs_signature(instrs.get(*start..end)?).ok()?;
+        origins.truncate(origins.len().saturating_sub(sig.args));
+        origins.extend((0..sig.outputs).map(|_| span));
+    }
+    // The first row is the value on top of the stack
+    Some(origins.into_iter().rev().cloned().collect())
+}
+
+/// The code of a modifier operand
+///
+/// The parentheses of a function without a declared signature only delimit the operand, so they are left out.
+fn quoted_code(word: &Sp<Word>) -> String {
+    let code = word.span.as_str();
+    match &word.value {
+        Word::Func(func) if func.signature.is_none() => code[1..code.len() - 1].trim().into(),
+        _ => code.into(),
+    }
+}
+
+/// The number of single-character edits needed to turn one string into another
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ac) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, &bc) in b.iter().enumerate() {
+            let sub = prev[j] + (ac != bc) as usize;
+            curr[j + 1] = sub.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::diagnostics;
+
+    #[test]
+    fn lints() {
+        let mut env = Uiua::with_native_sys()
+            .with_mode(RunMode::All)
+            .with_lints(true);
+        env.load_str("A ← 1\nB ← +A 1\nA ← 2\nB\n---\nC ← 3\n---")
+            .unwrap();
+        assert_eq!(
+            diagnostics(&mut env),
+            [
+                "A is never used",
+                "A shadows the binding of the same name on line 1",
+                "C is never used"
+            ]
+        );
+    }
+
+    #[test]
+    fn dead_code_lints() {
+        let mut env = Uiua::with_native_sys().with_lints(true);
+        let code = "\
+F ← +1
+G ← F
+H ← ×2
+H 3
+?(1)(2) 1
+(1|2) 0
+⍥(+1 ⎋1)5 0";
+        env.load_str(code).unwrap();
+        assert_eq!(
+            diagnostics(&mut env),
+            [
+                "F is only used by bindings that are never used",
+                "G is never used",
+                "This condition is always true, so the second branch is never run",
+                "This switch always runs branch 0",
+                "Code after this break is never run",
+            ]
+        );
+    }
+
+    #[test]
+    fn leak_checks() {
+        let warnings = |code: &str| {
+            let mut env = Uiua::with_native_sys().with_leak_checks(true);
+            env.load_str(code).unwrap();
+            diagnostics(&mut env)
+        };
+        assert_eq!(
+            warnings("⇡3 .5"),
+            ["This line leaves 3 values on the stack"]
+        );
+        assert_eq!(
+            warnings("1 2\n+"),
+            [
+                "This line leaves 2 values on the stack",
+                "This line takes 2 values left by earlier lines"
+            ]
+        );
+        assert!(warnings("F ← +1\nF 5").is_empty());
+        assert!(warnings("1\n2").is_empty());
+        // Lines are not checked by default
+        let mut env = Uiua::with_native_sys();
+        env.load_str("1 2\n+").unwrap();
+        assert!(env.take_diagnostics().is_empty());
+    }
+
+    #[test]
+    fn primitive_binding_names() {
+        let err = Uiua::with_native_sys().load_str("add ← 5").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("`add` cannot be bound because it starts with the name of a primitive"),
+            "{err}"
+        );
+        let mut env = Uiua::with_native_sys().with_lints(true);
+        env.load_str("e ← 5\n+1 e").unwrap();
+        assert_eq!(env.take_stack(), [Value::from(6.0)]);
+        let messages = diagnostics(&mut env);
+        assert!(
+            (messages.iter()).any(|m| m == "e shadows the built-in constant of the same name"),
+            "{messages:?}"
+        );
+    }
+
+    #[test]
+    fn experimental() {
+        let err = Uiua::with_native_sys().load_str("help(+1)").unwrap_err();
+        assert!(err.to_string().contains("help is experimental"));
+        assert!(Uiua::with_native_sys()
+            .load_str("# Experimental!\nhelp(+1)")
+            .is_ok());
+        assert!(Uiua::with_native_sys()
+            .load_str("# Uiua 0.0.23\n\n# Experimental!\nhelp(+1)")
+            .is_ok());
+        // The declaration must come before any code
+        assert!(Uiua::with_native_sys()
+            .load_str("1\n# Experimental!\nhelp(+1)")
+            .is_err());
+        assert!(Uiua::with_native_sys()
+            .with_experimental(true)
+            .load_str("help(+1)")
+            .is_ok());
+    }
+}
@@ -0,0 +1,259 @@
+//! Reporting which code ran
+
+use std::{collections::BTreeMap, fmt::Write, path::PathBuf, sync::Arc};
+
+use colored::Colorize;
+
+use crate::{CodeSpan, Span, Uiua};
+
+/// How many times each span of some code ran, as reported by [`Uiua::coverage`]
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    /// The code of each file, in the order they were first compiled
+    pub files: Vec<FileCoverage>,
+}
+
+/// How many times each span of a file ran
+#[derive(Debug, Clone)]
+pub struct FileCoverage {
+    /// `None` for code that was not loaded from a file
+    pub path: Option<PathBuf>,
+    pub source: Arc<str>,
+    /// The spans of code that could run, with how many times they ran, in the order they appear
+    ///
+    /// Spans can be inside other spans, like a function's span and the spans of the words in it.
+    pub spans: Vec<(CodeSpan, u64)>,
+}
+
+impl Uiua {
+    /// Count how many times each span of code runs
+    ///
+    /// Get the counts with [`Uiua::coverage`].
+    ///
+    /// Default is `false`
+    pub fn with_coverage(mut self, coverage: bool) -> Self {
+        self.coverage = coverage.then(Default::default);
+        self
+    }
+    /// Get how many times each span of code that was compiled has run
+    ///
+    /// Coverage must be enabled with [`Uiua::with_coverage`], otherwise nothing is reported.
+    /// Code in test scopes is only compiled in [`RunMode::Test`](crate::RunMode::Test) and [`RunMode::All`](crate::RunMode::All).
+    pub fn coverage(&self) -> Coverage {
+        let Some(counts) = &self.coverage else {
+            return Coverage::default();
+        };
+        let counts = counts.lock();
+        let table = self.spans.lock();
+        let mut files: Vec<FileCoverage> = Vec::new();
+        for (i, span) in table.spans().iter().enumerate() {
+            let Span::Code(span) = span else {
+                continue;
+            };
+            let count = counts.get(i).copied().unwrap_or(0);
+            let file = match (files.iter_mut()).find(|file| Arc::ptr_eq(&file.source, &span.input))
+            {
+                Some(file) => file,
+                None => {
+                    files.push(FileCoverage {
+                        path: span.path.as_deref().map(Into::into),
+                        source: span.input.clone(),
+                        spans: Vec::new(),
+                    });
+                    files.last_mut().unwrap()
+                }
+            };
+            file.spans.push((span.clone(), count));
+        }
+        // Only the latest version of each file is reported
+        for i in (0..files.len()).rev() {
+            if let Some(path) = &files[i].path {
+                if files[i + 1..]
+                    .iter()
+                    .any(|file| file.path.as_ref() == Some(path))
+                {
+                    files.remove(i);
+                }
+            }
+        }
+        for file in &mut files {
+            (file.spans).sort_by_key(|(span, _)| (span.start, std::cmp::Reverse(span.end)));
+        }
+        Coverage { files }
+    }
+    /// Count a run of the code in a span
+    pub(crate) fn cover(&self, span: usize) {
+        if let Some(counts) = &self.coverage {
+            let mut counts = counts.lock();
+            if counts.len() <= span {
+                counts.resize(span + 1, 0);
+            }
+            counts[span] += 1;
+        }
+    }
+}
+
+impl FileCoverage {
+    /// How many times each line with code on it ran, by line number
+    ///
+    /// A line ran as many times as the code on it that ran the most.
+    pub fn lines(&self) -> BTreeMap<usize, u64> {
+        let mut lines = BTreeMap::new();
+        for (span, count) in &self.spans {
+            for line in span.start.line..=span.end.line {
+                let line_count = lines.entry(line).or_insert(0);
+                *line_count = (*line_count).max(*count);
+            }
+        }
+        lines
+    }
+    /// The fraction of spans that ran at least once
+    pub fn fraction(&self) -> f64 {
+        if self.spans.is_empty() {
+            return 1.0;
+        }
+        let ran = self.spans.iter().filter(|(_, count)| *count > 0).count();
+        ran as f64 / self.spans.len() as f64
+    }
+    /// Show the source with the code that ran in green and the code that did not in red
+    ///
+    /// Each line is preceded by how many times it ran. Where spans overlap, the innermost one is colored.
+    pub fn render(&self) -> String {
+        let lines = self.lines();
+        // How many times the code at each character ran, by line
+        let mut chars: Vec<Vec<Option<u64>>> = (self.source.lines())
+            .map(|line| vec![None; line.chars().count()])
+            .collect();
+        let mut spans: Vec<&(CodeSpan, u64)> = self.spans.iter().collect();
+        spans.sort_by_key(|(span, _)| std::cmp::Reverse(span.end.char_pos - span.start.char_pos));
+        for (span, count) in spans {
+            for (i, line) in chars.iter_mut().enumerate() {
+                let line_number = i + 1;
+                if line_number < span.start.line || line_number > span.end.line {
+                    continue;
+                }
+                let start = if line_number == span.start.line {
+                    span.start.col - 1
+                } else {
+                    0
+                };
+                let end = if line_number == span.end.line {
+                    (span.end.col - 1).min(line.len())
+                } else {
+                    line.len()
+                };
+                for c in line.iter_mut().take(end).skip(start) {
+                    *c = Some(*count);
+                }
+            }
+        }
+        let mut rendered = String::new();
+        for (i, (line, counts)) in self.source.lines().zip(chars).enumerate() {
+            let gutter = match lines.get(&(i + 1)) {
+                Some(count) => format!("{count:>6}"),
+                None => " ".repeat(6),
+            };
+            _ = write!(rendered, "{} ", gutter.dimmed());
+            for (c, count) in line.chars().zip(counts) {
+                let c = c.to_string();
+                match count {
+                    None => rendered.push_str(&c),
+                    Some(0) => _ = write!(rendered, "{}", c.red()),
+                    Some(_) => _ = write!(rendered, "{}", c.green()),
+                }
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+}
+
+impl Coverage {
+    /// Write the coverage as JSON
+    ///
+    /// Each file has its path, the counts of its lines, and the counts of its spans.
+    /// Lines and columns start at 1, and a span's end is the position just after it.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"files\":[");
+        for (i, file) in self.files.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let path = match &file.path {
+                Some(path) => json_string(&path.to_string_lossy()),
+                None => "null".into(),
+            };
+            _ = write!(json, "{{\"path\":{path},\"lines\":[");
+            for (j, (line, count)) in file.lines().into_iter().enumerate() {
+                let comma = if j > 0 { "," } else { "" };
+                _ = write!(json, "{comma}{{\"line\":{line},\"count\":{count}}}");
+            }
+            json.push_str("],\"spans\":[");
+            for (j, (span, count)) in file.spans.iter().enumerate() {
+                let comma = if j > 0 { "," } else { "" };
+                _ = write!(
+                    json,
+                    "{comma}{{\"start\":[{},{}],\"end\":[{},{}],\"count\":{count}}}",
+                    span.start.line, span.start.col, span.end.line, span.end.col
+                );
+            }
+            json.push_str("]}");
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => _ = write!(json, "\\u{:04x}", c as u32),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RunMode, Uiua};
+
+    #[test]
+    fn coverage() {
+        let mut env = Uiua::with_native_sys()
+            .with_mode(RunMode::Test)
+            .with_coverage(true);
+        let code = "\
+Sign ← (\"neg\"|\"pos\") >0
+Unused ← +1
+---
+⍤.≍ \"pos\" Sign 5
+---";
+        env.load_str(code).unwrap();
+        let coverage = env.coverage();
+        let [file] = &coverage.files[..] else {
+            panic!("expected one file, found {}", coverage.files.len());
+        };
+        let count = |code: &str| {
+            (file.spans.iter())
+                .find(|(span, _)| span.as_str().trim_start_matches('|') == code)
+                .map(|(_, count)| *count)
+        };
+        assert_eq!(count("\"neg\""), Some(0));
+        assert_eq!(count("\"pos\""), Some(1));
+        assert_eq!(count(">"), Some(1));
+        assert_eq!(count("+"), Some(0));
+        let lines = file.lines();
+        assert_eq!((lines[&1], lines[&2]), (1, 0));
+        assert!(!lines.contains_key(&3));
+        assert!(coverage.to_json().contains("{\"line\":2,\"count\":0}"));
+        // Nothing is counted unless coverage is enabled
+        let mut env = Uiua::with_native_sys();
+        env.load_str("+1 2").unwrap();
+        assert!(env.coverage().files.is_empty());
+    }
+}
@@ -1,11 +1,14 @@
 use std::{
     borrow::Borrow,
     cmp::Ordering,
+    convert::Infallible,
     fmt,
     hash::{Hash, Hasher},
     iter::{Skip, Take},
+    marker::PhantomData,
+    mem::{align_of, size_of, take, MaybeUninit},
     ops::{Bound, Deref, RangeBounds},
-    ptr,
+    ptr, slice,
 };
 
 macro_rules! cowslice {
@@ -23,10 +26,107 @@ macro_rules! cowslice {
 pub(crate) use cowslice;
 use ecow::EcoVec;
 
-pub struct CowSlice<T> {
-    data: EcoVec<T>,
-    start: u32,
-    end: u32,
+/// A slice of a reference-counted buffer
+///
+/// Slices of up to 32 bytes of elements are stored inline, so scalars and tiny arrays
+/// do not allocate.
+pub struct CowSlice<T>(Repr<T>);
+
+enum Repr<T> {
+    Inline(Inline<T>),
+    Heap {
+        data: EcoVec<T>,
+        start: usize,
+        end: usize,
+    },
+}
+
+/// The number of bytes of elements that a [`CowSlice`] can store without allocating
+const INLINE_BYTES: usize = 32;
+
+/// Elements stored without a heap allocation
+struct Inline<T> {
+    len: u8,
+    buf: [MaybeUninit<u64>; INLINE_BYTES / 8],
+    _marker: PhantomData<T>,
+}
+
+impl<T> Inline<T> {
+    const CAPACITY: usize = if size_of::<T>() == 0 || align_of::<T>() > align_of::<u64>() {
+        0
+    } else {
+        INLINE_BYTES / size_of::<T>()
+    };
+    fn new() -> Self {
+        Inline {
+            len: 0,
+            buf: [MaybeUninit::uninit(); INLINE_BYTES / 8],
+            _marker: PhantomData,
+        }
+    }
+    fn as_ptr(&self) -> *const T {
+        self.buf.as_ptr() as *const T
+    }
+    fn as_mut_ptr(&mut self) -> *mut T {
+        self.buf.as_mut_ptr() as *mut T
+    }
+    fn as_slice(&self) -> &[T] {
+        // Safety: the first `len` elements are initialized, and `buf` is aligned for `T`
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len as usize) }
+    }
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        // Safety: the first `len` elements are initialized, and `buf` is aligned for `T`
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), self.len as usize) }
+    }
+    /// Push an item, or give it back if there is no room
+    fn push(&mut self, item: T) -> Result<(), T> {
+        let len = self.len as usize;
+        if len < Self::CAPACITY {
+            // Safety: `len` is in bounds and uninitialized
+            unsafe { self.as_mut_ptr().add(len).write(item) };
+            self.len += 1;
+            Ok(())
+        } else {
+            Err(item)
+        }
+    }
+    fn truncate(&mut self, len: usize) {
+        while self.len as usize > len {
+            self.len -= 1;
+            // Safety: the element was initialized and is no longer part of the slice
+            unsafe { ptr::drop_in_place(self.as_mut_ptr().add(self.len as usize)) };
+        }
+    }
+    /// Move the elements into a heap-allocated vector
+    fn take_vec(&mut self) -> EcoVec<T>
+    where
+        T: Clone,
+    {
+        let len = self.len as usize;
+        self.len = 0;
+        let mut vec = EcoVec::with_capacity(len);
+        for i in 0..len {
+            // Safety: the element was initialized, and `len` is now 0 so it will not be dropped
+            vec.push(unsafe { ptr::read(self.as_ptr().add(i)) });
+        }
+        vec
+    }
+}
+
+impl<T> Drop for Inline<T> {
+    fn drop(&mut self) {
+        self.truncate(0);
+    }
+}
+
+impl<T: Clone> Clone for Inline<T> {
+    fn clone(&self) -> Self {
+        let mut inline = Inline::new();
+        for item in self.as_slice() {
+            _ = inline.push(item.clone());
+        }
+        inline
+    }
 }
 
 impl<T> CowSlice<T> {
@@ -34,45 +134,101 @@ impl<T> CowSlice<T> {
         Self::default()
     }
     pub fn truncate(&mut self, len: usize) {
-        self.end = (self.start + len as u32).min(self.end);
+        match &mut self.0 {
+            Repr::Inline(inline) => inline.truncate(len),
+            Repr::Heap { start, end, .. } => *end = (*start + len).min(*end),
+        }
     }
     pub fn with_capacity(capacity: usize) -> Self {
-        Self {
-            data: EcoVec::with_capacity(capacity),
-            start: 0,
-            end: 0,
+        if capacity <= Inline::<T>::CAPACITY {
+            Self::new()
+        } else {
+            CowSlice(Repr::Heap {
+                data: EcoVec::with_capacity(capacity),
+                start: 0,
+                end: 0,
+            })
         }
     }
     pub fn as_slice(&self) -> &[T] {
-        &self.data[self.start as usize..self.end as usize]
+        match &self.0 {
+            Repr::Inline(inline) => inline.as_slice(),
+            Repr::Heap { data, start, end } => &data[*start..*end],
+        }
     }
     #[inline]
     pub fn is_unique(&mut self) -> bool {
-        self.data.is_unique()
+        match &mut self.0 {
+            Repr::Inline(_) => true,
+            Repr::Heap { data, .. } => data.is_unique(),
+        }
     }
     pub fn is_copy_of(&self, other: &Self) -> bool {
-        ptr::eq(self.data.as_ptr(), other.data.as_ptr())
-            && self.start == other.start
-            && self.end == other.end
+        match (&self.0, &other.0) {
+            (
+                Repr::Heap { data, start, end },
+                Repr::Heap {
+                    data: other_data,
+                    start: other_start,
+                    end: other_end,
+                },
+            ) => {
+                ptr::eq(data.as_ptr(), other_data.as_ptr())
+                    && start == other_start
+                    && end == other_end
+            }
+            _ => false,
+        }
+    }
+    /// Whether the elements are stored without a heap allocation
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline(_))
     }
 }
 
 impl<T: Clone> CowSlice<T> {
     pub fn as_mut_slice(&mut self) -> &mut [T] {
-        if !self.data.is_unique() {
-            let mut new_data = EcoVec::with_capacity(self.len());
-            new_data.extend_from_slice(&*self);
-            self.data = new_data;
-            self.start = 0;
-            self.end = self.data.len() as u32;
+        match &mut self.0 {
+            Repr::Inline(inline) => inline.as_mut_slice(),
+            Repr::Heap { data, start, end } => {
+                if !data.is_unique() {
+                    let mut new_data = EcoVec::with_capacity(*end - *start);
+                    new_data.extend_from_slice(&data[*start..*end]);
+                    *data = new_data;
+                    *start = 0;
+                    *end = data.len();
+                }
+                &mut data.make_mut()[*start..*end]
+            }
         }
-        &mut self.data.make_mut()[self.start as usize..self.end as usize]
     }
     pub fn extend_from_slice(&mut self, other: &[T]) {
-        self.modify(|vec| vec.extend_from_slice(other))
+        self.extend(other.iter().cloned())
     }
     pub fn try_extend<E>(&mut self, iter: impl IntoIterator<Item = Result<T, E>>) -> Result<(), E> {
+        let mut iter = iter.into_iter();
+        let overflow = if let Repr::Inline(inline) = &mut self.0 {
+            loop {
+                match iter.next() {
+                    Some(item) => {
+                        if let Err(item) = inline.push(item?) {
+                            break item;
+                        }
+                    }
+                    None => return Ok(()),
+                }
+            }
+        } else {
+            return self.modify(|vec| {
+                for item in iter {
+                    vec.push(item?);
+                }
+                Ok(())
+            });
+        };
         self.modify(|vec| {
+            vec.reserve(iter.size_hint().0 + 1);
+            vec.push(overflow);
             for item in iter {
                 vec.push(item?);
             }
@@ -85,41 +241,57 @@ impl<T: Clone> CowSlice<T> {
         R: RangeBounds<usize>,
     {
         let start = match range.start_bound() {
-            Bound::Included(&start) => self.start + start as u32,
-            Bound::Excluded(&start) => self.start + start as u32 + 1,
-            Bound::Unbounded => self.start,
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
         };
         let end = match range.end_bound() {
-            Bound::Included(&end) => self.start + end as u32 + 1,
-            Bound::Excluded(&end) => self.start + end as u32,
-            Bound::Unbounded => self.end,
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len(),
         };
         assert!(start <= end);
-        assert!(end <= self.end);
-        Self {
-            data: self.data.clone(),
-            start,
-            end,
+        assert!(end <= self.len());
+        match &self.0 {
+            _ if end - start <= Inline::<T>::CAPACITY => self[start..end].into(),
+            Repr::Inline(_) => unreachable!("inline slices are never longer than the capacity"),
+            Repr::Heap {
+                data,
+                start: offset,
+                ..
+            } => CowSlice(Repr::Heap {
+                data: data.clone(),
+                start: offset + start,
+                end: offset + end,
+            }),
         }
     }
     pub fn modify<F, R>(&mut self, f: F) -> R
     where
         F: FnOnce(&mut EcoVec<T>) -> R,
     {
-        if self.data.is_unique() && self.start == 0 && self.end == self.data.len() as u32 {
-            let res = f(&mut self.data);
-            self.end = self.data.len() as u32;
-            res
-        } else {
-            let mut vec = EcoVec::from(&**self);
-            let res = f(&mut vec);
-            *self = vec.into();
-            res
+        if let Repr::Heap { data, start, end } = &mut self.0 {
+            if data.is_unique() && *start == 0 && *end == data.len() {
+                let res = f(data);
+                *end = data.len();
+                return res;
+            }
         }
+        let mut vec = match &mut self.0 {
+            Repr::Inline(inline) => inline.take_vec(),
+            Repr::Heap { .. } => EcoVec::from(&**self),
+        };
+        let res = f(&mut vec);
+        *self = vec.into();
+        res
     }
     /// Ensure that the capacity is at least `min`
     pub fn reserve_min(&mut self, min: usize) {
-        if self.data.capacity() < min {
+        let capacity = match &self.0 {
+            Repr::Inline(_) => Inline::<T>::CAPACITY,
+            Repr::Heap { data, .. } => data.capacity(),
+        };
+        if capacity < min {
             self.modify(|vec| vec.reserve(vec.capacity().max(min) - vec.len()))
         }
     }
@@ -137,23 +309,50 @@ fn cow_slice_modify() {
     assert_eq!(sub, [2, 3, 5]);
 }
 
+#[test]
+fn cow_slice_inline() {
+    let mut slice = CowSlice::from([1.0, 2.0]);
+    assert!(slice.is_inline());
+    slice.extend([3.0, 4.0]);
+    assert!(slice.is_inline());
+    slice.extend([5.0]);
+    assert!(!slice.is_inline());
+    assert_eq!(slice, [1.0, 2.0, 3.0, 4.0, 5.0]);
+    assert!(slice.slice(1..3).is_inline());
+    assert!(!CowSlice::from([0u128; 2]).is_inline());
+
+    // Elements that need to be dropped
+    let words = ["a", "b"].map(String::from);
+    let mut slice: CowSlice<String> = words.iter().cloned().collect();
+    assert!(!slice.is_inline());
+    slice.truncate(1);
+    let mut single = slice.slice(..);
+    assert!(single.is_inline());
+    single.as_mut_slice()[0].push('c');
+    assert_eq!(single, ["ac".to_string()]);
+    assert_eq!(slice, ["a".to_string()]);
+    let mut iter = single.clone().into_iter();
+    assert_eq!(iter.next_back(), Some("ac".into()));
+    assert_eq!(iter.next(), None);
+    assert_eq!(Vec::from(single), ["ac".to_string()]);
+}
+
 impl<T> Default for CowSlice<T> {
     fn default() -> Self {
-        Self {
-            data: EcoVec::new(),
-            start: 0,
-            end: 0,
-        }
+        CowSlice(Repr::Inline(Inline::new()))
     }
 }
 
 impl<T: Clone> Clone for CowSlice<T> {
     fn clone(&self) -> Self {
-        Self {
-            data: self.data.clone(),
-            start: self.start,
-            end: self.end,
-        }
+        CowSlice(match &self.0 {
+            Repr::Inline(inline) => Repr::Inline(inline.clone()),
+            Repr::Heap { data, start, end } => Repr::Heap {
+                data: data.clone(),
+                start: *start,
+                end: *end,
+            },
+        })
     }
 }
 
@@ -178,41 +377,34 @@ fn cow_slice_deref_mut() {
 
 impl<T: Clone> From<CowSlice<T>> for Vec<T> {
     fn from(mut slice: CowSlice<T>) -> Self {
-        if slice.data.is_unique() && slice.start == 0 && slice.end == slice.data.len() as u32 {
-            slice.data.into_iter().collect()
-        } else {
-            slice.to_vec()
+        if let Repr::Heap { data, start, end } = &mut slice.0 {
+            if data.is_unique() && *start == 0 && *end == data.len() {
+                return take(data).into_iter().collect();
+            }
         }
+        slice.into_iter().collect()
     }
 }
 
 impl<T: Clone> From<EcoVec<T>> for CowSlice<T> {
     fn from(data: EcoVec<T>) -> Self {
-        Self {
+        CowSlice(Repr::Heap {
             start: 0,
-            end: data.len() as u32,
+            end: data.len(),
             data,
-        }
+        })
     }
 }
 
 impl<'a, T: Clone> From<&'a [T]> for CowSlice<T> {
     fn from(slice: &'a [T]) -> Self {
-        Self {
-            start: 0,
-            end: slice.len() as u32,
-            data: slice.into(),
-        }
+        slice.iter().cloned().collect()
     }
 }
 
 impl<T: Clone, const N: usize> From<[T; N]> for CowSlice<T> {
     fn from(array: [T; N]) -> Self {
-        Self {
-            start: 0,
-            end: N as u32,
-            data: array.into(),
-        }
+        array.into_iter().collect()
     }
 }
 
@@ -278,14 +470,96 @@ impl<T: Hash> Hash for CowSlice<T> {
     }
 }
 
+/// An owned iterator over a [`CowSlice`]
+pub struct IntoIter<T>(IntoIterRepr<T>);
+
+enum IntoIterRepr<T> {
+    Inline {
+        inline: Inline<T>,
+        front: usize,
+        back: usize,
+    },
+    Heap(Take<Skip<ecow::vec::IntoIter<T>>>),
+}
+
+impl<T: Clone> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IntoIterRepr::Inline {
+                inline,
+                front,
+                back,
+            } => (*front < *back).then(|| {
+                *front += 1;
+                // Safety: the element is initialized and has not been read yet
+                unsafe { ptr::read(inline.as_ptr().add(*front - 1)) }
+            }),
+            IntoIterRepr::Heap(iter) => iter.next(),
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            IntoIterRepr::Inline { front, back, .. } => (back - front, Some(back - front)),
+            IntoIterRepr::Heap(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<T: Clone> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IntoIterRepr::Inline {
+                inline,
+                front,
+                back,
+            } => (*front < *back).then(|| {
+                *back -= 1;
+                // Safety: the element is initialized and has not been read yet
+                unsafe { ptr::read(inline.as_ptr().add(*back)) }
+            }),
+            IntoIterRepr::Heap(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<T: Clone> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> Drop for IntoIter<T> {
+    fn drop(&mut self) {
+        if let IntoIterRepr::Inline {
+            inline,
+            front,
+            back,
+        } = &mut self.0
+        {
+            for i in *front..*back {
+                // Safety: the element is initialized and has not been read
+                unsafe { ptr::drop_in_place(inline.as_mut_ptr().add(i)) };
+            }
+        }
+    }
+}
+
 impl<T: Clone> IntoIterator for CowSlice<T> {
     type Item = T;
-    type IntoIter = Take<Skip<<EcoVec<T> as IntoIterator>::IntoIter>>;
+    type IntoIter = IntoIter<T>;
     fn into_iter(self) -> Self::IntoIter {
-        self.data
-            .into_iter()
-            .skip(self.start as usize)
-            .take((self.end - self.start) as usize)
+        IntoIter(match self.0 {
+            Repr::Inline(mut inline) => {
+                let back = inline.len as usize;
+                // The iterator is now responsible for dropping the elements
+                inline.len = 0;
+                IntoIterRepr::Inline {
+                    inline,
+                    front: 0,
+                    back,
+                }
+            }
+            Repr::Heap { data, start, end } => {
+                IntoIterRepr::Heap(data.into_iter().skip(start).take(end - start))
+            }
+        })
     }
 }
 
@@ -307,14 +581,24 @@ impl<'a, T: Clone> IntoIterator for &'a mut CowSlice<T> {
 
 impl<T: Clone> FromIterator<T> for CowSlice<T> {
     fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
-        let mut data = EcoVec::new();
-        data.extend(iter);
-        data.into()
+        let iter = iter.into_iter();
+        if iter.size_hint().0 > Inline::<T>::CAPACITY {
+            let mut data = EcoVec::new();
+            data.extend(iter);
+            data.into()
+        } else {
+            let mut slice = CowSlice::new();
+            slice.extend(iter);
+            slice
+        }
     }
 }
 
 impl<T: Clone> Extend<T> for CowSlice<T> {
     fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
-        self.modify(|vec| vec.extend(iter))
+        match self.try_extend(iter.into_iter().map(Ok::<_, Infallible>)) {
+            Ok(()) => {}
+            Err(e) => match e {},
+        }
     }
 }
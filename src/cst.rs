@@ -0,0 +1,459 @@
+//! A lossless concrete syntax tree of Uiua code
+//!
+//! Unlike the [`ast`](crate::ast), the CST keeps every character of the source,
+//! including whitespace, comments, and whether each primitive was written as a glyph,
+//! as an ASCII symbol, or by name.
+//! Printing a [`Node`] reproduces its source exactly, even if the code has errors.
+
+use std::{fmt, iter::Peekable, path::Path, sync::Arc, vec};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    ast::{Item, Word},
+    lex::{lex, AsciiToken, CodeSpan, Loc, Sp, Token},
+    parse::parse as parse_ast,
+    Primitive,
+};
+
+/// A node in the syntax tree
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub span: CodeSpan,
+    pub children: Vec<Element>,
+}
+
+/// The kind of a [`Node`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    /// A whole file
+    Root,
+    /// Items between a pair of `---`
+    TestScope,
+    /// A line of code that is not a binding
+    Line,
+    Binding,
+    Destructure,
+    LocalBinding,
+    Func,
+    Array,
+    Switch,
+    Strand,
+    Modified,
+    MultilineString,
+}
+
+/// A child of a [`Node`]
+#[derive(Debug, Clone)]
+pub enum Element {
+    Node(Node),
+    Token(CstToken),
+}
+
+/// A leaf of the syntax tree
+#[derive(Debug, Clone)]
+pub struct CstToken {
+    pub kind: TokenKind,
+    pub span: CodeSpan,
+}
+
+impl CstToken {
+    /// The token's source text
+    pub fn text(&self) -> &str {
+        self.span.as_str()
+    }
+}
+
+/// The kind of a [`CstToken`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Whitespace,
+    Newline,
+    Comment,
+    Ident,
+    Number,
+    Char,
+    String,
+    FormatString,
+    MultilineString,
+    Primitive(Primitive, Spelling),
+    /// A binding arrow
+    Arrow,
+    /// Brackets, bars, test scope delimiters, and other punctuation
+    Delimiter,
+    /// Text that could not be lexed
+    Error,
+}
+
+/// How a primitive was written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Spelling {
+    /// As its glyph, like `⇌`
+    Glyph,
+    /// As an ASCII symbol, like `*`
+    Ascii,
+    /// By its name or a prefix of it, like `rev`
+    Name,
+}
+
+impl Node {
+    /// Iterate over the tokens in this node, in source order
+    pub fn tokens(&self) -> Box<dyn Iterator<Item = &CstToken> + '_> {
+        Box::new(self.children.iter().flat_map(|child| match child {
+            Element::Node(node) => node.tokens(),
+            Element::Token(token) => Box::new(Some(token).into_iter()),
+        }))
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.tokens() {
+            write!(f, "{}", token.text())?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse Uiua code into a lossless syntax tree
+pub fn parse(input: &str, path: Option<&Path>) -> Node {
+    let (ast_tokens, _) = lex(input, path);
+    let (items, _, _) = parse_ast(input, path);
+    let mut ranges = Ranges::default();
+    ranges.test_scopes(&ast_tokens);
+    ranges.items(&items);
+    let mut nodes = ranges.nodes;
+    nodes.sort_by_key(|range| (range.start, usize::MAX - range.end));
+    let tokens = cst_tokens(input, path, ast_tokens, &ranges.arrows);
+    let start = Loc {
+        char_pos: 0,
+        byte_pos: 0,
+        line: 1,
+        col: 1,
+    };
+    let span = CodeSpan {
+        start,
+        end: advance(start, input),
+        path: path.map(Into::into),
+        input: input.into(),
+    };
+    let children = build(
+        input.len(),
+        &mut nodes.into_iter().peekable(),
+        &mut tokens.into_iter().peekable(),
+    );
+    Node {
+        kind: NodeKind::Root,
+        span,
+        children,
+    }
+}
+
+/// The byte range of a node that has not been built yet
+struct Range {
+    kind: NodeKind,
+    start: usize,
+    end: usize,
+}
+
+impl Range {
+    fn new(kind: NodeKind, start: &CodeSpan, end: &CodeSpan) -> Self {
+        Range {
+            kind,
+            start: start.start.byte_pos,
+            end: end.end.byte_pos.max(start.end.byte_pos),
+        }
+    }
+}
+
+fn build(
+    end: usize,
+    ranges: &mut Peekable<vec::IntoIter<Range>>,
+    tokens: &mut Peekable<vec::IntoIter<CstToken>>,
+) -> Vec<Element> {
+    let mut children = Vec::new();
+    while let Some(token) = tokens.peek() {
+        let token_start = token.span.start.byte_pos;
+        if token_start >= end {
+            break;
+        }
+        if ranges
+            .peek()
+            .is_some_and(|range| range.start <= token_start)
+        {
+            let range = ranges.next().unwrap();
+            let grandchildren = build(range.end.min(end), ranges, tokens);
+            if let Some(span) = children_span(&grandchildren) {
+                children.push(Element::Node(Node {
+                    kind: range.kind,
+                    span,
+                    children: grandchildren,
+                }));
+            }
+        } else {
+            children.push(Element::Token(tokens.next().unwrap()));
+        }
+    }
+    children
+}
+
+fn children_span(children: &[Element]) -> Option<CodeSpan> {
+    let span = |elem: &Element| match elem {
+        Element::Node(node) => node.span.clone(),
+        Element::Token(token) => token.span.clone(),
+    };
+    Some(span(children.first()?).merge(span(children.last()?)))
+}
+
+/// Collects the ranges of nodes and binding arrows from the AST
+#[derive(Default)]
+struct Ranges {
+    nodes: Vec<Range>,
+    arrows: Vec<Range>,
+}
+
+impl Ranges {
+    fn test_scopes(&mut self, tokens: &[Sp<Token>]) {
+        let mut delims = (tokens.iter())
+            .filter(|token| matches!(token.value, Token::Simple(AsciiToken::TripleMinus)));
+        while let Some(open) = delims.next() {
+            let end = match delims.next() {
+                Some(close) => close.span.end.byte_pos,
+                None => usize::MAX,
+            };
+            self.nodes.push(Range {
+                kind: NodeKind::TestScope,
+                start: open.span.start.byte_pos,
+                end,
+            });
+        }
+    }
+    fn arrow(&mut self, span: &CodeSpan) {
+        self.arrows.push(Range::new(NodeKind::Binding, span, span));
+    }
+    fn items(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::TestScope(items) => self.items(items),
+                Item::Words(words) => {
+                    if let (Some(first), Some(last)) = (words.first(), words.last()) {
+                        self.nodes
+                            .push(Range::new(NodeKind::Line, &first.span, &last.span));
+                        self.words(words);
+                    }
+                }
+                Item::Binding(binding) => {
+                    let end = (binding.words.last().map(|word| &word.span))
+                        .or(binding.signature.as_ref().map(|sig| &sig.span))
+                        .unwrap_or(&binding.arrow_span);
+                    self.nodes
+                        .push(Range::new(NodeKind::Binding, &binding.name.span, end));
+                    self.arrow(&binding.arrow_span);
+                    self.words(&binding.words);
+                }
+                Item::Destructure(des) => {
+                    let end = des.words.last().map_or(&des.arrow_span, |word| &word.span);
+                    self.nodes
+                        .push(Range::new(NodeKind::Destructure, &des.span(), end));
+                    self.arrow(&des.arrow_span);
+                    self.words(&des.words);
+                }
+                Item::ExtraNewlines(_) => {}
+            }
+        }
+    }
+    fn words(&mut self, words: &[Sp<Word>]) {
+        for word in words {
+            let kind = match &word.value {
+                Word::Strand(items) => {
+                    self.words(items);
+                    NodeKind::Strand
+                }
+                Word::Array(arr) => {
+                    for line in &arr.lines {
+                        self.words(line);
+                    }
+                    NodeKind::Array
+                }
+                Word::Func(func) => {
+                    for line in &func.lines {
+                        self.words(line);
+                    }
+                    NodeKind::Func
+                }
+                Word::Switch(sw) => {
+                    for branch in &sw.branches {
+                        for line in &branch.value.lines {
+                            self.words(line);
+                        }
+                    }
+                    NodeKind::Switch
+                }
+                Word::Modified(modified) => {
+                    self.words(&modified.operands);
+                    NodeKind::Modified
+                }
+                Word::Local(local) => {
+                    self.arrow(&local.arrow_span);
+                    self.words(&local.words);
+                    NodeKind::LocalBinding
+                }
+                Word::Destructure(des) => {
+                    self.arrow(&des.arrow_span);
+                    self.words(&des.words);
+                    NodeKind::Destructure
+                }
+                Word::MultilineString(_) => NodeKind::MultilineString,
+                _ => continue,
+            };
+            self.nodes.push(Range::new(kind, &word.span, &word.span));
+        }
+    }
+}
+
+/// Convert lexer tokens to CST tokens, filling in any text the lexer skipped
+fn cst_tokens(
+    input: &str,
+    path: Option<&Path>,
+    tokens: Vec<Sp<Token>>,
+    arrows: &[Range],
+) -> Vec<CstToken> {
+    let input: Arc<str> = input.into();
+    let path: Option<Arc<Path>> = path.map(Into::into);
+    let mut loc = Loc {
+        char_pos: 0,
+        byte_pos: 0,
+        line: 1,
+        col: 1,
+    };
+    let mut cst_tokens = Vec::with_capacity(tokens.len());
+    let push_gap = |start: Loc, end: Loc, cst_tokens: &mut Vec<CstToken>| {
+        if end.byte_pos <= start.byte_pos {
+            return;
+        }
+        let text = &input[start.byte_pos..end.byte_pos];
+        let kind = if !text.chars().all(char::is_whitespace) {
+            TokenKind::Error
+        } else if text.contains('\n') {
+            TokenKind::Newline
+        } else {
+            TokenKind::Whitespace
+        };
+        cst_tokens.push(CstToken {
+            kind,
+            span: CodeSpan {
+                start,
+                end,
+                path: path.clone(),
+                input: input.clone(),
+            },
+        });
+    };
+    // `=` can be used as a binding arrow
+    let is_arrow = |span: &CodeSpan| {
+        (arrows.iter())
+            .any(|arrow| arrow.start <= span.start.byte_pos && span.end.byte_pos <= arrow.end)
+    };
+    for token in tokens {
+        push_gap(loc, token.span.start, &mut cst_tokens);
+        loc = token.span.end;
+        let text = token.span.as_str();
+        let kind = match token.value {
+            Token::Comment => TokenKind::Comment,
+            Token::Ident => TokenKind::Ident,
+            Token::Number => TokenKind::Number,
+            Token::Char(_) => TokenKind::Char,
+            Token::Str(_) => TokenKind::String,
+            Token::FormatStr(..) => TokenKind::FormatString,
+            Token::MultilineString(..) => TokenKind::MultilineString,
+            Token::Glyph(prim) => {
+                let mut chars = text.chars();
+                let glyph = chars.next().filter(|_| chars.next().is_none());
+                let spelling = if glyph.is_some_and(|c| !c.is_ascii() || prim.glyph() == Some(c)) {
+                    Spelling::Glyph
+                } else {
+                    Spelling::Name
+                };
+                TokenKind::Primitive(prim, spelling)
+            }
+            Token::Simple(AsciiToken::Equal) if is_arrow(&token.span) => TokenKind::Arrow,
+            Token::Simple(ascii) => match Primitive::from_simple(ascii) {
+                Some(prim) => TokenKind::Primitive(prim, Spelling::Ascii),
+                None => TokenKind::Delimiter,
+            },
+            Token::LeftArrow | Token::HookArrow => TokenKind::Arrow,
+            Token::Newline => TokenKind::Newline,
+            Token::Spaces => TokenKind::Whitespace,
+        };
+        cst_tokens.push(CstToken {
+            kind,
+            span: token.span,
+        });
+    }
+    let end = advance(loc, &input[loc.byte_pos..]);
+    push_gap(loc, end, &mut cst_tokens);
+    cst_tokens
+}
+
+/// Get the location after some text, the same way the lexer does
+fn advance(mut loc: Loc, text: &str) -> Loc {
+    for c in text.graphemes(true) {
+        for c in c.chars() {
+            match c {
+                '\n' => {
+                    loc.line += 1;
+                    loc.col = 1;
+                }
+                '\r' => {}
+                _ => loc.col += 1,
+            }
+        }
+        loc.char_pos += 1;
+        loc.byte_pos += c.len();
+    }
+    loc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cst_round_trip() {
+        let paths = (std::fs::read_dir("tests").unwrap())
+            .chain(std::fs::read_dir("examples").unwrap())
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|s| s == "ua"));
+        for path in paths {
+            let input = std::fs::read_to_string(&path).unwrap();
+            let cst = parse(&input, Some(&path));
+            assert_eq!(
+                cst.to_string(),
+                input,
+                "{} did not round-trip",
+                path.display()
+            );
+        }
+        let input = "X ← rev /+[1 2] # comment\n---\n* ⇌ 2 \"unclosed";
+        let cst = parse(input, None);
+        assert_eq!(cst.to_string(), input);
+        assert!(matches!(&cst.children[0], Element::Node(node) if node.kind == NodeKind::Binding));
+        let spellings: Vec<Spelling> = (cst.tokens())
+            .filter_map(|token| match token.kind {
+                TokenKind::Primitive(_, spelling) => Some(spelling),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            spellings,
+            [
+                Spelling::Name,
+                Spelling::Glyph,
+                Spelling::Glyph,
+                Spelling::Ascii,
+                Spelling::Glyph
+            ]
+        );
+    }
+}
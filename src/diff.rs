@@ -0,0 +1,265 @@
+//! Comparing Uiua code by what it does rather than how it is written
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::{Item, Word},
+    format::{format_items, line_changes, FormatConfig},
+    lex::Sp,
+    parse::parse,
+    Ident, UiuaResult,
+};
+
+/// A difference between two versions of some code
+///
+/// Code is compared without its comments and spaces, after names of primitives are replaced with their glyphs,
+/// so differences in spelling, spacing, and comments are not reported.
+/// The code in a change is formatted without its comments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodeChange {
+    /// A binding that is only in the new code
+    Added { name: Ident, new: String },
+    /// A binding that is only in the old code
+    Removed { name: Ident, old: String },
+    /// A binding whose code is different
+    Changed {
+        name: Ident,
+        old: String,
+        new: String,
+    },
+    /// Lines of code outside of bindings that are different
+    ///
+    /// A line may be several lines long if it has a multiline function in it.
+    Code { old: Vec<String>, new: Vec<String> },
+}
+
+/// Compare two versions of some code
+///
+/// Bindings are matched up by name. If a name is bound more than once,
+/// each binding is matched with the one bound the same number of times before it.
+/// Changed and added bindings come first in the order of the new code,
+/// then removed bindings, then changes to the code outside of bindings.
+pub fn diff(old: &str, new: &str) -> UiuaResult<Vec<CodeChange>> {
+    let old = Canonical::new(old)?;
+    let new = Canonical::new(new)?;
+    let mut changes = Vec::new();
+    for (key, new_code) in &new.bindings {
+        let name = key.0;
+        match old.bindings.iter().find(|(old_key, _)| old_key == key) {
+            Some((_, old_code)) if old_code.key == new_code.key => {}
+            Some((_, old_code)) => changes.push(CodeChange::Changed {
+                name,
+                old: old_code.text.clone(),
+                new: new_code.text.clone(),
+            }),
+            None => changes.push(CodeChange::Added {
+                name,
+                new: new_code.text.clone(),
+            }),
+        }
+    }
+    for (key, old_code) in &old.bindings {
+        if !new.bindings.iter().any(|(new_key, _)| new_key == key) {
+            changes.push(CodeChange::Removed {
+                name: key.0,
+                old: old_code.text.clone(),
+            });
+        }
+    }
+    // Each line is compared as a whole, even if it is several lines long
+    let keys = |lines: &[Code]| {
+        let keys: Vec<String> = lines
+            .iter()
+            .map(|line| line.key.replace('\n', "\r"))
+            .collect();
+        keys.join("\n")
+    };
+    let texts = |lines: &[Code]| lines.iter().map(|line| line.text.clone()).collect();
+    let mut offset = 0;
+    for change in line_changes(&keys(&old.code), &keys(&new.code)) {
+        let start = change.line - 1;
+        let new_start = start.wrapping_add_signed(offset);
+        changes.push(CodeChange::Code {
+            old: texts(&old.code[start..start + change.old.len()]),
+            new: texts(&new.code[new_start..new_start + change.new.len()]),
+        });
+        offset += change.new.len() as isize - change.old.len() as isize;
+    }
+    Ok(changes)
+}
+
+/// Code split into items, without comments
+struct Canonical {
+    /// The code of each binding, keyed by its name and how many times the name was bound before
+    bindings: Vec<((Ident, usize), Code)>,
+    /// The lines of code outside of bindings
+    code: Vec<Code>,
+}
+
+/// The code of an item
+struct Code {
+    /// The code with a single space between every pair of words, which is what is compared
+    key: String,
+    /// The formatted code
+    text: String,
+}
+
+impl Canonical {
+    fn new(input: &str) -> UiuaResult<Self> {
+        let (items, errors, _) = parse(input, None);
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+        let mut canonical = Canonical {
+            bindings: Vec::new(),
+            code: Vec::new(),
+        };
+        canonical.items(items, &mut HashMap::new());
+        Ok(canonical)
+    }
+    fn items(&mut self, items: Vec<Item>, counts: &mut HashMap<Ident, usize>) {
+        let config = FormatConfig::default();
+        for mut item in items {
+            let name = match &mut item {
+                Item::TestScope(items) => {
+                    self.items(std::mem::take(items), counts);
+                    continue;
+                }
+                Item::ExtraNewlines(_) => continue,
+                Item::Words(words) => {
+                    strip_comments(words);
+                    if words.iter().all(|word| matches!(word.value, Word::Spaces)) {
+                        continue;
+                    }
+                    None
+                }
+                Item::Binding(binding) => {
+                    strip_comments(&mut binding.words);
+                    Some(binding.name.value)
+                }
+                Item::Destructure(des) => {
+                    strip_comments(&mut des.words);
+                    let names: Vec<&str> = des.names.iter().map(|name| &*name.value).collect();
+                    Some(names.join(" ").into())
+                }
+            };
+            let text = format_items(&[item.clone()], &config).output;
+            match &mut item {
+                Item::Words(words) => space_words(words),
+                Item::Binding(binding) => space_words(&mut binding.words),
+                Item::Destructure(des) => space_words(&mut des.words),
+                Item::TestScope(_) | Item::ExtraNewlines(_) => {}
+            }
+            let key = format_items(&[item], &config).output;
+            let code = Code {
+                key: key.trim_end().into(),
+                text: text.trim_end().into(),
+            };
+            if let Some(name) = name {
+                let count = counts.entry(name).or_default();
+                self.bindings.push(((name, *count), code));
+                *count += 1;
+            } else {
+                self.code.push(code);
+            }
+        }
+    }
+}
+
+/// Put a single space between every pair of words, and between the words in them
+fn space_words(words: &mut Vec<Sp<Word>>) {
+    let space_lines = |lines: &mut Vec<Vec<Sp<Word>>>| lines.iter_mut().for_each(space_words);
+    words.retain(|word| !matches!(word.value, Word::Spaces));
+    for word in words.iter_mut() {
+        match &mut word.value {
+            Word::Array(arr) => space_lines(&mut arr.lines),
+            Word::Func(func) => space_lines(&mut func.lines),
+            Word::Switch(sw) => {
+                for branch in &mut sw.branches {
+                    space_lines(&mut branch.value.lines);
+                }
+            }
+            Word::Modified(modified) => space_words(&mut modified.operands),
+            Word::Local(local) => space_words(&mut local.words),
+            Word::Destructure(des) => space_words(&mut des.words),
+            _ => {}
+        }
+    }
+    let mut spaced = Vec::with_capacity(words.len() * 2);
+    for (i, word) in words.drain(..).enumerate() {
+        if i > 0 {
+            spaced.push(word.span.clone().sp(Word::Spaces));
+        }
+        spaced.push(word);
+    }
+    *words = spaced;
+}
+
+/// Remove the comments from some words and the words in them
+fn strip_comments(words: &mut Vec<Sp<Word>>) {
+    let strip_lines = |lines: &mut Vec<Vec<Sp<Word>>>| {
+        lines.iter_mut().for_each(strip_comments);
+        lines.retain(|line| !line.is_empty());
+    };
+    words.retain(|word| !matches!(word.value, Word::Comment(_)));
+    for word in words {
+        match &mut word.value {
+            Word::Strand(items) => strip_comments(items),
+            Word::Array(arr) => strip_lines(&mut arr.lines),
+            Word::Func(func) => strip_lines(&mut func.lines),
+            Word::Switch(sw) => {
+                for branch in &mut sw.branches {
+                    strip_lines(&mut branch.value.lines);
+                }
+            }
+            Word::Modified(modified) => strip_comments(&mut modified.operands),
+            Word::Local(local) => strip_comments(&mut local.words),
+            Word::Destructure(des) => strip_comments(&mut des.words),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff() {
+        let old = "\
+# Double a number
+Double ← +.
+Sq ← ×.
+Gone ← 5
+reverse range 5
+Double 3";
+        let new = "\
+Double←+ . # Add to itself
+Sq ← ×. ×.
+⇌⇡5
+Double 4
+New ← 1";
+        assert_eq!(
+            super::diff(old, new).unwrap(),
+            [
+                CodeChange::Changed {
+                    name: "Sq".into(),
+                    old: "Sq ← ×.".into(),
+                    new: "Sq ← ×. ×.".into(),
+                },
+                CodeChange::Added {
+                    name: "New".into(),
+                    new: "New ← 1".into(),
+                },
+                CodeChange::Removed {
+                    name: "Gone".into(),
+                    old: "Gone ← 5".into(),
+                },
+                CodeChange::Code {
+                    old: vec!["Double 3".into()],
+                    new: vec!["Double 4".into()],
+                },
+            ]
+        );
+    }
+}
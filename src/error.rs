@@ -3,10 +3,14 @@ use std::{convert::Infallible, error::Error, fmt, io, path::PathBuf, sync::Arc};
 use colored::*;
 
 use crate::{
+    array::FormatShape,
+    explain::ErrorCode,
     function::FunctionId,
     lex::{Sp, Span},
     parse::ParseError,
+    primitive::Primitive,
     value::Value,
+    Ident,
 };
 
 #[derive(Debug, Clone)]
@@ -15,13 +19,23 @@ pub enum UiuaError {
     Format(PathBuf, Arc<io::Error>),
     Parse(Vec<Sp<ParseError>>),
     Run(Sp<String, Span>),
+    StackUnderflow(Box<StackUnderflow>),
+    ShapeMismatch(Box<ShapeMismatch>),
+    UnknownIdent(Box<UnknownIdent>),
     Traced {
         error: Box<Self>,
+        /// The frames of the trace, from innermost to outermost
         trace: Vec<TraceFrame>,
+        /// How many frames were left out of the middle of the trace
+        ///
+        /// The left out frames would be right after the first half of `trace`.
+        elided: usize,
     },
     Throw(Box<Value>, Span),
     Break(usize, Span),
+    Continue(usize, Span),
     Timeout(Span),
+    Interrupted(Span),
     Fill(Box<Self>),
 }
 
@@ -39,6 +53,38 @@ impl From<Sp<String>> for UiuaError {
     }
 }
 
+/// A value was popped from an empty stack
+#[derive(Debug, Clone)]
+pub struct StackUnderflow {
+    /// What the value was for, like `argument 2`
+    pub expected: String,
+    /// The primitive that tried to pop the value, if any
+    pub who: Option<Primitive>,
+    pub span: Span,
+}
+
+/// Two arrays' shapes were not compatible
+#[derive(Debug, Clone)]
+pub struct ShapeMismatch {
+    pub a: Vec<usize>,
+    pub b: Vec<usize>,
+    /// The labels of the arrays, if they have them
+    pub a_label: Option<String>,
+    pub b_label: Option<String>,
+    /// The primitive that required the shapes to match, if any
+    pub op: Option<Primitive>,
+    pub span: Span,
+}
+
+/// A name was used that is not bound
+#[derive(Debug, Clone)]
+pub struct UnknownIdent {
+    pub name: Ident,
+    /// Bound names that are similar to the unknown one
+    pub suggestions: Vec<Ident>,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TraceFrame {
     pub id: FunctionId,
@@ -61,22 +107,112 @@ impl fmt::Display for UiuaError {
                 Ok(())
             }
             UiuaError::Run(error) => write!(f, "{error}"),
-            UiuaError::Traced { error, trace } => {
+            UiuaError::StackUnderflow(_)
+            | UiuaError::ShapeMismatch(_)
+            | UiuaError::UnknownIdent(_) => {
+                let span = self.span().unwrap();
+                write!(f, "{span}: {}", self.structured_message().unwrap())
+            }
+            UiuaError::Traced {
+                error,
+                trace,
+                elided,
+            } => {
                 write!(f, "{error}")?;
-                for line in format_trace(trace) {
-                    writeln!(f, "{line}")?;
+                for line in format_trace(trace, *elided) {
+                    write!(f, "\n{line}")?;
                 }
                 Ok(())
             }
             UiuaError::Throw(value, span) => write!(f, "{span}: {value}"),
             UiuaError::Break(_, span) => write!(f, "{span}: Break amount exceeded loop depth"),
+            UiuaError::Continue(_, span) => {
+                write!(f, "{span}: Continue amount exceeded loop depth")
+            }
             UiuaError::Timeout(_) => write!(f, "Maximum execution time exceeded"),
+            UiuaError::Interrupted(_) => write!(f, "Interrupted"),
             UiuaError::Fill(error) => error.fmt(f),
         }
     }
 }
 
 impl UiuaError {
+    /// The message of a structured error, without its span
+    fn structured_message(&self) -> Option<String> {
+        Some(match self {
+            UiuaError::StackUnderflow(e) => {
+                format!("Stack was empty when evaluating {}", e.expected)
+            }
+            UiuaError::ShapeMismatch(e) => {
+                let shape = |shape: &[usize], label: &Option<String>| match label {
+                    Some(label) => format!("`{label}` {}", FormatShape(shape)),
+                    None => FormatShape(shape).to_string(),
+                };
+                format!(
+                    "Shapes {} and {} do not match",
+                    shape(&e.a, &e.a_label),
+                    shape(&e.b, &e.b_label)
+                )
+            }
+            UiuaError::UnknownIdent(e) => format!("Unknown identifier `{}`", e.name),
+            _ => return None,
+        })
+    }
+    /// Get the message of the error, without its span or trace
+    pub fn bare_message(&self) -> String {
+        if let Some(message) = self.structured_message() {
+            return message;
+        }
+        match self {
+            UiuaError::Run(error) => error.value.clone(),
+            UiuaError::Parse(errors) => (errors.iter())
+                .map(|error| error.value.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            UiuaError::Throw(value, _) => value.to_string(),
+            UiuaError::Break(..) => "Break amount exceeded loop depth".into(),
+            UiuaError::Continue(..) => "Continue amount exceeded loop depth".into(),
+            UiuaError::Traced { error, .. } | UiuaError::Fill(error) => error.bare_message(),
+            error => error.to_string(),
+        }
+    }
+    /// Get the underlying error, without any trace or fill marker
+    pub fn root(&self) -> &Self {
+        match self {
+            UiuaError::Traced { error, .. } | UiuaError::Fill(error) => error.root(),
+            error => error,
+        }
+    }
+    /// Get the span of the code that caused the error, if there is one
+    pub fn span(&self) -> Option<&Span> {
+        match self.root() {
+            UiuaError::Run(error) => Some(&error.span),
+            UiuaError::StackUnderflow(e) => Some(&e.span),
+            UiuaError::ShapeMismatch(e) => Some(&e.span),
+            UiuaError::UnknownIdent(e) => Some(&e.span),
+            UiuaError::Throw(_, span)
+            | UiuaError::Break(_, span)
+            | UiuaError::Continue(_, span)
+            | UiuaError::Timeout(span)
+            | UiuaError::Interrupted(span) => Some(span),
+            _ => None,
+        }
+    }
+    /// Get the primitive that caused the error, if it is known
+    pub fn primitive(&self) -> Option<Primitive> {
+        match self.root() {
+            UiuaError::StackUnderflow(e) => e.who,
+            UiuaError::ShapeMismatch(e) => e.op,
+            _ => None,
+        }
+    }
+    /// Get the names suggested in place of an unknown identifier
+    pub fn suggestions(&self) -> &[Ident] {
+        match self.root() {
+            UiuaError::UnknownIdent(e) => &e.suggestions,
+            _ => &[],
+        }
+    }
     pub fn message(&self) -> String {
         match self {
             UiuaError::Traced { error, .. } => error.message(),
@@ -92,16 +228,34 @@ impl UiuaError {
     }
     pub fn break_data(self) -> Result<(usize, Span), Self> {
         match self {
-            UiuaError::Traced { error, trace } => {
-                error.break_data().map_err(|error| UiuaError::Traced {
-                    error: Box::new(error),
-                    trace,
-                })
-            }
+            UiuaError::Traced {
+                error,
+                trace,
+                elided,
+            } => error.break_data().map_err(|error| UiuaError::Traced {
+                error: Box::new(error),
+                trace,
+                elided,
+            }),
             UiuaError::Break(n, span) => Ok((n, span)),
             error => Err(error),
         }
     }
+    pub fn continue_data(self) -> Result<(usize, Span), Self> {
+        match self {
+            UiuaError::Traced {
+                error,
+                trace,
+                elided,
+            } => error.continue_data().map_err(|error| UiuaError::Traced {
+                error: Box::new(error),
+                trace,
+                elided,
+            }),
+            UiuaError::Continue(n, span) => Ok((n, span)),
+            error => Err(error),
+        }
+    }
     /// Check if the error is fill-related
     pub(crate) fn is_fill(&self) -> bool {
         match self {
@@ -116,11 +270,11 @@ impl UiuaError {
     }
 }
 
-fn format_trace(trace: &[TraceFrame]) -> Vec<String> {
-    let max_id_length = trace
-        .iter()
-        .filter(|frame| frame.span != Span::Builtin)
-        .map(|frame| frame.id.to_string().chars().count())
+fn format_trace(trace: &[TraceFrame], elided: usize) -> Vec<String> {
+    let names: Vec<String> = trace.iter().map(frame_name).collect();
+    let max_id_length = (trace.iter().zip(&names))
+        .filter(|(frame, _)| frame.span != Span::Builtin)
+        .map(|(_, name)| name.chars().count())
         .max()
         .unwrap_or(0);
     let max_span_length = trace
@@ -132,20 +286,93 @@ fn format_trace(trace: &[TraceFrame]) -> Vec<String> {
         .max()
         .unwrap_or(0);
     let mut lines = Vec::new();
-    for frame in trace {
+    for (i, (frame, name)) in trace.iter().zip(names).enumerate() {
+        if elided > 0 && i == trace.len() / 2 {
+            lines.push(format!("  ({elided} frames omitted)"));
+        }
         if frame.id == FunctionId::Main {
             continue;
         }
         lines.push(match &frame.span {
-            Span::Code(span) => format!(
-                "  in {:max_id_length$} at {:max_span_length$}",
-                frame.id.to_string(),
-                span
-            ),
-            Span::Builtin => format!("  in {:max_id_length$}", frame.id.to_string()),
+            Span::Code(span) => format!("  in {name:max_id_length$} at {span:max_span_length$}"),
+            Span::Builtin => format!("  in {name:max_id_length$}"),
         });
     }
-    lines
+    if elided > 0 && trace.is_empty() {
+        lines.push(format!("  ({elided} frames omitted)"));
+    }
+    collapse_repeats(lines)
+}
+
+/// The most characters of code shown for an anonymous function in a trace
+const MAX_EXCERPT_CHARS: usize = 30;
+
+/// The name of a trace frame's function
+///
+/// Anonymous functions are named by an excerpt of their code.
+/// If the function was passed to a modifier right before it, the modifier is included.
+fn frame_name(frame: &TraceFrame) -> String {
+    let FunctionId::Anonymous(func) = &frame.id else {
+        return frame.id.to_string();
+    };
+    let start = match &frame.span {
+        Span::Code(call)
+            if Arc::ptr_eq(&call.input, &func.input)
+                && call.end.byte_pos <= func.start.byte_pos
+                && (call.input[call.end.byte_pos..func.start.byte_pos].trim()).is_empty() =>
+        {
+            call.start.byte_pos
+        }
+        _ => func.start.byte_pos,
+    };
+    let code = func.input[start..func.end.byte_pos]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if code.chars().count() <= MAX_EXCERPT_CHARS {
+        code
+    } else {
+        (code.chars().take(MAX_EXCERPT_CHARS - 1))
+            .chain(['…'])
+            .collect()
+    }
+}
+
+/// The longest run of lines that is collapsed when it repeats
+const MAX_REPEATED_LINES: usize = 8;
+
+/// Collapse lines that repeat, like the frames of a deep recursion
+///
+/// Runs of lines that repeat are shown once, followed by how many more times they repeat.
+fn collapse_repeats(lines: Vec<String>) -> Vec<String> {
+    let mut collapsed = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        // Find the run length that covers the most lines
+        let (mut run, mut reps) = (1, 1);
+        for len in 1..=MAX_REPEATED_LINES.min((lines.len() - i) / 2) {
+            let block = &lines[i..i + len];
+            let count = (lines[i..].chunks_exact(len))
+                .take_while(|chunk| *chunk == block)
+                .count();
+            if count > 1 && count * len > run * reps {
+                (run, reps) = (len, count);
+            }
+        }
+        collapsed.extend_from_slice(&lines[i..i + run]);
+        if reps > 1 {
+            let times = match reps - 1 {
+                1 => "1 more time".to_string(),
+                n => format!("{n} more times"),
+            };
+            collapsed.push(match run {
+                1 => format!("  (repeated {times})"),
+                run => format!("  (the last {run} frames repeated {times})"),
+            });
+        }
+        i += run * reps;
+    }
+    collapsed
 }
 
 impl From<Vec<Sp<ParseError>>> for UiuaError {
@@ -164,6 +391,13 @@ impl From<Infallible> for UiuaError {
 
 impl UiuaError {
     pub fn report(&self) -> Report {
+        let report = self.report_impl();
+        match self.code() {
+            Some(code) => report.code(code),
+            None => report,
+        }
+    }
+    fn report_impl(&self) -> Report {
         let kind = ReportKind::Error;
         match self {
             UiuaError::Parse(errors) => Report::new_multi(
@@ -173,14 +407,37 @@ impl UiuaError {
                     .map(|error| (error.value.to_string(), error.span.clone().into())),
             ),
             UiuaError::Run(error) => Report::new_multi(kind, [(&error.value, error.span.clone())]),
-            UiuaError::Traced { error, trace } => error.report().trace(trace),
+            UiuaError::StackUnderflow(_)
+            | UiuaError::ShapeMismatch(_)
+            | UiuaError::UnknownIdent(_) => {
+                let mut message = self.structured_message().unwrap();
+                if !self.suggestions().is_empty() {
+                    let names: Vec<String> = (self.suggestions().iter())
+                        .map(|s| format!("`{s}`"))
+                        .collect();
+                    message.push_str(&format!(". Did you mean {}?", names.join(" or ")));
+                }
+                Report::new_multi(kind, [(message, self.span().unwrap().clone())])
+            }
+            UiuaError::Traced {
+                error,
+                trace,
+                elided,
+            } => error.report().trace(trace, *elided),
             UiuaError::Throw(message, span) => Report::new_multi(kind, [(&message, span.clone())]),
             UiuaError::Break(_, span) => {
                 Report::new_multi(kind, [("Break amount exceeded loop depth", span.clone())])
             }
+            UiuaError::Continue(_, span) => Report::new_multi(
+                kind,
+                [("Continue amount exceeded loop depth", span.clone())],
+            ),
             UiuaError::Timeout(span) => {
                 Report::new_multi(kind, [("Maximum execution time exceeded", span.clone())])
             }
+            UiuaError::Interrupted(span) => {
+                Report::new_multi(kind, [("Interrupted", span.clone())])
+            }
             UiuaError::Fill(error) => error.report(),
             UiuaError::Load(..) | UiuaError::Format(..) => Report::new(kind, self.to_string()),
         }
@@ -262,8 +519,19 @@ impl Report {
         self.color = color;
         self
     }
-    pub fn trace(mut self, trace: &[TraceFrame]) -> Self {
-        for line in format_trace(trace) {
+    /// Show an error code next to the kind of each message
+    pub fn code(mut self, code: ErrorCode) -> Self {
+        for frag in &mut self.fragments {
+            if let ReportFragment::Colored(s) = frag {
+                if s == self.kind.str() {
+                    *s = format!("{s}[{code}]");
+                }
+            }
+        }
+        self
+    }
+    pub fn trace(mut self, trace: &[TraceFrame], elided: usize) -> Self {
+        for line in format_trace(trace, elided) {
             self.fragments.push(ReportFragment::Newline);
             self.fragments.push(ReportFragment::Plain(line));
         }
@@ -310,7 +578,7 @@ impl Report {
                 fragments.push(ReportFragment::Newline);
                 let line_prefix = format!("{} | ", span.start.line);
                 fragments.push(ReportFragment::Plain(line_prefix.clone()));
-                let line = span.input.lines().nth(span.start.line - 1).unwrap_or("");
+                let line = span.line_text();
                 let start_char_pos = span.start.col - 1;
                 let end_char_pos = if span.start.line == span.end.line {
                     span.end.col - 1
@@ -375,3 +643,112 @@ impl fmt::Display for Report {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Uiua;
+
+    #[test]
+    fn structured_errors() {
+        use crate::primitive::Primitive;
+        let err = Uiua::with_native_sys().load_str("+1").unwrap_err();
+        match err.root() {
+            UiuaError::StackUnderflow(e) => {
+                assert_eq!(e.expected, "argument 2");
+                assert_eq!(e.who, Some(Primitive::Add));
+            }
+            err => panic!("wrong error: {err:?}"),
+        }
+        assert!(err
+            .to_string()
+            .ends_with("Stack was empty when evaluating argument 2"));
+
+        let err = Uiua::with_native_sys()
+            .load_str("+[1 2] [1 2 3]")
+            .unwrap_err();
+        match err.root() {
+            UiuaError::ShapeMismatch(e) => {
+                assert_eq!((e.a.as_slice(), e.b.as_slice()), (&[2][..], &[3][..]));
+                assert_eq!(e.op, Some(Primitive::Add));
+            }
+            err => panic!("wrong error: {err:?}"),
+        }
+        assert!(err.span().is_some());
+        assert!(err.to_string().ends_with("Shapes [2] and [3] do not match"));
+
+        let err = Uiua::with_native_sys()
+            .load_str("Foo ← 5\nFoobar ← 6\nFo")
+            .unwrap_err();
+        match err.root() {
+            UiuaError::UnknownIdent(e) => assert_eq!(&*e.name, "Fo"),
+            err => panic!("wrong error: {err:?}"),
+        }
+        assert_eq!(err.suggestions(), [Ident::from("Foo")]);
+        assert!(err.to_string().ends_with("Unknown identifier `Fo`"));
+    }
+
+    #[test]
+    fn trace_frames() {
+        let mut env = Uiua::with_native_sys();
+        let error = (env.load_str("G ← ≡(+1 ⊟ [1 2 3])\nG [1 2]")).unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("in ≡(+1 ⊟ [1 2 3]) at 1:5"), "{message}");
+        assert!(!message.contains("fn from"), "{message}");
+
+        let frame = |name: &str| TraceFrame {
+            id: FunctionId::Named(name.into()),
+            span: Span::Builtin,
+        };
+        let mut trace = vec![frame("A")];
+        for _ in 0..50 {
+            trace.extend([frame("F"), frame("G")]);
+        }
+        trace.extend([frame("H"), frame("H")]);
+        let error = UiuaError::Traced {
+            error: UiuaError::Run(Span::Builtin.sp("oops".into())).into(),
+            trace,
+            elided: 0,
+        };
+        let lines: Vec<String> = error.to_string().lines().map(Into::into).collect();
+        assert_eq!(
+            lines[1..],
+            [
+                "  in `A`",
+                "  in `F`",
+                "  in `G`",
+                "  (the last 2 frames repeated 49 more times)",
+                "  in `H`",
+                "  (repeated 1 more time)",
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_limit() {
+        // Each function calls the one before it
+        let mut code = String::from("Fa ← +[1 2]\n");
+        for (prev, name) in ('a'..='j').zip('b'..='j') {
+            code.push_str(&format!("F{name} ← F{prev}\n"));
+        }
+        code.push_str("Fj [1 2 3]");
+        let mut env = Uiua::with_native_sys().with_trace_limit(4);
+        let error = env.load_str(&code).unwrap_err();
+        let UiuaError::Traced { trace, elided, .. } = &error else {
+            panic!("error is not traced: {error}");
+        };
+        assert_eq!(trace.len(), 4);
+        assert_eq!(*elided, 7);
+        assert!(matches!(error.root(), UiuaError::ShapeMismatch(_)));
+        let lines: Vec<String> = error.to_string().lines().map(Into::into).collect();
+        assert_eq!(
+            lines[1..],
+            [
+                "  in `Fa` at 2:6",
+                "  in `Fb` at 3:6",
+                "  (7 frames omitted)",
+                "  in `Fj` at 11:1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,404 @@
+//! Stable codes for errors and their explanations, and explanations of code
+
+use std::{fmt, mem::replace, str::FromStr};
+
+use enum_iterator::{all, Sequence};
+
+use crate::{
+    ast::{Item, Modifier, Word},
+    parse::parse,
+    version, CodeSpan, Signature, Sp, Uiua, UiuaError, UiuaResult,
+};
+
+/// A stable code for a kind of error
+///
+/// Codes are never reused, so they can be searched for and linked to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Sequence)]
+pub enum ErrorCode {
+    StackUnderflow,
+    ShapeMismatch,
+    UnknownIdent,
+    Parse,
+    Throw,
+    Break,
+    Timeout,
+    Load,
+    Format,
+    Interrupted,
+}
+
+impl ErrorCode {
+    /// Get all error codes
+    pub fn all() -> impl Iterator<Item = Self> {
+        all()
+    }
+    /// The code as it is written, like `E0001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorCode::StackUnderflow => "E0001",
+            ErrorCode::ShapeMismatch => "E0002",
+            ErrorCode::UnknownIdent => "E0003",
+            ErrorCode::Parse => "E0004",
+            ErrorCode::Throw => "E0005",
+            ErrorCode::Break => "E0006",
+            ErrorCode::Timeout => "E0007",
+            ErrorCode::Load => "E0008",
+            ErrorCode::Format => "E0009",
+            ErrorCode::Interrupted => "E0010",
+        }
+    }
+    /// A short description of the error
+    pub fn title(&self) -> &'static str {
+        match self {
+            ErrorCode::StackUnderflow => "Stack underflow",
+            ErrorCode::ShapeMismatch => "Shape mismatch",
+            ErrorCode::UnknownIdent => "Unknown identifier",
+            ErrorCode::Parse => "Syntax error",
+            ErrorCode::Throw => "Assertion failed",
+            ErrorCode::Break => "Break or continue out of too many loops",
+            ErrorCode::Timeout => "Time limit exceeded",
+            ErrorCode::Load => "Failed to load a file",
+            ErrorCode::Format => "Failed to format a file",
+            ErrorCode::Interrupted => "Interrupted",
+        }
+    }
+    /// A longer explanation of the error and how to fix it
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            ErrorCode::StackUnderflow => {
+                "A function needed more values than there were on the stack.\n\
+                This usually means a function was called with too few arguments, \
+                or an earlier function used up a value that was meant for a later one.\n\
+                The error says which argument was missing. \
+                Check the signatures of the functions that run before it."
+            }
+            ErrorCode::ShapeMismatch => {
+                "A function that pairs up the rows of two arrays was given arrays \
+                with different numbers of rows.\n\
+                Pervasive functions like `add` `+` need either the shape of one array \
+                to be a prefix of the other's, or one of them to be a scalar.\n\
+                Use `fill` `⬚` to pad the shorter array, or `take` `↙` to shorten the longer one."
+            }
+            ErrorCode::UnknownIdent => {
+                "A name was used that is not bound.\n\
+                Names must be bound with `←` before they are used, \
+                and names are case-sensitive. \
+                Lowercase names that are not bound are parsed as primitives, \
+                so this error usually comes from a capitalized name.\n\
+                If there are bound names that are spelled similarly, the error suggests them."
+            }
+            ErrorCode::Parse => {
+                "The code could not be parsed.\n\
+                This is often an unclosed bracket or string, \
+                or a character that is not part of Uiua's syntax."
+            }
+            ErrorCode::Throw => {
+                "`assert` `⍤` was called with a condition that was not 1.\n\
+                The message of the error is the first argument to `assert`. \
+                The error can be caught with `try` `⍣`."
+            }
+            ErrorCode::Break => {
+                "`break` `⎋` was told to break out of more loops than it was in.\n\
+                `⎋1` breaks out of the innermost loop, `⎋2` out of the one around it, and so on. \
+                `⎋0` does nothing. `continue` counts loops the same way."
+            }
+            ErrorCode::Timeout => {
+                "The program ran for longer than it was allowed to.\n\
+                Some environments, like the website's pad, limit how long code can run. \
+                Check for loops that never end."
+            }
+            ErrorCode::Load => {
+                "A file could not be read.\n\
+                Check that the file exists and that its path is right. \
+                Relative paths are relative to the directory the interpreter was run in."
+            }
+            ErrorCode::Format => {
+                "A file could not be written after it was formatted.\n\
+                Check that the file is not read-only and that no other program is writing to it."
+            }
+            ErrorCode::Interrupted => {
+                "The environment running the program stopped it.\n\
+                Environments can stop long-running operations, like big sorts or file loads, \
+                when they report how far along they are. \
+                This usually means someone cancelled the program."
+            }
+        }
+    }
+    /// Some code that causes the error, if there is any
+    pub fn example(&self) -> Option<&'static str> {
+        Some(match self {
+            ErrorCode::StackUnderflow => "+1",
+            ErrorCode::ShapeMismatch => "+[1 2] [1 2 3]",
+            ErrorCode::UnknownIdent => "Foo ← 5\n+1 Fo",
+            ErrorCode::Parse => "[1 2",
+            ErrorCode::Throw => "⍤\"Oops!\" =1 2",
+            ErrorCode::Break => "⎋1",
+            ErrorCode::Timeout | ErrorCode::Load | ErrorCode::Format | ErrorCode::Interrupted => {
+                return None
+            }
+        })
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let digits = s.strip_prefix(['E', 'e']).unwrap_or(s);
+        let n: usize = digits
+            .parse()
+            .map_err(|_| format!("{s:?} is not an error code"))?;
+        all::<ErrorCode>()
+            .find(|code| code.code()[1..].parse() == Ok(n))
+            .ok_or_else(|| format!("There is no error with the code {s}"))
+    }
+}
+
+impl UiuaError {
+    /// Get the code of the error, if it has one
+    pub fn code(&self) -> Option<ErrorCode> {
+        Some(match self.root() {
+            UiuaError::StackUnderflow(_) => ErrorCode::StackUnderflow,
+            UiuaError::ShapeMismatch(_) => ErrorCode::ShapeMismatch,
+            UiuaError::UnknownIdent(_) => ErrorCode::UnknownIdent,
+            UiuaError::Parse(_) => ErrorCode::Parse,
+            UiuaError::Throw(..) => ErrorCode::Throw,
+            UiuaError::Break(..) | UiuaError::Continue(..) => ErrorCode::Break,
+            UiuaError::Timeout(_) => ErrorCode::Timeout,
+            UiuaError::Load(..) => ErrorCode::Load,
+            UiuaError::Format(..) => ErrorCode::Format,
+            UiuaError::Interrupted(_) => ErrorCode::Interrupted,
+            _ => return None,
+        })
+    }
+}
+
+/// What a word of code does, as described by [`Uiua::explain`]
+#[derive(Debug, Clone)]
+pub struct ExplainStep {
+    /// The code of the word
+    pub code: String,
+    pub span: CodeSpan,
+    /// What the word does
+    pub description: String,
+    /// The word's signature, if it can be inferred
+    pub signature: Option<Signature>,
+    /// How many values are on the stack after the word runs,
+    /// counting the values its line takes from before it
+    ///
+    /// This is `None` for the words in other words,
+    /// and for the words after one whose signature cannot be inferred.
+    pub height: Option<usize>,
+    /// The words in the word, like a modifier's functions or the code of an array, in the order they run
+    pub inner: Vec<ExplainStep>,
+}
+
+impl Uiua {
+    /// Explain what each word in some code does, in the order they run
+    ///
+    /// Lines of code are explained one after another.
+    /// Bindings are not explained, but the code can use them,
+    /// as well as any bindings that were already loaded.
+    /// The code is compiled but not run.
+    pub fn explain(&mut self, input: &str) -> UiuaResult<Vec<ExplainStep>> {
+        let prev = replace(&mut self.compile_only, true);
+        let res = self.explain_impl(input);
+        self.compile_only = prev;
+        res
+    }
+    fn explain_impl(&mut self, input: &str) -> UiuaResult<Vec<ExplainStep>> {
+        let (items, errors, _) = parse(input, None);
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+        self.experimental = self.experimental || version::declares_experimental(&items);
+        let mut steps = Vec::new();
+        for item in items {
+            let words = match &item {
+                Item::Words(words) => Some(words.clone()),
+                _ => None,
+            };
+            // Compiling the line first reports errors in it
+            self.items(vec![item], false)?;
+            if let Some(words) = words {
+                steps.extend(self.explain_line(&words));
+            }
+        }
+        Ok(steps)
+    }
+    fn explain_line(&mut self, words: &[Sp<Word>]) -> Vec<ExplainStep> {
+        let mut steps = self.explain_words(words);
+        // Find how many values the line needs from before it
+        let (mut height, mut needed) = (0isize, 0isize);
+        for step in &steps {
+            let Some(sig) = step.signature else {
+                break;
+            };
+            height -= sig.args as isize;
+            needed = needed.max(-height);
+            height += sig.outputs as isize;
+        }
+        let mut height = Some(needed as usize);
+        for step in &mut steps {
+            height = height
+                .zip(step.signature)
+                .map(|(height, sig)| height - sig.args + sig.outputs);
+            step.height = height;
+        }
+        steps
+    }
+    fn explain_words(&mut self, words: &[Sp<Word>]) -> Vec<ExplainStep> {
+        (words.iter().rev())
+            .filter(|word| word.value.is_code())
+            .map(|word| self.explain_word(word))
+            .collect()
+    }
+    fn explain_word(&mut self, word: &Sp<Word>) -> ExplainStep {
+        let explain_lines = |env: &mut Self, lines: &[Vec<Sp<Word>>]| -> Vec<ExplainStep> {
+            (lines.iter())
+                .flat_map(|line| env.explain_words(line))
+                .collect()
+        };
+        let (description, inner) = match &word.value {
+            Word::Number(n, _) => (format!("Push the number {n}"), Vec::new()),
+            Word::Char(c) => (format!("Push the character {c}"), Vec::new()),
+            Word::String(s) => (format!("Push the string {s:?}"), Vec::new()),
+            Word::FormatString(_) | Word::MultilineString(_) => (
+                "Push a string with values from the stack put in it".into(),
+                Vec::new(),
+            ),
+            Word::Ident(ident) => (self.describe_binding(ident), Vec::new()),
+            Word::Strand(items) => (
+                format!("Make a list of {} items", items.len()),
+                self.explain_words(items),
+            ),
+            Word::Array(arr) if arr.constant => (
+                "Make an array of the values its code leaves, each in a box".into(),
+                explain_lines(self, &arr.lines),
+            ),
+            Word::Array(arr) => (
+                "Make an array of the values its code leaves".into(),
+                explain_lines(self, &arr.lines),
+            ),
+            Word::Func(func) => ("Call a function".into(), explain_lines(self, &func.lines)),
+            Word::Switch(sw) => (
+                format!(
+                    "Call one of {} functions, chosen by the first value on the stack",
+                    sw.branches.len()
+                ),
+                (sw.branches.iter())
+                    .map(|branch| {
+                        let mut step = self.explain_word(
+                            &branch.span.clone().sp(Word::Func(branch.value.clone())),
+                        );
+                        step.description = "A function to choose from".into();
+                        step
+                    })
+                    .collect(),
+            ),
+            Word::Ocean(prims) => (
+                "Call some primitives one after another".into(),
+                (prims.iter().rev())
+                    .map(|prim| {
+                        self.explain_word(&prim.span.clone().sp(Word::Primitive(prim.value)))
+                    })
+                    .collect(),
+            ),
+            Word::Primitive(prim) => (
+                match prim.doc() {
+                    Some(doc) => format!("{}: {}", prim.name(), doc.short_text()),
+                    None => prim.name().into(),
+                },
+                Vec::new(),
+            ),
+            Word::Modified(modified) => {
+                let description = match &modified.modifier.value {
+                    Modifier::Primitive(prim) => match prim.doc() {
+                        Some(doc) => format!("{}: {}", prim.name(), doc.short_text()),
+                        None => prim.name().into(),
+                    },
+                    Modifier::Ident(ident) => self.describe_binding(ident),
+                };
+                (description, self.explain_words(&modified.operands))
+            }
+            Word::Placeholder(_) => (
+                "Call a function passed to the macro this is in".into(),
+                Vec::new(),
+            ),
+            Word::Local(local) => (
+                format!("Bind the value its code leaves to {}", local.name.value),
+                self.explain_words(&local.words),
+            ),
+            Word::Destructure(des) => {
+                let names: Vec<&str> = des.names.iter().map(|name| &*name.value).collect();
+                (
+                    format!("Bind the values its code leaves to {}", names.join(", ")),
+                    self.explain_words(&des.words),
+                )
+            }
+            Word::Comment(_) | Word::Spaces | Word::RowBreak => (String::new(), Vec::new()),
+        };
+        let signature =
+            (self.compile_operand_words(vec![word.clone()]).ok()).and_then(|(_, sig)| sig.ok());
+        ExplainStep {
+            code: word.span.as_str().into(),
+            span: word.span.clone(),
+            description,
+            signature,
+            height: None,
+            inner,
+        }
+    }
+    fn describe_binding(&self, name: &str) -> String {
+        match self.binding_doc(name) {
+            Some(doc) => format!("{name}: {}", doc.lines().next().unwrap_or_default()),
+            None => format!("Use the binding {name}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explain() {
+        let mut env = Uiua::with_native_sys();
+        let steps = env.explain("Sum ← /+\nSum ⊞×. ⇡3").unwrap();
+        let codes: Vec<&str> = steps.iter().map(|step| &*step.code).collect();
+        assert_eq!(codes, ["3", "⇡", ".", "⊞×", "Sum"]);
+        let heights: Vec<Option<usize>> = steps.iter().map(|step| step.height).collect();
+        assert_eq!(heights, [Some(1), Some(1), Some(2), Some(1), Some(1)]);
+        assert_eq!(steps[3].signature, Some(Signature::new(2, 1)));
+        assert_eq!(steps[3].inner[0].code, "×");
+        assert!(steps[1].description.starts_with("range"));
+        // Lines can use values from before them
+        let steps = env.explain("+1").unwrap();
+        assert_eq!(steps[1].height, Some(1));
+        assert!(env.explain("Foo 1").is_err());
+    }
+
+    #[test]
+    fn error_codes() {
+        use std::collections::HashSet;
+        let mut seen = HashSet::new();
+        for code in ErrorCode::all() {
+            assert!(seen.insert(code.code()), "{code} is used twice");
+            assert_eq!(code.code().parse::<ErrorCode>(), Ok(code));
+            let Some(example) = code.example() else {
+                continue;
+            };
+            let err = Uiua::with_native_sys().load_str(example).unwrap_err();
+            assert_eq!(err.code(), Some(code), "{err}");
+            assert!(err.report().to_string().contains(&format!("Error[{code}]")));
+        }
+        assert_eq!("e2".parse::<ErrorCode>(), Ok(ErrorCode::ShapeMismatch));
+        assert!("E9999".parse::<ErrorCode>().is_err());
+    }
+}
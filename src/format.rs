@@ -2,10 +2,11 @@
 
 use std::{
     any::Any,
-    collections::BTreeMap,
+    collections::{BTreeMap, HashMap},
     env,
     fmt::Display,
     fs,
+    ops::RangeInclusive,
     path::{Path, PathBuf},
 };
 
@@ -15,10 +16,11 @@ use crate::{
     ast::*,
     function::Signature,
     grid_fmt::GridFmt,
-    lex::{is_ident_char, CodeSpan, Loc, Sp},
+    lex::{is_ident_char, lex, CodeSpan, Loc, Sp, Token},
     parse::parse,
+    run::Global,
     value::Value,
-    SysBackend, Uiua, UiuaError, UiuaResult,
+    Ident, Primitive, SysBackend, Uiua, UiuaError, UiuaResult,
 };
 
 // For now disallow any syscalls in the format config file.
@@ -120,7 +122,9 @@ macro_rules! create_config {
                         let $name = {
                             let requirement = requirement!([<$name:camel>], $ty);
                             let function_name = stringify!([<$name:camel>]);
-                            if let Some(binding) = bindings.remove(function_name) {
+                            if let Some(binding) =
+                                Ident::get(function_name).and_then(|name| bindings.remove(&name))
+                            {
                                 Some($ty::from_value(&binding, &env, requirement)?)
                             } else {
                                 None
@@ -199,6 +203,11 @@ create_config!(
     (multiline_compact_threshold, usize, 10),
     /// Whether to align consecutive end-of-line comments
     (align_comments, bool, true),
+    /// Whether to write primitives that have non-ASCII glyphs with their ASCII names instead
+    (ascii_names, bool, false),
+    /// Whether to add a comment like `# a b -- c` with the inferred stack effect above each binding that takes arguments.
+    /// Existing stack effect comments are updated if they do not match.
+    (stack_effect_comments, bool, false),
 );
 
 /// The source from which to populate the formatter configuration.
@@ -312,6 +321,57 @@ impl FormatOutput {
     }
 }
 
+/// A replacement of the names or ASCII symbols of primitives with their glyphs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlyphSubstitution {
+    /// The char position of the start of the replaced text
+    pub start: usize,
+    /// The char position of the end of the replaced text
+    pub end: usize,
+    pub glyphs: String,
+}
+
+/// Get the glyph substitution that the formatter would make for the word at a cursor
+///
+/// The cursor is a char position, and the word is the one that contains it or ends at it.
+/// This lets editors substitute glyphs as code is typed rather than only when it is formatted.
+pub fn glyph_substitution(input: &str, cursor: usize) -> Option<GlyphSubstitution> {
+    let (tokens, _) = lex(input, None);
+    let glyphs = |token: &Sp<Token>| {
+        let prim = match token.value {
+            Token::Glyph(prim) => prim,
+            Token::Simple(ascii) => Primitive::from_simple(ascii)?,
+            _ => return None,
+        };
+        let glyphs = prim.to_string();
+        (glyphs != token.span.as_str()).then_some(glyphs)
+    };
+    let touching = |a: &Sp<Token>, b: &Sp<Token>| a.span.end.char_pos == b.span.start.char_pos;
+    let i = (tokens.iter())
+        .position(|t| t.span.start.char_pos < cursor && cursor <= t.span.end.char_pos)?;
+    glyphs(&tokens[i])?;
+    // A word may be several primitives' names run together
+    let mut start = i;
+    while start > 0
+        && touching(&tokens[start - 1], &tokens[start])
+        && glyphs(&tokens[start - 1]).is_some()
+    {
+        start -= 1;
+    }
+    let mut end = i + 1;
+    while end < tokens.len()
+        && touching(&tokens[end - 1], &tokens[end])
+        && glyphs(&tokens[end]).is_some()
+    {
+        end += 1;
+    }
+    Some(GlyphSubstitution {
+        start: tokens[start].span.start.char_pos,
+        end: tokens[end - 1].span.end.char_pos,
+        glyphs: tokens[start..end].iter().filter_map(glyphs).collect(),
+    })
+}
+
 pub fn format<P: AsRef<Path>>(
     input: &str,
     path: P,
@@ -323,12 +383,126 @@ pub fn format_str(input: &str, config: &FormatConfig) -> UiuaResult<FormatOutput
     format_impl(input, None, config)
 }
 
+/// A run of lines that formatting changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineChange {
+    /// The line number of the first changed line, starting at 1
+    ///
+    /// If no lines are removed, the new lines are inserted before this line.
+    pub line: usize,
+    /// The lines that are removed
+    pub old: Vec<String>,
+    /// The lines that replace them
+    pub new: Vec<String>,
+}
+
+impl LineChange {
+    /// Whether the change touches any of some lines
+    pub fn overlaps(&self, lines: &RangeInclusive<usize>) -> bool {
+        self.line <= *lines.end() && self.line + self.old.len().max(1) > *lines.start()
+    }
+}
+
+/// Get the runs of lines that differ between some code and its formatted version
+pub fn line_changes(input: &str, output: &str) -> Vec<LineChange> {
+    let old: Vec<&str> = input.split('\n').collect();
+    let new: Vec<&str> = output.split('\n').collect();
+    let prefix = (old.iter().zip(&new)).take_while(|(a, b)| a == b).count();
+    let suffix = (old[prefix..].iter().rev())
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+    // Match up the lines in between with a longest common subsequence,
+    // unless there are too many to compare
+    let mut pairs = Vec::new();
+    if old_mid.len() * new_mid.len() <= 1 << 22 {
+        let width = new_mid.len() + 1;
+        let mut lcs = vec![0u32; (old_mid.len() + 1) * width];
+        for i in (0..old_mid.len()).rev() {
+            for j in (0..new_mid.len()).rev() {
+                lcs[i * width + j] = if old_mid[i] == new_mid[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old_mid.len() && j < new_mid.len() {
+            if old_mid[i] == new_mid[j] {
+                pairs.push((i, j));
+                i += 1;
+                j += 1;
+            } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+    pairs.push((old_mid.len(), new_mid.len()));
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (a, b) in pairs {
+        if a > i || b > j {
+            changes.push(LineChange {
+                line: prefix + i + 1,
+                old: old_mid[i..a].iter().map(|s| s.to_string()).collect(),
+                new: new_mid[j..b].iter().map(|s| s.to_string()).collect(),
+            });
+        }
+        (i, j) = (a + 1, b + 1);
+    }
+    changes
+}
+
+/// Format only some lines of some code
+///
+/// The whole code is formatted, but only the changes that touch the lines are made.
+/// Lines are numbered starting at 1.
+pub fn format_lines(
+    input: &str,
+    path: Option<&Path>,
+    config: &FormatConfig,
+    lines: RangeInclusive<usize>,
+) -> UiuaResult<String> {
+    let formatted = format_impl(input, path, config)?;
+    let mut output: Vec<&str> = Vec::new();
+    let mut old_lines = input.split('\n');
+    let mut line = 1;
+    let changes = line_changes(input, &formatted.output);
+    for change in changes.iter().filter(|change| change.overlaps(&lines)) {
+        while line < change.line {
+            output.extend(old_lines.next());
+            line += 1;
+        }
+        for _ in &change.old {
+            old_lines.next();
+            line += 1;
+        }
+        output.extend(change.new.iter().map(String::as_str));
+    }
+    output.extend(old_lines);
+    Ok(output.join("\n"))
+}
+
 pub fn format_items(items: &[Item], config: &FormatConfig) -> FormatOutput {
+    format_items_impl(items, config, HashMap::new())
+}
+
+fn format_items_impl(
+    items: &[Item],
+    config: &FormatConfig,
+    stack_effects: HashMap<usize, Signature>,
+) -> FormatOutput {
     let mut formatter = Formatter {
         config,
         output: String::new(),
         glyph_map: BTreeMap::new(),
         end_of_line_comments: Vec::new(),
+        stack_effects,
     };
     formatter.format_items(items);
     let mut output = formatter.output;
@@ -350,10 +524,62 @@ fn format_impl(
     config: &FormatConfig,
 ) -> UiuaResult<FormatOutput> {
     let (items, errors, _) = parse(input, path);
-    if errors.is_empty() {
-        Ok(format_items(&items, config))
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+    let stack_effects = if config.stack_effect_comments {
+        binding_signatures(input, path)
     } else {
-        Err(errors.into())
+        HashMap::new()
+    };
+    Ok(format_items_impl(&items, config, stack_effects))
+}
+
+/// Infer the signatures of the bindings in some code that take arguments, by the line of each binding
+fn binding_signatures(input: &str, path: Option<&Path>) -> HashMap<usize, Signature> {
+    let mut env = Uiua::with_backend(FormatConfigBackend);
+    env.binding_refs = Some(Vec::new());
+    // Bindings compiled before an error still have their signatures
+    _ = env.check_impl(input, path);
+    let globals = env.globals.lock();
+    (env.binding_refs.iter().flatten())
+        .filter(|binding| binding.definition)
+        .filter_map(|binding| match &globals[binding.index] {
+            Global::Func(f) if f.signature().args > 0 => {
+                Some((binding.span.start.line, f.signature()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Get the signature described by a stack effect comment like `a b -- c`
+pub(crate) fn stack_effect_comment(comment: &str) -> Option<Signature> {
+    let mut args = None;
+    let mut count = 0;
+    for word in comment.split_whitespace() {
+        if word == "--" && args.is_none() {
+            args = Some(count);
+            count = 0;
+        } else if word.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            count += 1;
+        } else {
+            return None;
+        }
+    }
+    Some(Signature::new(args?, count))
+}
+
+fn item_stack_effect(item: &Item) -> Option<Signature> {
+    match item {
+        Item::Words(words) => match words.as_slice() {
+            [Sp {
+                value: Word::Comment(comment),
+                ..
+            }] => stack_effect_comment(comment),
+            _ => None,
+        },
+        _ => None,
     }
 }
 
@@ -378,11 +604,32 @@ struct Formatter<'a> {
     output: String,
     glyph_map: BTreeMap<CodeSpan, (Loc, Loc)>,
     end_of_line_comments: Vec<(usize, String)>,
+    /// The inferred signatures of bindings that get stack effect comments, by line
+    stack_effects: HashMap<usize, Signature>,
 }
 
 impl<'a> Formatter<'a> {
     fn format_items(&mut self, items: &[Item]) {
-        for item in items {
+        for (i, item) in items.iter().enumerate() {
+            // A binding's stack effect comment is written along with the binding
+            if item_stack_effect(item).is_some()
+                && matches!(items.get(i + 1), Some(Item::Binding(binding))
+                    if self.stack_effects.contains_key(&binding.name.span.start.line))
+            {
+                continue;
+            }
+            if let Item::Binding(binding) = item {
+                if let Some(&sig) = self.stack_effects.get(&binding.name.span.start.line) {
+                    let prev = i.checked_sub(1).map(|j| &items[j]);
+                    if prev.and_then(item_stack_effect) == Some(sig) {
+                        // Keep the names in a comment that is already correct
+                        self.format_item(prev.unwrap());
+                    } else {
+                        self.format_stack_effect(sig);
+                    }
+                    self.output.push('\n');
+                }
+            }
             self.format_item(item);
             self.output.push('\n');
         }
@@ -390,13 +637,11 @@ impl<'a> Formatter<'a> {
         if self.config.align_comments && !self.end_of_line_comments.is_empty() {
             // Group comments by consecutive lines
             let mut groups: Vec<(usize, Vec<(usize, String)>)> = Vec::new();
-            let mut lines: Vec<String> = self
-                .output
-                .split('\n')
-                .map(|s| s.trim_end().into())
-                .collect();
+            let mut lines: Vec<String> = self.output.split('\n').map(Into::into).collect();
             for (line_number, comment) in self.end_of_line_comments.drain(..) {
-                let line = &lines[line_number - 1];
+                // Only lines with comments are trimmed, so that multiline strings keep their trailing spaces
+                let line = &mut lines[line_number - 1];
+                line.truncate(line.trim_end().len());
                 let line_len = line.chars().count();
                 if let Some((max, group)) = groups.last_mut() {
                     if line_number - group.last().unwrap().0 == 1 {
@@ -441,18 +686,69 @@ impl<'a> Formatter<'a> {
             }
             Item::Binding(binding) => {
                 self.output.push_str(&binding.name.value);
-                self.output.push_str(" ←");
-                if !binding.words.is_empty() || binding.signature.is_some() {
+                self.output
+                    .push_str(if binding.mutable { " ↩" } else { " ←" });
+                if binding.code_macro {
+                    self.output.push('^');
+                }
+                if !binding.words.is_empty()
+                    || binding.signature.is_some()
+                    || binding.annotation.is_some()
+                {
                     self.output.push(' ');
                 }
-                if let Some(sig) = &binding.signature {
+                if let Some(annotation) = &binding.annotation {
+                    self.output.push_str(&format!("|{}|", annotation.value));
+                    if !binding.words.is_empty() {
+                        self.output.push(' ');
+                    }
+                } else if let Some(sig) = &binding.signature {
                     self.format_signature('|', sig.value, true);
                 }
                 self.format_words(&binding.words, true, 0);
             }
+            Item::Destructure(des) => self.format_destructure(des, 0),
             Item::ExtraNewlines(_) => {}
         }
     }
+    fn format_destructure(&mut self, des: &Destructure, depth: usize) {
+        if des.brackets.is_some() {
+            self.output.push('[');
+        }
+        for (i, name) in des.names.iter().enumerate() {
+            if i > 0 {
+                self.output.push(' ');
+            }
+            self.output.push_str(&name.value);
+        }
+        if des.brackets.is_some() {
+            self.output.push(']');
+        }
+        self.output.push_str(" ←");
+        if !des.words.is_empty() {
+            self.output.push(' ');
+        }
+        self.format_words(&des.words, true, depth);
+    }
+    fn format_stack_effect(&mut self, sig: Signature) {
+        let name = |i: usize| match u8::try_from(i) {
+            Ok(i @ 0..=25) => char::from(b'a' + i).to_string(),
+            _ => format!("x{i}"),
+        };
+        self.output.push('#');
+        if self.config.comment_space_after_hash {
+            self.output.push(' ');
+        }
+        for i in 0..sig.args {
+            self.output.push_str(&name(i));
+            self.output.push(' ');
+        }
+        self.output.push_str("--");
+        for i in sig.args..sig.args + sig.outputs {
+            self.output.push(' ');
+            self.output.push_str(&name(i));
+        }
+    }
     fn format_signature(&mut self, init_char: char, sig: Signature, trailing_space: bool) {
         self.output.push(init_char);
         self.output.push_str(&sig.args.to_string());
@@ -472,8 +768,12 @@ impl<'a> Formatter<'a> {
     fn format_word(&mut self, word: &Sp<Word>, depth: usize) {
         match &word.value {
             Word::Number(s, n) => {
+                if self.config.ascii_names && self.output.ends_with(is_ident_char) {
+                    self.output.push(' ');
+                }
                 let grid_str = n.grid_string();
-                if grid_str.len() < s.len() {
+                let radix = s.contains(['x', 'b']);
+                if grid_str.len() < s.len() && !radix {
                     self.output.push_str(&grid_str);
                 } else {
                     self.output.push_str(&s.replace('`', "¯"));
@@ -566,27 +866,35 @@ impl<'a> Formatter<'a> {
             }
             Word::Ocean(prims) => {
                 for prim in prims {
-                    self.push(&prim.span, &prim.value.to_string());
+                    self.push_prim(&prim.span, prim.value);
                 }
             }
             Word::Primitive(prim) => {
-                self.push(&word.span, &prim.to_string());
+                self.push_prim(&word.span, *prim);
                 if prim.is_modifier() {
                     self.output.push('|');
                 }
             }
             Word::Modified(m) => {
-                self.push(
-                    &m.modifier.span,
-                    &match &m.modifier.value {
-                        Modifier::Primitive(prim) => prim.to_string(),
-                        Modifier::Ident(ident) => ident.to_string(),
-                    },
-                );
+                match &m.modifier.value {
+                    Modifier::Primitive(prim) => self.push_prim(&m.modifier.span, *prim),
+                    Modifier::Ident(ident) => self.push(&m.modifier.span, ident),
+                }
                 self.format_words(&m.operands, true, depth);
             }
             Word::Placeholder(sig) => self.format_signature('^', *sig, false),
+            Word::Local(local) => {
+                self.output.push_str(&local.name.value);
+                self.output
+                    .push_str(if local.mutable { " ↩" } else { " ←" });
+                if !local.words.is_empty() {
+                    self.output.push(' ');
+                }
+                self.format_words(&local.words, true, depth);
+            }
+            Word::Destructure(des) => self.format_destructure(des, depth),
             Word::Spaces => self.push(&word.span, " "),
+            Word::RowBreak => self.push(&word.span, "|"),
             Word::Comment(comment) => {
                 let beginning_of_line = self
                     .output
@@ -673,6 +981,19 @@ impl<'a> Formatter<'a> {
             }
         }
     }
+    fn push_prim(&mut self, span: &CodeSpan, prim: Primitive) {
+        let formatted = match prim.glyph() {
+            Some(glyph) if self.config.ascii_names && !glyph.is_ascii() => prim.ascii_name(),
+            _ => prim.to_string(),
+        };
+        // Names must be separated from other names
+        if formatted.starts_with(is_ident_char)
+            && (self.output).ends_with(|c: char| is_ident_char(c) || c.is_ascii_digit())
+        {
+            self.output.push(' ');
+        }
+        self.push(span, &formatted);
+    }
     fn push(&mut self, span: &CodeSpan, formatted: &str) {
         let start = end_loc(&self.output);
         self.output.push_str(formatted);
@@ -737,8 +1058,14 @@ fn word_is_multiline(word: &Word) -> bool {
         Word::Primitive(_) => false,
         Word::Modified(m) => m.operands.iter().any(|word| word_is_multiline(&word.value)),
         Word::Placeholder(_) => false,
+        Word::Local(local) => local
+            .words
+            .iter()
+            .any(|word| word_is_multiline(&word.value)),
+        Word::Destructure(des) => des.words.iter().any(|word| word_is_multiline(&word.value)),
         Word::Comment(_) => false,
         Word::Spaces => false,
+        Word::RowBreak => false,
     }
 }
 
@@ -764,3 +1091,104 @@ fn end_loc(s: &str) -> Loc {
         byte_pos,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::run;
+
+    #[test]
+    fn ascii_names() {
+        use crate::lex::{lex, Token};
+        for prim in Primitive::all().filter(|prim| prim.glyph().is_some_and(|c| !c.is_ascii())) {
+            let name = prim.ascii_name();
+            assert!(name.is_ascii(), "{name} is not ASCII");
+            let (tokens, errors) = lex(&name, None);
+            assert!(errors.is_empty(), "{name}");
+            let tokens: Vec<Token> = tokens.into_iter().map(|t| t.value).collect();
+            assert_eq!(tokens, [Token::Glyph(prim)], "{name}");
+        }
+        let ascii = FormatConfig::default().with_ascii_names(true);
+        let code = "↯3⇡10\n≠≤1 2 3\n/+×⌵¯1 X\n";
+        let names = format_str(code, &ascii).unwrap().output;
+        assert_eq!(
+            names,
+            "reshape 3 range 10\nnotequals lessorequal 1 2 3\n/+multiply absolutevalue ¯1 X\n"
+        );
+        assert_eq!(
+            format_str(&names, &FormatConfig::default()).unwrap().output,
+            "↯ 3 ⇡ 10\n≠ ≤ 1 2 3\n/+× ⌵ ¯1 X\n"
+        );
+    }
+
+    #[test]
+    fn stack_effect_comments() {
+        let config = FormatConfig::default().with_stack_effect_comments(true);
+        let code = "\
+# Add then square
+F ← ×.+
+# x -- y
+G ← ⊃+-
+# n -- twice
+H ← ×2
+X ← 5
+";
+        assert_eq!(
+            format_str(code, &config).unwrap().output,
+            "\
+# Add then square
+# a b -- c
+F ← ×.+
+# a b -- c d
+G ← ⊃+-
+# n -- twice
+H ← ×2
+X ← 5
+"
+        );
+        let stack = run(&format!("{code}\nhelp F")).unwrap();
+        assert_eq!(stack, [Value::from("Add then square")]);
+    }
+
+    #[test]
+    fn format_lines() {
+        let config = FormatConfig::default();
+        let code = "X ← +1 2\nY ← reverse range 5\nZ ← X\nW ← ⇌ ⇡ 3\n";
+        let formatted = format_str(code, &config).unwrap().output;
+        assert_eq!(
+            line_changes(code, &formatted),
+            [LineChange {
+                line: 2,
+                old: vec!["Y ← reverse range 5".into()],
+                new: vec!["Y ← ⇌ ⇡ 5".into()],
+            }]
+        );
+        // Only changes that touch the lines are made
+        assert_eq!(
+            super::format_lines(code, None, &config, 1..=1).unwrap(),
+            code
+        );
+        assert_eq!(
+            super::format_lines(code, None, &config, 2..=3).unwrap(),
+            "X ← +1 2\nY ← ⇌ ⇡ 5\nZ ← X\nW ← ⇌ ⇡ 3\n"
+        );
+    }
+
+    #[test]
+    fn glyph_substitution() {
+        let sub = |start, end, glyphs: &str| {
+            Some(GlyphSubstitution {
+                start,
+                end,
+                glyphs: glyphs.into(),
+            })
+        };
+        assert_eq!(super::glyph_substitution("revrev 1_2", 3), sub(0, 6, "⇌⇌"));
+        assert_eq!(super::glyph_substitution("1 + rev", 7), sub(4, 7, "⇌"));
+        assert_eq!(super::glyph_substitution("* 2 3", 1), sub(0, 1, "×"));
+        assert_eq!(super::glyph_substitution("+ 2 3", 1), None);
+        assert_eq!(super::glyph_substitution("re", 2), None);
+        assert_eq!(super::glyph_substitution("\"rev\"", 4), None);
+        assert_eq!(super::glyph_substitution("rev 1", 4), None);
+    }
+}
@@ -1,8 +1,10 @@
 use std::{
+    borrow::Cow,
     cmp::Ordering,
     fmt,
     hash::{Hash, Hasher},
-    mem::{discriminant, transmute},
+    mem::{self, discriminant, transmute},
+    path::Path,
     sync::Arc,
 };
 
@@ -17,11 +19,18 @@ use crate::{
 #[derive(Clone)]
 #[repr(u8)]
 pub enum Instr {
+    /// Push a value that is not in a constant pool
+    ///
+    /// [`Function::new`] moves these values into the function's constant pool.
     Push(Box<Value>) = 0,
+    /// Push a value from the function's constant pool
+    PushConst(usize),
     BeginArray,
     EndArray {
         boxed: bool,
         span: usize,
+        /// The spans of the words that push each row, if they are known
+        row_spans: Option<Arc<[usize]>>,
     },
     Prim(Primitive, usize),
     ImplPrim(ImplPrimitive, usize),
@@ -34,11 +43,11 @@ pub enum Instr {
     PushTempFunctions(usize),
     PopTempFunctions(usize),
     GetTempFunction {
-        offset: usize,
+        offset: u32,
         sig: Signature,
         span: usize,
     },
-    Dynamic(DynamicFunction),
+    Dynamic(Box<DynamicFunction>),
     PushTempUnder {
         count: usize,
         span: usize,
@@ -64,12 +73,45 @@ pub enum Instr {
         count: usize,
         span: usize,
     },
+    InitLocals {
+        count: usize,
+    },
+    /// Set a local binding
+    ///
+    /// `up` counts the functions that declare locals between this one and the one whose local it is.
+    /// `frame` is the index of that function's call frame, once it is known.
+    SetLocal {
+        up: u32,
+        index: usize,
+        frame: Option<u32>,
+        span: usize,
+    },
+    /// Get a local binding, like [`Instr::SetLocal`]
+    GetLocal {
+        up: u32,
+        index: usize,
+        frame: Option<u32>,
+        span: usize,
+    },
+    GetGlobal {
+        index: usize,
+        span: usize,
+    },
+    SetGlobal {
+        index: usize,
+        span: usize,
+    },
+    Unpack {
+        count: usize,
+        span: usize,
+    },
 }
 
 impl PartialEq for Instr {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Push(a), Self::Push(b)) => a == b,
+            (Self::PushConst(a), Self::PushConst(b)) => a == b,
             (Self::BeginArray, Self::BeginArray) => true,
             (Self::EndArray { .. }, Self::EndArray { .. }) => true,
             (Self::Prim(a, s_span), Self::Prim(b, b_span)) => a == b && s_span == b_span,
@@ -95,6 +137,26 @@ impl PartialEq for Instr {
             (Self::DropTempInline { count: a, .. }, Self::DropTempInline { count: b, .. }) => {
                 a == b
             }
+            (Self::InitLocals { count: a }, Self::InitLocals { count: b }) => a == b,
+            (
+                Self::SetLocal {
+                    up: au, index: ai, ..
+                },
+                Self::SetLocal {
+                    up: bu, index: bi, ..
+                },
+            ) => au == bu && ai == bi,
+            (
+                Self::GetLocal {
+                    up: au, index: ai, ..
+                },
+                Self::GetLocal {
+                    up: bu, index: bi, ..
+                },
+            ) => au == bu && ai == bi,
+            (Self::GetGlobal { index: a, .. }, Self::GetGlobal { index: b, .. }) => a == b,
+            (Self::SetGlobal { index: a, .. }, Self::SetGlobal { index: b, .. }) => a == b,
+            (Self::Unpack { count: a, .. }, Self::Unpack { count: b, .. }) => a == b,
             _ => false,
         }
     }
@@ -131,6 +193,7 @@ impl Hash for Instr {
         disc.hash(state);
         match self {
             Instr::Push(val) => val.hash(state),
+            Instr::PushConst(index) => index.hash(state),
             Instr::BeginArray => {}
             Instr::EndArray { .. } => {}
             Instr::Prim(p, _) => p.hash(state),
@@ -151,6 +214,14 @@ impl Hash for Instr {
                 count.hash(state);
             }
             Instr::DropTempInline { count, .. } => count.hash(state),
+            Instr::InitLocals { count } => count.hash(state),
+            Instr::SetLocal { up, index, .. } | Instr::GetLocal { up, index, .. } => {
+                up.hash(state);
+                index.hash(state);
+            }
+            Instr::GetGlobal { index, .. } => index.hash(state),
+            Instr::SetGlobal { index, .. } => index.hash(state),
+            Instr::Unpack { count, .. } => count.hash(state),
         }
     }
 }
@@ -168,6 +239,14 @@ impl Instr {
             _ => None,
         }
     }
+    /// Get the value an instruction pushes, looking up constants in a function's constant pool
+    pub fn as_constant<'a>(&'a self, constants: &'a [Value]) -> Option<&'a Value> {
+        match self {
+            Instr::Push(val) => Some(val),
+            &Instr::PushConst(index) => constants.get(index),
+            _ => None,
+        }
+    }
     pub fn is_temp(&self) -> bool {
         matches!(
             self,
@@ -200,6 +279,7 @@ impl fmt::Display for Instr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Instr::Push(val) => write!(f, "{val:?}"),
+            Instr::PushConst(index) => write!(f, "<constant {index}>"),
             Instr::BeginArray => write!(f, "]"),
             Instr::EndArray { .. } => write!(f, "["),
             Instr::Prim(prim @ Primitive::Over, _) => write!(f, "`{prim}`"),
@@ -220,6 +300,12 @@ impl fmt::Display for Instr {
                 write!(f, "<copy inline {offset}/{count}>")
             }
             Instr::DropTempInline { count, .. } => write!(f, "<drop inline {count}>"),
+            Instr::InitLocals { count } => write!(f, "<init {count} locals>"),
+            Instr::SetLocal { up, index, .. } => write!(f, "<set local {up}/{index}>"),
+            Instr::GetLocal { up, index, .. } => write!(f, "<get local {up}/{index}>"),
+            Instr::GetGlobal { index, .. } => write!(f, "<get global {index}>"),
+            Instr::SetGlobal { index, .. } => write!(f, "<set global {index}>"),
+            Instr::Unpack { count, .. } => write!(f, "<unpack {count}>"),
         }
     }
 }
@@ -228,7 +314,15 @@ impl fmt::Display for Instr {
 pub struct Function {
     pub id: FunctionId,
     pub instrs: Vec<Instr>,
+    /// The values pushed by the function's [`Instr::PushConst`]s
+    pub constants: Vec<Value>,
     signature: Signature,
+    /// Whether the function uses local bindings of the code around it that are not bound to a call frame yet
+    pub(crate) captures: bool,
+    /// The file this function was imported from with [`import pure`](crate::SysOp::ImportPure), if it was
+    ///
+    /// Code called by the function cannot do IO.
+    pub(crate) pure_import: Option<Arc<Path>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -311,7 +405,7 @@ impl Hash for DynamicFunction {
 
 impl PartialEq for Function {
     fn eq(&self, other: &Self) -> bool {
-        self.id == other.id && self.instrs == other.instrs
+        self.id == other.id && self.instrs == other.instrs && self.constants == other.constants
     }
 }
 
@@ -328,6 +422,7 @@ impl Ord for Function {
         self.id
             .cmp(&other.id)
             .then_with(|| self.instrs.cmp(&other.instrs))
+            .then_with(|| self.constants.cmp(&other.constants))
     }
 }
 
@@ -335,6 +430,7 @@ impl Hash for Function {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.id.hash(state);
         self.instrs.hash(state);
+        self.constants.hash(state);
     }
 }
 
@@ -358,21 +454,20 @@ impl fmt::Display for Function {
 
 impl Function {
     pub fn new(id: FunctionId, instrs: impl Into<Vec<Instr>>, signature: Signature) -> Self {
-        let instrs = instrs.into();
+        let (instrs, constants) = pool_constants(instrs.into());
         Self {
             id,
+            captures: uses_outer_locals(&instrs, 0),
             instrs,
+            constants,
             signature,
+            pure_import: None,
         }
     }
     pub fn new_inferred(id: FunctionId, instrs: impl Into<Vec<Instr>>) -> Result<Self, String> {
         let instrs = instrs.into();
         let signature = instrs_signature(&instrs)?;
-        Ok(Self {
-            id,
-            signature,
-            instrs,
-        })
+        Ok(Self::new(id, instrs, signature))
     }
     pub fn into_inner(f: Arc<Self>) -> Self {
         Arc::try_unwrap(f).unwrap_or_else(|f| (*f).clone())
@@ -382,6 +477,21 @@ impl Function {
     pub fn signature(&self) -> Signature {
         self.signature
     }
+    /// Get the function's instructions with its constants pushed directly
+    ///
+    /// This is what instructions that are built into another function must use,
+    /// because that function has its own constant pool.
+    pub fn unpooled_instrs(&self) -> Cow<'_, [Instr]> {
+        if self.constants.is_empty() {
+            return Cow::Borrowed(&self.instrs);
+        }
+        (self.instrs.iter())
+            .map(|instr| match instr {
+                &Instr::PushConst(index) => Instr::push(self.constants[index].clone()),
+                instr => instr.clone(),
+            })
+            .collect()
+    }
     pub fn as_primitive(&self) -> Option<(Primitive, usize)> {
         match self.instrs.as_slice() {
             [Instr::Prim(prim, span)] => Some((*prim, *span)),
@@ -412,6 +522,97 @@ impl Function {
         self.under(g_sig)
             .ok_or_else(|| env.error("No inverse found"))
     }
+    /// Bind the function's uses of the local bindings of the code around it to a call frame
+    ///
+    /// This is done when the function is pushed, so a function always sees the locals
+    /// of the code it was written in, not those of whatever calls it.
+    pub(crate) fn capture(&self, frame: u32) -> Self {
+        Function {
+            constants: self.constants.clone(),
+            pure_import: self.pure_import.clone(),
+            ..Function::new(
+                self.id.clone(),
+                capture_instrs(&self.instrs, 0, frame),
+                self.signature,
+            )
+        }
+    }
+}
+
+/// Move the values that instructions push into a constant pool
+fn pool_constants(mut instrs: Vec<Instr>) -> (Vec<Instr>, Vec<Value>) {
+    let mut constants = Vec::new();
+    for instr in &mut instrs {
+        if matches!(instr, Instr::Push(_)) {
+            let index = Instr::PushConst(constants.len());
+            if let Instr::Push(val) = mem::replace(instr, index) {
+                constants.push(*val);
+            }
+        }
+    }
+    (instrs, constants)
+}
+
+fn declares_locals(instrs: &[Instr]) -> u32 {
+    matches!(instrs.first(), Some(Instr::InitLocals { .. })) as u32
+}
+
+/// Check if instructions use local bindings from outside of them that are not bound to a call frame
+///
+/// `depth` is how many functions that declare locals the instructions are nested in, not counting themselves.
+fn uses_outer_locals(instrs: &[Instr], depth: u32) -> bool {
+    let depth = depth + declares_locals(instrs);
+    instrs.iter().any(|instr| match instr {
+        Instr::GetLocal {
+            up, frame: None, ..
+        }
+        | Instr::SetLocal {
+            up, frame: None, ..
+        } => *up >= depth,
+        Instr::PushFunc(f) => f.captures && uses_outer_locals(&f.instrs, depth),
+        _ => false,
+    })
+}
+
+/// Bind the uses of the locals of the function right outside some instructions to a call frame
+fn capture_instrs(instrs: &[Instr], depth: u32, frame: u32) -> Vec<Instr> {
+    let depth = depth + declares_locals(instrs);
+    let capture = |up: u32, old: Option<u32>| old.or((up == depth).then_some(frame));
+    (instrs.iter())
+        .map(|instr| match instr {
+            &Instr::GetLocal {
+                up,
+                index,
+                frame: old,
+                span,
+            } => Instr::GetLocal {
+                up,
+                index,
+                frame: capture(up, old),
+                span,
+            },
+            &Instr::SetLocal {
+                up,
+                index,
+                frame: old,
+                span,
+            } => Instr::SetLocal {
+                up,
+                index,
+                frame: capture(up, old),
+                span,
+            },
+            Instr::PushFunc(f) if f.captures => Instr::PushFunc(Arc::new(Function {
+                constants: f.constants.clone(),
+                ..Function::new(
+                    f.id.clone(),
+                    capture_instrs(&f.instrs, depth, frame),
+                    f.signature,
+                )
+            })),
+            instr => instr.clone(),
+        })
+        .collect()
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -457,3 +658,30 @@ impl fmt::Display for FunctionId {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instr_size() {
+        // Instructions are kept small so that more of them fit in the cache
+        assert!(std::mem::size_of::<Instr>() <= 32);
+    }
+
+    #[test]
+    fn constant_pool() {
+        let instrs = vec![
+            Instr::push(1.0),
+            Instr::push(2.0),
+            Instr::Prim(Primitive::Add, 0),
+        ];
+        let f = Function::new(FunctionId::Main, instrs.clone(), Signature::new(0, 1));
+        assert!(matches!(
+            f.instrs.as_slice(),
+            [Instr::PushConst(0), Instr::PushConst(1), Instr::Prim(..)]
+        ));
+        assert_eq!(f.constants, [Value::from(1.0), Value::from(2.0)]);
+        assert_eq!(f.unpooled_instrs(), instrs.as_slice());
+    }
+}
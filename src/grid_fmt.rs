@@ -123,6 +123,8 @@ impl<T: GridFmt + ArrayValue> GridFmt for Array<T> {
                 } else {
                     vec![vec!['"', '"']]
                 }
+            } else if boxed {
+                vec![vec!['⟦', '⟧']]
             } else {
                 vec![vec!['[', ']']]
             };
@@ -0,0 +1,150 @@
+//! Interned identifiers
+
+use std::{cmp::Ordering, collections::HashMap, fmt, ops::Deref};
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+
+/// An interned identifier, like the name of a binding
+///
+/// Identifiers with the same text are the same number, so they are compared and hashed without looking at their text.
+/// They are ordered by their text.
+///
+/// The text of every identifier is kept for the rest of the program.
+/// Get it back with [`Ident::as_str`], or by dereferencing.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ident(u32);
+
+#[derive(Default)]
+struct Interner {
+    ids: HashMap<&'static str, u32>,
+    texts: Vec<&'static str>,
+}
+
+static INTERNER: Lazy<RwLock<Interner>> = Lazy::new(Default::default);
+
+impl Ident {
+    /// Get the identifier with some text
+    pub fn new(text: &str) -> Self {
+        if let Some(&id) = INTERNER.read().ids.get(text) {
+            return Ident(id);
+        }
+        let mut interner = INTERNER.write();
+        // Another thread may have added it
+        if let Some(&id) = interner.ids.get(text) {
+            return Ident(id);
+        }
+        let id = interner.texts.len() as u32;
+        let text: &'static str = Box::leak(text.into());
+        interner.texts.push(text);
+        interner.ids.insert(text, id);
+        Ident(id)
+    }
+    /// Get the identifier with some text, if there is one
+    ///
+    /// Unlike [`Ident::new`], this does not intern the text, so it is good for looking up names that may not exist.
+    pub fn get(text: &str) -> Option<Self> {
+        INTERNER.read().ids.get(text).copied().map(Ident)
+    }
+    /// Get the text of the identifier
+    pub fn as_str(&self) -> &'static str {
+        INTERNER.read().texts[self.0 as usize]
+    }
+}
+
+impl Deref for Ident {
+    type Target = str;
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Ident {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialOrd for Ident {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ident {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            Ordering::Equal
+        } else {
+            self.as_str().cmp(other.as_str())
+        }
+    }
+}
+
+impl From<&str> for Ident {
+    fn from(text: &str) -> Self {
+        Ident::new(text)
+    }
+}
+
+impl From<String> for Ident {
+    fn from(text: String) -> Self {
+        Ident::new(&text)
+    }
+}
+
+impl From<&String> for Ident {
+    fn from(text: &String) -> Self {
+        Ident::new(text)
+    }
+}
+
+impl fmt::Debug for Ident {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Ident {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl PartialEq<str> for Ident {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Ident {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Uiua;
+
+    #[test]
+    fn interned_idents() {
+        let a = Ident::new("Foo");
+        let b = Ident::from(String::from("Foo"));
+        let c = Ident::new("Bar");
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a.as_str(), "Foo");
+        assert_eq!(a.to_string(), "Foo");
+        assert!(c < a);
+        assert_eq!(Ident::get("Foo"), Some(a));
+        assert_eq!(Ident::get("NeverInterned"), None);
+
+        let mut env = Uiua::with_native_sys();
+        env.load_str("Foo ← 5\nBar ← 6").unwrap();
+        let bindings = env.all_values_is_scope();
+        assert_eq!(bindings[&a], 5.into());
+        assert_eq!(bindings[&c], 6.into());
+    }
+}
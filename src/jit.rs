@@ -0,0 +1,281 @@
+//! Compiling hot scalar functions to native code
+//!
+//! Functions that are called often and only do arithmetic and stack manipulation
+//! are compiled with Cranelift. When such a function is called with scalar numbers,
+//! the native code runs instead of the interpreter. Any other call falls back to the interpreter.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    mem,
+    sync::{Arc, Weak},
+};
+
+use cranelift_codegen::{
+    ir::{types::F64, AbiParam, InstBuilder, MemFlags, Value as Reg},
+    settings::{self, Configurable},
+};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Module};
+
+use crate::{
+    function::{Function, FunctionId, Instr},
+    value::Value,
+    Primitive, Uiua, UiuaResult,
+};
+
+/// How many times a function is called before it is compiled
+const HOT_CALLS: usize = 1000;
+/// The most arguments or outputs a compiled function can have
+const MAX_VALUES: usize = 8;
+
+/// A compiled function
+///
+/// It reads its arguments from the first pointer and writes its outputs to the second, both starting with the top of the stack.
+type NativeFn = extern "C" fn(*const f64, *mut f64);
+
+struct Jit {
+    module: JITModule,
+    functions: HashMap<FunctionId, Hot>,
+}
+
+/// A function whose calls are being counted
+struct Hot {
+    /// The function being counted
+    ///
+    /// This does not keep the function alive, but it does keep its address from being reused.
+    function: Weak<Function>,
+    calls: usize,
+    /// `Some(None)` if the function cannot be compiled
+    native: Option<Option<NativeFn>>,
+}
+
+thread_local! {
+    /// `None` if native code cannot be generated for this machine
+    static JIT: RefCell<Option<Jit>> = RefCell::new(Jit::new());
+}
+
+/// Try to call a function with native code
+///
+/// Returns `false` if the function should be run by the interpreter instead.
+pub(crate) fn try_call(env: &mut Uiua, f: &Arc<Function>) -> UiuaResult<bool> {
+    let sig = f.signature();
+    if sig.args > MAX_VALUES || sig.outputs > MAX_VALUES || env.stack.len() < sig.args {
+        return Ok(false);
+    }
+    // Native code does not check units or keep labels, so values with metadata are left to the interpreter
+    let args_are_scalars = env.stack[env.stack.len() - sig.args..]
+        .iter()
+        .all(|val| matches!(val, Value::Num(arr) if arr.rank() == 0 && arr.meta.is_none()));
+    if !args_are_scalars {
+        return Ok(false);
+    }
+    let native = JIT.with(|jit| {
+        let mut jit = jit.borrow_mut();
+        let jit = jit.as_mut()?;
+        jit.hot(f).native.flatten()
+    });
+    let Some(native) = native else {
+        return Ok(false);
+    };
+    let mut args = [0.0; MAX_VALUES];
+    for arg in args.iter_mut().take(sig.args) {
+        if let Value::Num(arr) = env.pop("argument")? {
+            *arg = arr.data[0];
+        }
+    }
+    let mut outputs = [0.0; MAX_VALUES];
+    native(args.as_ptr(), outputs.as_mut_ptr());
+    for &output in outputs[..sig.outputs].iter().rev() {
+        env.push(output);
+    }
+    Ok(true)
+}
+
+impl Jit {
+    fn new() -> Option<Self> {
+        let mut flags = settings::builder();
+        flags.set("opt_level", "speed").ok()?;
+        let isa = cranelift_native::builder()
+            .ok()?
+            .finish(settings::Flags::new(flags))
+            .ok()?;
+        let module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+        Some(Jit {
+            module,
+            functions: HashMap::new(),
+        })
+    }
+    /// Count a call to a function, compiling it if it has become hot
+    fn hot(&mut self, f: &Arc<Function>) -> &Hot {
+        let is_same = |hot: &Hot| Weak::as_ptr(&hot.function) == Arc::as_ptr(f);
+        if !self.functions.get(&f.id).is_some_and(is_same) {
+            // Forget functions that no longer exist before the map grows
+            if self.functions.len() == self.functions.capacity() {
+                (self.functions).retain(|_, hot| hot.function.strong_count() > 0);
+            }
+            let hot = Hot {
+                function: Arc::downgrade(f),
+                calls: 0,
+                native: None,
+            };
+            self.functions.insert(f.id.clone(), hot);
+        }
+        let hot = self.functions.get_mut(&f.id).unwrap();
+        hot.calls += 1;
+        if hot.native.is_none() && hot.calls >= HOT_CALLS {
+            let native = self.compile(f);
+            self.functions.get_mut(&f.id).unwrap().native = Some(native);
+        }
+        &self.functions[&f.id]
+    }
+    fn compile(&mut self, f: &Function) -> Option<NativeFn> {
+        let sig = f.signature();
+        if !supported(f, sig.args, sig.outputs) {
+            return None;
+        }
+        let ptr = self.module.target_config().pointer_type();
+        let mut ctx = self.module.make_context();
+        ctx.func.signature.params.push(AbiParam::new(ptr));
+        ctx.func.signature.params.push(AbiParam::new(ptr));
+        let mut builder_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut ctx.func, &mut builder_ctx);
+        let block = builder.create_block();
+        builder.append_block_params_for_function_params(block);
+        builder.switch_to_block(block);
+        builder.seal_block(block);
+        let (args, outputs) = (
+            builder.block_params(block)[0],
+            builder.block_params(block)[1],
+        );
+        let mut stack: Vec<Reg> = (0..sig.args)
+            .rev()
+            .map(|i| {
+                builder
+                    .ins()
+                    .load(F64, MemFlags::trusted(), args, (i * 8) as i32)
+            })
+            .collect();
+        for instr in &f.instrs {
+            let ins = builder.ins();
+            match instr {
+                Instr::PushConst(index) => match &f.constants[*index] {
+                    Value::Num(arr) => stack.push(ins.f64const(arr.data[0])),
+                    _ => unreachable!(),
+                },
+                Instr::Prim(prim, _) => match prim {
+                    Primitive::Identity => {}
+                    Primitive::Pop => {
+                        pop(&mut stack);
+                    }
+                    Primitive::Dup => {
+                        let a = pop(&mut stack);
+                        stack.extend([a, a]);
+                    }
+                    Primitive::Flip => {
+                        let (a, b) = (pop(&mut stack), pop(&mut stack));
+                        stack.extend([a, b]);
+                    }
+                    Primitive::Over => {
+                        let (a, b) = (pop(&mut stack), pop(&mut stack));
+                        stack.extend([b, a, b]);
+                    }
+                    Primitive::Neg => {
+                        let a = pop(&mut stack);
+                        stack.push(ins.fneg(a));
+                    }
+                    Primitive::Abs => {
+                        let a = pop(&mut stack);
+                        stack.push(ins.fabs(a));
+                    }
+                    Primitive::Sqrt => {
+                        let a = pop(&mut stack);
+                        stack.push(ins.sqrt(a));
+                    }
+                    Primitive::Floor => {
+                        let a = pop(&mut stack);
+                        stack.push(ins.floor(a));
+                    }
+                    Primitive::Ceil => {
+                        let a = pop(&mut stack);
+                        stack.push(ins.ceil(a));
+                    }
+                    Primitive::Add => {
+                        let (a, b) = (pop(&mut stack), pop(&mut stack));
+                        stack.push(ins.fadd(b, a));
+                    }
+                    Primitive::Sub => {
+                        let (a, b) = (pop(&mut stack), pop(&mut stack));
+                        stack.push(ins.fsub(b, a));
+                    }
+                    Primitive::Mul => {
+                        let (a, b) = (pop(&mut stack), pop(&mut stack));
+                        stack.push(ins.fmul(b, a));
+                    }
+                    Primitive::Div => {
+                        let (a, b) = (pop(&mut stack), pop(&mut stack));
+                        stack.push(ins.fdiv(b, a));
+                    }
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            }
+        }
+        for (i, &val) in stack.iter().rev().enumerate() {
+            builder
+                .ins()
+                .store(MemFlags::trusted(), val, outputs, (i * 8) as i32);
+        }
+        builder.ins().return_(&[]);
+        builder.finalize();
+        let id = self
+            .module
+            .declare_anonymous_function(&ctx.func.signature)
+            .ok()?;
+        self.module.define_function(id, &mut ctx).ok()?;
+        self.module.clear_context(&mut ctx);
+        self.module.finalize_definitions().ok()?;
+        let code = self.module.get_finalized_function(id);
+        // SAFETY: The function was declared with this signature
+        Some(unsafe { mem::transmute::<*const u8, NativeFn>(code) })
+    }
+}
+
+fn pop(stack: &mut Vec<Reg>) -> Reg {
+    stack.pop().expect("supported functions do not underflow")
+}
+
+/// Check that a function's instructions only do supported things to scalars,
+/// and that they take and leave as many values as its signature says
+fn supported(f: &Function, args: usize, outputs: usize) -> bool {
+    let mut height = args;
+    for instr in &f.instrs {
+        let (taken, pushed) = match instr {
+            Instr::PushConst(_) => match instr.as_constant(&f.constants) {
+                Some(Value::Num(arr)) if arr.rank() == 0 => (0, 1),
+                _ => return false,
+            },
+            Instr::Prim(prim, _) => match prim {
+                Primitive::Identity => (1, 1),
+                Primitive::Pop => (1, 0),
+                Primitive::Dup => (1, 2),
+                Primitive::Flip => (2, 2),
+                Primitive::Over => (2, 3),
+                Primitive::Neg
+                | Primitive::Abs
+                | Primitive::Sqrt
+                | Primitive::Floor
+                | Primitive::Ceil => (1, 1),
+                Primitive::Add | Primitive::Sub | Primitive::Mul | Primitive::Div => (2, 1),
+                _ => return false,
+            },
+            _ => return false,
+        };
+        let Some(rest) = height.checked_sub(taken) else {
+            return false;
+        };
+        height = rest + pushed;
+    }
+    height == outputs
+}
@@ -0,0 +1,479 @@
+//! A Jupyter kernel for Uiua
+//!
+//! The kernel speaks the [Jupyter messaging protocol](https://jupyter-client.readthedocs.io/en/latest/messaging.html)
+//! and runs each cell with [`Uiua::run_cell`] on a single persistent runtime.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value as Json};
+use sha2::Sha256;
+
+use crate::{
+    format::{format_str, FormatConfig},
+    CellOutput, Uiua,
+};
+
+const PROTOCOL_VERSION: &str = "5.3";
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+
+/// Install the kernel spec so that Jupyter can find the kernel
+///
+/// Returns the directory the spec was installed to.
+pub fn install_kernel() -> io::Result<PathBuf> {
+    let dir = jupyter_data_dir()?.join("kernels").join("uiua");
+    fs::create_dir_all(&dir)?;
+    let exe = env::current_exe()?;
+    let spec = json!({
+        "argv": [exe, "jupyter", "{connection_file}"],
+        "display_name": "Uiua",
+        "language": "uiua",
+        "interrupt_mode": "message",
+    });
+    fs::write(dir.join("kernel.json"), format!("{spec:#}"))?;
+    Ok(dir)
+}
+
+fn jupyter_data_dir() -> io::Result<PathBuf> {
+    if let Some(dir) = env::var_os("JUPYTER_DATA_DIR") {
+        return Ok(dir.into());
+    }
+    let var = |name: &str| {
+        env::var_os(name)
+            .map(PathBuf::from)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{name} is not set")))
+    };
+    Ok(if cfg!(windows) {
+        var("APPDATA")?.join("jupyter")
+    } else if cfg!(target_os = "macos") {
+        var("HOME")?.join("Library").join("Jupyter")
+    } else if let Ok(data_home) = var("XDG_DATA_HOME") {
+        data_home.join("jupyter")
+    } else {
+        var("HOME")?.join(".local").join("share").join("jupyter")
+    })
+}
+
+/// Run the kernel with the connection file Jupyter passes to it
+///
+/// Returns when the kernel is shut down.
+pub fn run_kernel(connection_file: &Path) -> Result<(), String> {
+    let connection = fs::read_to_string(connection_file)
+        .map_err(|e| format!("Failed to read connection file: {e}"))?;
+    let connection: Json =
+        serde_json::from_str(&connection).map_err(|e| format!("Invalid connection file: {e}"))?;
+    let endpoint = |port: &str| {
+        format!(
+            "{}://{}:{}",
+            connection["transport"].as_str().unwrap_or("tcp"),
+            connection["ip"].as_str().unwrap_or("127.0.0.1"),
+            connection[port]
+        )
+    };
+    let key = connection["key"].as_str().unwrap_or_default();
+    if !matches!(
+        connection["signature_scheme"].as_str(),
+        None | Some("hmac-sha256")
+    ) {
+        return Err("Only the hmac-sha256 signature scheme is supported".into());
+    }
+
+    let context = zmq::Context::new();
+    let socket = |kind: zmq::SocketType, port: &str| -> Result<zmq::Socket, String> {
+        let socket = context.socket(kind).map_err(|e| e.to_string())?;
+        (socket.bind(&endpoint(port))).map_err(|e| format!("Failed to bind {port}: {e}"))?;
+        Ok(socket)
+    };
+    let shell = socket(zmq::ROUTER, "shell_port")?;
+    let control = socket(zmq::ROUTER, "control_port")?;
+    let iopub = socket(zmq::PUB, "iopub_port")?;
+    let _stdin = socket(zmq::ROUTER, "stdin_port")?;
+    let heartbeat = socket(zmq::REP, "hb_port")?;
+    thread::spawn(move || {
+        // The heartbeat just echoes whatever it receives
+        while let Ok(msg) = heartbeat.recv_bytes(0) {
+            if heartbeat.send(msg, 0).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut kernel = Kernel {
+        key: key.as_bytes().to_vec(),
+        session: new_id(),
+        iopub,
+        rt: Uiua::with_native_sys(),
+        execution_count: 0,
+    };
+    kernel.publish(
+        "status",
+        &Json::Null,
+        json!({ "execution_state": "starting" }),
+    );
+    loop {
+        let mut items = [
+            shell.as_poll_item(zmq::POLLIN),
+            control.as_poll_item(zmq::POLLIN),
+        ];
+        zmq::poll(&mut items, -1).map_err(|e| e.to_string())?;
+        for (socket, readable) in [
+            (&shell, items[0].is_readable()),
+            (&control, items[1].is_readable()),
+        ] {
+            if !readable {
+                continue;
+            }
+            let parts = socket.recv_multipart(0).map_err(|e| e.to_string())?;
+            let Some(msg) = parse(&kernel.key, parts) else {
+                continue;
+            };
+            if !kernel.handle(socket, msg) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+struct Message {
+    ids: Vec<Vec<u8>>,
+    header: Json,
+    content: Json,
+}
+
+struct Kernel {
+    key: Vec<u8>,
+    session: String,
+    iopub: zmq::Socket,
+    rt: Uiua,
+    execution_count: usize,
+}
+
+/// Get the HMAC of the parts of a message, or `None` if the key is empty and messages are not signed
+fn mac(key: &[u8], parts: &[&[u8]]) -> Option<Hmac<Sha256>> {
+    if key.is_empty() {
+        return None;
+    }
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes any key size");
+    for part in parts {
+        mac.update(part);
+    }
+    Some(mac)
+}
+
+/// Sign the parts of a message, giving the signature as hex
+fn sign(key: &[u8], parts: &[&[u8]]) -> String {
+    mac(key, parts).map_or_else(String::new, |mac| {
+        (mac.finalize().into_bytes().iter())
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    })
+}
+
+/// Check a hex signature of the parts of a message
+///
+/// The comparison takes the same time however much of the signature is right.
+fn verify(key: &[u8], parts: &[&[u8]], signature: &[u8]) -> bool {
+    let Some(mac) = mac(key, parts) else {
+        return true;
+    };
+    decode_hex(signature).is_some_and(|signature| mac.verify_slice(&signature).is_ok())
+}
+
+fn decode_hex(hex: &[u8]) -> Option<Vec<u8>> {
+    let pairs = hex.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    let digit = |c: u8| (c as char).to_digit(16);
+    pairs
+        .map(|pair| Some((digit(pair[0])? * 16 + digit(pair[1])?) as u8))
+        .collect()
+}
+
+/// Parse a multipart message, ignoring it if its signature is wrong
+fn parse(key: &[u8], parts: Vec<Vec<u8>>) -> Option<Message> {
+    let delim = parts.iter().position(|part| part == DELIMITER)?;
+    let [signature, header, parent, metadata, content, ..] = &parts[delim + 1..] else {
+        return None;
+    };
+    if !verify(key, &[header, parent, metadata, content], signature) {
+        return None;
+    }
+    Some(Message {
+        ids: parts[..delim].to_vec(),
+        header: serde_json::from_slice(header).ok()?,
+        content: serde_json::from_slice(content).ok()?,
+    })
+}
+
+impl Kernel {
+    fn send(
+        &self,
+        socket: &zmq::Socket,
+        ids: &[Vec<u8>],
+        msg_type: &str,
+        parent: &Json,
+        content: Json,
+    ) {
+        let header = json!({
+            "msg_id": new_id(),
+            "session": self.session,
+            "username": "kernel",
+            "date": timestamp(),
+            "msg_type": msg_type,
+            "version": PROTOCOL_VERSION,
+        })
+        .to_string();
+        let parent = parent.to_string();
+        let metadata = "{}";
+        let content = content.to_string();
+        let signature = sign(
+            &self.key,
+            &[
+                header.as_bytes(),
+                parent.as_bytes(),
+                metadata.as_bytes(),
+                content.as_bytes(),
+            ],
+        );
+        let mut parts: Vec<&[u8]> = ids.iter().map(Vec::as_slice).collect();
+        parts.extend([
+            DELIMITER,
+            signature.as_bytes(),
+            header.as_bytes(),
+            parent.as_bytes(),
+            metadata.as_bytes(),
+            content.as_bytes(),
+        ]);
+        if let Err(e) = socket.send_multipart(parts, 0) {
+            eprintln!("Failed to send {msg_type}: {e}");
+        }
+    }
+    fn publish(&self, msg_type: &str, parent: &Json, content: Json) {
+        let topic = msg_type.as_bytes().to_vec();
+        self.send(&self.iopub, &[topic], msg_type, parent, content);
+    }
+    /// Handle a message, returning `false` if the kernel should shut down
+    fn handle(&mut self, socket: &zmq::Socket, msg: Message) -> bool {
+        let parent = &msg.header;
+        let msg_type = parent["msg_type"].as_str().unwrap_or_default();
+        let reply_type = msg_type.replace("_request", "_reply");
+        self.publish("status", parent, json!({ "execution_state": "busy" }));
+        let reply = match msg_type {
+            "kernel_info_request" => json!({
+                "status": "ok",
+                "protocol_version": PROTOCOL_VERSION,
+                "implementation": "uiua",
+                "implementation_version": env!("CARGO_PKG_VERSION"),
+                "language_info": {
+                    "name": "uiua",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "mimetype": "text/x-uiua",
+                    "file_extension": ".ua",
+                },
+                "banner": format!("Uiua {}", env!("CARGO_PKG_VERSION")),
+                "help_links": [{ "text": "Uiua Documentation", "url": "https://uiua.org/docs" }],
+            }),
+            "execute_request" => self.execute(parent, &msg.content),
+            "is_complete_request" => json!({ "status": "complete" }),
+            "comm_info_request" => json!({ "status": "ok", "comms": {} }),
+            "history_request" => json!({ "status": "ok", "history": [] }),
+            "inspect_request" => {
+                json!({ "status": "ok", "found": false, "data": {}, "metadata": {} })
+            }
+            "complete_request" => {
+                let cursor = msg.content["cursor_pos"].as_u64().unwrap_or(0);
+                json!({
+                    "status": "ok",
+                    "matches": [],
+                    "cursor_start": cursor,
+                    "cursor_end": cursor,
+                    "metadata": {},
+                })
+            }
+            // Cells run on the thread that receives messages, so there is no running cell to interrupt
+            "interrupt_request" => json!({
+                "status": "error",
+                "ename": "Error",
+                "evalue": "The Uiua kernel cannot be interrupted",
+                "traceback": [],
+            }),
+            "shutdown_request" => {
+                let restart = msg.content["restart"].as_bool().unwrap_or(false);
+                self.send(
+                    socket,
+                    &msg.ids,
+                    &reply_type,
+                    parent,
+                    json!({ "status": "ok", "restart": restart }),
+                );
+                self.publish("status", parent, json!({ "execution_state": "idle" }));
+                return false;
+            }
+            _ => {
+                self.publish("status", parent, json!({ "execution_state": "idle" }));
+                return true;
+            }
+        };
+        self.send(socket, &msg.ids, &reply_type, parent, reply);
+        self.publish("status", parent, json!({ "execution_state": "idle" }));
+        true
+    }
+    fn execute(&mut self, parent: &Json, content: &Json) -> Json {
+        let code = content["code"].as_str().unwrap_or_default();
+        let silent = content["silent"].as_bool().unwrap_or(false);
+        if !silent {
+            self.execution_count += 1;
+        }
+        let count = self.execution_count;
+        self.publish(
+            "execute_input",
+            parent,
+            json!({ "code": code, "execution_count": count }),
+        );
+        let code =
+            format_str(code, &FormatConfig::default()).map_or_else(|_| code.into(), |f| f.output);
+        let res = self.rt.run_cell(format!("In[{count}]"), &code);
+        for diagnostic in &res.diagnostics {
+            let text = format!("{}\n", diagnostic.report().color(false));
+            self.publish("stream", parent, json!({ "name": "stderr", "text": text }));
+        }
+        for output in res.output {
+            let (msg_type, content) = match output {
+                CellOutput::Stdout(text) => ("stream", json!({ "name": "stdout", "text": text })),
+                CellOutput::Stderr(text) | CellOutput::Trace(text) => {
+                    ("stream", json!({ "name": "stderr", "text": text }))
+                }
+                CellOutput::Image(bytes) => display_data("image/png", BASE64.encode(bytes)),
+                CellOutput::Gif(bytes) => display_data("image/gif", BASE64.encode(bytes)),
+                CellOutput::Audio(bytes) => display_data(
+                    "text/html",
+                    format!(
+                        r#"<audio controls src="data:audio/wav;base64,{}"></audio>"#,
+                        BASE64.encode(bytes)
+                    ),
+                ),
+            };
+            if !silent {
+                self.publish(msg_type, parent, content);
+            }
+        }
+        if let Some(error) = res.error {
+            let traceback = error.report().color(false).to_string();
+            let error = json!({
+                "ename": "Error",
+                "evalue": error.message(),
+                "traceback": traceback.lines().collect::<Vec<_>>(),
+            });
+            self.publish("error", parent, error.clone());
+            let mut reply = error;
+            reply["status"] = "error".into();
+            reply["execution_count"] = count.into();
+            return reply;
+        }
+        if !silent && !res.pushed.is_empty() {
+            let shown: Vec<String> = res.pushed.iter().rev().map(|val| val.show()).collect();
+            self.publish(
+                "execute_result",
+                parent,
+                json!({
+                    "execution_count": count,
+                    "data": { "text/plain": shown.join("\n") },
+                    "metadata": {},
+                }),
+            );
+        }
+        json!({ "status": "ok", "execution_count": count, "user_expressions": {} })
+    }
+}
+
+fn display_data(mime: &str, data: String) -> (&'static str, Json) {
+    (
+        "display_data",
+        json!({ "data": { mime: data }, "metadata": {} }),
+    )
+}
+
+fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// The current time as an ISO 8601 UTC timestamp
+fn timestamp() -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (hour, min, sec) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    // Convert days since the epoch to a civil date
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{hour:02}:{min:02}:{sec:02}.{:06}Z",
+        since_epoch.subsec_micros()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(key: &[u8], signature: Option<&str>) -> Vec<Vec<u8>> {
+        let header = br#"{"msg_type":"kernel_info_request"}"#;
+        let content = b"{}";
+        let parts: [&[u8]; 4] = [header, b"{}", b"{}", content];
+        let signature = signature.map_or_else(|| sign(key, &parts), Into::into);
+        let mut message = vec![b"id".to_vec(), DELIMITER.to_vec(), signature.into_bytes()];
+        message.extend(parts.map(<[u8]>::to_vec));
+        message
+    }
+
+    #[test]
+    fn signatures() {
+        let key = b"secret";
+        let signature = sign(key, &[b"a", b"b"]);
+        assert_eq!(signature.len(), 64);
+        assert_eq!(signature, sign(key, &[b"ab"]));
+        assert_ne!(signature, sign(b"other", &[b"a", b"b"]));
+        assert!(verify(key, &[b"a", b"b"], signature.as_bytes()));
+        assert!(verify(
+            key,
+            &[b"a", b"b"],
+            signature.to_uppercase().as_bytes()
+        ));
+        assert!(!verify(key, &[b"a", b"c"], signature.as_bytes()));
+        assert!(!verify(key, &[b"a", b"b"], &signature.as_bytes()[1..]));
+        assert!(!verify(key, &[b"a", b"b"], b"not hex"));
+        // Messages are not signed without a key
+        assert_eq!(sign(b"", &[b"a"]), "");
+        assert!(verify(b"", &[b"a"], b""));
+    }
+
+    #[test]
+    fn parse_messages() {
+        let key = b"secret";
+        let msg = parse(key, message(key, None)).unwrap();
+        assert_eq!(msg.ids, [b"id".to_vec()]);
+        assert_eq!(msg.header["msg_type"], "kernel_info_request");
+        assert!(parse(key, message(b"wrong", None)).is_none());
+        assert!(parse(key, message(key, Some(&"0".repeat(64)))).is_none());
+        assert!(parse(b"", message(b"", None)).is_some());
+        let mut truncated = message(key, None);
+        truncated.pop();
+        assert!(parse(key, truncated).is_none());
+    }
+}
@@ -11,6 +11,21 @@ use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{Primitive, UiuaError};
 
+/// Lex the code in a span of some input
+pub(crate) fn lex_span(span: &CodeSpan) -> (Vec<Sp<Token>>, Vec<Sp<LexError>>) {
+    Lexer {
+        input_segments: (span.input.graphemes(true))
+            .take(span.end.char_pos)
+            .collect(),
+        loc: span.start,
+        file: span.path.clone(),
+        input: span.input.clone(),
+        tokens: Vec::new(),
+        errors: Vec::new(),
+    }
+    .run()
+}
+
 pub fn lex(input: &str, file: Option<&Path>) -> (Vec<Sp<Token>>, Vec<Sp<LexError>>) {
     Lexer {
         input_segments: input.graphemes(true).collect(),
@@ -167,6 +182,10 @@ impl CodeSpan {
     pub fn as_str(&self) -> &str {
         &self.input[self.start.byte_pos..self.end.byte_pos]
     }
+    /// Get the text of the line the span starts on
+    pub fn line_text(&self) -> &str {
+        self.input.lines().nth(self.start.line - 1).unwrap_or("")
+    }
     pub fn contains_line_col(&self, line: usize, col: usize) -> bool {
         if self.start.line == self.end.line {
             self.start.line == line && (self.start.col..=self.end.col).contains(&col)
@@ -308,11 +327,14 @@ pub enum Token {
     Number,
     Char(String),
     Str(String),
-    FormatStr(Vec<String>),
-    MultilineString(Vec<String>),
+    /// A format string's fragments, and the code interpolated between them, if any
+    FormatStr(Vec<String>, Vec<Option<CodeSpan>>),
+    /// A line of a multiline string, split the same way as a format string
+    MultilineString(Vec<String>, Vec<Option<CodeSpan>>),
     Simple(AsciiToken),
     Glyph(Primitive),
     LeftArrow,
+    HookArrow,
     Newline,
     Spaces,
 }
@@ -330,15 +352,15 @@ impl Token {
             _ => None,
         }
     }
-    pub fn as_format_string(&self) -> Option<Vec<String>> {
+    pub fn as_format_string(&self) -> Option<(Vec<String>, Vec<Option<CodeSpan>>)> {
         match self {
-            Token::FormatStr(frags) => Some(frags.clone()),
+            Token::FormatStr(frags, args) => Some((frags.clone(), args.clone())),
             _ => None,
         }
     }
-    pub fn as_multiline_string(&self) -> Option<Vec<String>> {
+    pub fn as_multiline_string(&self) -> Option<(Vec<String>, Vec<Option<CodeSpan>>)> {
         match self {
-            Token::MultilineString(parts) => Some(parts.clone()),
+            Token::MultilineString(frags, args) => Some((frags.clone(), args.clone())),
             _ => None,
         }
     }
@@ -539,6 +561,7 @@ impl<'a> Lexer<'a> {
                 ">" if self.next_char_exact("=") => self.end(GreaterEqual, start),
                 "!" if self.next_char_exact("=") => self.end(BangEqual, start),
                 "←" => self.end(LeftArrow, start),
+                "↩" => self.end(HookArrow, start),
                 // Comments
                 "#" => {
                     let mut comment = String::new();
@@ -571,13 +594,24 @@ impl<'a> Lexer<'a> {
                 // Strings
                 "\"" | "$" => {
                     let format = c == "$";
-                    if format && self.next_char_exact(" ") {
+                    let mut raw = format && self.next_chars_exact(["$", " "]);
+                    if raw || format && self.next_char_exact(" ") {
                         // Multiline strings
                         let mut start = start;
                         loop {
-                            let inner = self.parse_string_contents(start, None);
-                            let string = parse_format_fragments(&inner);
-                            self.end(MultilineString(string), start);
+                            if raw {
+                                // Raw lines have no escapes or format arguments
+                                let mut line = String::new();
+                                while let Some(c) = self.next_char_if(|c| !"\r\n".contains(c)) {
+                                    line.push_str(c);
+                                }
+                                self.end(MultilineString(vec![line], Vec::new()), start);
+                            } else {
+                                let (inner, interpolated) = self.parse_string_contents(start, None);
+                                let (frags, args) =
+                                    parse_format_fragments(&inner, interpolated, true);
+                                self.end(MultilineString(frags, args), start);
+                            }
                             let checkpoint = self.loc;
                             while self.next_char_exact("\r") {}
                             if self.next_char_if(|c| c.ends_with('\n')).is_some() {
@@ -588,7 +622,8 @@ impl<'a> Lexer<'a> {
                                     .is_some()
                                 {}
                                 start = self.loc;
-                                if self.next_chars_exact(["$", " "]) {
+                                raw = self.next_chars_exact(["$", "$", " "]);
+                                if raw || self.next_chars_exact(["$", " "]) {
                                     continue;
                                 }
                             }
@@ -606,16 +641,16 @@ impl<'a> Lexer<'a> {
                         errored = true;
                     }
                     // Single-line strings
-                    let inner = self.parse_string_contents(start, Some('"'));
+                    let (inner, interpolated) = self.parse_string_contents(start, Some('"'));
                     if !self.next_char_exact("\"") && !errored {
                         self.errors.push(
                             self.end_span(start)
                                 .sp(LexError::ExpectedCharacter(Some('"'))),
                         );
                     }
-                    if format {
-                        let frags = parse_format_fragments(&inner);
-                        self.end(FormatStr(frags), start)
+                    if format || !interpolated.is_empty() {
+                        let (frags, args) = parse_format_fragments(&inner, interpolated, format);
+                        self.end(FormatStr(frags, args), start)
                     } else {
                         self.end(Str(inner), start)
                     }
@@ -699,6 +734,10 @@ impl<'a> Lexer<'a> {
                             continue;
                         }
                     }
+                    if let Some(alias) = Primitive::from_alias(c) {
+                        self.end(Glyph(alias.prim), start);
+                        continue;
+                    }
                     self.errors
                         .push(self.end_span(start).sp(LexError::UnexpectedChar(c.into())));
                 }
@@ -707,6 +746,9 @@ impl<'a> Lexer<'a> {
         (self.tokens, self.errors)
     }
     fn number(&mut self, init: &str) -> bool {
+        if self.radix_number(init) {
+            return true;
+        }
         // Whole part
         let mut got_digit = false;
         while self
@@ -749,6 +791,38 @@ impl<'a> Lexer<'a> {
         }
         true
     }
+    /// Lex a hexadecimal number like `0x1F` or a binary number like `0b1010`
+    ///
+    /// Digits that are not valid in the radix, or a missing number after `0x` or `0b`,
+    /// are kept in the literal so that the parser reports it as invalid.
+    fn radix_number(&mut self, init: &str) -> bool {
+        let start = self.loc;
+        if !(init == "0" || init == "-" && self.next_char_exact("0")) {
+            return false;
+        }
+        let is_digit: fn(char) -> bool = if self.next_char_exact("x") {
+            |c| c.is_ascii_hexdigit()
+        } else if self.next_char_exact("b") {
+            |c| c.is_ascii_digit()
+        } else {
+            self.loc = start;
+            return false;
+        };
+        let mut got_digit = false;
+        while self.next_char_if_all(is_digit).is_some() {
+            got_digit = true;
+        }
+        // A letter right after the prefix starts a word, like in `0bits`
+        if !got_digit
+            && self
+                .peek_char()
+                .is_some_and(|c| c.chars().all(|c| c.is_ascii_alphabetic()))
+        {
+            self.loc = start;
+            return false;
+        }
+        true
+    }
     fn character(
         &mut self,
         escaped: &mut bool,
@@ -782,6 +856,21 @@ impl<'a> Lexer<'a> {
                     }
                     std::char::from_u32(code).ok_or("x")?.into()
                 }
+                "u" if self.next_char_exact("{") => {
+                    let mut code = 0;
+                    let mut digits = 0;
+                    while let Some(c) = self.next_char_if_all(|c| c.is_ascii_hexdigit()) {
+                        code = code << 4 | c.chars().next().unwrap().to_digit(16).unwrap();
+                        digits += 1;
+                        if digits > 6 {
+                            return Err("u");
+                        }
+                    }
+                    if digits == 0 || !self.next_char_exact("}") {
+                        return Err("u");
+                    }
+                    std::char::from_u32(code).ok_or("u")?.into()
+                }
                 "u" => {
                     let mut code = 0;
                     for _ in 0..4 {
@@ -801,10 +890,23 @@ impl<'a> Lexer<'a> {
             c.into()
         }))
     }
-    fn parse_string_contents(&mut self, start: Loc, escape_char: Option<char>) -> String {
+    /// Parse the contents of a string
+    ///
+    /// Interpolations are replaced with [`INTERPOLATION`] and their code spans are returned.
+    fn parse_string_contents(
+        &mut self,
+        start: Loc,
+        escape_char: Option<char>,
+    ) -> (String, Vec<CodeSpan>) {
         let mut string = String::new();
+        let mut interpolated = Vec::new();
         let mut escaped = false;
         loop {
+            if self.next_chars_exact(["\\", "{"]) {
+                string.push(INTERPOLATION);
+                interpolated.push(self.interpolation(start));
+                continue;
+            }
             match self.character(&mut escaped, escape_char) {
                 Ok(Some(c)) => string.push_str(&c),
                 Ok(None) => break,
@@ -814,17 +916,60 @@ impl<'a> Lexer<'a> {
                 }
             }
         }
-        string
+        (string, interpolated)
+    }
+    /// Skip the code of an interpolation, up to its closing `}`
+    fn interpolation(&mut self, start: Loc) -> CodeSpan {
+        let code_start = self.loc;
+        let mut code_end = self.loc;
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+        while let Some(c) = self.next_char_if(|c| !c.ends_with('\n')) {
+            match c {
+                _ if escaped => escaped = false,
+                "\\" if in_string => escaped = true,
+                "\"" => in_string = !in_string,
+                "{" if !in_string => depth += 1,
+                "}" if !in_string && depth == 0 => return self.make_span(code_start, code_end),
+                "}" if !in_string => depth -= 1,
+                _ => {}
+            }
+            code_end = self.loc;
+        }
+        self.errors.push(
+            self.end_span(start)
+                .sp(LexError::ExpectedCharacter(Some('}'))),
+        );
+        self.make_span(code_start, code_end)
     }
 }
 
-fn parse_format_fragments(s: &str) -> Vec<String> {
+/// Marks where an interpolation was in a string
+const INTERPOLATION: char = '\u{10FFFE}';
+
+/// Split a string into the fragments between its arguments
+///
+/// If `placeholders` is true, `_`s are arguments taken from the stack.
+fn parse_format_fragments(
+    s: &str,
+    interpolated: Vec<CodeSpan>,
+    placeholders: bool,
+) -> (Vec<String>, Vec<Option<CodeSpan>>) {
     let mut frags: Vec<String> = Vec::new();
+    let mut args = Vec::new();
+    let mut interpolated = interpolated.into_iter();
     let mut curr = String::new();
     for c in s.chars() {
         match c {
-            '_' => {
+            '_' if placeholders => {
                 frags.push(curr);
+                args.push(None);
+                curr = String::new();
+            }
+            INTERPOLATION => {
+                frags.push(curr);
+                args.push(interpolated.next());
                 curr = String::new();
             }
             char::MAX => curr.push('_'),
@@ -832,7 +977,7 @@ fn parse_format_fragments(s: &str) -> Vec<String> {
         }
     }
     frags.push(curr);
-    frags
+    (frags, args)
 }
 
 pub fn is_ident_char(c: char) -> bool {
@@ -840,6 +985,9 @@ pub fn is_ident_char(c: char) -> bool {
 }
 
 pub fn is_custom_glyph(c: &str) -> bool {
+    if Primitive::from_alias(c).is_some() {
+        return false;
+    }
     match c.chars().count() {
         0 => false,
         1 => {
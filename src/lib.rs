@@ -8,44 +8,69 @@ The current API should be considered deeply unstable.
 #![allow(clippy::single_match, clippy::needless_range_loop)]
 
 mod algorithm;
+mod annotation;
 mod array;
-mod ast;
+pub mod ast;
+pub mod bench;
 mod boxed;
 mod check;
 mod compile;
+pub mod coverage;
 mod cowslice;
+pub mod cst;
+pub mod diff;
 mod error;
+mod explain;
 pub mod format;
 mod function;
 mod grid_fmt;
+mod ident;
+#[cfg(feature = "jit")]
+mod jit;
+#[cfg(feature = "jupyter")]
+pub mod jupyter;
 mod lex;
 pub mod lsp;
 mod parse;
 mod primitive;
 #[doc(hidden)]
 pub mod profile;
+mod progress;
+pub mod refactor;
 mod run;
+mod session;
+mod shape_check;
+mod snapshot;
+pub mod spec;
+pub mod stats;
 mod sys;
 mod sys_native;
+#[cfg(test)]
+mod test_util;
+mod unit;
 mod value;
-
-use std::sync::Arc;
+mod version;
 
 pub use {
-    array::Array,
+    array::{Array, ArrayMeta},
     error::*,
-    lex::is_ident_char,
+    explain::{ErrorCode, ExplainStep},
+    function::{FunctionId, Signature},
+    ident::Ident,
+    lex::{is_ident_char, CodeSpan, Loc, Sp, Span},
     lsp::{spans, SpanKind},
     parse::parse,
     primitive::*,
     run::*,
+    session::{Session, SessionBinding},
+    snapshot::{Snapshot, SnapshotLimits, SnapshotValue, Snapshots},
     sys::*,
     sys_native::*,
+    unit::Unit,
     value::Value,
+    version::Version,
 };
 
-pub type Ident = Arc<str>;
-
 #[test]
 fn suite() {
     for entry in std::fs::read_dir("tests").unwrap() {
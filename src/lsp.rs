@@ -1,7 +1,7 @@
 use std::slice;
 
 use crate::{
-    ast::{Item, Modifier, Word},
+    ast::{Destructure, Item, Modifier, Word},
     lex::{CodeSpan, Loc, Sp},
     parse::parse,
     Primitive,
@@ -40,6 +40,7 @@ fn items_spans(items: &[Item]) -> Vec<Sp<SpanKind>> {
                 }
                 spans.extend(words_spans(&binding.words));
             }
+            Item::Destructure(des) => spans.extend(destructure_spans(des)),
             Item::ExtraNewlines(span) => spans.push(span.clone().sp(SpanKind::Whitespace)),
         }
     }
@@ -132,13 +133,34 @@ fn words_spans(words: &[Sp<Word>]) -> Vec<Sp<SpanKind>> {
                 spans.extend(words_spans(&m.operands));
             }
             Word::Spaces => spans.push(word.span.clone().sp(SpanKind::Whitespace)),
+            Word::RowBreak => spans.push(word.span.clone().sp(SpanKind::Delimiter)),
             Word::Comment(_) => spans.push(word.span.clone().sp(SpanKind::Comment)),
             Word::Placeholder(_) => spans.push(word.span.clone().sp(SpanKind::Placeholder)),
+            Word::Local(local) => {
+                spans.push(local.name.span.clone().sp(SpanKind::Ident));
+                spans.push(local.arrow_span.clone().sp(SpanKind::Delimiter));
+                spans.extend(words_spans(&local.words));
+            }
+            Word::Destructure(des) => spans.extend(destructure_spans(des)),
         }
     }
     spans
 }
 
+fn destructure_spans(des: &Destructure) -> Vec<Sp<SpanKind>> {
+    let mut spans = Vec::new();
+    if let Some((open, _)) = &des.brackets {
+        spans.push(open.clone().sp(SpanKind::Delimiter));
+    }
+    spans.extend((des.names.iter()).map(|name| name.span.clone().sp(SpanKind::Ident)));
+    if let Some((_, close)) = &des.brackets {
+        spans.push(close.clone().sp(SpanKind::Delimiter));
+    }
+    spans.push(des.arrow_span.clone().sp(SpanKind::Delimiter));
+    spans.extend(words_spans(&des.words));
+    spans
+}
+
 #[cfg(feature = "lsp")]
 pub use server::run_server;
 
@@ -152,7 +174,7 @@ mod server {
     use super::*;
 
     use crate::{
-        format::{format_str, FormatConfig},
+        format::{format_lines, format_str, line_changes, FormatConfig},
         lex::Loc,
         primitive::{PrimClass, PrimDocFragment},
         Ident, Uiua,
@@ -189,51 +211,40 @@ mod server {
     fn bindings_info(items: &[Item]) -> BindingsInfo {
         let mut bindings = BindingsInfo::new();
         let mut scope_bindings = Vec::new();
-        let mut last_comment: Option<String> = None;
         for item in items {
             match item {
                 Item::TestScope(items) => scope_bindings.push(bindings_info(items)),
                 Item::Words(words) => {
-                    if let [Sp {
-                        value: Word::Comment(comment),
-                        ..
-                    }] = words.as_slice()
-                    {
-                        let full = last_comment.get_or_insert_with(String::new);
-                        if !full.is_empty() {
-                            if comment.trim().is_empty() {
-                                full.push('\n');
-                                full.push('\n');
-                            } else {
-                                full.push(' ');
-                            }
-                        }
-                        full.push_str(comment.trim());
-                    } else {
-                        last_comment = None;
-                        for word in words {
-                            if let Word::Ident(ident) = &word.value {
-                                if let Some((_, info)) =
-                                    bindings.iter().rev().find(|(name, _)| name.value == *ident)
-                                {
-                                    let info = info.clone();
-                                    bindings.insert(word.span.clone().sp(ident.clone()), info);
-                                }
+                    for word in words {
+                        if let Word::Ident(ident) = &word.value {
+                            if let Some((_, info)) =
+                                bindings.iter().rev().find(|(name, _)| name.value == *ident)
+                            {
+                                let info = info.clone();
+                                bindings.insert(word.span.clone().sp(*ident), info);
                             }
                         }
                     }
                 }
                 Item::Binding(binding) => {
-                    let comment = last_comment.take();
                     bindings.insert(
                         binding.name.clone(),
                         BindingInfo {
-                            comment,
+                            comment: binding.doc.clone(),
                             span: binding.name.span.clone(),
                         }
                         .into(),
                     );
                 }
+                Item::Destructure(des) => {
+                    for name in &des.names {
+                        let info = BindingInfo {
+                            comment: None,
+                            span: name.span.clone(),
+                        };
+                        bindings.insert(name.clone(), info.into());
+                    }
+                }
                 Item::ExtraNewlines(_) => {}
             }
         }
@@ -302,6 +313,7 @@ mod server {
                     )),
                     hover_provider: Some(HoverProviderCapability::Simple(true)),
                     document_formatting_provider: Some(OneOf::Left(true)),
+                    document_range_formatting_provider: Some(OneOf::Left(true)),
                     semantic_tokens_provider: Some(
                         SemanticTokensServerCapabilities::SemanticTokensOptions(
                             SemanticTokensOptions {
@@ -448,6 +460,48 @@ mod server {
             }]))
         }
 
+        async fn range_formatting(
+            &self,
+            params: DocumentRangeFormattingParams,
+        ) -> Result<Option<Vec<TextEdit>>> {
+            let doc = if let Some(doc) = self.docs.get(&params.text_document.uri) {
+                doc
+            } else {
+                return Ok(None);
+            };
+            let config = FormatConfig::find().unwrap_or_default();
+            let lines = params.range.start.line as usize + 1..=params.range.end.line as usize + 1;
+            let Ok(formatted) = format_lines(&doc.input, None, &config, lines) else {
+                return Ok(None);
+            };
+            // Only the changed lines are replaced so that the rest of the document is untouched
+            let line_count = doc.input.split('\n').count();
+            let edits = (line_changes(&doc.input, &formatted).into_iter())
+                .map(|change| {
+                    let line = change.line as u32 - 1;
+                    let (range, new_text) = if change.line + change.old.len() <= line_count {
+                        let end = Position::new(line + change.old.len() as u32, 0);
+                        let text = change.new.iter().map(|line| format!("{line}\n"));
+                        (Range::new(Position::new(line, 0), end), text.collect())
+                    } else {
+                        // The last line has no newline after it, so replace from the end of the line before
+                        let end = Position::new(line_count as u32 - 1, u32::MAX);
+                        if line == 0 {
+                            (Range::new(Position::new(0, 0), end), change.new.join("\n"))
+                        } else {
+                            let text = change.new.iter().map(|line| format!("\n{line}"));
+                            (
+                                Range::new(Position::new(line - 1, u32::MAX), end),
+                                text.collect(),
+                            )
+                        }
+                    };
+                    TextEdit { range, new_text }
+                })
+                .collect();
+            Ok(Some(edits))
+        }
+
         async fn inline_value(
             &self,
             params: InlineValueParams,
@@ -4,8 +4,9 @@ compile_error!("To compile the uiua interpreter binary, you must enable the `bin
 use std::{
     env, fmt, fs,
     io::{self, stderr, Write},
+    ops::RangeInclusive,
     path::{Path, PathBuf},
-    process::{exit, Child, Command, Stdio},
+    process::{exit, id as process_id, Child, Command, Stdio},
     sync::mpsc::channel,
     thread::sleep,
     time::Duration,
@@ -18,9 +19,17 @@ use notify::{EventKind, RecursiveMode, Watcher};
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use uiua::{
-    format::{format_file, format_str, FormatConfig, FormatConfigSource},
+    coverage::Coverage,
+    diff::{diff, CodeChange},
+    format::{
+        format, format_file, format_lines, format_str, line_changes, FormatConfig,
+        FormatConfigSource,
+    },
     lsp::{spans, SpanKind},
-    PrimClass, RunMode, Uiua, UiuaError, UiuaResult,
+    spec::{spec_files, SpecFile},
+    stats::{project_stats, ProgramStats},
+    DiagnosticKind, ErrorCode, ExplainStep, NumPolicy, PrimClass, RunMode, Session, Snapshot,
+    SnapshotLimits, SnapshotValue, Uiua, UiuaError, UiuaResult, Value,
 };
 
 fn main() {
@@ -36,6 +45,7 @@ fn main() {
         } else {
             if let Ok(App::Watch { .. }) | Err(_) = App::try_parse() {
                 clear_watching_with(" ", "");
+                _ = fs::remove_file(watch_memo_file());
             }
             exit(0)
         }
@@ -49,6 +59,11 @@ fn main() {
 
 static WATCH_CHILD: Lazy<Mutex<Option<Child>>> = Lazy::new(Default::default);
 
+/// The file that memoized results are persisted to between runs in watch mode
+fn watch_memo_file() -> PathBuf {
+    env::temp_dir().join(format!("uiua-watch-memo-{}", process_id()))
+}
+
 fn run() -> UiuaResult {
     if cfg!(feature = "profile") {
         uiua::profile::run_profile();
@@ -66,6 +81,8 @@ fn run() -> UiuaResult {
             }
             App::Fmt {
                 path,
+                check,
+                lines,
                 formatter_options,
             } => {
                 let config = FormatConfig::from_source(
@@ -73,7 +90,24 @@ fn run() -> UiuaResult {
                     path.as_deref(),
                 )?;
 
-                if let Some(path) = path {
+                if check {
+                    let paths = path.map_or_else(uiua_files, |path| vec![path]);
+                    if check_formatting(&paths, &config, lines)? {
+                        exit(1);
+                    }
+                } else if let Some(lines) = lines {
+                    let path = path.expect("a path is required for --lines");
+                    let input = fs::read_to_string(&path)
+                        .map_err(|e| UiuaError::Load(path.clone(), e.into()))?;
+                    let output = format_lines(&input, Some(&path), &config, lines)?;
+                    if output != input {
+                        fs::write(&path, &output)
+                            .map_err(|e| UiuaError::Format(path.clone(), e.into()))?;
+                    }
+                    if formatter_options.stdout {
+                        println!("{output}");
+                    }
+                } else if let Some(path) = path {
                     format_single_file(path, &config, formatter_options.stdout)?;
                 } else {
                     format_multi_files(&config, formatter_options.stdout)?;
@@ -86,6 +120,11 @@ fn run() -> UiuaResult {
                 no_update,
                 time_instrs,
                 mode,
+                memo_file,
+                check_shapes,
+                check_stack,
+                check_leaks,
+                num_policy,
                 #[cfg(feature = "audio")]
                 audio_options,
                 args,
@@ -119,14 +158,27 @@ fn run() -> UiuaResult {
                     .with_file_path(&path)
                     .with_args(args)
                     .print_diagnostics(true)
+                    .with_lints(true)
+                    .with_shape_checks(check_shapes)
+                    .with_stack_checks(check_stack)
+                    .with_leak_checks(check_leaks)
+                    .with_num_policy(num_policy.unwrap_or_default())
                     .time_instrs(time_instrs);
-                rt.load_file(path)?;
+                if let Some(memo_file) = memo_file {
+                    rt = rt.with_memo_file(memo_file);
+                }
+                let res = rt.load_file(path);
+                if let Err(e) = rt.save_memo_file() {
+                    eprintln!("Failed to save memoized results: {e}");
+                }
+                res?;
                 for value in rt.take_stack() {
                     println!("{}", value.show());
                 }
             }
             App::Eval {
                 code,
+                steps,
                 #[cfg(feature = "audio")]
                 audio_options,
                 args,
@@ -137,6 +189,14 @@ fn run() -> UiuaResult {
                     .with_mode(RunMode::Normal)
                     .with_args(args)
                     .print_diagnostics(true);
+                if steps {
+                    let snapshots = rt.snapshots(&code, SnapshotLimits::default());
+                    print_snapshots(&snapshots.steps);
+                    if let Some(error) = snapshots.error {
+                        return Err(error);
+                    }
+                    return Ok(());
+                }
                 rt.load_str(&code)?;
                 for value in rt.take_stack() {
                     println!("{}", value.show());
@@ -145,6 +205,8 @@ fn run() -> UiuaResult {
             App::Test {
                 path,
                 formatter_options,
+                coverage,
+                coverage_json,
             } => {
                 let path = if let Some(path) = path {
                     path
@@ -160,12 +222,104 @@ fn run() -> UiuaResult {
                 let config =
                     FormatConfig::from_source(formatter_options.format_config_source, Some(&path))?;
                 format_file(&path, &config)?;
-                Uiua::with_native_sys()
+                let mut rt = Uiua::with_native_sys()
                     .with_mode(RunMode::Test)
                     .print_diagnostics(true)
-                    .load_file(path)?;
+                    .with_lints(true)
+                    .with_coverage(coverage || coverage_json.is_some());
+                let res = rt.load_file(path);
+                let report = rt.coverage();
+                if coverage {
+                    print_coverage(&report);
+                }
+                if let Some(json_path) = coverage_json {
+                    fs::write(&json_path, report.to_json())
+                        .map_err(|e| UiuaError::Format(json_path, e.into()))?;
+                }
+                res?;
                 println!("No failures!");
             }
+            App::Check { path, check_shapes } => {
+                let path = if let Some(path) = path {
+                    path
+                } else {
+                    match working_file_path() {
+                        Ok(path) => path,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            return Ok(());
+                        }
+                    }
+                };
+                let mut rt = Uiua::with_native_sys()
+                    .with_lints(true)
+                    .with_shape_checks(check_shapes);
+                rt.check_file(path)?;
+                let diagnostics = rt.take_diagnostics();
+                for diagnostic in &diagnostics {
+                    println!("{}", diagnostic.report());
+                }
+                if diagnostics.is_empty() {
+                    println!("No problems found");
+                } else if (diagnostics.iter()).any(|diag| diag.kind == DiagnosticKind::Warning) {
+                    // Advice and style diagnostics are reported but don't fail the check
+                    exit(1);
+                }
+            }
+            App::Diff { old, new } => {
+                let read = |path: &PathBuf| {
+                    fs::read_to_string(path).map_err(|e| UiuaError::Load(path.clone(), e.into()))
+                };
+                let changes = diff(&read(&old)?, &read(&new)?)?;
+                print_code_changes(&changes);
+                if !changes.is_empty() {
+                    exit(1);
+                }
+            }
+            App::Stats { paths } => {
+                let paths = if paths.is_empty() {
+                    uiua_files()
+                } else {
+                    paths
+                };
+                print_stats(&project_stats(&paths)?);
+            }
+            App::Spec { path, update } => {
+                let path = path.unwrap_or_else(|| "tests/spec".into());
+                let mut failed = 0;
+                for path in spec_files(&path)? {
+                    let text = fs::read_to_string(&path)
+                        .map_err(|e| UiuaError::Load(path.clone(), e.into()))?;
+                    let spec = SpecFile::parse(&path, &text);
+                    if update {
+                        let updated = spec.updated();
+                        if updated != text {
+                            fs::write(&path, updated)
+                                .map_err(|e| UiuaError::Format(path.clone(), e.into()))?;
+                            println!("Updated {}", path.display());
+                        }
+                        continue;
+                    }
+                    for failure in spec.check() {
+                        println!("{failure}");
+                        failed += 1;
+                    }
+                }
+                if failed > 0 {
+                    eprintln!(
+                        "{failed} spec case{} failed",
+                        if failed == 1 { "" } else { "s" }
+                    );
+                    exit(1);
+                }
+            }
+            App::Explain { code, expr: None } => explain(code.as_deref()),
+            App::Explain {
+                expr: Some(expr), ..
+            } => {
+                let mut rt = Uiua::with_native_sys();
+                print_explain_steps(&rt.explain(&expr)?, 0);
+            }
             App::Watch {
                 no_format,
                 formatter_options,
@@ -190,10 +344,30 @@ fn run() -> UiuaResult {
             }
             #[cfg(feature = "lsp")]
             App::Lsp => uiua::lsp::run_server(),
+            #[cfg(feature = "jupyter")]
+            App::Jupyter {
+                connection_file,
+                install,
+            } => {
+                if install {
+                    match uiua::jupyter::install_kernel() {
+                        Ok(dir) => println!("Installed kernel spec to {}", dir.display()),
+                        Err(e) => eprintln!("Failed to install kernel spec: {e}"),
+                    }
+                } else if let Some(connection_file) = connection_file {
+                    if let Err(e) = uiua::jupyter::run_kernel(&connection_file) {
+                        eprintln!("{e}");
+                        exit(1);
+                    }
+                } else {
+                    eprintln!("Either a connection file or --install must be given");
+                }
+            }
             App::Repl {
                 formatter_options,
                 #[cfg(feature = "audio")]
                 audio_options,
+                session,
                 args,
             } => {
                 let config =
@@ -205,7 +379,7 @@ fn run() -> UiuaResult {
                     .with_mode(RunMode::Normal)
                     .with_args(args)
                     .print_diagnostics(true);
-                repl(rt, config);
+                repl(rt, config, session);
             }
         },
         Err(e) if e.kind() == ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand => {
@@ -319,6 +493,7 @@ fn watch(
         socket.set_nonblocking(true)?;
         (socket, port)
     };
+    let memo_file = watch_memo_file();
     let run = |path: &Path, stdin_file: Option<&PathBuf>| -> io::Result<()> {
         if let Some(mut child) = WATCH_CHILD.lock().take() {
             _ = child.kill();
@@ -366,6 +541,8 @@ fn watch(
                                 #[cfg(feature = "audio")]
                                 &audio_port,
                             ])
+                            .arg("--memo-file")
+                            .arg(&memo_file)
                             .args(&args)
                             .stdin(stdin_file.map_or_else(Stdio::inherit, Into::into))
                             .spawn()
@@ -447,6 +624,28 @@ enum App {
         time_instrs: bool,
         #[clap(long, help = "Run the file in a specific mode")]
         mode: Option<RunMode>,
+        #[clap(long, help = "Persist memoized results in a file between runs")]
+        memo_file: Option<PathBuf>,
+        #[clap(
+            long,
+            help = "Warn about arrays whose shapes will not fit before running"
+        )]
+        check_shapes: bool,
+        #[clap(
+            long,
+            help = "Error if a function takes more values than its signature says"
+        )]
+        check_stack: bool,
+        #[clap(
+            long,
+            help = "Warn about lines that leave more than one value or take values left by earlier lines"
+        )]
+        check_leaks: bool,
+        #[clap(
+            long,
+            help = "What to do when arithmetic divides by zero or produces infinity or NaN: allow, warn, or error"
+        )]
+        num_policy: Option<NumPolicy>,
         #[cfg(feature = "audio")]
         #[clap(flatten)]
         audio_options: AudioOptions,
@@ -456,6 +655,8 @@ enum App {
     #[clap(about = "Evaluate an expression and print its output")]
     Eval {
         code: String,
+        #[clap(long, help = "Print the stack after each word runs")]
+        steps: bool,
         #[cfg(feature = "audio")]
         #[clap(flatten)]
         audio_options: AudioOptions,
@@ -467,6 +668,18 @@ enum App {
         path: Option<PathBuf>,
         #[clap(flatten)]
         formatter_options: FormatterOptions,
+        #[clap(long, help = "Show which code the tests ran and which they did not")]
+        coverage: bool,
+        #[clap(long, help = "Write which code the tests ran to a JSON file")]
+        coverage_json: Option<PathBuf>,
+    },
+    #[clap(
+        about = "Check a file for errors, unused bindings, and code that can never run, without running it"
+    )]
+    Check {
+        path: Option<PathBuf>,
+        #[clap(long, help = "Warn about arrays whose shapes will not fit")]
+        check_shapes: bool,
     },
     #[clap(about = "Run .ua files in the current directory when they change")]
     Watch {
@@ -483,15 +696,66 @@ enum App {
         #[clap(trailing_var_arg = true)]
         args: Vec<String>,
     },
+    #[clap(about = "Show how the code in two files differs, ignoring comments and formatting")]
+    Diff { old: PathBuf, new: PathBuf },
+    #[clap(
+        about = "Show which primitives some code uses, how big its bindings are, and which imports are unused"
+    )]
+    Stats {
+        #[clap(
+            help = "The files to count together. Defaults to all files in the current directory"
+        )]
+        paths: Vec<PathBuf>,
+    },
+    #[clap(about = "Check that the code in spec files does what their comments say")]
+    Spec {
+        #[clap(help = "A spec file or a directory of them. Defaults to tests/spec")]
+        path: Option<PathBuf>,
+        #[clap(long, help = "Rewrite the expectations to match what the code does")]
+        update: bool,
+    },
+    #[clap(about = "Explain an error code, list all error codes, or explain what some code does")]
+    Explain {
+        #[clap(help = "The error code, like E0001")]
+        code: Option<String>,
+        #[clap(
+            long,
+            short,
+            conflicts_with = "code",
+            help = "Explain what each word of some code does instead"
+        )]
+        expr: Option<String>,
+    },
     #[clap(about = "Format a uiua file or all files in the current directory")]
     Fmt {
         path: Option<PathBuf>,
+        #[clap(
+            long,
+            help = "Print the changes formatting would make instead of making them, \
+                    and fail if there are any"
+        )]
+        check: bool,
+        #[clap(
+            long,
+            requires = "path",
+            value_parser = parse_line_range,
+            help = "Only format some lines, like 3:7"
+        )]
+        lines: Option<RangeInclusive<usize>>,
         #[clap(flatten)]
         formatter_options: FormatterOptions,
     },
     #[cfg(feature = "lsp")]
     #[clap(about = "Run the Language Server")]
     Lsp,
+    #[cfg(feature = "jupyter")]
+    #[clap(about = "Run the Jupyter kernel")]
+    Jupyter {
+        #[clap(help = "The connection file passed by Jupyter")]
+        connection_file: Option<PathBuf>,
+        #[clap(long, help = "Install the kernel spec so Jupyter can find the kernel")]
+        install: bool,
+    },
     #[clap(about = "Run very simple REPL")]
     Repl {
         #[clap(flatten)]
@@ -499,6 +763,11 @@ enum App {
         #[cfg(feature = "audio")]
         #[clap(flatten)]
         audio_options: AudioOptions,
+        #[clap(
+            long,
+            help = "Save the inputs, bindings, and final stack to this file, and restore them when the REPL starts"
+        )]
+        session: Option<PathBuf>,
         #[clap(trailing_var_arg = true)]
         args: Vec<String>,
     },
@@ -543,6 +812,101 @@ fn setup_audio(options: AudioOptions) {
     }
 }
 
+fn explain(code: Option<&str>) {
+    let Some(code) = code else {
+        for code in ErrorCode::all() {
+            println!("{}: {}", code.code().bright_red(), code.title());
+        }
+        return;
+    };
+    let code = match code.parse::<ErrorCode>() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{e}");
+            exit(1)
+        }
+    };
+    println!(
+        "{}: {}",
+        code.code().bright_red().bold(),
+        code.title().bold()
+    );
+    println!();
+    println!("{}", code.explanation());
+    if let Some(example) = code.example() {
+        println!();
+        println!("For example, this code:");
+        println!();
+        for line in example.lines() {
+            println!("    {line}");
+        }
+        if let Err(e) = Uiua::with_native_sys().load_str(example) {
+            println!();
+            println!("gives this error:");
+            println!();
+            println!("{}", e.report());
+        }
+    }
+}
+
+fn print_coverage(coverage: &Coverage) {
+    for file in coverage.files.iter().filter(|file| file.path.is_some()) {
+        let path = file.path.as_ref().unwrap();
+        let percent = format!("{:.0}%", file.fraction() * 100.0);
+        println!("{} {}", path.display().to_string().bold(), percent.dimmed());
+        print!("{}", file.render());
+        println!();
+    }
+}
+
+fn print_snapshots(snapshots: &[Snapshot]) {
+    for snapshot in snapshots {
+        println!("{}", snapshot.code.bright_cyan());
+        if snapshot.omitted > 0 {
+            println!(
+                "  {}",
+                format!("({} more below)", snapshot.omitted).dimmed()
+            );
+        }
+        for value in &snapshot.stack {
+            let shown = match value {
+                SnapshotValue::Value(value) => value.show(),
+                SnapshotValue::TooBig(shape) => {
+                    let shape: Vec<String> = shape.iter().map(|n| n.to_string()).collect();
+                    format!("<array of shape {}>", shape.join("×"))
+                        .dimmed()
+                        .to_string()
+                }
+            };
+            for line in shown.lines() {
+                println!("  {line}");
+            }
+        }
+    }
+}
+
+fn print_explain_steps(steps: &[ExplainStep], depth: usize) {
+    let indent = "  ".repeat(depth);
+    for step in steps {
+        let sig = step
+            .signature
+            .map(|sig| sig.to_string())
+            .unwrap_or_default();
+        print!(
+            "{indent}{} {}  {}",
+            step.code.bright_cyan(),
+            sig.dimmed(),
+            step.description
+        );
+        if let Some(height) = step.height {
+            let values = if height == 1 { "value" } else { "values" };
+            print!(" {}", format!("({height} {values} on the stack)").dimmed());
+        }
+        println!();
+        print_explain_steps(&step.inner, depth + 1);
+    }
+}
+
 fn uiua_files() -> Vec<PathBuf> {
     fs::read_dir(".")
         .unwrap()
@@ -622,6 +986,124 @@ fn format_single_file(path: PathBuf, config: &FormatConfig, stdout: bool) -> Res
     Ok(())
 }
 
+fn print_code_changes(changes: &[CodeChange]) {
+    let print_lines = |sign: &str, code: &str| {
+        for line in code.lines() {
+            let line = format!("  {sign} {line}");
+            println!(
+                "{}",
+                if sign == "-" {
+                    line.red()
+                } else {
+                    line.green()
+                }
+            );
+        }
+    };
+    for change in changes {
+        match change {
+            CodeChange::Added { name, new } => {
+                println!("{}", format!("added {name}").bold());
+                print_lines("+", new);
+            }
+            CodeChange::Removed { name, old } => {
+                println!("{}", format!("removed {name}").bold());
+                print_lines("-", old);
+            }
+            CodeChange::Changed { name, old, new } => {
+                println!("{}", format!("changed {name}").bold());
+                print_lines("-", old);
+                print_lines("+", new);
+            }
+            CodeChange::Code { old, new } => {
+                println!("{}", "changed code".bold());
+                old.iter().for_each(|code| print_lines("-", code));
+                new.iter().for_each(|code| print_lines("+", code));
+            }
+        }
+    }
+}
+
+fn print_stats(stats: &ProgramStats) {
+    println!("{}", "primitives".bold());
+    for (prim, count) in stats.primitives_by_count() {
+        if prim.glyph().is_some() {
+            println!("  {count:>5} {prim} {}", prim.name());
+        } else {
+            println!("  {count:>5} {prim}");
+        }
+    }
+    println!("{}", "bindings".bold());
+    let mut bindings: Vec<_> = stats.bindings.iter().collect();
+    bindings.sort_by_key(|binding| std::cmp::Reverse(binding.words));
+    for binding in bindings {
+        println!(
+            "  {:>5} {} {}",
+            binding.words,
+            binding.name.value,
+            binding.name.span.to_string().dimmed()
+        );
+    }
+    if let Some((rank, span)) = &stats.max_rank_literal {
+        println!(
+            "{} {rank} {}",
+            "highest rank literal".bold(),
+            span.to_string().dimmed()
+        );
+    }
+    if !stats.unused_imports.is_empty() {
+        println!("{}", "unused imports".bold());
+        for import in &stats.unused_imports {
+            println!(
+                "  {} {}",
+                import.value.to_string().yellow(),
+                import.span.to_string().dimmed()
+            );
+        }
+    }
+}
+
+/// Parse a range of line numbers like `3:7`
+fn parse_line_range(s: &str) -> Result<RangeInclusive<usize>, String> {
+    let (start, end) = s.split_once(':').unwrap_or((s, s));
+    let line = |n: &str| match n.trim().parse::<usize>() {
+        Ok(0) | Err(_) => Err(format!("{n:?} is not a line number")),
+        Ok(n) => Ok(n),
+    };
+    Ok(line(start)?..=line(end)?)
+}
+
+/// Print the changes formatting would make to some files without making them
+///
+/// Returns whether there are any
+fn check_formatting(
+    paths: &[PathBuf],
+    config: &FormatConfig,
+    lines: Option<RangeInclusive<usize>>,
+) -> Result<bool, UiuaError> {
+    let mut changed = false;
+    for path in paths {
+        let input =
+            fs::read_to_string(path).map_err(|e| UiuaError::Load(path.clone(), e.into()))?;
+        let output = match &lines {
+            Some(lines) => format_lines(&input, Some(path), config, lines.clone())?,
+            None => format(&input, path, config)?.output,
+        };
+        for change in line_changes(&input, &output) {
+            changed = true;
+            let location = format!("{}:{}", path.display(), change.line);
+            println!("{}", location.bright_white().bold());
+            for line in &change.old {
+                println!("{}", format!("-{line}").red());
+            }
+            for line in &change.new {
+                println!("{}", format!("+{line}").green());
+            }
+        }
+    }
+    Ok(changed)
+}
+
 fn format_multi_files(config: &FormatConfig, stdout: bool) -> Result<(), UiuaError> {
     for path in uiua_files() {
         let path_as_string = path.to_string_lossy().into_owned();
@@ -634,18 +1116,21 @@ fn format_multi_files(config: &FormatConfig, stdout: bool) -> Result<(), UiuaErr
     Ok(())
 }
 
-fn repl(mut rt: Uiua, config: FormatConfig) {
-    let repl = |rt: &mut Uiua| -> Result<(), UiuaError> {
-        print!("{} ", "»".bright_white().bold());
-        _ = io::stdout().flush();
-
-        let mut code = String::new();
-        io::stdin()
-            .read_line(&mut code)
-            .expect("Failed to read from Stdin"); // TODO: this could be handled differently
-
-        let formatted = format_str(&code, &config)?.output;
-        code = formatted;
+/// Run a REPL
+///
+/// If there is a session file, the bindings and final stack saved in it are restored first,
+/// without running the inputs that made them again, and each successful input is saved to it.
+fn repl(mut rt: Uiua, config: FormatConfig, session_path: Option<PathBuf>) {
+    let show_stack = |stack: &[Value]| {
+        for value in stack {
+            let pretty = &value.show();
+            for line in pretty.lines() {
+                println!("  {line}");
+            }
+        }
+    };
+    let run = |rt: &mut Uiua, code: &str| -> Result<Vec<Value>, UiuaError> {
+        let code = format_str(code, &config)?.output;
         print!("↪ ");
         for span in spans(&code) {
             let (r, g, b) = match span.value {
@@ -685,19 +1170,59 @@ fn repl(mut rt: Uiua, config: FormatConfig) {
         println!();
 
         rt.load_str(&code)?;
-        for value in rt.take_stack() {
-            let pretty = &value.show();
-            for line in pretty.lines() {
-                println!("  {line}");
-            }
-        }
-        Ok(())
+        let stack = rt.take_stack();
+        show_stack(&stack);
+        Ok(stack)
     };
 
     println!("Uiua {} (end with ctrl+C)\n", env!("CARGO_PKG_VERSION"));
+    let mut session = Session::default();
+    if let Some(path) = &session_path {
+        match Session::load(path) {
+            Ok(saved) if saved.history.is_empty() => {}
+            Ok(saved) => {
+                // Show the earlier inputs as history
+                for input in &saved.history {
+                    println!("{} {}", "»".bright_black(), input.bright_black());
+                }
+                match saved.restore(&mut rt) {
+                    Ok(()) => {
+                        show_stack(&rt.take_stack());
+                        println!("Restored session from {}\n", path.display());
+                        session = saved;
+                    }
+                    Err(e) => eprintln!("Failed to restore session:\n{}", e.report()),
+                }
+            }
+            Err(e) => eprintln!("Failed to read session file: {e}"),
+        }
+    }
     loop {
-        if let Err(e) = repl(&mut rt) {
-            eprintln!("{}", e.report());
+        print!("{} ", "»".bright_white().bold());
+        _ = io::stdout().flush();
+
+        let mut code = String::new();
+        let read = io::stdin()
+            .read_line(&mut code)
+            .expect("Failed to read from Stdin"); // TODO: this could be handled differently
+        if read == 0 {
+            println!();
+            break;
+        }
+
+        let code = code.trim_end();
+        let stack = match run(&mut rt, code) {
+            Ok(stack) => stack,
+            Err(e) => {
+                eprintln!("{}", e.report());
+                continue;
+            }
+        };
+        if let Some(path) = session_path.as_ref().filter(|_| !code.is_empty()) {
+            session.record(code, &rt, stack);
+            if let Err(e) = session.save(path) {
+                eprintln!("Failed to save session: {e}");
+            }
         }
     }
 }
@@ -2,9 +2,10 @@ use std::{error::Error, fmt, iter::once, path::Path};
 
 use crate::{
     ast::*,
+    format,
     function::{FunctionId, Signature},
     lex::{AsciiToken::*, Token::*, *},
-    Diagnostic, DiagnosticKind, Ident, Primitive,
+    version, Diagnostic, DiagnosticKind, Ident, Primitive,
 };
 
 #[derive(Debug, Clone)]
@@ -16,7 +17,10 @@ pub enum ParseError {
     InvalidArgCount(String),
     InvalidOutCount(String),
     AmpersandBindingName,
+    PrimitiveBindingName(Ident),
     FunctionNotAllowed,
+    EmptyInterpolation,
+    EmptyArrayRow,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -78,17 +82,86 @@ impl fmt::Display for ParseError {
             ParseError::InvalidArgCount(n) => write!(f, "Invalid argument count `{n}`"),
             ParseError::InvalidOutCount(n) => write!(f, "Invalid output count `{n}`"),
             ParseError::AmpersandBindingName => write!(f, "Binding names may not contain `&`"),
+            ParseError::PrimitiveBindingName(name) => write!(
+                f,
+                "`{name}` cannot be bound because it starts with the name of a primitive. \
+                Try `{}` instead",
+                capitalized(name)
+            ),
             ParseError::FunctionNotAllowed => write!(
                 f,
                 "Inline functions are only allowed in modifiers \
                 or as the only item in a binding"
             ),
+            ParseError::EmptyInterpolation => write!(f, "String interpolations may not be empty"),
+            ParseError::EmptyArrayRow => write!(f, "Array rows separated by `|` may not be empty"),
         }
     }
 }
 
 impl Error for ParseError {}
 
+/// Parse a number literal, with `-` for negation
+fn parse_number(s: &str) -> Option<f64> {
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, s),
+    };
+    let radix_digits = (unsigned.strip_prefix("0x").map(|digits| (16, digits)))
+        .or_else(|| unsigned.strip_prefix("0b").map(|digits| (2, digits)));
+    let n = match radix_digits {
+        Some((radix, digits)) => u128::from_str_radix(digits, radix).ok()? as f64,
+        None => return s.parse().ok(),
+    };
+    Some(if negative { -n } else { n })
+}
+
+/// Attach the comment lines just before each binding to it as its documentation
+///
+/// Lines are joined with spaces, and an empty comment line starts a new paragraph.
+fn attach_docs(items: &mut [Item]) {
+    let mut doc: Option<String> = None;
+    for item in items {
+        match item {
+            Item::Words(words) => {
+                if let [Sp {
+                    value: Word::Comment(comment),
+                    span,
+                }] = words.as_slice()
+                {
+                    // Declarations of the version and experimental features are not documentation
+                    if version::is_experimental_pragma(comment)
+                        || span.start.line == 1 && version::pragma_text(comment).is_some()
+                    {
+                        doc = None;
+                        continue;
+                    }
+                    // Stack effect comments are written by the formatter
+                    if format::stack_effect_comment(comment).is_some() {
+                        continue;
+                    }
+                    let full = doc.get_or_insert_with(String::new);
+                    if comment.trim().is_empty() {
+                        full.push_str("\n\n");
+                    } else if !full.is_empty() && !full.ends_with('\n') {
+                        full.push(' ');
+                    }
+                    full.push_str(comment.trim());
+                } else {
+                    doc = None;
+                }
+            }
+            Item::Binding(binding) => {
+                binding.doc = (doc.take())
+                    .map(|doc| doc.trim().into())
+                    .filter(|doc: &String| !doc.is_empty());
+            }
+            Item::Destructure(_) => doc = None,
+            Item::TestScope(_) | Item::ExtraNewlines(_) => {}
+        }
+    }
+}
+
 pub fn parse(
     input: &str,
     path: Option<&Path>,
@@ -194,12 +267,16 @@ impl Parser {
                 }
             }
         }
+        attach_docs(&mut items);
         items
     }
     fn try_item(&mut self, parse_scopes: bool) -> Option<Item> {
         self.try_spaces();
         Some(if let Some(binding) = self.try_binding() {
             Item::Binding(binding)
+        } else if let Some(des) = self.try_destructure() {
+            self.validate_words(&des.words, false);
+            Item::Destructure(des)
         } else if let Some(words) = self.try_words() {
             Item::Words(words)
         } else if parse_scopes && self.try_exact(TripleMinus).is_some() {
@@ -220,7 +297,9 @@ impl Parser {
     }
     fn try_binding(&mut self) -> Option<Binding> {
         let start = self.index;
-        Some(if let Some(name) = self.try_ident() {
+        let prim_name = self.try_primitive_binding_name();
+        let is_prim_name = prim_name.is_some();
+        Some(if let Some(name) = prim_name.or_else(|| self.try_ident()) {
             // Check for invalid binding names
             if name.value.contains('&') {
                 self.errors
@@ -228,7 +307,12 @@ impl Parser {
             }
             // Left arrow
             let mut arrow_span = self.try_spaces().map(|w| w.span);
-            if let Some(span) = self.try_exact(Equal).or_else(|| self.try_exact(LeftArrow)) {
+            let mutable_span = self.try_exact(HookArrow);
+            let mutable = mutable_span.is_some();
+            if let Some(span) = mutable_span
+                .or_else(|| self.try_exact(Equal))
+                .or_else(|| self.try_exact(LeftArrow))
+            {
                 arrow_span = Some(if let Some(arrow_span) = arrow_span {
                     arrow_span.merge(span)
                 } else {
@@ -239,11 +323,23 @@ impl Parser {
                 return None;
             }
             let mut arrow_span = arrow_span.unwrap();
+            // Code macro marker
+            let code_macro = !mutable && self.at_code_macro_marker();
+            if code_macro {
+                arrow_span = arrow_span.merge(self.try_exact(Caret).unwrap());
+            }
             if let Some(span) = self.try_spaces().map(|w| w.span) {
                 arrow_span = arrow_span.merge(span);
             }
-            // Signature
-            let signature = self.try_signature(Bar);
+            // Signature or annotation
+            let annotation = self.try_annotation();
+            let signature = if mutable {
+                None
+            } else if let Some(annotation) = &annotation {
+                Some(annotation.clone().map(|annotation| annotation.signature()))
+            } else {
+                self.try_signature(Bar)
+            };
             // Words
             let words = self.try_words().unwrap_or_default();
             match words.as_slice() {
@@ -258,17 +354,11 @@ impl Parser {
                 words => self.validate_words(words, false),
             }
             // Check for uncapitalized binding names
-            if name.value.trim_end_matches('!').chars().count() >= 3
+            if !is_prim_name
+                && name.value.trim_end_matches('!').chars().count() >= 3
                 && name.value.chars().next().unwrap().is_ascii_lowercase()
             {
-                let captialized: String = name
-                    .value
-                    .chars()
-                    .next()
-                    .map(|c| c.to_ascii_uppercase())
-                    .into_iter()
-                    .chain(name.value.chars().skip(1))
-                    .collect();
+                let captialized = capitalized(&name.value);
                 self.diagnostics.push(Diagnostic::new(
                     format!(
                         "Binding names with 3 or more characters should be TitleCase \
@@ -283,13 +373,136 @@ impl Parser {
             Binding {
                 name,
                 arrow_span,
+                mutable,
+                code_macro,
                 words,
                 signature,
+                annotation,
+                doc: None,
             }
         } else {
             return None;
         })
     }
+    /// Whether the next token is a `^` that marks a code macro rather than starting a placeholder
+    fn at_code_macro_marker(&self) -> bool {
+        let mut tokens = self.tokens[self.index..].iter();
+        tokens
+            .next()
+            .is_some_and(|t| t.value == Token::Simple(Caret))
+            && !(tokens.find(|t| t.value != Token::Spaces))
+                .is_some_and(|t| t.value == Token::Number)
+    }
+    fn try_local_binding(&mut self) -> Option<Sp<Word>> {
+        let start = self.index;
+        let name = self.try_ident()?;
+        let spaces = self.try_spaces().map(|w| w.span);
+        let mutable_arrow = self.try_exact(HookArrow);
+        let mutable = mutable_arrow.is_some();
+        let Some(arrow) = mutable_arrow.or_else(|| self.try_exact(LeftArrow)) else {
+            self.index = start;
+            return None;
+        };
+        let mut arrow_span = spaces.map_or(arrow.clone(), |spaces| spaces.merge(arrow));
+        if let Some(span) = self.try_spaces().map(|w| w.span) {
+            arrow_span = arrow_span.merge(span);
+        }
+        let words = self.try_words().unwrap_or_default();
+        let end = words
+            .last()
+            .map_or(arrow_span.clone(), |word| word.span.clone());
+        let span = name.span.clone().merge(end);
+        Some(span.sp(Word::Local(Box::new(LocalBinding {
+            name,
+            arrow_span,
+            mutable,
+            words,
+        }))))
+    }
+    fn try_destructure(&mut self) -> Option<Destructure> {
+        let start = self.index;
+        let open = self.try_exact(OpenBracket);
+        let mut names = Vec::new();
+        let mut spaces = self.try_spaces().map(|w| w.span);
+        while let Some(name) = self.try_ident() {
+            names.push(name);
+            spaces = self.try_spaces().map(|w| w.span);
+        }
+        let brackets = if let Some(open) = open {
+            let Some(close) = self.try_exact(CloseBracket).filter(|_| !names.is_empty()) else {
+                self.index = start;
+                return None;
+            };
+            spaces = self.try_spaces().map(|w| w.span);
+            Some((open, close))
+        } else if names.len() >= 2 {
+            None
+        } else {
+            self.index = start;
+            return None;
+        };
+        let Some(arrow) = self.try_exact(LeftArrow) else {
+            self.index = start;
+            return None;
+        };
+        let mut arrow_span = spaces.map_or(arrow.clone(), |spaces| spaces.merge(arrow));
+        if let Some(span) = self.try_spaces().map(|w| w.span) {
+            arrow_span = arrow_span.merge(span);
+        }
+        let words = self.try_words().unwrap_or_default();
+        for name in &names {
+            if name.value.contains('&') {
+                self.errors
+                    .push(name.span.clone().sp(ParseError::AmpersandBindingName));
+            }
+        }
+        Some(Destructure {
+            names,
+            brackets,
+            arrow_span,
+            words,
+        })
+    }
+    /// Try to parse a binding name that the lexer read as the names of primitives, like `add ←`
+    ///
+    /// Such a name cannot be bound, so this adds an error, but the rest of the binding is still parsed.
+    fn try_primitive_binding_name(&mut self) -> Option<Sp<Ident>> {
+        let start = self.index;
+        let mut span: Option<CodeSpan> = None;
+        while let Some(token) = self.tokens.get(self.index) {
+            let written_as_name = token.span.as_str().chars().all(|c| c.is_ascii_alphabetic());
+            let adjacent = match &span {
+                Some(span) => span.end == token.span.start,
+                None => true,
+            };
+            match token.value {
+                Glyph(_) if written_as_name && adjacent => {}
+                Ident if span.is_some() && adjacent => {}
+                _ => break,
+            }
+            span = Some(match span {
+                Some(span) => span.merge(token.span.clone()),
+                None => token.span.clone(),
+            });
+            self.index += 1;
+        }
+        let end = self.index;
+        self.try_exact(Spaces);
+        let is_binding = self.try_exact(LeftArrow).is_some() || self.try_exact(HookArrow).is_some();
+        match span {
+            Some(span) if is_binding => {
+                self.index = end;
+                let name: Ident = span.as_str().into();
+                self.errors
+                    .push(span.clone().sp(ParseError::PrimitiveBindingName(name)));
+                Some(span.sp(name))
+            }
+            _ => {
+                self.index = start;
+                None
+            }
+        }
+    }
     fn try_ident(&mut self) -> Option<Sp<Ident>> {
         let span = self.try_exact(Token::Ident)?;
         let s: Ident = span.as_str().into();
@@ -304,6 +517,92 @@ impl Parser {
         }
         Some(ident)
     }
+    /// Parse value patterns given in place of a binding's signature, like `|[_ 3] Char[N].[]|`
+    fn try_annotation(&mut self) -> Option<Sp<Annotation>> {
+        let start_index = self.index;
+        let start = self.try_exact(Bar)?;
+        self.try_spaces();
+        let at_pattern = self.at_value_type()
+            || (self.tokens.get(self.index)).is_some_and(|token| {
+                matches!(
+                    token.value,
+                    Simple(OpenBracket | Underscore) | Glyph(Primitive::Dup)
+                )
+            });
+        if !at_pattern {
+            self.index = start_index;
+            return None;
+        }
+        let args = self.value_patterns();
+        let outputs = (self.try_exact(Primitive::Dup)).map(|_| self.value_patterns());
+        let end = self.expect_close(Bar);
+        self.try_spaces();
+        Some(start.merge(end).sp(Annotation { args, outputs }))
+    }
+    fn at_value_type(&self) -> bool {
+        (self.tokens.get(self.index)).is_some_and(|token| {
+            token.value == Token::Ident && ValueType::from_name(token.span.as_str()).is_some()
+        })
+    }
+    fn value_patterns(&mut self) -> Vec<Sp<ValuePattern>> {
+        let mut patterns = Vec::new();
+        while let Some(pattern) = self.try_value_pattern() {
+            patterns.push(pattern);
+            self.try_spaces();
+        }
+        patterns
+    }
+    fn try_value_pattern(&mut self) -> Option<Sp<ValuePattern>> {
+        if let Some(span) = self.try_exact(Underscore) {
+            return Some(span.sp(ValuePattern {
+                ty: None,
+                shape: None,
+            }));
+        }
+        let ty = if self.at_value_type() {
+            self.index += 1;
+            let span = self.prev_span();
+            ValueType::from_name(span.as_str()).map(|ty| span.sp(ty))
+        } else {
+            None
+        };
+        let shape = self.try_exact(OpenBracket).map(|open| {
+            let mut dims = Vec::new();
+            loop {
+                self.try_spaces();
+                let dim = if self.try_exact(Underscore).is_some() {
+                    DimPattern::Any
+                } else if self.try_exact(Star).is_some() {
+                    DimPattern::Rest
+                } else if let Some(ident) = self.try_ident() {
+                    DimPattern::Named(ident.value)
+                } else if let Some(n) = self.try_num() {
+                    match n.value.0.parse() {
+                        Ok(n) => DimPattern::Len(n),
+                        Err(_) => {
+                            (self.errors).push(n.span.sp(ParseError::InvalidNumber(n.value.0)));
+                            DimPattern::Any
+                        }
+                    }
+                } else {
+                    break;
+                };
+                dims.push(dim);
+            }
+            let close = self.expect_close(CloseBracket);
+            open.merge(close).sp(dims)
+        });
+        let span = match (&ty, &shape) {
+            (Some(ty), Some(shape)) => ty.span.clone().merge(shape.span.clone()),
+            (Some(ty), None) => ty.span.clone(),
+            (None, Some(shape)) => shape.span.clone(),
+            (None, None) => return None,
+        };
+        Some(span.sp(ValuePattern {
+            ty: ty.map(|ty| ty.value),
+            shape: shape.map(|shape| shape.value),
+        }))
+    }
     fn try_signature(&mut self, initial_token: AsciiToken) -> Option<Sp<Signature>> {
         let start = self.try_exact(initial_token)?;
         self.try_spaces();
@@ -394,10 +693,62 @@ impl Parser {
             Some(words)
         }
     }
-    fn multiline_words(&mut self) -> Vec<Vec<Sp<Word>>> {
+    /// Parse the lines of an array literal, which may have `|`s between its rows
+    ///
+    /// Empty rows are only allowed in box arrays, where they are empty boxes
+    fn array_lines(&mut self, allow_empty: bool) -> Vec<Vec<Sp<Word>>> {
+        let has_code = |lines: &[Vec<Sp<Word>>]| lines.iter().flatten().any(|w| w.value.is_code());
+        let mut lines = self.multiline_words(false);
+        let mut row_empty = !has_code(&lines);
+        let mut last_bar = None;
+        while let Some(span) = self.try_exact(Bar) {
+            // Only report a trailing empty row if this bar wasn't already reported
+            last_bar = if row_empty && !allow_empty {
+                self.errors.push(span.clone().sp(ParseError::EmptyArrayRow));
+                None
+            } else {
+                Some(span.clone())
+            };
+            let line = span.start.line;
+            let on_line = |words: &[Sp<Word>], first: bool| {
+                let word = if first { words.first() } else { words.last() };
+                word.is_some_and(|word| word.span.start.line == line)
+            };
+            let mut break_line = vec![span.sp(Word::RowBreak)];
+            break_line.extend(self.try_spaces());
+            match lines.last_mut() {
+                Some(last) if on_line(last, false) => last.extend(break_line),
+                _ => lines.push(break_line),
+            }
+            let more = self.multiline_words(false);
+            row_empty = !has_code(&more);
+            let mut more = more.into_iter().peekable();
+            if let Some(first) = more.next_if(|first| on_line(first, true)) {
+                lines.last_mut().unwrap().extend(first);
+            }
+            lines.extend(more);
+        }
+        if let Some(span) = last_bar.filter(|_| row_empty && !allow_empty) {
+            self.errors.push(span.sp(ParseError::EmptyArrayRow));
+        }
+        lines
+    }
+    fn multiline_words(&mut self, allow_locals: bool) -> Vec<Vec<Sp<Word>>> {
         let mut lines = Vec::new();
         while self.try_exact(Newline).is_some() || self.try_spaces().is_some() {}
-        while let Some(words) = self.try_words() {
+        while let Some(words) = allow_locals
+            .then(|| {
+                self.try_local_binding().or_else(|| {
+                    let des = self.try_destructure()?;
+                    let end = des.words.last().map_or(&des.arrow_span, |word| &word.span);
+                    let span = des.span().merge(end.clone());
+                    Some(span.sp(Word::Destructure(Box::new(des))))
+                })
+            })
+            .flatten()
+            .map(|local| vec![local])
+            .or_else(|| self.try_words())
+        {
             lines.push(words);
             let mut newlines = 0;
             while self.try_exact(Newline).is_some() {
@@ -580,20 +931,20 @@ impl Parser {
             c.map(Into::into).map(Word::Char)
         } else if let Some(s) = self.next_token_map(Token::as_string) {
             s.map(Into::into).map(Word::String)
-        } else if let Some(frags) = self.next_token_map(Token::as_format_string) {
-            frags.map(Word::FormatString)
+        } else if let Some(parts) = self.next_token_map(Token::as_format_string) {
+            parts.map(|(frags, args)| Word::FormatString(self.format_str(frags, args)))
         } else if let Some(line) = self.next_token_map(Token::as_multiline_string) {
             let start = line.span.clone();
             let mut end = start.clone();
-            let mut lines = vec![line];
+            let mut lines = vec![line.map(|(frags, args)| self.format_str(frags, args))];
             while let Some(line) = self.next_token_map(Token::as_multiline_string) {
                 end = line.span.clone();
-                lines.push(line);
+                lines.push(line.map(|(frags, args)| self.format_str(frags, args)));
             }
             let span = start.merge(end);
             span.sp(Word::MultilineString(lines))
         } else if let Some(start) = self.try_exact(OpenBracket) {
-            let items = self.multiline_words();
+            let items = self.array_lines(false);
             let end = self.expect_close(CloseBracket);
             let span = start.merge(end);
             span.sp(Word::Array(Arr {
@@ -601,7 +952,7 @@ impl Parser {
                 constant: false,
             }))
         } else if let Some(start) = self.try_exact(OpenCurly) {
-            let items = self.multiline_words();
+            let items = self.array_lines(true);
             let end = self.expect_close(CloseCurly);
             let span = start.merge(end);
             span.sp(Word::Array(Arr {
@@ -621,13 +972,48 @@ impl Parser {
             return None;
         })
     }
+    fn format_str(&mut self, frags: Vec<String>, args: Vec<Option<CodeSpan>>) -> FormatString {
+        let args = (args.into_iter())
+            .map(|arg| match arg {
+                Some(span) => FormatArg::Code(span.clone().sp(self.interpolation(&span))),
+                None => FormatArg::Stack,
+            })
+            .collect();
+        FormatString { frags, args }
+    }
+    /// Parse the code of a string interpolation
+    fn interpolation(&mut self, span: &CodeSpan) -> Vec<Sp<Word>> {
+        let (tokens, lex_errors) = lex_span(span);
+        let mut parser = Parser {
+            tokens,
+            index: 0,
+            errors: (lex_errors.into_iter())
+                .map(|e| e.map(ParseError::Lex))
+                .collect(),
+            diagnostics: Vec::new(),
+        };
+        let words = parser.try_words().unwrap_or_default();
+        if parser.errors.is_empty() {
+            if let Some(token) = parser.tokens.get(parser.index) {
+                (parser.errors).push(token.clone().map(ParseError::Unexpected));
+            } else if !words.iter().any(|word| word.value.is_code()) {
+                parser
+                    .errors
+                    .push(span.clone().sp(ParseError::EmptyInterpolation));
+            }
+        }
+        parser.validate_words(&words, false);
+        self.errors.extend(parser.errors);
+        self.diagnostics.extend(parser.diagnostics);
+        words
+    }
     fn try_num(&mut self) -> Option<Sp<(String, f64)>> {
         let span = self.try_exact(Token::Number)?;
         let s = span.as_str().to_string();
         let parseable = s.replace(['`', '¯'], "-");
-        let n: f64 = match parseable.parse() {
-            Ok(n) => n,
-            Err(_) => {
+        let n: f64 = match parse_number(&parseable) {
+            Some(n) => n,
+            None => {
                 self.errors
                     .push(self.prev_span().sp(ParseError::InvalidNumber(s.clone())));
                 0.0
@@ -703,7 +1089,7 @@ impl Parser {
     fn func_contents(&mut self) -> FunctionContents {
         while self.try_exact(Newline).is_some() || self.try_spaces().is_some() {}
         let signature = self.try_signature(Bar);
-        let lines = self.multiline_words();
+        let lines = self.multiline_words(true);
         let start = signature
             .as_ref()
             .map(|sig| sig.span.clone())
@@ -755,15 +1141,17 @@ impl Parser {
                     }
                 }
                 Word::Modified(m) => self.validate_words(&m.operands, true),
+                Word::Local(local) => self.validate_words(&local.words, false),
+                Word::Destructure(des) => self.validate_words(&des.words, false),
                 _ => {}
             }
         }
     }
 }
 
-pub(crate) fn ident_modifier_args(ident: &Ident) -> u8 {
+pub(crate) fn ident_modifier_args(ident: &str) -> u8 {
     let mut count: u8 = 0;
-    let mut prefix = ident.as_ref();
+    let mut prefix = ident;
     while let Some(pre) = prefix.strip_suffix('!') {
         prefix = pre;
         count = count.saturating_add(1);
@@ -788,8 +1176,21 @@ pub(crate) fn count_placeholders(words: &[Sp<Word>]) -> usize {
                 }
             }
             Word::Modified(m) => count += count_placeholders(&m.operands),
+            Word::Local(local) => count += count_placeholders(&local.words),
+            Word::Destructure(des) => count += count_placeholders(&des.words),
             _ => {}
         }
     }
     count
 }
+
+/// Capitalize the first letter of a name
+fn capitalized(name: &str) -> String {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map(|c| c.to_ascii_uppercase())
+        .into_iter()
+        .chain(chars)
+        .collect()
+}
@@ -452,6 +452,26 @@ primitive!(
     ///
     /// It is a triangle`△` because a triangle is a shape.
     (1, Shape, MonadicArray, ("shape", '△')),
+    /// Get the number of dimensions of an array
+    ///
+    /// ex: rank 5
+    /// ex: rank 1_2_3
+    /// ex: rank [1_2 3_4 5_6]
+    ///
+    /// [rank] is equivalent to the [length] of the [shape].
+    /// ex: rank [1_2 3_4 5_6]
+    ///   : ⧻△[1_2 3_4 5_6]
+    (1, Rank, MonadicArray, "rank"),
+    /// Get the number of elements in an array
+    ///
+    /// ex: count 5
+    /// ex: count []
+    /// ex: count [1_2 3_4 5_6]
+    ///
+    /// [count] is equivalent to the [reduce][multiply] of the [shape].
+    /// ex: count [1_2 3_4 5_6]
+    ///   : /×△[1_2 3_4 5_6]
+    (1, Count, MonadicArray, "count"),
     /// Make an array of all natural numbers less than a number
     ///
     /// The rank of the input must be `0` or `1`.
@@ -572,6 +592,18 @@ primitive!(
     /// ex: ⊝"Hello, World!"
     /// ex: ⊝[3_2 1_4 3_2 5_6 1_4 7_8]
     (1, Deduplicate, MonadicArray, ("deduplicate", '⊝')),
+    /// Run-length encode an array
+    ///
+    /// Runs of equal rows are replaced by a single row.
+    /// The first output is the length of each run, and the second is the row of each run.
+    /// ex: runlength [1 1 1 2 2 3 1 1]
+    /// ex: runlength "aaabccdddd"
+    /// ex: runlength [1_2 1_2 3_4]
+    /// Because the counts are on top of the stack, [keep] decodes the runs.
+    /// ex: ▽ runlength "aaabccdddd"
+    /// [invert][runlength] is the same as [keep].
+    /// ex: ⍘runlength [3 1 2] "abc"
+    (1(2), RunLength, MonadicArray, "runlength"),
     /// Turn an array into a box
     ///
     /// This is Uiua's primary way to create nested or mixed-type arrays.
@@ -675,6 +707,23 @@ primitive!(
     /// ex: ⊏ [0_1 1_2 2_3] [2 3 5 7]
     /// ex: ⊏ [0_1 1_2 2_0] [1_2_3 4_5_6 7_8_9]
     (2, Select, DyadicArray, ("select", '⊏')),
+    /// Replace the rows of an array at some indices
+    ///
+    /// Takes a list of indices, the new rows, and the array to change.
+    /// ex: amend 1_3 [10 20] [1 2 3 4 5]
+    /// ex: amend [0 2] ["abc" "xyz"] ["foo" "bar" "baz"]
+    /// A single index replaces a single row.
+    /// ex: amend 2 [0 0] ↯4_2⇡8
+    /// If the new value is a single row, it replaces every indexed row.
+    /// ex: amend [0 2 4] 0 [1 2 3 4 5]
+    /// If an index is repeated, the last row for it is used.
+    /// ex: amend [1 1] [5 6] [1 2 3]
+    /// Negative indices count from the end.
+    /// ex: amend ¯1 10 [1 2 3]
+    ///
+    /// [amend] is like [under][select], but allows repeated indices and single new rows.
+    /// ex: ⍜⊏([10 20];) 1_3 [1 2 3 4 5]
+    (3, Amend, DyadicArray, "amend"),
     /// Index a row or elements from an array
     ///
     /// An index with rank `0` or `1` will pick a single row or element from an array.
@@ -683,6 +732,10 @@ primitive!(
     ///
     /// If the index's rank is `2` or greater, then multiple rows or elements will be picked.
     /// ex: ⊡ [1_2 0_1] [1_2_3 4_5_6]
+    /// Negative indices count from the end of each axis.
+    /// ex: ⊡ [¯1_¯1 0_¯2] [1_2_3 4_5_6]
+    /// Out-of-bounds indices are an error that reports the full index.
+    /// ex! ⊡ [0_1 1_3] [1_2_3 4_5_6]
     ///
     /// [under][pick] can be used to modify the value at an index.
     /// ex: ⍜⊡(×10) 2 [8 3 9 2 0]
@@ -744,6 +797,10 @@ primitive!(
     /// ex! ↙7 [8 3 9 2 0]
     /// If you would like to fill the excess length with some fill value, use [fill].
     /// ex: ⬚π↙ 7 [8 3 9 2 0]
+    /// This works along multiple axes, and from either end.
+    /// ex: ⬚0↙ ¯3_4 [1_2 3_4]
+    /// With a fill, a scalar can be taken from as if it were an array with a single element.
+    /// ex: ⬚0↙ 4 5
     (2, Take, DyadicArray, ("take", '↙')),
     /// Drop the first n elements of an array
     ///
@@ -808,7 +865,22 @@ primitive!(
     /// ex: ⌕ 5 [1 8 5 2 3 5 4 5 6 7]
     /// ex: ⌕ "ab" "abracadabra"
     /// ex: ⌕ 1_2 . ↯4_4⇡3
+    /// [where] gets the indices of the occurences.
+    /// ex: ⊚⌕ "ab" "abracadabra"
     (2, Find, DyadicArray, ("find", '⌕')),
+    /// Split an array into boxed segments separated by a delimiter
+    ///
+    /// ex: split @, "apple,banana,cherry"
+    /// ex: split ", " "apple, banana, cherry"
+    /// The delimiter can be a single row or a sequence of rows.
+    /// ex: split 0 [1 2 0 3 0 4 5]
+    /// ex: split [0 0] [1 0 0 2 0 3 0 0 0 0 4]
+    /// Unlike splitting with [partition], segments between adjacent delimiters or at the ends are kept, but empty.
+    /// ex: split @, ",a,,b,"
+    /// ex: ⊜□ ≠@, . ",a,,b,"
+    /// Use [each] to process each segment.
+    /// ex: ∵(parse⊔) split @, "1,22,333"
+    (2, Split, DyadicArray, "split"),
     /// Check if each row of one array exists in another
     ///
     /// ex: ∊ 2 [1 2 3]
@@ -860,13 +932,17 @@ primitive!(
     /// [break]ing out of [reduce] discards the unreduced values.
     /// ex: /(⎋≥10.+) [3 4 8 9]
     ///
-    /// Some functions have default values if the array is empty.
-    /// Functions without default values will throw an error if the array is empty.
+    /// Some primitives have identity elements, which are what reducing an empty array gives.
+    /// Functions without identity elements will throw an error if the array is empty.
     /// ex: /+ []
     /// ex: /× []
     /// ex: /↥ []
     /// ex: /↧ []
+    /// ex: /= []
+    /// ex: /+ ↯0_3 0
     /// ex! /∠ []
+    /// To reduce possibly empty arrays with other functions, use [fold] with an initial value.
+    /// ex: ∧0_0(+×2) 0 []
     (1[1], Reduce, AggregatingModifier, ("reduce", '/')),
     /// Reduce, but keep intermediate values
     ///
@@ -947,6 +1023,9 @@ primitive!(
     /// ex: △⊞⊂ 1_2 3_4_5
     /// ex: △⊞+ [1_2_3 4_5_6] [7 8 9 10]
     /// ex: △⊞⊂ [1_2_3 4_5_6] [7 8 9 10]
+    ///
+    /// [table] makes tables like this multiplication table.
+    /// ex: ⊞×. +1⇡9
     (2[1], Table, IteratingModifier, ("table", '⊞')),
     /// Apply a function to each combination of rows of two arrays
     ///
@@ -955,6 +1034,9 @@ primitive!(
     /// ex: a ← .[1_2 3_4 5_6]
     ///   : b ← .[7_8 9_10]
     ///   : ⊠⊂ a b
+    ///
+    /// [cross] with arithmetic combines the rows element-wise, so it can find the distances between each pair of points.
+    /// ex: √≑1/+ ⁿ2 ⊠-. [0_0 3_4 6_8]
     (2[1], Cross, IteratingModifier, ("cross", '⊠')),
     /// Repeat a function a number of times
     ///
@@ -970,6 +1052,20 @@ primitive!(
     ///
     /// [repeat]'s glyph is a combination of a circle, representing a loop, and the 𝄇 symbol from musical notation.
     (1[1], Repeat, IteratingModifier, ("repeat", '⍥')),
+    /// Repeat a function while a condition holds
+    ///
+    /// The first function is the loop body. The second function is the condition.
+    /// The condition is called before each iteration. It must leave a boolean on top of the stack, which is popped.
+    /// If the boolean is `1`, the body is called and the loop continues. If it is `0`, the loop ends.
+    /// ex: ⍢(×2)(<1000.) 1
+    /// ex: ⍢(↘1)(>3⧻.) ⇡10
+    /// The condition usually needs to [duplicate] the values it checks so that the body can still use them.
+    /// The body must leave the stack the same height, and the condition must add exactly one value.
+    /// ex! ⍢+(<10.) 1 2
+    ///
+    /// You can use [break] to exit the loop early.
+    /// ex: ⍢(⎋>20.×3)1 1
+    ([2], Do, IteratingModifier, ("do", '⍢')),
     /// Group elements of an array into buckets by index
     ///
     /// Takes a function and two arrays.
@@ -1242,6 +1338,21 @@ primitive!(
     /// ex: F = ⬚∘+
     ///   : F 100 [1 2 3 4] [5 6]
     ([2], Fill, OtherModifier, ("fill", '⬚')),
+    /// Call a function with a comparison tolerance
+    ///
+    /// Numbers that are at most the tolerance apart are considered equal.
+    /// This affects comparisons, [match], [classify], and [deduplicate], which can otherwise be thrown off by floating-point error.
+    /// The tolerance is set the same way as a [fill] value.
+    /// ex: # Experimental!
+    ///   : = 0.3 +0.1 0.2
+    ///   : tolerance1e-9= 0.3 +0.1 0.2
+    /// ex: # Experimental!
+    ///   : tolerance0.01⊝ [1 1.001 2 2.005 3]
+    /// ex: # Experimental!
+    ///   : tolerance0.01≍ [1 2] [1.001 2]
+    ///
+    /// [tolerance] is experimental. To use it, add `# Experimental!` to the top of the file.
+    ([2], Tolerance, OtherModifier, "tolerance"),
     /// Apply a function at a different array depth
     ///
     /// Expects a rank to operate on, a function, and an array.
@@ -1462,6 +1573,53 @@ primitive!(
     /// ex: ↯3_3⇡9
     ///   : wait≡spawn/+.
     ([1], Spawn, OtherModifier, "spawn"),
+    /// Call a function and cache its results
+    ///
+    /// If a [memo]ized function is called again with the same arguments, the cached results are pushed instead of calling it again.
+    /// ex: F ← memo(/+⇡)
+    ///   : [F 1000 F 1000]
+    /// The cache is kept for as long as the interpreter runs.
+    /// Only use [memo] with functions that do not have side effects and do not depend on [fill].
+    ([1], Memo, OtherModifier, "memo"),
+    /// Push the code of a function as a string
+    ///
+    /// The function is not compiled or called.
+    /// ex: quote(+1 2)
+    /// Functions in parentheses are quoted without them.
+    /// ex: quote(×2)
+    ///
+    /// [quote] is useful for passing code to [eval].
+    ([1], Quote, OtherModifier, "quote"),
+    /// Push the documentation comment of a function as a string
+    ///
+    /// Comment lines just before a binding document it.
+    /// ex: # Experimental!
+    ///   : # Add 1 to a number
+    ///   : Inc ← +1
+    ///   : help Inc
+    /// Functions without documentation give an empty string.
+    /// ex: # Experimental!
+    ///   : help(+1)
+    ///
+    /// [help] is useful for learning what an imported function does.
+    ///
+    /// [help] is experimental. To use it, add `# Experimental!` to the top of the file.
+    (0[1], Help, OtherModifier, "help"),
+    /// Compile and run a string as Uiua code
+    ///
+    /// The code can use any bindings that are in scope where [eval] is called.
+    /// ex: eval "+1 2"
+    /// ex: X ← 5
+    ///   : eval "×X 3"
+    /// Bindings made by the code are kept after it runs.
+    /// ex: eval "Y ← 10"
+    ///   : +1 Y
+    /// ex: eval quote(⇌⇡5)
+    /// Because the signature of the code cannot be known ahead of time, functions that use [eval] must declare their signatures.
+    /// ex: F ← |1.1 eval
+    ///   : F "+1 2"
+    /// The code can do anything that the rest of the program can, including IO.
+    (1(None), Eval, Misc, "eval"),
     /// Wait for a thread to finish and push its results to the stack
     ///
     /// The argument must be a thread id returned by [spawn].
@@ -1503,11 +1661,28 @@ primitive!(
     /// Break out of a loop
     ///
     /// Expects a non-negative integer. This integer is how many loops will be broken out of.
-    /// Loops that can be broken out of are [reduce], [fold], [scan], [each], [rows], and [repeat].
+    /// Loops that can be broken out of are [reduce], [fold], [scan], [each], [rows], [repeat], and [do].
     ///
     /// ex: /(⎋>10.+) ⇡8  # Break when the sum exceeds 10
     /// ex: ⍥(⎋>100.×2)∞ 1  # Break when the product exceeds 100
+    /// Whatever the function has left on the stack when it breaks is kept, so a loop can break with a value.
+    /// [each] and [rows] can only be broken out of when they take a single array. Rows that were not yet visited are left unchanged.
+    /// ex: ∵(⎋=6.×2) [1 2 3 4 5]
+    /// Breaking with `0` does nothing, so a boolean condition can be used to break.
+    /// Breaking with a larger number breaks out of that many nested loops.
+    /// ex: ⍥(⍥(⎋×2>10.+1)∞)∞ 0
     (1(0), Break, Control, ("break", '⎋')),
+    /// Skip the rest of an iteration of a loop
+    ///
+    /// Expects a non-negative integer. Continuing with `1` ends the current iteration of the innermost loop, and the loop goes on with the next one.
+    /// Whatever the function has left on the stack when it continues is the result of the iteration.
+    /// ex: ∵(×10 continue>3.) [1 2 3 4 5]
+    /// ex: ⍥(+1 continue=0◿3. +1) 5 0
+    /// Like [break], continuing with `0` does nothing, so a boolean condition can be used to continue.
+    /// Continuing with a larger number breaks out of the loops inside the one that goes on.
+    /// Functions that continue must leave the stack as if they had run to the end.
+    /// ex! ∵(+ continue>3 ..) [1 2 3 4 5]
+    (1(0), Continue, Control, "continue"),
     /// Generate a random number in the range `[0, 1)`
     ///
     /// If you need a seeded random number, use [gen].
@@ -1586,12 +1761,136 @@ primitive!(
     /// ex: ∵ type   {10 "dog" [1 2 3]}
     ///   : ∵(type⊔) {10 "dog" [1 2 3]}
     (1, Type, Misc, "type"),
+    /// Get the name of how an array's elements are stored
+    ///
+    /// The names are `"number"`, `"byte"`, `"character"`, and `"box"`.
+    /// Unlike [type], this tells apart numbers that are stored as bytes, which take less memory.
+    /// Arrays made by [bits], for example, are stored as bytes.
+    /// ex: datatype 5
+    /// ex: datatype ⋯5
+    /// ex: datatype "hello"
+    /// ex: datatype {1 2}
+    (1, Datatype, Misc, "datatype"),
+    /// Get the approximate number of bytes of memory an array uses
+    ///
+    /// This includes the memory of the shape and elements of the array and of any boxed arrays in it.
+    /// ex: memory ⇡10
+    /// ex: memory ⋯⇡10
+    /// ex: memory {1_2_3 "hello"}
+    (1, Memory, Misc, "memory"),
+    /// Get the names of the functions being called
+    ///
+    /// The outermost function is first and the function that calls [calls] is last.
+    /// ex: # Experimental!
+    ///   : F ← ⊂calls □
+    ///   : G ← F
+    ///   : G 1
+    /// At the top level, no functions are being called.
+    /// ex: # Experimental!
+    ///   : calls
+    /// The [length] of the result is how deeply nested the call is.
+    /// ex: # Experimental!
+    ///   : Depth ← +⧻calls
+    ///   : Depth 0
+    ///
+    /// [calls] is experimental. To use it, add `# Experimental!` to the top of the file.
+    (0, Calls, Misc, "calls"),
+    /// Attach a label to a value
+    ///
+    /// The label is shown when the value is displayed.
+    /// ex: # Experimental!
+    ///   : label "prices" [3 5 2]
+    /// Pervasive functions keep the label of their first labeled argument.
+    /// ex: # Experimental!
+    ///   : ×2 label "prices" [3 5 2]
+    /// Labels are also shown in errors about the shapes of labeled arrays.
+    /// ex! # Experimental!
+    ///   : Prices ← label "prices" [3 5 2]
+    ///   : Dates ← label "dates" [1 2]
+    ///   : + Prices Dates
+    /// An empty label removes the label.
+    /// ex: # Experimental!
+    ///   : label "" label "prices" [3 5 2]
+    ///
+    /// [label] is experimental. To use it, add `# Experimental!` to the top of the file.
+    (2, Label, Misc, "label"),
+    /// Name the axes of an array
+    ///
+    /// The first argument is a list of names, one for each axis. An empty name leaves an axis unnamed.
+    /// The names are shown when the array is displayed.
+    /// ex: # Experimental!
+    ///   : axes {"time" "sensor"} [1_2_3 4_5_6]
+    /// Pervasive functions keep the names of the axes of their arguments.
+    /// [transpose] rotates them along with the axes.
+    /// ex: # Experimental!
+    ///   : ⍉ ×10 axes {"time" "sensor"} [1_2_3 4_5_6]
+    /// Dyadic pervasive functions check that the axes of their arguments have the same names.
+    /// ex! # Experimental!
+    ///   : A ← axes {"time" "sensor"} [1_2 3_4]
+    ///   : B ← axes {"sensor" "time"} [1_2 3_4]
+    ///   : + A B
+    /// Names that are all empty remove the names.
+    /// ex: # Experimental!
+    ///   : axes {"" ""} axes {"time" "sensor"} [1_2 3_4]
+    ///
+    /// [axes] is experimental. To use it, add `# Experimental!` to the top of the file.
+    (2, Axes, Misc, "axes"),
+    /// Reorder the axes of an array by their names
+    ///
+    /// The axes with the given names are moved to the front, in the order they are given.
+    /// The other axes keep their order after them.
+    /// ex: # Experimental!
+    ///   : orient {"sensor" "time"} axes {"time" "sensor"} [1_2_3 4_5_6]
+    /// ex: # Experimental!
+    ///   : △ orient "c" axes {"a" "b" "c"} ↯2_3_4 ⇡24
+    ///
+    /// [orient] is experimental. To use it, add `# Experimental!` to the top of the file.
+    (2, Orient, Misc, "orient"),
+    /// Give numbers a unit of measure
+    ///
+    /// The unit is shown after the numbers.
+    /// ex: # Experimental!
+    ///   : unit "m/s" [3 5 2]
+    /// Base units are multiplied with `*` and divided with `/`, and can be raised to a whole power with `^`.
+    /// ex: # Experimental!
+    ///   : unit "kg*m/s^2" 9.8
+    /// Arithmetic checks and combines units. [add]ing or [subtract]ing numbers with different units is an error.
+    /// ex! # Experimental!
+    ///   : + unit "m" 3 unit "s" 2
+    /// [multiply] and [divide] combine units.
+    /// ex: # Experimental!
+    ///   : ÷ unit "s" 2 unit "m" 10
+    /// Numbers without units can be used with numbers that have them.
+    /// ex: # Experimental!
+    ///   : ×2 +1 unit "m" 3
+    /// An empty unit removes the unit.
+    /// ex: # Experimental!
+    ///   : unit "" unit "m" 3
+    ///
+    /// [unit] is experimental. To use it, add `# Experimental!` to the top of the file.
+    (2, Unit, Misc, "unit"),
     /// Get the current time in seconds
     ///
     /// ex: now
     /// [under][now] can be used to time a function.
     /// ex: ⍜now(5&sl1)
+    ///
+    /// To time how long code takes, [clock] and [timeit] are more precise.
     (0, Now, Misc, "now"),
+    /// Get the time in seconds from a high-resolution clock
+    ///
+    /// Unlike [now], the clock never goes backwards, but it starts at an arbitrary time.
+    /// Only the differences between its times are meaningful.
+    /// ex: clock
+    /// ex: -∶clock ⊙(/+⇡100000) clock
+    (0, Clock, Misc, "clock"),
+    /// Call a function and get how many seconds it took
+    ///
+    /// The time is pushed on top of the function's outputs.
+    /// ex: timeit(/+⇡100000)
+    /// ex: timeit(⊃+- 3 5)
+    /// The time comes from [clock].
+    ([1], TimeIt, Misc, "timeit"),
     /// The number of radians in a quarter circle
     ///
     /// Equivalent to `divide``2``pi` or `divide``4``tau`
@@ -1627,6 +1926,15 @@ primitive!(
     /// ex: [1 5 2 9 11 0 7 12 8 3]
     ///   : ▽×⸮≥5∶⸮≤10..
     (1, Trace, Stack, ("trace", '⸮')),
+    /// Debug print the result of calling a function on the top value on the stack, without changing the stack
+    ///
+    /// The output is labeled with the location of [inspect] and the code of its function.
+    /// ex: inspect△ ↯2_3⇡6
+    /// This is useful when a value is too big to read, but something about it is not.
+    /// ex: ⧻ inspect(/+) inspect△ ⇡1000
+    /// Errors encountered within [inspect]'s function are caught and printed as strings.
+    /// ex: inspect⊢ []
+    (1[1], Inspect, Stack, "inspect"),
     /// Debug print all the values currently on stack without popping them
     ///
     /// The function is used to preprocess the values before printing.
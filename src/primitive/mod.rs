@@ -30,12 +30,16 @@ use crate::{
     algorithm::{fork, loops, reduce, table, zip},
     array::Array,
     boxed::Boxed,
+    function::FunctionId,
     lex::AsciiToken,
     sys::*,
     value::*,
-    Uiua, UiuaError, UiuaResult,
+    Uiua, UiuaError, UiuaResult, Unit, Version,
 };
 
+/// The function of a dyadic pervasive primitive
+pub(crate) type PervasiveDyadic = fn(Value, Value, &Uiua) -> UiuaResult<Value>;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Sequence)]
 pub enum PrimClass {
     Stack,
@@ -105,6 +109,62 @@ impl From<(&'static str, AsciiToken, char)> for PrimNames {
     }
 }
 
+/// A former name or glyph of a primitive that was renamed
+///
+/// Code that uses it still works, but compiling it gives a deprecation warning,
+/// and the formatter replaces it with the primitive's current spelling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrimAlias {
+    pub spelling: &'static str,
+    pub prim: Primitive,
+    /// The version in which the primitive was renamed
+    pub since: Version,
+}
+
+/// The former spellings of renamed primitives
+pub static ALIASES: &[PrimAlias] = &[
+    PrimAlias {
+        spelling: "·",
+        prim: Primitive::Identity,
+        since: Version::new(0, 0, 15),
+    },
+    PrimAlias {
+        spelling: "noop",
+        prim: Primitive::Identity,
+        since: Version::new(0, 0, 15),
+    },
+    PrimAlias {
+        spelling: "⇉",
+        prim: Primitive::Fork,
+        since: Version::new(0, 0, 15),
+    },
+    PrimAlias {
+        spelling: "share",
+        prim: Primitive::Fork,
+        since: Version::new(0, 0, 15),
+    },
+    PrimAlias {
+        spelling: "⍛",
+        prim: Primitive::Fill,
+        since: Version::new(0, 0, 15),
+    },
+    PrimAlias {
+        spelling: "⌂",
+        prim: Primitive::Rise,
+        since: Version::new(0, 0, 17),
+    },
+    PrimAlias {
+        spelling: "grade",
+        prim: Primitive::Rise,
+        since: Version::new(0, 0, 17),
+    },
+    PrimAlias {
+        spelling: "constant",
+        prim: Primitive::Box,
+        since: Version::new(0, 0, 17),
+    },
+];
+
 impl fmt::Display for Primitive {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if let Some(c) = self.glyph() {
@@ -174,6 +234,12 @@ impl Primitive {
     pub fn glyph(&self) -> Option<char> {
         self.names().glyph
     }
+    /// The name that can be typed in place of the primitive's glyph
+    ///
+    /// This is its name without spaces, like `lessorequal` for `≤`.
+    pub fn ascii_name(&self) -> String {
+        self.name().replace(' ', "")
+    }
     /// Find a primitive by its text name
     pub fn from_name(name: &str) -> Option<Self> {
         Self::all().find(|p| p.name().eq_ignore_ascii_case(name))
@@ -201,6 +267,33 @@ impl Primitive {
     pub fn is_ocean(&self) -> bool {
         self.ocean_constant().is_some()
     }
+    /// Whether the primitive compares numbers, so that it is affected by the comparison tolerance
+    pub(crate) fn is_comparison(&self) -> bool {
+        use Primitive::*;
+        matches!(self, Eq | Ne | Lt | Le | Gt | Ge)
+    }
+    /// Whether the primitive always gives the same outputs for the same inputs and has no side effects
+    pub fn is_pure(&self) -> bool {
+        use Primitive::*;
+        !matches!(
+            self,
+            Rand | Tag
+                | Now
+                | Clock
+                | TimeIt
+                | Trace
+                | Inspect
+                | Dump
+                | Spawn
+                | Wait
+                | Send
+                | Recv
+                | TryRecv
+                | Eval
+                | Calls
+                | Sys(_)
+        )
+    }
     pub fn constant(&self) -> Option<f64> {
         use Primitive::*;
         match self {
@@ -218,6 +311,23 @@ impl Primitive {
     pub fn is_deprecated(&self) -> bool {
         self.deprecation_suggestion().is_some()
     }
+    /// Whether the primitive is unstable and can only be used in files marked `# Experimental!`
+    pub fn is_experimental(&self) -> bool {
+        matches!(
+            self,
+            Primitive::Help
+                | Primitive::Calls
+                | Primitive::Label
+                | Primitive::Axes
+                | Primitive::Orient
+                | Primitive::Unit
+                | Primitive::Tolerance
+        )
+    }
+    /// Find the primitive that a former spelling refers to
+    pub fn from_alias(spelling: &str) -> Option<&'static PrimAlias> {
+        ALIASES.iter().find(|alias| alias.spelling == spelling)
+    }
     /// Try to parse a primitive from a name prefix
     pub fn from_format_name(name: &str) -> Option<Self> {
         if name.chars().any(char::is_uppercase) {
@@ -237,6 +347,15 @@ impl Primitive {
         if let Some(prim) = Primitive::all().find(|p| p.name() == name) {
             return Some(prim);
         }
+        if let Some(alias) = Primitive::from_alias(name) {
+            return Some(alias.prim);
+        }
+        // Names with spaces are typed without them
+        if let Some(prim) =
+            Primitive::all().find(|p| p.name().contains(' ') && p.ascii_name() == name)
+        {
+            return Some(prim);
+        }
         if name.len() < 3 {
             return None;
         }
@@ -271,6 +390,30 @@ impl Primitive {
             break None;
         }
     }
+    /// Get the function of a dyadic pervasive primitive
+    ///
+    /// These can be fused with the instruction before them into a superinstruction.
+    pub(crate) fn pervasive_dyadic(&self) -> Option<PervasiveDyadic> {
+        Some(match self {
+            Primitive::Eq => Value::is_eq_tolerant,
+            Primitive::Ne => Value::is_ne_tolerant,
+            Primitive::Lt => Value::is_lt_tolerant,
+            Primitive::Le => Value::is_le_tolerant,
+            Primitive::Gt => Value::is_gt_tolerant,
+            Primitive::Ge => Value::is_ge_tolerant,
+            Primitive::Add => Value::add,
+            Primitive::Sub => Value::sub,
+            Primitive::Mul => Value::mul,
+            Primitive::Div => Value::div,
+            Primitive::Mod => Value::modulus,
+            Primitive::Pow => Value::pow,
+            Primitive::Log => Value::log,
+            Primitive::Min => Value::min,
+            Primitive::Max => Value::max,
+            Primitive::Atan => Value::atan2,
+            _ => return None,
+        })
+    }
     pub(crate) fn run(&self, env: &mut Uiua) -> UiuaResult {
         match self {
             Primitive::Eta => env.push(eta()),
@@ -287,12 +430,12 @@ impl Primitive {
             Primitive::Floor => env.monadic_env(Value::floor)?,
             Primitive::Ceil => env.monadic_env(Value::ceil)?,
             Primitive::Round => env.monadic_env(Value::round)?,
-            Primitive::Eq => env.dyadic_oo_env(Value::is_eq)?,
-            Primitive::Ne => env.dyadic_oo_env(Value::is_ne)?,
-            Primitive::Lt => env.dyadic_oo_env(Value::is_lt)?,
-            Primitive::Le => env.dyadic_oo_env(Value::is_le)?,
-            Primitive::Gt => env.dyadic_oo_env(Value::is_gt)?,
-            Primitive::Ge => env.dyadic_oo_env(Value::is_ge)?,
+            Primitive::Eq => env.dyadic_oo_env(Value::is_eq_tolerant)?,
+            Primitive::Ne => env.dyadic_oo_env(Value::is_ne_tolerant)?,
+            Primitive::Lt => env.dyadic_oo_env(Value::is_lt_tolerant)?,
+            Primitive::Le => env.dyadic_oo_env(Value::is_le_tolerant)?,
+            Primitive::Gt => env.dyadic_oo_env(Value::is_gt_tolerant)?,
+            Primitive::Ge => env.dyadic_oo_env(Value::is_ge_tolerant)?,
             Primitive::Add => env.dyadic_oo_env(Value::add)?,
             Primitive::Sub => env.dyadic_oo_env(Value::sub)?,
             Primitive::Mul => env.dyadic_oo_env(Value::mul)?,
@@ -303,7 +446,7 @@ impl Primitive {
             Primitive::Min => env.dyadic_oo_env(Value::min)?,
             Primitive::Max => env.dyadic_oo_env(Value::max)?,
             Primitive::Atan => env.dyadic_oo_env(Value::atan2)?,
-            Primitive::Match => env.dyadic_rr(|a, b| a == b)?,
+            Primitive::Match => env.dyadic_rr_env(|a, b, env| Ok(a.matches(b, env)))?,
             Primitive::Join => env.dyadic_oo_env(Value::join)?,
             Primitive::Transpose => env.monadic_mut(Value::transpose)?,
             Primitive::Keep => env.dyadic_ro_env(Value::keep)?,
@@ -315,16 +458,29 @@ impl Primitive {
             Primitive::Fall => env.monadic_ref_env(|v, env| v.fall(env))?,
             Primitive::Pick => env.dyadic_oo_env(Value::pick)?,
             Primitive::Select => env.dyadic_rr_env(Value::select)?,
+            Primitive::Amend => {
+                let indices = env.pop(1)?;
+                let values = env.pop(2)?;
+                let into = env.pop(3)?;
+                env.push(values.amend(indices, into, env)?);
+            }
             Primitive::Windows => env.dyadic_rr_env(Value::windows)?,
             Primitive::Where => env.monadic_ref_env(Value::wher)?,
             Primitive::Classify => env.monadic_ref_env(Value::classify)?,
-            Primitive::Deduplicate => env.monadic_mut(Value::deduplicate)?,
+            Primitive::Deduplicate => env.monadic_mut_env(Value::deduplicate)?,
+            Primitive::RunLength => {
+                let val = env.pop(1)?;
+                let (counts, rows) = val.run_length(env)?;
+                env.push(rows);
+                env.push(counts);
+            }
             Primitive::Member => env.dyadic_rr_env(Value::member)?,
             Primitive::Find => env.dyadic_rr_env(Value::find)?,
+            Primitive::Split => env.dyadic_rr_env(Value::split)?,
             Primitive::IndexOf => env.dyadic_rr_env(Value::index_of)?,
             Primitive::Box => {
                 let val = env.pop(1)?;
-                env.push(Boxed(val));
+                env.push(Boxed::checked(val, env)?);
             }
             Primitive::Unbox => {
                 let val = match env.pop(1)? {
@@ -356,6 +512,17 @@ impl Primitive {
                     .copied()
                     .collect::<Value>()
             })?,
+            Primitive::Rank => env.monadic_ref(|v| {
+                v.generic_ref_deep(Array::rank, Array::rank, Array::rank, Array::rank)
+            })?,
+            Primitive::Count => env.monadic_ref(|v| {
+                v.generic_ref_deep(
+                    Array::flat_len,
+                    Array::flat_len,
+                    Array::flat_len,
+                    Array::flat_len,
+                )
+            })?,
             Primitive::Bits => env.monadic_ref_env(Value::bits)?,
             Primitive::Reduce => reduce::reduce(env)?,
             Primitive::Scan => reduce::scan(env)?,
@@ -369,6 +536,7 @@ impl Primitive {
             Primitive::Cross => table::cross(env)?,
             Primitive::Combinate => table::combinate(env)?,
             Primitive::Repeat => loops::repeat(env)?,
+            Primitive::Do => loops::do_while(env)?,
             Primitive::Group => loops::group(env)?,
             Primitive::Partition => loops::partition(env)?,
             Primitive::Reshape => {
@@ -383,6 +551,14 @@ impl Primitive {
                     return Err(UiuaError::Break(n - 1, env.span().clone()));
                 }
             }
+            Primitive::Continue => {
+                let n = env
+                    .pop(1)?
+                    .as_nat(env, "Continue expects a natural number")?;
+                if n > 0 {
+                    return Err(UiuaError::Continue(n - 1, env.span().clone()));
+                }
+            }
             Primitive::Dup => {
                 let x = env.pop(1)?;
                 env.push(x.clone());
@@ -448,6 +624,13 @@ impl Primitive {
                 env.call(g)?;
                 env.call(f_after)?;
             }
+            Primitive::Tolerance => {
+                let tolerance = env.pop_function()?;
+                let f = env.pop_function()?;
+                env.call(tolerance)?;
+                let tolerance = env.pop("tolerance")?;
+                env.with_tolerance(tolerance, |env| env.call(f))?;
+            }
             Primitive::Pack => {
                 let f = env.pop_function()?;
                 env.with_pack(|env| env.call(f))?;
@@ -493,10 +676,8 @@ impl Primitive {
                 }
             }
             Primitive::Rand => {
-                thread_local! {
-                    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::seed_from_u64(instant::now().to_bits()));
-                }
-                env.push(RNG.with(|rng| rng.borrow_mut().gen::<f64>()));
+                let n = env.random();
+                env.push(n);
             }
             Primitive::Gen => {
                 let seed = env.pop(1)?;
@@ -527,10 +708,86 @@ impl Primitive {
                     Value::Box(_) => 2,
                 });
             }
+            Primitive::Datatype => env.monadic_ref(Value::datatype_name)?,
+            Primitive::Memory => env.monadic_ref(Value::memory_size)?,
+            Primitive::Calls => {
+                let calls: Array<Boxed> = (env.call_stack().into_iter())
+                    .map(|id| match id {
+                        FunctionId::Named(name) => Boxed(name.to_string().into()),
+                        id => Boxed(id.to_string().into()),
+                    })
+                    .collect();
+                env.push(calls);
+            }
+            Primitive::Label => {
+                let label = env.pop(1)?.as_string(env, "Label must be a string")?;
+                let mut val = env.pop(2)?;
+                val.set_label((!label.is_empty()).then_some(label));
+                env.push(val);
+            }
+            Primitive::Axes => {
+                let names = axis_names(env.pop(1)?, env)?;
+                let mut val = env.pop(2)?;
+                if names.len() != val.rank() {
+                    return Err(env.error(format!(
+                        "{} axis names were given, but the array has rank {}",
+                        names.len(),
+                        val.rank()
+                    )));
+                }
+                for (i, name) in names.iter().enumerate() {
+                    if !name.is_empty() && names[..i].contains(name) {
+                        return Err(env.error(format!("Axis name `{name}` is used more than once")));
+                    }
+                }
+                let names = (names.into_iter())
+                    .map(|name| (!name.is_empty()).then_some(name))
+                    .collect();
+                val.set_axes(names);
+                env.push(val);
+            }
+            Primitive::Unit => {
+                let unit = env.pop(1)?.as_string(env, "Unit must be a string")?;
+                let unit = Unit::parse(&unit).map_err(|e| env.error(e))?;
+                let mut val = env.pop(2)?;
+                if !matches!(val, Value::Num(_) | Value::Byte(_)) {
+                    return Err(env.error(format!(
+                        "Only numbers can have units, but its type is {}",
+                        val.type_name()
+                    )));
+                }
+                val.set_unit(Some(unit));
+                env.push(val);
+            }
+            Primitive::Orient => {
+                let names = axis_names(env.pop(1)?, env)?;
+                let mut val = env.pop(2)?;
+                val.orient(&names, env)?;
+                env.push(val);
+            }
             Primitive::Spawn => {
                 let f = env.pop_function()?;
                 env.spawn(f.signature().args, |env| env.call(f))?;
             }
+            Primitive::Memo => {
+                let f = env.pop_function()?;
+                env.call_memo(f)?;
+            }
+            Primitive::Quote => {
+                return Err(env.error(
+                    "Quote should have been inlined. \
+                    This is an interpreter bug.",
+                ))
+            }
+            Primitive::Help => {
+                let f = env.pop_function()?;
+                let doc = env.function_doc(&f).unwrap_or_default();
+                env.push(doc);
+            }
+            Primitive::Eval => {
+                let code = env.pop(1)?.as_string(env, "Eval expects a string")?;
+                env.eval(&code)?;
+            }
             Primitive::Wait => {
                 let id = env.pop(1)?;
                 env.wait(id)?;
@@ -548,8 +805,17 @@ impl Primitive {
                 let id = env.pop(1)?;
                 env.try_recv(id)?;
             }
-            Primitive::Now => env.push(instant::now() / 1000.0),
+            Primitive::Now => env.push(env.now()),
+            Primitive::Clock => env.push(env.clock()),
+            Primitive::TimeIt => {
+                let f = env.pop_function()?;
+                let start = env.clock();
+                env.call(f)?;
+                let elapsed = env.clock() - start;
+                env.push(elapsed);
+            }
             Primitive::Trace => trace(env, false)?,
+            Primitive::Inspect => inspect(env)?,
             Primitive::Dump => dump(env)?,
             Primitive::Sys(io) => io.run(env)?,
             Primitive::Regex => {
@@ -662,20 +928,60 @@ fn trace(env: &mut Uiua, inverse: bool) -> UiuaResult {
     } else {
         env.span().to_string()
     };
-    let max_line_len = span.chars().count() + 2;
+    print_traced(env, &span, &val);
+    env.push(val);
+    Ok(())
+}
+
+fn inspect(env: &mut Uiua) -> UiuaResult {
+    let f = env.pop_function()?;
+    if f.signature() != (1, 1) {
+        return Err(env.error(format!(
+            "Inspect's function's signature must be |1.1, but it is {}",
+            f.signature()
+        )));
+    }
+    let val = env.pop(1)?;
+    let code = match &f.id {
+        FunctionId::Anonymous(span) => span.as_str().to_string(),
+        _ => f.to_string(),
+    };
+    let label = format!("{} {code}", env.span());
+    env.push(val.clone());
+    let inspected = match env.call(f) {
+        Ok(()) => env.pop("inspect's function's result")?,
+        Err(e) => e.value(),
+    };
+    print_traced(env, &label, &inspected);
+    env.push(val);
+    Ok(())
+}
+
+/// Get a list of axis names from a string or a list of strings
+fn axis_names(val: Value, env: &Uiua) -> UiuaResult<Vec<String>> {
+    const REQUIREMENT: &str = "Axis names must be a string or a list of strings";
+    match val {
+        Value::Box(boxes) if boxes.rank() == 1 => (boxes.data.iter())
+            .map(|bx| bx.as_value().as_string(env, REQUIREMENT))
+            .collect(),
+        val => Ok(vec![val.as_string(env, REQUIREMENT)?]),
+    }
+}
+
+/// Print a value in a box with a label, as [`trace`](Primitive::Trace) does
+fn print_traced(env: &Uiua, label: &str, val: &Value) {
+    let max_line_len = label.chars().count() + 2;
     let item_lines =
         format_trace_item_lines(val.show().lines().map(Into::into).collect(), max_line_len);
-    env.push(val);
-    env.backend.print_str_trace(&format!("┌╴{span}\n"));
+    env.print_str_trace(&format!("┌╴{label}\n"));
     for line in item_lines {
-        env.backend.print_str_trace(&line);
+        env.print_str_trace(&line);
     }
-    env.backend.print_str_trace("└");
+    env.print_str_trace("└");
     for _ in 0..max_line_len - 1 {
-        env.backend.print_str_trace("╴");
+        env.print_str_trace("╴");
     }
-    env.backend.print_str_trace("\n");
-    Ok(())
+    env.print_str_trace("\n");
 }
 
 fn dump(env: &mut Uiua) -> UiuaResult {
@@ -703,15 +1009,15 @@ fn dump(env: &mut Uiua) -> UiuaResult {
         .map(|s| s.lines().map(Into::into).collect::<Vec<String>>())
         .map(|lines| format_trace_item_lines(lines, max_line_len))
         .collect();
-    env.backend.print_str_trace(&format!("┌╴{span}\n"));
+    env.print_str_trace(&format!("┌╴{span}\n"));
     for line in item_lines.iter().flatten() {
-        env.backend.print_str_trace(line);
+        env.print_str_trace(line);
     }
-    env.backend.print_str_trace("└");
+    env.print_str_trace("└");
     for _ in 0..max_line_len - 1 {
-        env.backend.print_str_trace("╴");
+        env.print_str_trace("╴");
     }
-    env.backend.print_str_trace("\n");
+    env.print_str_trace("\n");
     Ok(())
 }
 
@@ -849,7 +1155,7 @@ impl PrimExample {
         self.should_error
     }
     pub fn should_run(&self) -> bool {
-        !["&sl", "&tcpc", "&ast"]
+        !["&sl", "&tcpc", "&ast", "&wf"]
             .iter()
             .any(|prim| self.input.contains(prim))
     }
@@ -1066,6 +1372,32 @@ mod tests {
         assert_eq!(Primitive::from_format_name_multi("foo"), None);
     }
 
+    #[test]
+    fn aliases() {
+        use crate::format::{format_str, FormatConfig};
+        for alias in ALIASES {
+            assert!(
+                Primitive::all().all(|p| p.name() != alias.spelling
+                    && p.glyph().map(String::from).as_deref() != Some(alias.spelling)),
+                "{} is the spelling of a current primitive",
+                alias.spelling
+            );
+        }
+        assert_eq!(Primitive::from_format_name("grade"), Some(Primitive::Rise));
+        let formatted = format_str("⌂ noop [3 1 2]", &FormatConfig::default()).unwrap();
+        assert_eq!(formatted.output, "⍏ ∘ [3 1 2]\n");
+        let mut env = Uiua::with_native_sys();
+        env.load_str("⍤∶≍, [1 2 0] ⌂ [3 1 2]").unwrap();
+        let messages: Vec<String> = (env.take_diagnostics().into_iter())
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            ["Warning: `⌂` was renamed to rise ⍏ in version 0.0.17. \
+            Formatting the code will update it."]
+        );
+    }
+
     #[cfg(test)]
     #[test]
     fn gen_grammar_file() {
@@ -0,0 +1,149 @@
+//! Progress reporting for long-running operations
+
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+};
+
+use crate::{SysBackend, Uiua, UiuaError, UiuaResult};
+
+/// Operations with fewer steps than this are not reported
+const MIN_STEPS: usize = 1 << 14;
+/// How many times an operation is reported
+const REPORTS: usize = 100;
+
+/// Tracks how far along a long-running operation is and reports it to the [`SysBackend`]
+///
+/// The operation is cancelled if the backend asks for it or if the execution limit is exceeded.
+/// Steps can be taken from multiple threads, like in a parallel sort.
+pub(crate) struct Progress {
+    backend: Arc<dyn SysBackend>,
+    op: &'static str,
+    total: usize,
+    done: AtomicUsize,
+    deadline: Option<f64>,
+    cancelled: AtomicBool,
+    timed_out: AtomicBool,
+}
+
+impl Progress {
+    /// Take a step
+    ///
+    /// Returns `false` if the operation was cancelled, in which case it should stop as soon as it can.
+    pub fn step(&self) -> bool {
+        if self.total < MIN_STEPS {
+            return true;
+        }
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.stopped() {
+            return false;
+        }
+        // Only report when another hundredth of the steps is done
+        if done * REPORTS / self.total == (done - 1) * REPORTS / self.total {
+            return true;
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| instant::now() > deadline)
+        {
+            self.timed_out.store(true, Ordering::Relaxed);
+            return false;
+        }
+        let fraction = (done as f64 / self.total as f64).min(1.0);
+        if !self.backend.progress(self.op, fraction) {
+            self.cancelled.store(true, Ordering::Relaxed);
+            return false;
+        }
+        true
+    }
+    fn stopped(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) || self.timed_out.load(Ordering::Relaxed)
+    }
+    /// Finish the operation
+    ///
+    /// Errors if it was cancelled.
+    pub fn finish(self, env: &Uiua) -> UiuaResult {
+        if self.timed_out.into_inner() {
+            return Err(UiuaError::Timeout(env.span()));
+        }
+        if self.cancelled.into_inner() {
+            return Err(UiuaError::Interrupted(env.span()));
+        }
+        if self.total >= MIN_STEPS {
+            self.backend.progress(self.op, 1.0);
+        }
+        Ok(())
+    }
+}
+
+impl Uiua {
+    /// Start tracking the progress of an operation that takes some number of steps
+    pub(crate) fn progress(&self, op: &'static str, total: usize) -> Progress {
+        Progress {
+            backend: self.backend.clone(),
+            op,
+            total,
+            done: AtomicUsize::new(0),
+            deadline: self.deadline(),
+            cancelled: AtomicBool::new(false),
+            timed_out: AtomicBool::new(false),
+        }
+    }
+    /// Report that an operation whose steps cannot be counted has reached some fraction of the way done
+    ///
+    /// Errors if the operation is cancelled.
+    pub(crate) fn report_progress(&self, op: &str, fraction: f64) -> UiuaResult {
+        if self
+            .deadline()
+            .is_some_and(|deadline| instant::now() > deadline)
+        {
+            return Err(UiuaError::Timeout(self.span()));
+        }
+        if !self.backend.progress(op, fraction) {
+            return Err(UiuaError::Interrupted(self.span()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorCode;
+
+    #[test]
+    fn progress() {
+        use std::{any::Any, sync::Mutex};
+
+        #[derive(Default)]
+        struct Recorder {
+            reports: Mutex<Vec<(String, f64)>>,
+            cancel_at: Option<f64>,
+        }
+        impl SysBackend for Recorder {
+            fn any(&self) -> &dyn Any {
+                self
+            }
+            fn progress(&self, op: &str, done: f64) -> bool {
+                self.reports.lock().unwrap().push((op.into(), done));
+                !self.cancel_at.is_some_and(|at| done >= at)
+            }
+        }
+
+        let mut env = Uiua::with_backend(Recorder::default());
+        env.load_str("⍏◿1×π⇡100000").unwrap();
+        let recorder = env.backend().any().downcast_ref::<Recorder>().unwrap();
+        let reports = recorder.reports.lock().unwrap();
+        assert!(reports.len() > 10, "{reports:?}");
+        assert!(reports.iter().all(|(op, _)| op == "rise"));
+        assert_eq!(reports.last().unwrap().1, 1.0);
+        drop(reports);
+
+        let mut env = Uiua::with_backend(Recorder {
+            cancel_at: Some(0.5),
+            ..Default::default()
+        });
+        let err = env.load_str("⍏◿1×π⇡100000").unwrap_err();
+        assert_eq!(err.code(), Some(ErrorCode::Interrupted));
+    }
+}
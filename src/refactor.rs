@@ -0,0 +1,250 @@
+//! Refactorings of Uiua code
+
+use std::{collections::HashSet, fs, path::Path};
+
+use crate::{
+    ast::{walk_binding, Binding, Visitor, Word},
+    lex::{lex, CodeSpan, Sp, Span, Token},
+    parse::{ident_modifier_args, parse},
+    run::BindingRef,
+    Ident, Primitive, Uiua, UiuaError, UiuaResult,
+};
+
+/// The places where a binding is defined and referenced
+#[derive(Debug, Clone, Default)]
+pub struct BindingSpans {
+    /// The names of the binding, including where it is bound again with the same name
+    pub definitions: Vec<CodeSpan>,
+    /// Uses of the binding by name
+    pub references: Vec<CodeSpan>,
+    /// String literals that name the binding in [`import`](crate::SysOp::Import)s in other files
+    pub imports: Vec<CodeSpan>,
+}
+
+/// A replacement of the text of a span
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub span: CodeSpan,
+    pub new_text: String,
+}
+
+/// Find where a top-level binding in a file is defined and referenced
+///
+/// Names are resolved the same way they are when the file is compiled,
+/// so bindings of the same name in test scopes are not included.
+/// The other files are searched for imports of the binding.
+pub fn find_binding<P: AsRef<Path>>(
+    files: &[P],
+    path: &Path,
+    name: &str,
+) -> UiuaResult<BindingSpans> {
+    let refs = binding_refs(path)?;
+    find_binding_impl(files, path, name, &refs)
+}
+
+/// Rename a top-level binding in a file
+///
+/// Returns edits for the binding's definitions and references in the file,
+/// and for the names of the binding in imports in the other files.
+/// Bindings that imports are bound to in the other files keep their names.
+pub fn rename<P: AsRef<Path>>(
+    files: &[P],
+    path: &Path,
+    name: &str,
+    new_name: &str,
+) -> UiuaResult<Vec<TextEdit>> {
+    let (tokens, errors) = lex(new_name, None);
+    if !errors.is_empty()
+        || !matches!(tokens.as_slice(), [Sp { value: Token::Ident, span }] if span.as_str() == new_name)
+    {
+        return Err(Span::Builtin.error(format!("`{new_name}` is not a valid binding name")));
+    }
+    if ident_modifier_args(name) != ident_modifier_args(new_name) {
+        return Err(Span::Builtin.error(format!(
+            "{new_name} must end in the same number of `!`s as {name}"
+        )));
+    }
+    let refs = binding_refs(path)?;
+    if refs.iter().any(|r| &*r.name == new_name) {
+        return Err(
+            Span::Builtin.error(format!("{new_name} is already bound in {}", path.display()))
+        );
+    }
+    let spans = find_binding_impl(files, path, name, &refs)?;
+    let mut edits: Vec<TextEdit> = (spans.definitions.into_iter())
+        .chain(spans.references)
+        .map(|span| TextEdit {
+            span,
+            new_text: new_name.into(),
+        })
+        .chain(spans.imports.into_iter().map(|span| TextEdit {
+            span,
+            new_text: format!("\"{new_name}\""),
+        }))
+        .collect();
+    edits.sort_by(|a, b| {
+        (a.span.path.cmp(&b.span.path)).then(a.span.start.byte_pos.cmp(&b.span.start.byte_pos))
+    });
+    Ok(edits)
+}
+
+/// Compile a file without running it, recording where its bindings are defined and referenced
+fn binding_refs(path: &Path) -> UiuaResult<Vec<BindingRef>> {
+    let mut env = Uiua::with_native_sys();
+    env.binding_refs = Some(Vec::new());
+    env.check_file(path)?;
+    Ok(env.binding_refs.take().unwrap_or_default())
+}
+
+fn find_binding_impl<P: AsRef<Path>>(
+    files: &[P],
+    path: &Path,
+    name: &str,
+    refs: &[BindingRef],
+) -> UiuaResult<BindingSpans> {
+    let targets: HashSet<usize> = (refs.iter())
+        .filter(|r| r.definition && r.top_level && &*r.name == name)
+        .map(|r| r.index)
+        .collect();
+    if targets.is_empty() {
+        return Err(Span::Builtin.error(format!("{name} is not bound in {}", path.display())));
+    }
+    let mut spans = BindingSpans::default();
+    for r in refs.iter().filter(|r| targets.contains(&r.index)) {
+        if r.definition {
+            spans.definitions.push(r.span.clone());
+        } else {
+            spans.references.push(r.span.clone());
+        }
+    }
+    for file in files {
+        let file = file.as_ref();
+        if same_file(file, path) {
+            continue;
+        }
+        let input = fs::read_to_string(file).map_err(|e| UiuaError::Load(file.into(), e.into()))?;
+        let (items, errors, _) = parse(&input, Some(file));
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+        let mut finder = ImportFinder {
+            path,
+            name,
+            importers: HashSet::new(),
+            spans: Vec::new(),
+        };
+        finder.visit_items(&items);
+        spans.imports.extend(finder.spans);
+    }
+    Ok(spans)
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Finds the item names of imports of a binding
+///
+/// Both `&i "path" "Name"` and `Name` called on a binding of `&i "path"` are found.
+struct ImportFinder<'a> {
+    path: &'a Path,
+    name: &'a str,
+    /// Bindings of `&i` partially applied to the path
+    importers: HashSet<Ident>,
+    spans: Vec<CodeSpan>,
+}
+
+impl ImportFinder<'_> {
+    fn is_path(&self, word: &Sp<Word>) -> bool {
+        matches!(&word.value, Word::String(s) if same_file(Path::new(s), self.path))
+    }
+    fn is_import(word: &Sp<Word>) -> bool {
+        matches!(word.value, Word::Primitive(Primitive::Sys(op)) if op.is_import())
+    }
+}
+
+impl Visitor for ImportFinder<'_> {
+    fn visit_binding(&mut self, binding: &Binding) {
+        let code: Vec<&Sp<Word>> = binding.words.iter().filter(|w| w.value.is_code()).collect();
+        if let [import, path] = code.as_slice() {
+            if Self::is_import(import) && self.is_path(path) {
+                self.importers.insert(binding.name.value);
+            }
+        }
+        walk_binding(self, binding);
+    }
+    fn visit_words(&mut self, words: &[Sp<Word>]) {
+        let code: Vec<&Sp<Word>> = words.iter().filter(|w| w.value.is_code()).collect();
+        for (i, word) in code.iter().enumerate() {
+            let item = match (&word.value, code.get(i + 1), code.get(i + 2)) {
+                (Word::Ident(ident), Some(item), _) if self.importers.contains(ident) => item,
+                (_, Some(path), Some(item)) if Self::is_import(word) && self.is_path(path) => item,
+                _ => continue,
+            };
+            if matches!(&item.value, Word::String(s) if s == self.name) {
+                self.spans.push(item.span.clone());
+            }
+        }
+        for word in words {
+            self.visit_word(word);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn rename() {
+        let dir = std::env::temp_dir().join(format!("uiua-rename-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lib = dir.join("lib.ua");
+        let main = dir.join("main.ua");
+        std::fs::write(&lib, "F ← +1\nG ← F F\n---\nF ← ×2\nF 3\n---\nF ↯2 G 5").unwrap();
+        let main_code = format!(
+            "F ← &i {0:?} \"F\"\nLib ← &i {0:?}\nH ← Lib \"F\"\nF H 1",
+            lib.display()
+        );
+        std::fs::write(&main, &main_code).unwrap();
+        let item_col = main_code[..main_code.find("\"F\"").unwrap()]
+            .chars()
+            .count()
+            + 1;
+        let edits = super::rename(&[&lib, &main], &lib, "F", "Inc").unwrap();
+        let edits: Vec<(String, usize, usize, String)> = (edits.into_iter())
+            .map(|edit| {
+                let file = edit
+                    .span
+                    .path
+                    .unwrap()
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into();
+                (
+                    file,
+                    edit.span.start.line,
+                    edit.span.start.col,
+                    edit.new_text,
+                )
+            })
+            .collect();
+        let expected: Vec<(String, usize, usize, String)> = [
+            ("lib.ua", 1, 1, "Inc"),
+            ("lib.ua", 2, 5, "Inc"),
+            ("lib.ua", 2, 7, "Inc"),
+            ("lib.ua", 7, 1, "Inc"),
+            ("main.ua", 1, item_col, "\"Inc\""),
+            ("main.ua", 3, 9, "\"Inc\""),
+        ]
+        .into_iter()
+        .map(|(file, line, col, text)| (file.into(), line, col, text.into()))
+        .collect();
+        assert_eq!(edits, expected);
+        assert!(super::rename(&[&lib], &lib, "F", "G").is_err());
+        assert!(super::rename(&[&lib], &lib, "F", "rev").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,8 @@
 use std::{
-    collections::{BTreeSet, HashMap, HashSet},
+    cell::RefCell,
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap, HashSet},
     fs,
-    hash::Hash,
+    hash::{Hash, Hasher},
     mem::{replace, take},
     panic::{catch_unwind, AssertUnwindSafe},
     path::{Path, PathBuf},
@@ -12,17 +13,21 @@ use std::{
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
 use instant::Duration;
 use parking_lot::Mutex;
+use rand::prelude::*;
+use tinyvec::TinyVec;
 
 use crate::{
-    array::Array,
+    array::{Array, FormatShape, Shape},
+    ast::{Annotation, ValuePattern},
     boxed::Boxed,
     function::*,
-    lex::Span,
+    lex::{CodeSpan, Sp, Span},
     parse::parse,
-    primitive::{Primitive, CONSTANTS},
+    primitive::{ImplPrimitive, PervasiveDyadic, PrimClass, Primitive, CONSTANTS},
     value::Value,
-    Diagnostic, DiagnosticKind, Ident, NativeSys, SysBackend, SysOp, TraceFrame, UiuaError,
-    UiuaResult,
+    version::{self, Version},
+    Diagnostic, DiagnosticKind, Ident, NativeSys, ShapeMismatch, StackUnderflow, SysBackend,
+    TraceFrame, UiuaError, UiuaResult, Unit,
 };
 
 /// The Uiua runtime
@@ -30,10 +35,16 @@ use crate::{
 pub struct Uiua {
     /// Functions which are under construction
     pub(crate) new_functions: Vec<Vec<Instr>>,
+    /// The names of the local bindings of functions under construction
+    ///
+    /// `None` for functions that do not declare any
+    pub(crate) local_names: Vec<Option<Vec<Ident>>>,
     /// Global values
     pub(crate) globals: Arc<Mutex<Vec<Global>>>,
+    /// The documentation comments of globals, by index
+    pub(crate) binding_docs: Arc<Mutex<HashMap<usize, String>>>,
     /// Indexable spans
-    spans: Arc<Mutex<Vec<Span>>>,
+    pub(crate) spans: Arc<Mutex<SpanTable>>,
     /// The thread's stack
     pub(crate) stack: Vec<Value>,
     /// The thread's function stack
@@ -53,17 +64,65 @@ pub struct Uiua {
     /// A limit on the execution duration in milliseconds
     execution_limit: Option<f64>,
     /// The time at which execution started
-    execution_start: f64,
+    pub(crate) execution_start: f64,
     /// The paths of files currently being imported (used to detect import cycles)
     current_imports: Arc<Mutex<HashSet<PathBuf>>>,
     /// The stacks of imported files
     imports: Arc<Mutex<HashMap<PathBuf, HashMap<Ident, usize>>>>,
+    /// The file being loaded by [`import pure`](crate::SysOp::ImportPure), if any, whose code cannot do IO
+    pure_load: Option<Arc<Path>>,
+    /// Cached results of memoized functions
+    memo: Arc<Mutex<MemoCache>>,
+    /// Memoized outputs that persist between runs
+    memo_file: Option<Arc<Mutex<MemoFile>>>,
+    /// Output that is being captured instead of being sent to the backend
+    output_capture: Option<Arc<Mutex<Vec<CellOutput>>>>,
     /// Accumulated diagnostics
     pub(crate) diagnostics: BTreeSet<Diagnostic>,
     /// Print diagnostics as they are encountered
     pub(crate) print_diagnostics: bool,
     /// Whether to print the time taken to execute each instruction
     time_instrs: bool,
+    /// How many times each span has run, if coverage is being counted
+    pub(crate) coverage: Option<Arc<Mutex<Vec<u64>>>>,
+    /// Whether to warn about unused and shadowed bindings
+    pub(crate) lints: bool,
+    /// Whether to check the shapes of arrays at compile time
+    pub(crate) shape_checks: bool,
+    /// Whether to check that functions only take as many values as their signatures say
+    stack_checks: bool,
+    /// Whether to warn about top-level lines that leave or take values unexpectedly
+    pub(crate) leak_checks: bool,
+    /// What to do when a pervasive function divides by zero or produces infinity or NaN
+    num_policy: NumPolicy,
+    /// How far apart numbers can be and still be considered equal, outside of [`tolerance`](Primitive::Tolerance)
+    tolerance: f64,
+    /// The most frames kept in the trace of an error
+    trace_limit: usize,
+    /// Whether code is only being compiled and checked, not run
+    pub(crate) compile_only: bool,
+    /// The version of Uiua that the file being compiled was written for, if it declares one
+    pub(crate) language_version: Option<Version>,
+    /// Whether experimental primitives can be used
+    pub(crate) experimental: bool,
+    /// The random number generator used in deterministic mode
+    rng: Option<SmallRng>,
+    /// How many functions are being called on proxy rows to find the shape of an empty result
+    pub(crate) proxy_depth: usize,
+    /// The name of the binding currently being compiled
+    pub(crate) current_binding: Option<CodeSpan>,
+    /// How many code macro expansions are being compiled
+    pub(crate) macro_depth: usize,
+    /// The code macro that is being run to generate code, if any
+    ///
+    /// Macros run at compile time, even when code is only being checked, so they cannot do IO.
+    pub(crate) running_macro: Option<FunctionId>,
+    /// The annotated patterns of mutable bindings, checked whenever they are assigned
+    pub(crate) var_annotations: HashMap<usize, Sp<ValuePattern>>,
+    /// The names and annotations of annotated functions, by the ids of their checks
+    pub(crate) annotated_fns: HashMap<u64, (Ident, Annotation)>,
+    /// Where global bindings are defined and referenced, if it is being recorded
+    pub(crate) binding_refs: Option<Vec<BindingRef>>,
     /// The time at which the last instruction was executed
     last_time: f64,
     /// Arguments passed from the command line
@@ -72,26 +131,248 @@ pub struct Uiua {
     cli_file_path: PathBuf,
     /// The system backend
     pub(crate) backend: Arc<dyn SysBackend>,
+    /// Operations for [`&ext`](crate::SysOp::Ext), by name
+    pub(crate) ext_ops: HashMap<Ident, ExtOp>,
     /// The thread interface
     thread: ThisThread,
 }
 
+/// An operation for [`&ext`](crate::SysOp::Ext)
+pub(crate) type ExtOp = Arc<dyn Fn(&mut Uiua) -> UiuaResult + Send + Sync>;
+
+/// Memoized outputs, keyed by function and then by arguments
+type MemoCache = HashMap<Arc<Function>, HashMap<Vec<Value>, Vec<Value>>>;
+
+/// The spans that instructions refer to by index, and the sources they are in
+///
+/// Each distinct span and source is only stored once,
+/// so compiling the same code again does not grow the table.
+pub(crate) struct SpanTable {
+    spans: Vec<Span>,
+    /// Span indices, keyed by the span and the address of its interned source
+    indices: HashMap<(Span, usize), usize>,
+    sources: HashSet<Arc<str>>,
+    /// The source of each file, as it was when it was last compiled
+    files: HashMap<Arc<Path>, Arc<str>>,
+    /// The last source that was interned and what it was interned as
+    ///
+    /// The spans of a file are added together, so this avoids hashing the whole source for each one.
+    last_source: Option<(Arc<str>, Arc<str>)>,
+}
+
+impl Default for SpanTable {
+    fn default() -> Self {
+        SpanTable {
+            spans: vec![Span::Builtin],
+            indices: HashMap::new(),
+            sources: HashSet::new(),
+            files: HashMap::new(),
+            last_source: None,
+        }
+    }
+}
+
+impl SpanTable {
+    /// Add a span, or get the index of an equal one
+    fn add(&mut self, mut span: Span) -> usize {
+        let source = match &mut span {
+            Span::Code(span) => {
+                self.intern_source(span);
+                Arc::as_ptr(&span.input) as *const u8 as usize
+            }
+            Span::Builtin => 0,
+        };
+        if let Some(&index) = self.indices.get(&(span.clone(), source)) {
+            return index;
+        }
+        let index = self.spans.len();
+        self.spans.push(span.clone());
+        self.indices.insert((span, source), index);
+        index
+    }
+    /// Make a span point into the stored copy of its source
+    fn intern_source(&mut self, span: &mut CodeSpan) {
+        if let Some((last, interned)) = &self.last_source {
+            if Arc::ptr_eq(last, &span.input) {
+                span.input = interned.clone();
+                return;
+            }
+        }
+        let interned = if let Some(interned) = self.sources.get(&span.input) {
+            interned.clone()
+        } else {
+            self.sources.insert(span.input.clone());
+            span.input.clone()
+        };
+        if let Some(path) = &span.path {
+            self.files.insert(path.clone(), interned.clone());
+        }
+        self.last_source = Some((replace(&mut span.input, interned.clone()), interned));
+    }
+    pub(crate) fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+    /// The number of distinct spans
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+}
+
+impl std::ops::Index<usize> for SpanTable {
+    type Output = Span;
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.spans[index]
+    }
+}
+
+/// Memoized outputs and binding values loaded from and saved to a file, keyed by a hash of the code and arguments
+struct MemoFile {
+    path: PathBuf,
+    /// Outputs loaded from the file
+    loaded: HashMap<u64, Vec<Value>>,
+    /// Outputs that were used or computed during this run
+    used: HashMap<u64, Vec<Value>>,
+}
+
+const MEMO_FILE_HEADER: &str = concat!("uiua memo ", env!("CARGO_PKG_VERSION"), "\n");
+
+impl MemoFile {
+    fn load(path: PathBuf) -> Self {
+        let mut loaded = HashMap::new();
+        if let Ok(bytes) = fs::read(&path) {
+            if let Some(mut bytes) = bytes.strip_prefix(MEMO_FILE_HEADER.as_bytes()) {
+                while let Some((key, outputs)) = Self::decode_entry(&mut bytes) {
+                    loaded.insert(key, outputs);
+                }
+            }
+        }
+        MemoFile {
+            path,
+            loaded,
+            used: HashMap::new(),
+        }
+    }
+    fn decode_entry(bytes: &mut &[u8]) -> Option<(u64, Vec<Value>)> {
+        if bytes.len() < 16 {
+            return None;
+        }
+        let key = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+        let count = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+        *bytes = &bytes[16..];
+        let outputs = (0..count)
+            .map(|_| Value::decode(bytes))
+            .collect::<Option<_>>()?;
+        Some((key, outputs))
+    }
+    /// Get outputs by key, marking them as used
+    fn get(&mut self, key: u64) -> Option<Vec<Value>> {
+        if let Some(outputs) = self.loaded.remove(&key) {
+            self.used.insert(key, outputs.clone());
+            Some(outputs)
+        } else {
+            self.used.get(&key).cloned()
+        }
+    }
+    fn save(&self) -> std::io::Result<()> {
+        let mut bytes = MEMO_FILE_HEADER.as_bytes().to_vec();
+        for (key, outputs) in &self.used {
+            bytes.extend(key.to_le_bytes());
+            bytes.extend((outputs.len() as u64).to_le_bytes());
+            for output in outputs {
+                output.encode(&mut bytes);
+            }
+        }
+        fs::write(&self.path, bytes)
+    }
+}
+
+/// Hash instructions in a way that is stable between runs of the same program
+///
+/// Returns `false` if the instructions depend on mutable state or have side effects.
+fn hash_pure_instrs(instrs: &[Instr], hasher: &mut DefaultHasher) -> bool {
+    for instr in instrs {
+        match instr {
+            Instr::GetGlobal { .. } | Instr::SetGlobal { .. } | Instr::Dynamic(_) => return false,
+            // Locals of the code around a function can hold different values each time it is called
+            Instr::GetLocal { up, frame, .. } | Instr::SetLocal { up, frame, .. }
+                if *up > 0 || frame.is_some() =>
+            {
+                return false
+            }
+            Instr::Prim(prim, _) if !prim.is_pure() => return false,
+            Instr::ImplPrim(ImplPrimitive::InvTrace, _) => return false,
+            // A pushed function is only hashed by its id, so its body must be hashed too
+            Instr::PushFunc(f) => {
+                f.id.hash(hasher);
+                if !hash_pure_instrs(&f.instrs, hasher) {
+                    return false;
+                }
+                f.constants.hash(hasher);
+            }
+            instr => instr.hash(hasher),
+        }
+    }
+    true
+}
+
+/// Hash the runtime settings that change the results of pure code
+fn hash_settings(env: &Uiua, hasher: &mut DefaultHasher) {
+    env.tolerance().to_bits().hash(hasher);
+    env.num_policy.hash(hasher);
+}
+
+/// The key for a memoized function's outputs in a [`MemoFile`]
+fn memo_key(f: &Function, args: &[Value], env: &Uiua) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    "memo".hash(&mut hasher);
+    hash_settings(env, &mut hasher);
+    f.id.hash(&mut hasher);
+    if !hash_pure_instrs(&f.instrs, &mut hasher) {
+        return None;
+    }
+    f.constants.hash(&mut hasher);
+    args.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// The key for a binding's value in a [`MemoFile`]
+///
+/// The values of other bindings are compiled into the instructions that refer to them,
+/// so the key changes whenever a binding this one depends on changes.
+fn binding_key(instrs: &[Instr], env: &Uiua) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    "binding".hash(&mut hasher);
+    hash_settings(env, &mut hasher);
+    hash_pure_instrs(instrs, &mut hasher).then(|| hasher.finish())
+}
+
 #[derive(Clone)]
 pub(crate) enum Global {
     Val(Value),
+    /// A value bound with `↩` that may be reassigned at runtime
+    Var(Value),
     Func(Arc<Function>),
+    /// A code macro, which is run at compile time to generate code
+    Macro(Arc<Function>),
 }
 
 #[derive(Clone)]
 pub struct Scope {
     /// The stack height at the start of each array currently being built
     pub array: Vec<usize>,
+    /// The lowest stack height reached by each function call whose stack effect is being checked
+    floors: Vec<usize>,
     /// The call stack
     call: Vec<StackFrame>,
     /// Map local names to global indices
     pub names: HashMap<Ident, usize>,
+    /// Where each binding in this scope was made and whether it has been used, by global index
+    pub(crate) bindings: BTreeMap<usize, BindingUsage>,
     /// The current fill values
     fills: Fills,
+    /// The comparison tolerances set by [`tolerance`](Primitive::Tolerance), innermost last
+    tolerances: Vec<f64>,
     /// The current clear state
     pack_depth: usize,
 }
@@ -100,6 +381,7 @@ impl Default for Scope {
     fn default() -> Self {
         Self {
             array: Vec::new(),
+            floors: Vec::new(),
             call: vec![StackFrame {
                 function: Arc::new(Function::new(
                     FunctionId::Main,
@@ -108,15 +390,180 @@ impl Default for Scope {
                 )),
                 call_span: 0,
                 pc: 0,
-                spans: Vec::new(),
+                spans: TinyVec::new(),
+                locals: None,
             }],
             names: HashMap::new(),
+            bindings: BTreeMap::new(),
             fills: Fills::default(),
+            tolerances: Vec::new(),
             pack_depth: 0,
         }
     }
 }
 
+#[derive(Clone)]
+pub(crate) struct BindingUsage {
+    pub name: Ident,
+    pub span: CodeSpan,
+    /// The bindings that refer to this one, by the span of their name
+    ///
+    /// `None` is a reference from code outside of any binding.
+    pub users: Vec<Option<CodeSpan>>,
+    /// Whether the binding's code was run when it was bound
+    pub eager: bool,
+}
+
+/// The label, axis names, unit, and shape of an argument of a primitive
+struct ArgMeta {
+    label: Option<String>,
+    axes: Option<Vec<Option<String>>>,
+    unit: Option<Unit>,
+    shape: Vec<usize>,
+}
+
+/// The metadata of up to two arguments of a primitive
+type ArgMetas = [Option<ArgMeta>; 2];
+
+/// Get the metadata of some arguments, if any of them are labeled, have named axes, or have units
+fn metas_of<'a>(args: impl Iterator<Item = &'a Value>) -> Option<ArgMetas> {
+    let mut metas: ArgMetas = [None, None];
+    for (val, meta) in args.zip(&mut metas) {
+        let label = val.label().map(Into::into);
+        let axes = val.axes().map(<[_]>::to_vec);
+        let unit = val.unit().cloned();
+        if label.is_some() || axes.is_some() || unit.is_some() {
+            *meta = Some(ArgMeta {
+                label,
+                axes,
+                unit,
+                shape: val.shape().to_vec(),
+            });
+        }
+    }
+    metas.iter().any(Option::is_some).then_some(metas)
+}
+
+/// Check that the arguments of a pervasive primitive agree on the names of their axes and on their units
+fn check_meta(prim: Primitive, metas: &ArgMetas) -> Result<(), String> {
+    if !is_pervasive(prim) {
+        return Ok(());
+    }
+    output_unit(prim, metas)?;
+    let [Some(ArgMeta { axes: Some(a), .. }), Some(ArgMeta { axes: Some(b), .. })] = metas else {
+        return Ok(());
+    };
+    for (i, (a, b)) in a.iter().zip(b).enumerate() {
+        if let (Some(a), Some(b)) = (a, b) {
+            if a != b {
+                return Err(format!(
+                    "Axis {i} is named `{a}` in one array but `{b}` in the other"
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_pervasive(prim: Primitive) -> bool {
+    matches!(
+        prim.class(),
+        PrimClass::MonadicPervasive | PrimClass::DyadicPervasive
+    )
+}
+
+/// Get the unit of the output of a pervasive primitive from the units of its arguments
+///
+/// Arguments without units can be used with arguments that have them.
+fn output_unit(prim: Primitive, metas: &ArgMetas) -> Result<Option<Unit>, String> {
+    use Primitive::*;
+    let unit = |i: usize| metas[i].as_ref().and_then(|meta| meta.unit.as_ref());
+    let (a, b) = (unit(0), unit(1));
+    Ok(match prim {
+        Neg | Abs | Floor | Ceil | Round => a.cloned(),
+        Not | Sign => None,
+        Sqrt => match a {
+            Some(a) => Some(a.root(2).ok_or_else(|| {
+                format!("Cannot take the square root of a value with unit `{a}`")
+            })?),
+            None => None,
+        },
+        Add | Sub | Mod | Min | Max | Eq | Ne | Lt | Le | Gt | Ge | Atan => {
+            if let (Some(a), Some(b)) = (a, b) {
+                if a != b {
+                    return Err(format!("Units `{a}` and `{b}` do not match"));
+                }
+            }
+            matches!(prim, Add | Sub | Mod | Min | Max)
+                .then(|| a.or(b).cloned())
+                .flatten()
+        }
+        Mul => match (a, b) {
+            (Some(a), Some(b)) => Some(a * b),
+            (a, b) => a.or(b).cloned(),
+        },
+        // The first argument is the divisor
+        Div => match (a, b) {
+            (Some(a), Some(b)) => Some(b / a),
+            (Some(a), None) => Some(&crate::Unit::default() / a),
+            (None, b) => b.cloned(),
+        },
+        _ if a.is_some() || b.is_some() => {
+            return Err(format!("{prim} cannot be used with values that have units"))
+        }
+        _ => None,
+    })
+}
+
+/// Give the output of a pervasive primitive the label of its first labeled argument,
+/// the names of its arguments' axes, and the unit that follows from its arguments' units
+fn meta_output(prim: Primitive, metas: ArgMetas, val: &mut Value) {
+    if !is_pervasive(prim) {
+        return;
+    }
+    val.set_unit(output_unit(prim, &metas).ok().flatten());
+    let [a, b] = metas;
+    let axis = |meta: &Option<ArgMeta>, i: usize| {
+        let axes = meta.as_ref()?.axes.as_ref()?;
+        axes.get(i).cloned().flatten()
+    };
+    if val.axes().is_none() {
+        let axes: Vec<Option<String>> = (0..val.rank())
+            .map(|i| axis(&a, i).or_else(|| axis(&b, i)))
+            .collect();
+        val.set_axes(axes);
+    }
+    if val.label().is_none() {
+        let label = |meta: Option<ArgMeta>| meta?.label;
+        val.set_label(label(a).or_else(|| label(b)));
+    }
+}
+
+/// Put the labels of the arguments of a primitive in a shape mismatch error
+fn label_error(metas: ArgMetas, error: UiuaError) -> UiuaError {
+    let mut e = match error {
+        UiuaError::ShapeMismatch(e) => e,
+        UiuaError::Fill(error) => return label_error(metas, *error).fill(),
+        error => return error,
+    };
+    let label = |arg: Option<ArgMeta>, shape: &[usize]| arg.filter(|arg| arg.shape == shape)?.label;
+    let [a, b] = metas;
+    e.a_label = label(a, &e.a);
+    e.b_label = label(b, &e.b);
+    UiuaError::ShapeMismatch(e)
+}
+
+/// A definition of or reference to a global binding
+#[derive(Debug, Clone)]
+pub(crate) struct BindingRef {
+    pub index: usize,
+    pub name: Ident,
+    pub span: CodeSpan,
+    pub definition: bool,
+    /// Whether the binding is in the outermost scope
+    pub top_level: bool,
+}
+
 #[derive(Default, Clone)]
 struct Fills {
     nums: Vec<f64>,
@@ -124,6 +571,14 @@ struct Fills {
     boxes: Vec<Boxed>,
 }
 
+/// The instruction before a dyadic pervasive primitive in a superinstruction
+enum Fused {
+    /// A constant, which is the primitive's first argument
+    Push(Value),
+    /// [`duplicate`](Primitive::Dup) or [`flip`](Primitive::Flip)
+    Stack(Primitive, usize),
+}
+
 #[derive(Clone)]
 struct StackFrame {
     /// The function being executed
@@ -133,7 +588,11 @@ struct StackFrame {
     /// The program counter for the function
     pc: usize,
     /// Additional spans for error reporting
-    spans: Vec<(usize, Option<Primitive>)>,
+    ///
+    /// These are kept inline so that calling a function does not allocate.
+    spans: TinyVec<[(usize, Option<Primitive>); 4]>,
+    /// The values of the function's local bindings, if it has any
+    locals: Option<Vec<Value>>,
 }
 
 #[derive(Debug, Clone)]
@@ -200,17 +659,107 @@ impl FromStr for RunMode {
     }
 }
 
+/// What to do when a pervasive function divides by zero or produces infinity or NaN
+///
+/// Results that are infinity or NaN because an argument is are always allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum NumPolicy {
+    /// Produce the IEEE 754 result
+    #[default]
+    Allow,
+    /// Produce the IEEE 754 result, but emit a warning
+    Warn,
+    /// Error at the function's span
+    Error,
+}
+
+impl FromStr for NumPolicy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(NumPolicy::Allow),
+            "warn" => Ok(NumPolicy::Warn),
+            "error" => Ok(NumPolicy::Error),
+            _ => Err(format!("unknown numeric policy `{}`", s)),
+        }
+    }
+}
+
+/// Which special numbers a value has
+#[derive(Default)]
+struct NumFlags {
+    nan: bool,
+    inf: bool,
+    zero: bool,
+}
+
+impl NumFlags {
+    fn of(val: &Value) -> Self {
+        let mut flags = NumFlags::default();
+        match val {
+            Value::Num(arr) => {
+                for &n in arr.data.iter() {
+                    flags.nan |= n.is_nan();
+                    flags.inf |= n.is_infinite();
+                    flags.zero |= n == 0.0;
+                }
+            }
+            Value::Byte(arr) => flags.zero = arr.data.contains(&0),
+            Value::Char(_) | Value::Box(_) => {}
+        }
+        flags
+    }
+}
+
+/// Something output by a cell run with [`Uiua::run_cell`] or while output is captured with [`Uiua::capture_output`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CellOutput {
+    /// Text printed to stdout
+    Stdout(String),
+    /// Text printed to stderr
+    Stderr(String),
+    /// Text printed by [`trace`](Primitive::Trace) and [`dump`](Primitive::Dump)
+    Trace(String),
+    /// A PNG-encoded image
+    Image(Vec<u8>),
+    /// A GIF
+    Gif(Vec<u8>),
+    /// WAV-encoded audio
+    Audio(Vec<u8>),
+}
+
+/// The result of running a cell with [`Uiua::run_cell`]
+#[derive(Debug, Clone)]
+pub struct CellResult {
+    /// The id the cell was run with
+    pub id: String,
+    /// Everything the cell output, in order
+    pub output: Vec<CellOutput>,
+    /// The values the cell took off of the stack, bottom first
+    pub popped: Vec<Value>,
+    /// The values the cell left on the stack, bottom first
+    pub pushed: Vec<Value>,
+    /// The diagnostics emitted while running the cell
+    pub diagnostics: BTreeSet<Diagnostic>,
+    /// The error the cell failed with
+    ///
+    /// A cell that fails has no effect on the stack or on bindings.
+    pub error: Option<UiuaError>,
+}
+
 impl Uiua {
     /// Create a new Uiua runtime with the standard IO backend
     pub fn with_native_sys() -> Self {
         let mut scope = Scope::default();
         let mut globals = Vec::new();
+        let mut binding_docs = HashMap::new();
         for def in &*CONSTANTS {
             scope.names.insert(def.name.into(), globals.len());
+            binding_docs.insert(globals.len(), def.doc.trim().into());
             globals.push(Global::Val(def.value.clone()));
         }
         Uiua {
-            spans: Arc::new(Mutex::new(vec![Span::Builtin])),
+            spans: Arc::new(Mutex::new(SpanTable::default())),
             stack: Vec::new(),
             function_stack: Vec::new(),
             inline_stack: Vec::new(),
@@ -219,14 +768,40 @@ impl Uiua {
             scope,
             higher_scopes: Vec::new(),
             globals: Arc::new(Mutex::new(globals)),
+            binding_docs: Arc::new(Mutex::new(binding_docs)),
             new_functions: Vec::new(),
+            local_names: Vec::new(),
             current_imports: Arc::new(Mutex::new(HashSet::new())),
             imports: Arc::new(Mutex::new(HashMap::new())),
+            pure_load: None,
+            memo: Arc::new(Mutex::new(MemoCache::new())),
+            memo_file: None,
+            output_capture: None,
             mode: RunMode::Normal,
             diagnostics: BTreeSet::new(),
             backend: Arc::new(NativeSys),
+            ext_ops: HashMap::new(),
             print_diagnostics: false,
             time_instrs: false,
+            coverage: None,
+            lints: false,
+            shape_checks: false,
+            stack_checks: false,
+            leak_checks: false,
+            num_policy: NumPolicy::Allow,
+            tolerance: 0.0,
+            trace_limit: 64,
+            compile_only: false,
+            language_version: None,
+            experimental: false,
+            rng: None,
+            proxy_depth: 0,
+            current_binding: None,
+            macro_depth: 0,
+            running_macro: None,
+            var_annotations: HashMap::new(),
+            annotated_fns: HashMap::new(),
+            binding_refs: None,
             last_time: 0.0,
             cli_arguments: Vec::new(),
             cli_file_path: PathBuf::new(),
@@ -256,11 +831,151 @@ impl Uiua {
         self.time_instrs = time_instrs;
         self
     }
+    /// Set whether runs are reproducible
+    ///
+    /// In deterministic mode, [`random`](crate::Primitive::Rand) gives the same numbers every run,
+    /// and [`now`](crate::Primitive::Now) and [`clock`](crate::Primitive::Clock) always give `0`.
+    ///
+    /// Default is `false`
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.rng = deterministic.then(|| SmallRng::seed_from_u64(0));
+        self
+    }
+    /// Get a random number between 0 and 1
+    pub(crate) fn random(&mut self) -> f64 {
+        thread_local! {
+            static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::seed_from_u64(instant::now().to_bits()));
+        }
+        match &mut self.rng {
+            Some(rng) => rng.gen(),
+            None => RNG.with(|rng| rng.borrow_mut().gen()),
+        }
+    }
+    /// Get the current time in seconds since the Unix epoch
+    pub(crate) fn now(&self) -> f64 {
+        if self.rng.is_some() {
+            0.0
+        } else {
+            self.backend.now()
+        }
+    }
+    /// Get the time of the high-resolution clock
+    ///
+    /// This is always `0` in deterministic mode.
+    pub(crate) fn clock(&self) -> f64 {
+        if self.rng.is_some() {
+            0.0
+        } else {
+            self.backend.clock()
+        }
+    }
+    /// Warn about bindings that are never used, bindings that shadow earlier ones,
+    /// and code that can never run
+    ///
+    /// Default is `false`
+    pub fn with_lints(mut self, lints: bool) -> Self {
+        self.lints = lints;
+        self
+    }
+    /// Check the shapes of arrays at compile time
+    ///
+    /// Shapes are followed through the primitives whose effects on shape are known,
+    /// starting from constants and the [annotations](crate::ast::Annotation) of bindings.
+    /// Arrays whose shapes are certain not to fit are reported as diagnostics.
+    ///
+    /// Default is `false`
+    pub fn with_shape_checks(mut self, shape_checks: bool) -> Self {
+        self.shape_checks = shape_checks;
+        self
+    }
+    /// Check the stack effects of functions at runtime
+    ///
+    /// Every function call always checks that the stack height changed by as much as the function's signature says.
+    /// With these checks, a call also errors if the function took more values from the stack than its signature says,
+    /// even if it put enough back. This makes calls slower, so it is meant for debugging.
+    pub fn with_stack_checks(mut self, stack_checks: bool) -> Self {
+        self.stack_checks = stack_checks;
+        self
+    }
+    /// Warn about top-level lines of code that leave more than one value on the stack,
+    /// or that take values left by earlier lines
+    ///
+    /// These are often caused by a missing call or an extra [`duplicate`](Primitive::Dup).
+    /// Bindings are not checked.
+    ///
+    /// Default is `false`
+    pub fn with_leak_checks(mut self, leak_checks: bool) -> Self {
+        self.leak_checks = leak_checks;
+        self
+    }
+    /// Set what to do when a pervasive function divides by zero or produces infinity or NaN
+    ///
+    /// Unless the policy is [`NumPolicy::Allow`], functions are never run as native code.
+    ///
+    /// Default is [`NumPolicy::Allow`]
+    pub fn with_num_policy(mut self, num_policy: NumPolicy) -> Self {
+        self.num_policy = num_policy;
+        self
+    }
+    /// Set how far apart numbers can be and still be considered equal
+    ///
+    /// This affects comparisons, [`match`](Primitive::Match), [`classify`](Primitive::Classify),
+    /// and [`deduplicate`](Primitive::Deduplicate). Code can change it with [`tolerance`](Primitive::Tolerance).
+    ///
+    /// Default is `0`
+    pub fn with_comparison_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+    /// Set the most frames kept in the trace of an error
+    ///
+    /// When an error passes through more function calls than this, the innermost and outermost frames are kept,
+    /// and the trace says how many were left out between them.
+    ///
+    /// Default is `64`
+    pub fn with_trace_limit(mut self, trace_limit: usize) -> Self {
+        self.trace_limit = trace_limit;
+        self
+    }
+    /// Add an operation that code can call with [`&ext`](crate::SysOp::Ext)
+    ///
+    /// The operation's argument is on top of the stack when it is called.
+    /// It should pop it and push its result.
+    /// Operations added this way are found before those of the backend.
+    pub fn with_ext(
+        mut self,
+        name: impl Into<Ident>,
+        op: impl Fn(&mut Uiua) -> UiuaResult + Send + Sync + 'static,
+    ) -> Self {
+        self.ext_ops.insert(name.into(), Arc::new(op));
+        self
+    }
+    /// Record output in a buffer instead of sending it to the backend
+    ///
+    /// Printed text, traces, and media like images and audio are all captured.
+    /// The output can be inspected with [`Uiua::captured_output`] and [`Uiua::captured_stdout`].
+    pub fn capture_output(mut self) -> Self {
+        self.output_capture = Some(Arc::new(Mutex::new(Vec::new())));
+        self
+    }
+    /// Set whether experimental primitives can be used
+    ///
+    /// Files can also enable them with an `# Experimental!` comment before their code.
+    ///
+    /// Default is `false`
+    pub fn with_experimental(mut self, experimental: bool) -> Self {
+        self.experimental = experimental;
+        self
+    }
     /// Limit the execution duration
     pub fn with_execution_limit(mut self, limit: Duration) -> Self {
         self.execution_limit = Some(limit.as_millis() as f64);
         self
     }
+    /// Get the time at which the execution limit will be exceeded, if there is one
+    pub(crate) fn deadline(&self) -> Option<f64> {
+        (self.execution_limit).map(|limit| self.execution_start + limit)
+    }
     /// Set the [`RunMode`]
     ///
     /// Default is [`RunMode::Normal`]
@@ -268,6 +983,26 @@ impl Uiua {
         self.mode = mode;
         self
     }
+    /// Persist the results of [`memo`](Primitive::Memo)ized functions in a file
+    ///
+    /// Results saved by a previous run with [`Uiua::save_memo_file`] are reused
+    /// if the function and its arguments are unchanged.
+    /// The values of bindings are persisted too, and a binding is only re-evaluated
+    /// if its code or the bindings it depends on change.
+    /// Code that has side effects or reads mutable bindings is never persisted.
+    pub fn with_memo_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.memo_file = Some(Arc::new(Mutex::new(MemoFile::load(path.into()))));
+        self
+    }
+    /// Save the results of [`memo`](Primitive::Memo)ized functions to the file set with [`Uiua::with_memo_file`]
+    ///
+    /// Only results that were used during this run are saved.
+    pub fn save_memo_file(&self) -> std::io::Result<()> {
+        match &self.memo_file {
+            Some(memo_file) => memo_file.lock().save(),
+            None => Ok(()),
+        }
+    }
     /// Get the [`RunMode`]
     pub fn mode(&self) -> RunMode {
         self.mode
@@ -304,6 +1039,148 @@ impl Uiua {
     pub fn load_str_path<P: AsRef<Path>>(&mut self, input: &str, path: P) -> UiuaResult {
         self.load_impl(input, Some(path.as_ref()))
     }
+    /// Check a Uiua file from a path without running it
+    ///
+    /// See [`Uiua::check_str`]
+    pub fn check_file<P: AsRef<Path>>(&mut self, path: P) -> UiuaResult {
+        let path = path.as_ref();
+        let input = fs::read_to_string(path).map_err(|e| UiuaError::Load(path.into(), e.into()))?;
+        self.check_impl(&input, Some(path))
+    }
+    /// Check a Uiua file from a string without running it
+    ///
+    /// Every item, including those in test blocks, is parsed and compiled.
+    /// Identifiers are resolved and function signatures are checked,
+    /// but no code is executed, not even to compute the values of bindings.
+    /// The exception is code macros, which must run to generate the code they are replaced with.
+    /// They cannot do IO.
+    /// Imported files are not loaded.
+    ///
+    /// Lints are reported as diagnostics if enabled with [`Uiua::with_lints`].
+    pub fn check_str(&mut self, input: &str) -> UiuaResult {
+        self.check_impl(input, None)
+    }
+    pub(crate) fn check_impl(&mut self, input: &str, path: Option<&Path>) -> UiuaResult {
+        let prev = replace(&mut self.compile_only, true);
+        let res = self.load_impl(input, path);
+        self.compile_only = prev;
+        res
+    }
+    /// Run a cell of code against this runtime
+    ///
+    /// Cells share the stack and bindings, but each cell's output is captured separately
+    /// instead of being sent to the backend.
+    /// The id is used as the path in error messages.
+    pub fn run_cell(&mut self, id: impl Into<String>, source: &str) -> CellResult {
+        let id = id.into();
+        let stack = self.stack.clone();
+        let scope = self.scope.clone();
+        let higher_scopes = self.higher_scopes.clone();
+        let capture = Arc::new(Mutex::new(Vec::new()));
+        let prev_capture = self.output_capture.replace(capture.clone());
+        let res = self.load_str_path(source, &id);
+        self.output_capture = prev_capture;
+        let output = take(&mut *capture.lock());
+        let diagnostics = self.take_diagnostics();
+        if let Err(error) = res {
+            self.stack = stack;
+            self.scope = scope;
+            self.higher_scopes = higher_scopes;
+            self.function_stack.clear();
+            self.inline_stack.clear();
+            self.under_stack.clear();
+            self.temp_function_stack.clear();
+            return CellResult {
+                id,
+                output,
+                popped: Vec::new(),
+                pushed: Vec::new(),
+                diagnostics,
+                error: Some(error),
+            };
+        }
+        let common = (stack.iter().zip(&self.stack))
+            .take_while(|(a, b)| a == b)
+            .count();
+        CellResult {
+            id,
+            output,
+            popped: stack[common..].to_vec(),
+            pushed: self.stack[common..].to_vec(),
+            diagnostics,
+            error: None,
+        }
+    }
+    /// Get everything output since output capture was enabled with [`Uiua::capture_output`]
+    pub fn captured_output(&self) -> Vec<CellOutput> {
+        (self.output_capture.as_ref())
+            .map(|capture| capture.lock().clone())
+            .unwrap_or_default()
+    }
+    /// Take everything output since output capture was enabled or the output was last taken
+    pub fn take_captured_output(&mut self) -> Vec<CellOutput> {
+        (self.output_capture.as_ref())
+            .map(|capture| take(&mut *capture.lock()))
+            .unwrap_or_default()
+    }
+    /// Get the text printed to stdout since output capture was enabled with [`Uiua::capture_output`]
+    pub fn captured_stdout(&self) -> String {
+        (self.captured_output().into_iter())
+            .filter_map(|output| match output {
+                CellOutput::Stdout(text) => Some(text),
+                _ => None,
+            })
+            .collect()
+    }
+    /// Capture some output if output is being captured
+    ///
+    /// Returns `false` if the output should be sent to the backend instead.
+    pub(crate) fn capture(&self, output: CellOutput) -> bool {
+        let Some(capture) = &self.output_capture else {
+            return false;
+        };
+        let mut capture = capture.lock();
+        match (capture.last_mut(), output) {
+            (Some(CellOutput::Stdout(a)), CellOutput::Stdout(b))
+            | (Some(CellOutput::Stderr(a)), CellOutput::Stderr(b))
+            | (Some(CellOutput::Trace(a)), CellOutput::Trace(b)) => a.push_str(&b),
+            (_, output) => capture.push(output),
+        }
+        true
+    }
+    pub(crate) fn output_capturing(&self) -> bool {
+        self.output_capture.is_some()
+    }
+    pub(crate) fn print_str_stdout(&self, s: &str) -> UiuaResult {
+        if !self.capture(CellOutput::Stdout(s.into())) {
+            self.backend
+                .print_str_stdout(s)
+                .map_err(|e| self.error(e))?;
+        }
+        Ok(())
+    }
+    /// Write any output that the backend has buffered for stdout
+    pub(crate) fn flush_stdout(&self) -> UiuaResult {
+        if !self.output_capturing() {
+            self.backend.flush_stdout().map_err(|e| self.error(e))?;
+        }
+        Ok(())
+    }
+    pub(crate) fn print_str_stderr(&self, s: &str) -> UiuaResult {
+        if !self.capture(CellOutput::Stderr(s.into())) {
+            self.flush_stdout()?;
+            self.backend
+                .print_str_stderr(s)
+                .map_err(|e| self.error(e))?;
+        }
+        Ok(())
+    }
+    pub(crate) fn print_str_trace(&self, s: &str) {
+        if !self.capture(CellOutput::Trace(s.into())) {
+            _ = self.backend.flush_stdout();
+            self.backend.print_str_trace(s);
+        }
+    }
     /// Run in a scoped context. Names defined in this context will be removed when the scope ends.
     ///
     /// While names defined in this context will be removed when the scope ends, values *bound* to
@@ -316,8 +1193,12 @@ impl Uiua {
     ) -> UiuaResult<HashMap<Ident, usize>> {
         self.higher_scopes.push(take(&mut self.scope));
         let start_height = self.stack.len();
-        f(self)?;
-        let scope = replace(&mut self.scope, self.higher_scopes.pop().unwrap());
+        let res = f(self);
+        let Some(higher_scope) = self.higher_scopes.pop() else {
+            return Err(self.bug("a scope was exited more than once"));
+        };
+        let scope = replace(&mut self.scope, higher_scope);
+        res?;
         let mut names = HashMap::new();
         for (name, idx) in scope.names {
             if idx >= CONSTANTS.len() {
@@ -329,24 +1210,19 @@ impl Uiua {
     }
     fn load_impl(&mut self, input: &str, path: Option<&Path>) -> UiuaResult {
         self.execution_start = instant::now();
-        let (items, errors, diagnostics) = parse(input, path);
-        if self.print_diagnostics {
-            for diagnostic in diagnostics {
-                println!("{}", diagnostic.report());
-            }
-        } else {
-            self.diagnostics.extend(diagnostics);
-        }
-        if !errors.is_empty() {
-            return Err(errors.into());
-        }
+        let prev_version = self.language_version;
+        let prev_experimental = self.experimental;
+        let depths = self.depths();
         if let Some(path) = path {
             self.current_imports.lock().insert(path.into());
         }
-        let res = match catch_unwind(AssertUnwindSafe(|| self.items(items, false))) {
+        // A bug in the interpreter should not take down the process it is embedded in
+        let res = match catch_unwind(AssertUnwindSafe(|| self.parse_and_run(input, path))) {
             Ok(res) => res,
-            Err(_) => Err(self.error(format!(
-                "\
+            Err(_) => {
+                self.restore_depths(depths);
+                Err(self.error(format!(
+                    "\
 The interpreter has crashed!
 Hooray! You found a bug!
 Please report this at http://github.com/uiua-lang/uiua/issues/new
@@ -354,14 +1230,82 @@ Please report this at http://github.com/uiua-lang/uiua/issues/new
 code:
 {}
 {}",
-                self.span(),
-                input
-            ))),
+                    self.span(),
+                    input
+                )))
+            }
         };
         if let Some(path) = path {
             self.current_imports.lock().remove(path);
         }
-        res
+        self.language_version = prev_version;
+        self.experimental = prev_experimental;
+        // Everything printed should be written before the caller prints anything else
+        let flushed = self.flush_stdout();
+        res.and(flushed)
+    }
+    fn parse_and_run(&mut self, input: &str, path: Option<&Path>) -> UiuaResult {
+        let (items, errors, diagnostics) = parse(input, path);
+        if self.print_diagnostics {
+            _ = self.backend.flush_stdout();
+            for diagnostic in diagnostics {
+                eprintln!("{}", diagnostic.report());
+            }
+        } else {
+            self.diagnostics.extend(diagnostics);
+        }
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+        self.language_version = version::declared(&items)?;
+        self.experimental = self.experimental || version::declares_experimental(&items);
+        self.items(items, false)?;
+        self.flush_diagnostics();
+        // Imported files are checked by the scope they are imported in
+        if self.higher_scopes.is_empty() {
+            self.warn_unused_bindings();
+        }
+        Ok(())
+    }
+    /// The heights of the runtime's internal stacks
+    fn depths(&self) -> [usize; 6] {
+        [
+            self.higher_scopes.len(),
+            self.scope.call.len(),
+            self.scope.array.len(),
+            self.scope.floors.len(),
+            self.function_stack.len(),
+            self.inline_stack.len(),
+        ]
+    }
+    /// Unwind the runtime's internal stacks to heights from [`Uiua::depths`]
+    ///
+    /// This leaves the runtime usable after a panic
+    fn restore_depths(
+        &mut self,
+        [higher_scopes, call, array, floors, functions, inline]: [usize; 6],
+    ) {
+        while self.higher_scopes.len() > higher_scopes {
+            if let Some(scope) = self.higher_scopes.pop() {
+                self.scope = scope;
+            }
+        }
+        self.scope.call.truncate(call);
+        self.scope.array.truncate(array);
+        self.scope.floors.truncate(floors);
+        self.function_stack.truncate(functions);
+        self.inline_stack.truncate(inline);
+    }
+    /// Get the index of the call frame whose locals a local binding instruction uses
+    ///
+    /// Locals of the code around a function are bound to a frame when the function is pushed.
+    /// Otherwise, they are the locals of the current frame.
+    fn local_frame(&self, up: u32, frame: Option<u32>) -> Option<usize> {
+        match frame {
+            Some(frame) => Some(frame as usize).filter(|&frame| frame < self.scope.call.len()),
+            None if up == 0 => self.scope.call.len().checked_sub(1),
+            None => None,
+        }
     }
     fn trace_error(&self, mut error: UiuaError, frame: StackFrame) -> UiuaError {
         let mut frames = Vec::new();
@@ -379,15 +1323,43 @@ code:
         });
         if let UiuaError::Traced { trace, .. } = &mut error {
             trace.extend(frames);
-            error
         } else {
-            UiuaError::Traced {
+            error = UiuaError::Traced {
                 error: error.into(),
                 trace: frames,
+                elided: 0,
+            };
+        }
+        // Frames are left out of the middle so the innermost and outermost ones are kept
+        if let UiuaError::Traced { trace, elided, .. } = &mut error {
+            if trace.len() > self.trace_limit {
+                let head = self.trace_limit / 2;
+                let excess = trace.len() - self.trace_limit;
+                trace.drain(head..head + excess);
+                *elided += excess;
             }
         }
+        error
+    }
+    /// Compile and run code in the current scope
+    pub(crate) fn eval(&mut self, input: &str) -> UiuaResult {
+        let (items, errors, diagnostics) = parse(input, None);
+        self.diagnostics.extend(diagnostics);
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+        let mode = replace(&mut self.mode, RunMode::Normal);
+        let res = self.items(items, false);
+        self.mode = mode;
+        res
     }
-    pub(crate) fn import(&mut self, input: &str, path: &Path, item: &str) -> UiuaResult {
+    pub(crate) fn import(
+        &mut self,
+        input: &str,
+        path: &Path,
+        item: &str,
+        pure: bool,
+    ) -> UiuaResult {
         if self.current_imports.lock().contains(path) {
             return Err(self.error(format!(
                 "Cycle detected importing {}",
@@ -395,29 +1367,57 @@ code:
             )));
         }
         if !self.imports.lock().contains_key(path) {
-            let import = self.in_scope(|env| env.load_str_path(input, path).map(drop))?;
-            self.imports.lock().insert(path.into(), import);
+            // The file's code runs as it is loaded, so it is pure if it is being imported as pure
+            // or is imported by a file that is
+            let pure_load = if pure && self.pure_load.is_none() {
+                self.pure_load.replace(path.into())
+            } else {
+                self.pure_load.clone()
+            };
+            let import = self.in_scope(|env| env.load_str_path(input, path).map(drop));
+            self.pure_load = pure_load;
+            self.imports.lock().insert(path.into(), import?);
         }
         let imports_gaurd = self.imports.lock();
         let imports = &imports_gaurd[path];
-        let idx = imports.get(item).ok_or_else(|| {
+        let idx = (Ident::get(item).and_then(|item| imports.get(&item))).ok_or_else(|| {
             self.error(format!("Item `{}` not found in {}", item, path.display()))
         })?;
         let global = self.globals.lock()[*idx].clone();
         drop(imports_gaurd);
         match global {
-            Global::Val(val) => self.push(val),
+            Global::Val(val) | Global::Var(val) => self.push(val),
+            // Purity belongs to this import rather than to the file,
+            // so the same function imported with `&i` elsewhere can still do IO
+            Global::Func(f) if pure => {
+                let mut f = Function::into_inner(f);
+                f.pure_import = Some(path.into());
+                self.function_stack.push(f.into())
+            }
             Global::Func(f) => self.function_stack.push(f),
+            Global::Macro(_) => {
+                return Err(self.error(format!("Code macro `{item}` cannot be imported")))
+            }
         }
         Ok(())
     }
+    /// Find a file imported as pure whose code is running
+    ///
+    /// This is either a file being loaded by [`import pure`](crate::SysOp::ImportPure)
+    /// or the file of a function imported that way that is on the call stack.
+    pub(crate) fn pure_import_on_stack(&self) -> Option<Arc<Path>> {
+        self.pure_load.clone().or_else(|| {
+            (self.scope.call.iter()).find_map(|frame| frame.function.pure_import.clone())
+        })
+    }
     pub(crate) fn exec_global_instrs(&mut self, instrs: Vec<Instr>) -> UiuaResult {
         let func = Function::new(FunctionId::Main, instrs, Signature::new(0, 0));
         self.exec(StackFrame {
             function: Arc::new(func),
             call_span: 0,
-            spans: Vec::new(),
+            spans: TinyVec::new(),
             pc: 0,
+            locals: None,
         })?;
         Ok(())
     }
@@ -425,9 +1425,13 @@ code:
         self.scope.call.push(frame);
         let mut formatted_instr = String::new();
         Ok(loop {
-            let frame = self.scope.call.last().unwrap();
+            let Some(frame) = self.scope.call.last() else {
+                return Err(self.bug("a function has no call frame"));
+            };
             let Some(instr) = frame.function.instrs.get(frame.pc) else {
-                break self.scope.call.pop().unwrap().function;
+                let function = frame.function.clone();
+                self.scope.call.pop();
+                break function;
             };
             // Uncomment to debug
             // if !self.scope.array.is_empty() {
@@ -451,9 +1455,33 @@ code:
                 self.last_time = instant::now();
             }
             let res = match instr {
+                // Superinstructions
+                &Instr::Prim(prim @ (Primitive::Dup | Primitive::Flip), span)
+                    if self.next_pervasive_dyadic().is_some() =>
+                {
+                    self.run_fused(Fused::Stack(prim, span))
+                }
+                &Instr::PushConst(index) if self.next_pervasive_dyadic().is_some() => {
+                    let mut val = frame.function.constants[index].clone();
+                    if self.pack_boxes() {
+                        val.unpack();
+                    }
+                    self.run_fused(Fused::Push(val))
+                }
                 &Instr::Prim(prim, span) => {
                     self.push_span(span, Some(prim));
-                    let res = prim.run(self);
+                    let num_args = self.num_arg_flags(prim);
+                    let res = match self.arg_metas(prim) {
+                        Some(metas) => self.run_with_metas(prim, metas),
+                        None => prim.run(self),
+                    };
+                    let res = match (res, num_args) {
+                        (Ok(()), Some(args)) => {
+                            let output = self.stack.last().map(NumFlags::of).unwrap_or_default();
+                            self.check_num_output(prim, &args, output)
+                        }
+                        (res, _) => res,
+                    };
                     self.pop_span();
                     res
                 }
@@ -463,6 +1491,10 @@ code:
                     self.pop_span();
                     res
                 }
+                &Instr::PushConst(index) => {
+                    self.stack.push(frame.function.constants[index].clone());
+                    Ok(())
+                }
                 Instr::Push(val) => {
                     self.stack.push(Value::clone(val));
                     Ok(())
@@ -471,29 +1503,55 @@ code:
                     self.scope.array.push(self.stack.len());
                     Ok(())
                 }
-                &Instr::EndArray { span, boxed } => (|| {
-                    let start = self.scope.array.pop().unwrap();
+                &Instr::EndArray { span, boxed, .. } => (|| {
+                    let Some(start) = self.scope.array.pop() else {
+                        return Err(self.bug("an array was ended without being started"));
+                    };
                     self.push_span(span, None);
-                    let values = self.stack.drain(start..).rev();
+                    let values: Vec<Value> = self.stack.drain(start..).rev().collect();
                     let values: Vec<Value> = if boxed {
-                        values.map(Boxed).map(Value::from).collect()
+                        values
+                            .into_iter()
+                            .map(|val| Boxed::checked(val, self).map(Value::from))
+                            .collect::<UiuaResult<_>>()?
                     } else {
-                        values.collect()
+                        values
                     };
                     let val = if values.is_empty() && boxed {
                         Array::<Boxed>::default().into()
                     } else {
-                        Value::from_row_values(values, self)?
+                        let rows = values.len();
+                        self.array_from_rows(values, |i| {
+                            // The row spans are only looked up if the array cannot be built
+                            let frame = self.scope.call.last()?;
+                            let Some(Instr::EndArray {
+                                row_spans: Some(row_spans),
+                                ..
+                            }) = frame.function.instrs.get(frame.pc)
+                            else {
+                                return None;
+                            };
+                            (row_spans.len() == rows)
+                                .then(|| self.spans.lock()[row_spans[i]].clone())
+                        })?
                     };
                     self.pop_span();
                     self.push(val);
                     Ok(())
                 })(),
-                &Instr::Call(span) => self
-                    .pop_function()
-                    .and_then(|f| self.call_with_span(f, span)),
+                &Instr::Call(span) => {
+                    self.cover(span);
+                    self.pop_function()
+                        .and_then(|f| self.call_with_span(f, span))
+                }
                 Instr::PushFunc(f) => {
-                    self.function_stack.push(f.clone());
+                    let f = match self.scope.call.len().checked_sub(1) {
+                        Some(frame) if f.captures && self.scope.call[frame].locals.is_some() => {
+                            f.capture(frame as u32).into()
+                        }
+                        _ => f.clone(),
+                    };
+                    self.function_stack.push(f);
                     Ok(())
                 }
                 &Instr::Switch { count, span } => {
@@ -501,7 +1559,7 @@ code:
                     let res = (|| {
                         let i = self
                             .pop("switch index")?
-                            .as_nat(self, "Switch index mut be a natural number")?;
+                            .as_nat(self, "Switch index must be a natural number")?;
                         if i >= count {
                             return Err(self.error(format!(
                                 "Switch index {i} is out of bounds for switch of size {count}"
@@ -512,6 +1570,11 @@ code:
                             .drain(self.function_stack.len() - count..)
                             .nth(i);
                         if let Some(f) = f {
+                            if let (Some(_), FunctionId::Anonymous(span)) = (&self.coverage, &f.id)
+                            {
+                                let span = self.add_span(span.clone());
+                                self.cover(span);
+                            }
                             self.call(f)
                         } else {
                             Err(self.error(
@@ -540,7 +1603,7 @@ code:
                     let res = (|| {
                         let f = self
                             .temp_function_stack
-                            .get(self.temp_function_stack.len() - 1 - offset)
+                            .get(self.temp_function_stack.len() - 1 - offset as usize)
                             .ok_or_else(|| {
                                 self.error(
                                     "Error getting placeholder function. \
@@ -560,6 +1623,7 @@ code:
                     self.pop_span();
                     res
                 }
+                // The function is cloned out of the frame so that it can call back into the interpreter
                 Instr::Dynamic(df) => df.f.clone()(self),
                 &Instr::PushTempUnder { count, span } => (|| {
                     self.push_span(span, None);
@@ -627,6 +1691,95 @@ code:
                     self.pop_span();
                     Ok(())
                 })(),
+                &Instr::GetGlobal { index, span } => (|| {
+                    self.push_span(span, None);
+                    let value = match &self.globals.lock()[index] {
+                        Global::Val(val) | Global::Var(val) => val.clone(),
+                        Global::Func(_) | Global::Macro(_) => {
+                            return Err(self.error(
+                                "Error getting mutable binding. \
+                                This is a bug in the interpreter.",
+                            ))
+                        }
+                    };
+                    self.push(value);
+                    self.pop_span();
+                    Ok(())
+                })(),
+                &Instr::SetGlobal { index, span } => (|| {
+                    self.push_span(span, None);
+                    let mut value = self.pop("value to bind")?;
+                    value.compress();
+                    self.globals.lock()[index] = Global::Var(value);
+                    self.pop_span();
+                    Ok(())
+                })(),
+                &Instr::Unpack { count, span } => (|| {
+                    self.push_span(span, None);
+                    let value = self.pop("value to destructure")?;
+                    if value.row_count() != count {
+                        return Err(self.error(format!(
+                            "Cannot destructure array with {} row{} into {count} name{}",
+                            value.row_count(),
+                            if value.row_count() == 1 { "" } else { "s" },
+                            if count == 1 { "" } else { "s" },
+                        )));
+                    }
+                    let rows: Vec<Value> = value.into_rows().collect();
+                    for row in rows.into_iter().rev() {
+                        self.push(row);
+                    }
+                    self.pop_span();
+                    Ok(())
+                })(),
+                &Instr::InitLocals { count } => match self.scope.call.last_mut() {
+                    Some(frame) => {
+                        frame.locals = Some(vec![Value::default(); count]);
+                        Ok(())
+                    }
+                    None => Err(self.bug("locals were initialized without a call frame")),
+                },
+                &Instr::SetLocal {
+                    up,
+                    index,
+                    frame,
+                    span,
+                } => (|| {
+                    self.push_span(span, None);
+                    let value = self.pop("value to bind")?;
+                    let slot = (self.local_frame(up, frame))
+                        .and_then(|frame| self.scope.call[frame].locals.as_mut())
+                        .and_then(|locals| locals.get_mut(index));
+                    let Some(slot) = slot else {
+                        return Err(self.error(
+                            "Error setting local binding. This is a bug in the interpreter.",
+                        ));
+                    };
+                    *slot = value;
+                    self.pop_span();
+                    Ok(())
+                })(),
+                &Instr::GetLocal {
+                    up,
+                    index,
+                    frame,
+                    span,
+                } => (|| {
+                    self.push_span(span, None);
+                    let value = (self.local_frame(up, frame))
+                        .and_then(|frame| self.scope.call[frame].locals.as_ref())
+                        .and_then(|locals| locals.get(index))
+                        .cloned()
+                        .ok_or_else(|| {
+                            self.error(
+                                "Error getting local binding. \
+                                This is a bug in the interpreter.",
+                            )
+                        })?;
+                    self.push(value);
+                    self.pop_span();
+                    Ok(())
+                })(),
             };
             if self.time_instrs {
                 let end_time = instant::now();
@@ -641,11 +1794,16 @@ code:
             }
             if let Err(err) = res {
                 // Trace errors
-                let frame = self.scope.call.pop().unwrap();
+                let Some(frame) = self.scope.call.pop() else {
+                    return Err(err);
+                };
                 return Err(self.trace_error(err, frame));
             } else {
                 // Go to next instruction
-                self.scope.call.last_mut().unwrap().pc += 1;
+                let Some(frame) = self.scope.call.last_mut() else {
+                    return Err(self.bug("a function's call frame was removed while it ran"));
+                };
+                frame.pc += 1;
                 if let Some(limit) = self.execution_limit {
                     if instant::now() - self.execution_start > limit {
                         return Err(UiuaError::Timeout(self.span()));
@@ -654,38 +1812,145 @@ code:
             }
         })
     }
+    /// Get the next instruction if it is a dyadic pervasive primitive
+    fn next_pervasive_dyadic(&self) -> Option<(Primitive, usize, PervasiveDyadic)> {
+        let frame = self.scope.call.last()?;
+        match frame.function.instrs.get(frame.pc + 1)? {
+            &Instr::Prim(prim, span) => Some((prim, span, prim.pervasive_dyadic()?)),
+            _ => None,
+        }
+    }
+    /// Run an instruction and the dyadic pervasive primitive after it as one superinstruction
+    ///
+    /// This skips the pushes and pops that would happen between the two instructions.
+    fn run_fused(&mut self, first: Fused) -> UiuaResult {
+        let Some((prim, span, f)) = self.next_pervasive_dyadic() else {
+            return Err(self.bug("a superinstruction was run without a pervasive primitive"));
+        };
+        if let Some(frame) = self.scope.call.last_mut() {
+            frame.pc += 1;
+        }
+        let args = match first {
+            Fused::Push(a) => Ok((a, None)),
+            Fused::Stack(first, first_span) => {
+                self.push_span(first_span, Some(first));
+                let args = self.pop(1).and_then(|a| {
+                    Ok(if first == Primitive::Dup {
+                        (a.clone(), Some(a))
+                    } else {
+                        (self.pop(2)?, Some(a))
+                    })
+                });
+                self.pop_span();
+                args
+            }
+        };
+        self.push_span(span, Some(prim));
+        let res = args.and_then(|(a, b)| {
+            let b = match b {
+                Some(b) => b,
+                None => self.pop(2)?,
+            };
+            let num_args =
+                (self.num_policy != NumPolicy::Allow).then(|| [NumFlags::of(&a), NumFlags::of(&b)]);
+            let metas = metas_of([&a, &b].into_iter());
+            if let Some(metas) = &metas {
+                check_meta(prim, metas).map_err(|e| self.error(e))?;
+            }
+            let output = match (f(a, b, self), metas) {
+                (Ok(val), None) => val,
+                (Ok(mut val), Some(metas)) => {
+                    meta_output(prim, metas, &mut val);
+                    val
+                }
+                (Err(e), Some(metas)) => return Err(label_error(metas, e)),
+                (Err(e), None) => return Err(e),
+            };
+            if let Some(args) = num_args {
+                self.check_num_output(prim, &args, NumFlags::of(&output))?;
+            }
+            Ok(output)
+        });
+        self.pop_span();
+        self.push(res?);
+        Ok(())
+    }
+    /// Track the span of the code being run
+    ///
+    /// Does nothing if no function is being called, like when a primitive is run directly.
     pub(crate) fn push_span(&mut self, span: usize, prim: Option<Primitive>) {
-        self.scope.call.last_mut().unwrap().spans.push((span, prim));
+        self.cover(span);
+        if let Some(frame) = self.scope.call.last_mut() {
+            frame.spans.push((span, prim));
+        }
     }
     pub(crate) fn pop_span(&mut self) {
-        self.scope.call.last_mut().unwrap().spans.pop();
+        if let Some(frame) = self.scope.call.last_mut() {
+            frame.spans.pop();
+        }
     }
     fn call_with_span(&mut self, f: impl Into<Arc<Function>>, call_span: usize) -> UiuaResult {
         let function = f.into();
         let sig = function.signature();
+        // Native code skips coverage, instruction timing, and stack checks, so those need the interpreter
+        #[cfg(feature = "jit")]
+        if self.num_policy == NumPolicy::Allow
+            && self.coverage.is_none()
+            && !self.time_instrs
+            && !self.stack_checks
+            && crate::jit::try_call(self, &function)?
+        {
+            return Ok(());
+        }
         let start_height = self.stack.len();
-        let function = self.exec(StackFrame {
+        if self.stack_checks {
+            self.scope.floors.push(start_height);
+        }
+        let res = self.exec(StackFrame {
             function,
             call_span,
-            spans: Vec::new(),
+            spans: TinyVec::new(),
             pc: 0,
-        })?;
+            locals: None,
+        });
+        let floor = if self.stack_checks {
+            self.scope.floors.pop()
+        } else {
+            None
+        };
+        let function = res?;
+        if function
+            .instrs
+            .iter()
+            .any(|instr| matches!(instr, Instr::Prim(Primitive::Sys(op), _) if op.is_import()))
+        {
+            return Ok(());
+        }
+        let id = &function.id;
         let height_diff = self.stack.len() as isize - start_height as isize;
         let sig_diff = sig.outputs as isize - sig.args as isize;
-        if height_diff != sig_diff
-            && !function
-                .instrs
-                .iter()
-                .any(|instr| matches!(instr, Instr::Prim(Primitive::Sys(SysOp::Import), _)))
-        {
+        if height_diff != sig_diff {
             return Err(self.spans.lock()[call_span]
                 .clone()
                 .sp(format!(
-                    "Function modified the stack by {height_diff} values, but its \
+                    "{id} modified the stack by {height_diff} values, but its \
                     signature of {sig} implies a change of {sig_diff}"
                 ))
                 .into());
         }
+        if let Some(floor) = floor {
+            let taken = start_height - floor;
+            if taken > sig.args {
+                return Err(self.spans.lock()[call_span]
+                    .clone()
+                    .sp(format!(
+                        "{id} took {taken} values from the stack, but its \
+                        signature of {sig} says it takes {}",
+                        sig.args
+                    ))
+                    .into());
+            }
+        }
         Ok(())
     }
     /// Call a function
@@ -700,7 +1965,7 @@ code:
             Err(e) => match e.break_data() {
                 Ok((0, _)) => Ok(true),
                 Ok((n, span)) => Err(UiuaError::Break(n - 1, span)),
-                Err(e) => Err(e),
+                Err(e) => Self::catch_continue(e).map(|_| false),
             },
         }
     }
@@ -721,10 +1986,18 @@ code:
             Err(e) => match e.break_data() {
                 Ok((0, span)) => Err(span.sp(message()).into()),
                 Ok((n, span)) => Err(UiuaError::Break(n - 1, span)),
-                Err(e) => Err(e),
+                Err(e) => Self::catch_continue(e),
             },
         }
     }
+    /// Continuing the loop a function is called by ends the call early
+    fn catch_continue(e: UiuaError) -> UiuaResult {
+        match e.continue_data() {
+            Ok((0, _)) => Ok(()),
+            Ok((n, span)) => Err(UiuaError::Continue(n - 1, span)),
+            Err(e) => Err(e),
+        }
+    }
     pub(crate) fn span_index(&self) -> usize {
         self.scope.call.last().map_or(0, |frame| {
             frame
@@ -741,16 +2014,160 @@ code:
     pub fn get_span(&self, span: usize) -> Span {
         self.spans.lock()[span].clone()
     }
+    /// Get the source of a file that has been compiled, as it was when it was last compiled
+    ///
+    /// This includes imported files. Spans in the file point into this source.
+    pub fn source(&self, path: &Path) -> Option<Arc<str>> {
+        self.spans.lock().files.get(path).cloned()
+    }
     pub fn add_span(&mut self, span: impl Into<Span>) -> usize {
-        let mut spans = self.spans.lock();
-        let idx = spans.len();
-        spans.push(span.into());
-        idx
+        self.spans.lock().add(span.into())
     }
     /// Construct an error with the current span
     pub fn error(&self, message: impl ToString) -> UiuaError {
         UiuaError::Run(self.span().clone().sp(message.to_string()))
     }
+    /// Construct an error for two arrays whose shapes do not match
+    pub(crate) fn shape_mismatch(&self, a: &[usize], b: &[usize]) -> UiuaError {
+        UiuaError::ShapeMismatch(Box::new(ShapeMismatch {
+            a: a.to_vec(),
+            b: b.to_vec(),
+            a_label: None,
+            b_label: None,
+            op: self.current_primitive(),
+            span: self.span(),
+        }))
+    }
+    /// Get the metadata of the arguments of a primitive that is about to run,
+    /// if any of them are labeled, have named axes, or have units
+    fn arg_metas(&self, prim: Primitive) -> Option<ArgMetas> {
+        let args = prim.args()?.min(2) as usize;
+        metas_of(self.stack.iter().rev().take(args))
+    }
+    /// Run a primitive whose arguments have metadata
+    ///
+    /// The arguments' axes and units are checked, and their metadata is given to the output or put in the error.
+    fn run_with_metas(&mut self, prim: Primitive, metas: ArgMetas) -> UiuaResult {
+        check_meta(prim, &metas).map_err(|e| self.error(e))?;
+        match prim.run(self) {
+            Ok(()) => {
+                if let Some(val) = self.stack.last_mut() {
+                    meta_output(prim, metas, val);
+                }
+                Ok(())
+            }
+            Err(e) => Err(label_error(metas, e)),
+        }
+    }
+    /// Get a function's primitive, and whether it is flipped, if the primitive's fast implementation can be used
+    ///
+    /// Fast implementations skip the checks that are done when a primitive runs,
+    /// drop metadata, and compare numbers exactly, so they are not used when those checks are needed,
+    /// when any of the arguments has metadata, or when a comparison tolerance is set.
+    pub(crate) fn fast_primitive(
+        &self,
+        f: &Function,
+        args: &[&Value],
+    ) -> Option<(Primitive, bool)> {
+        if self.num_policy != NumPolicy::Allow || args.iter().any(|arg| arg.meta().is_some()) {
+            return None;
+        }
+        let (prim, flipped) = f.as_flipped_primitive()?;
+        if self.tolerance() > 0.0 && prim.is_comparison() {
+            return None;
+        }
+        Some((prim, flipped))
+    }
+    /// Get which special numbers the arguments of a pervasive primitive that is about to run have,
+    /// if the numeric policy needs them
+    fn num_arg_flags(&self, prim: Primitive) -> Option<Vec<NumFlags>> {
+        if self.num_policy == NumPolicy::Allow || !is_pervasive(prim) {
+            return None;
+        }
+        let args = prim.args()? as usize;
+        Some(
+            self.stack
+                .iter()
+                .rev()
+                .take(args)
+                .map(NumFlags::of)
+                .collect(),
+        )
+    }
+    /// Apply the numeric policy to the output of a pervasive primitive
+    fn check_num_output(
+        &mut self,
+        prim: Primitive,
+        args: &[NumFlags],
+        output: NumFlags,
+    ) -> UiuaResult {
+        let new_nan = output.nan && !args.iter().any(|arg| arg.nan);
+        let new_inf = output.inf && !args.iter().any(|arg| arg.inf);
+        let message = if !(new_nan || new_inf) {
+            return Ok(());
+        } else if prim == Primitive::Div && args.first().is_some_and(|divisor| divisor.zero) {
+            "Division by zero".to_string()
+        } else if new_nan {
+            format!("{prim} produced NaN")
+        } else {
+            format!("{prim} produced infinity")
+        };
+        match self.num_policy {
+            NumPolicy::Allow => Ok(()),
+            NumPolicy::Warn => {
+                self.diagnostic(message, DiagnosticKind::Warning);
+                Ok(())
+            }
+            NumPolicy::Error => Err(self.error(message)),
+        }
+    }
+    /// Get the primitive currently being executed, if any
+    pub(crate) fn current_primitive(&self) -> Option<Primitive> {
+        self.scope.call.last()?.spans.last()?.1
+    }
+    /// Construct an error for a state the interpreter should never be in
+    pub(crate) fn bug(&self, what: &str) -> UiuaError {
+        self.error(format!(
+            "The interpreter is in an invalid state because {what}. \
+            This is a bug, please report it at http://github.com/uiua-lang/uiua/issues/new"
+        ))
+    }
+    /// Construct an array from its rows
+    ///
+    /// If the rows' shapes do not match, the error names the offending row
+    /// and points at the word that produced it if its span is known
+    pub(crate) fn array_from_rows(
+        &self,
+        rows: Vec<Value>,
+        row_span: impl FnOnce(usize) -> Option<Span>,
+    ) -> UiuaResult<Value> {
+        // Shapes are only copied if there is a row whose shape differs from the first
+        let mismatch = (rows.iter())
+            .position(|row| row.shape() != rows[0].shape())
+            .map(|i| {
+                (
+                    i,
+                    Shape::from(rows[i].shape()),
+                    Shape::from(rows[0].shape()),
+                )
+            });
+        Value::from_row_values(rows, self).map_err(|e| {
+            let Some((i, shape, first)) = mismatch.filter(|_| e.is_fill()) else {
+                return e;
+            };
+            let message = format!(
+                "Cannot construct array: row {i} has shape {} \
+                but row 0 has shape {}",
+                FormatShape(&shape),
+                FormatShape(&first)
+            );
+            let error = match row_span(i) {
+                Some(span) => span.sp(message).into(),
+                None => self.error(message),
+            };
+            error.fill()
+        })
+    }
     pub fn diagnostic(&mut self, message: impl Into<String>, kind: DiagnosticKind) {
         self.diagnostic_with_span(message, kind, self.span());
     }
@@ -772,14 +2189,18 @@ code:
                 }
                 Ok(val)
             }
-            None => Err(self.error(format!(
-                "Stack was empty when evaluating {}",
-                arg.arg_name()
-            ))),
+            None => Err(UiuaError::StackUnderflow(Box::new(StackUnderflow {
+                expected: arg.arg_name(),
+                who: self.current_primitive(),
+                span: self.span(),
+            }))),
         };
         for bottom in &mut self.scope.array {
             *bottom = (*bottom).min(self.stack.len());
         }
+        for floor in &mut self.scope.floors {
+            *floor = (*floor).min(self.stack.len());
+        }
         res
     }
     /// Simulates popping a value and imediately pushing it back
@@ -800,6 +2221,62 @@ code:
     pub fn take_stack(&mut self) -> Vec<Value> {
         take(&mut self.stack)
     }
+    /// Call a function, reusing its outputs if it has been called with the same arguments before
+    pub(crate) fn call_memo(&mut self, f: Arc<Function>) -> UiuaResult {
+        let sig = f.signature();
+        let mut args = Vec::with_capacity(sig.args);
+        for i in 0..sig.args {
+            args.push(self.pop(i + 1)?);
+        }
+        // Memo keys don't include metadata, so values that have it are not cached
+        if args.iter().any(|arg| arg.meta().is_some()) {
+            self.stack.extend(args.into_iter().rev());
+            return self.call(f);
+        }
+        let cached = (self.memo.lock().get(&f)).and_then(|cache| cache.get(&args).cloned());
+        if let Some(outputs) = cached {
+            self.stack.extend(outputs.into_iter().rev());
+            return Ok(());
+        }
+        let key = (self.memo_file.as_ref()).and_then(|_| memo_key(&f, &args, self));
+        if let Some(outputs) = key.and_then(|key| self.memo_file_get(key)) {
+            self.stack.extend(outputs.iter().rev().cloned());
+            self.memo.lock().entry(f).or_default().insert(args, outputs);
+            return Ok(());
+        }
+        self.stack.extend(args.iter().rev().cloned());
+        self.call(f.clone())?;
+        let mut outputs = Vec::with_capacity(sig.outputs);
+        for i in 0..sig.outputs {
+            outputs.push(self.pop(i + 1)?);
+        }
+        self.stack.extend(outputs.iter().rev().cloned());
+        if let Some(key) = key {
+            if outputs.iter().all(|output| output.meta().is_none()) {
+                self.memo_file_insert(key, outputs.clone());
+            }
+        }
+        self.memo.lock().entry(f).or_default().insert(args, outputs);
+        Ok(())
+    }
+    /// Get the key for a binding's value in the memo file, if there is one and the binding can be persisted
+    pub(crate) fn binding_key(&self, instrs: &[Instr]) -> Option<u64> {
+        self.memo_file
+            .as_ref()
+            .and_then(|_| binding_key(instrs, self))
+    }
+    pub(crate) fn memo_file_get(&self, key: u64) -> Option<Vec<Value>> {
+        self.memo_file.as_ref()?.lock().get(key)
+    }
+    pub(crate) fn memo_file_insert(&self, key: u64, outputs: Vec<Value>) {
+        if let Some(memo_file) = &self.memo_file {
+            memo_file.lock().used.insert(key, outputs);
+        }
+    }
+    /// Clear the results cached by [`memo`](Primitive::Memo)
+    pub fn clear_memo(&self) {
+        self.memo.lock().clear();
+    }
     pub fn pop_function(&mut self) -> UiuaResult<Arc<Function>> {
         self.function_stack.pop().ok_or_else(|| {
             self.error(
@@ -808,14 +2285,92 @@ code:
             )
         })
     }
+    /// Get the documentation comment of a binding in the current scope
+    ///
+    /// A binding of an imported function without its own comment has the comment of the function.
+    pub fn binding_doc(&self, name: &str) -> Option<String> {
+        let index = *self.scope.names.get(&Ident::get(name)?)?;
+        if let Some(doc) = self.binding_docs.lock().get(&index) {
+            return Some(doc.clone());
+        }
+        let global = self.globals.lock()[index].clone();
+        match global {
+            Global::Func(f) => self.function_doc(&f),
+            _ => None,
+        }
+    }
+    /// Get the documentation comment of the first documented binding of a function
+    pub(crate) fn function_doc(&self, f: &Function) -> Option<String> {
+        let globals = self.globals.lock();
+        let docs = self.binding_docs.lock();
+        (globals.iter().enumerate())
+            .filter(|(_, global)| matches!(global, Global::Func(g) if **g == *f))
+            .find_map(|(i, _)| docs.get(&i).cloned())
+    }
+    /// Bind a function implemented in Rust to a name
+    ///
+    /// Code loaded afterwards can call the function by that name.
+    /// The function must take and leave as many values as its signature says.
+    /// If the name ends in `!`s, the function is a modifier,
+    /// and it can get its function arguments with [`Uiua::pop_function`].
+    ///
+    /// The function is given the interpreter it was called from, so it can call back into it.
+    /// [`Uiua::call`] runs a function it was passed, even one that calls this function again.
+    /// Errors from such calls should be returned rather than handled.
+    pub fn bind_native_function(
+        &mut self,
+        name: impl Into<Ident>,
+        signature: Signature,
+        f: impl Fn(&mut Uiua) -> UiuaResult + Send + Sync + 'static,
+    ) {
+        let name = name.into();
+        let mut hasher = DefaultHasher::new();
+        name.hash(&mut hasher);
+        let dynamic = DynamicFunction {
+            id: hasher.finish(),
+            f: Arc::new(f),
+            signature,
+        };
+        let instrs = vec![Instr::Dynamic(Box::new(dynamic))];
+        let function = Function::new(FunctionId::Named(name), instrs, signature);
+        let mut globals = self.globals.lock();
+        let index = globals.len();
+        globals.push(Global::Func(function.into()));
+        drop(globals);
+        self.scope.names.insert(name, index);
+    }
+    /// Bind a value to a name without compiling any code
+    pub(crate) fn bind_saved_value(&mut self, name: Ident, value: Value) {
+        let mut globals = self.globals.lock();
+        let index = globals.len();
+        globals.push(Global::Val(value));
+        drop(globals);
+        self.scope.names.insert(name, index);
+    }
+    /// Get the functions being called, from the outermost to the innermost
+    ///
+    /// Code outside of any function is not included, so this is empty at the top level.
+    pub fn call_stack(&self) -> Vec<FunctionId> {
+        (self.scope.call.iter())
+            .map(|frame| &frame.function.id)
+            .filter(|id| **id != FunctionId::Main)
+            .cloned()
+            .collect()
+    }
+    /// Get how many function calls deep execution is
+    pub fn call_depth(&self) -> usize {
+        (self.scope.call.iter())
+            .filter(|frame| frame.function.id != FunctionId::Main)
+            .count()
+    }
     /// Get the values for all bindings in the current scope
     pub fn all_values_is_scope(&self) -> HashMap<Ident, Value> {
         let mut bindings = HashMap::new();
         let globals = self.globals.lock();
         for (name, idx) in &self.scope.names {
             if !CONSTANTS.iter().any(|c| c.name == name.as_ref()) {
-                if let Global::Val(val) = &globals[*idx] {
-                    bindings.insert(name.clone(), val.clone());
+                if let Global::Val(val) | Global::Var(val) = &globals[*idx] {
+                    bindings.insert(*name, val.clone());
                 }
             }
         }
@@ -860,10 +2415,10 @@ code:
         self.push(a);
         Ok(())
     }
-    pub(crate) fn dyadic_rr<V: Into<Value>>(&mut self, f: fn(&Value, &Value) -> V) -> UiuaResult {
-        let a = self.pop(1)?;
-        let b = self.pop(2)?;
-        self.push(f(&a, &b));
+    pub(crate) fn monadic_mut_env(&mut self, f: fn(&mut Value, &Self)) -> UiuaResult {
+        let mut a = self.pop(1)?;
+        f(&mut a, self);
+        self.push(a);
         Ok(())
     }
     pub(crate) fn dyadic_oo_env<V: Into<Value>>(
@@ -965,6 +2520,27 @@ code:
         }
         res
     }
+    /// Do something with a comparison tolerance set
+    pub(crate) fn with_tolerance(
+        &mut self,
+        tolerance: Value,
+        in_ctx: impl FnOnce(&mut Self) -> UiuaResult,
+    ) -> UiuaResult {
+        let tolerance = tolerance.as_num(self, "Tolerance must be a number")?;
+        if tolerance < 0.0 {
+            return Err(self.error(format!(
+                "Tolerance must not be negative, but it is {tolerance}"
+            )));
+        }
+        self.scope.tolerances.push(tolerance);
+        let res = in_ctx(self);
+        self.scope.tolerances.pop();
+        res
+    }
+    /// Get how far apart numbers can be and still be considered equal
+    pub(crate) fn tolerance(&self) -> f64 {
+        (self.scope.tolerances.last().copied()).unwrap_or(self.tolerance)
+    }
     pub(crate) fn with_pack(&mut self, in_ctx: impl FnOnce(&mut Self) -> UiuaResult) -> UiuaResult {
         self.scope.pack_depth += 1;
         let res = in_ctx(self);
@@ -998,7 +2574,9 @@ code:
         };
         let mut env = Uiua {
             new_functions: Vec::new(),
+            local_names: Vec::new(),
             globals: self.globals.clone(),
+            binding_docs: self.binding_docs.clone(),
             spans: self.spans.clone(),
             stack: self
                 .stack
@@ -1013,13 +2591,37 @@ code:
             mode: self.mode,
             current_imports: self.current_imports.clone(),
             imports: self.imports.clone(),
+            pure_load: self.pure_load.clone(),
+            memo: self.memo.clone(),
+            memo_file: self.memo_file.clone(),
+            output_capture: self.output_capture.clone(),
             diagnostics: BTreeSet::new(),
             print_diagnostics: self.print_diagnostics,
             time_instrs: self.time_instrs,
+            coverage: self.coverage.clone(),
+            lints: self.lints,
+            shape_checks: self.shape_checks,
+            stack_checks: self.stack_checks,
+            leak_checks: self.leak_checks,
+            num_policy: self.num_policy,
+            tolerance: self.tolerance,
+            trace_limit: self.trace_limit,
+            compile_only: self.compile_only,
+            language_version: self.language_version,
+            experimental: self.experimental,
+            rng: (self.rng.as_mut()).map(|rng| SmallRng::seed_from_u64(rng.gen())),
+            proxy_depth: 0,
+            current_binding: None,
+            macro_depth: 0,
+            running_macro: self.running_macro.clone(),
+            var_annotations: self.var_annotations.clone(),
+            annotated_fns: self.annotated_fns.clone(),
+            binding_refs: None,
             last_time: self.last_time,
             cli_arguments: self.cli_arguments.clone(),
             cli_file_path: self.cli_file_path.clone(),
             backend: self.backend.clone(),
+            ext_ops: self.ext_ops.clone(),
             execution_limit: self.execution_limit,
             execution_start: self.execution_start,
             thread,
@@ -1067,7 +2669,7 @@ code:
             )
             .ok_or_else(|| self.error("Cannot wait on thread spawned in cloned environment"))?
             .join()
-            .unwrap()?;
+            .map_err(|_| self.error("The thread crashed"))??;
             #[cfg(target_arch = "wasm32")]
             let thread_stack = self
                 .thread
@@ -1089,7 +2691,7 @@ code:
                 )
                 .ok_or_else(|| self.error("Cannot wait on thread spawned in cloned environment"))?
                 .join()
-                .unwrap()?;
+                .map_err(|_| self.error("The thread crashed"))??;
                 #[cfg(target_arch = "wasm32")]
                 let thread_stack = self
                     .thread
@@ -1097,10 +2699,9 @@ code:
                     .remove(&handle)
                     .ok_or_else(|| self.error("Invalid thread id"))?
                     .result?;
-                let row = if thread_stack.len() == 1 {
-                    thread_stack.into_iter().next().unwrap()
-                } else {
-                    Value::from_row_values(thread_stack, self)?
+                let row = match <[Value; 1]>::try_from(thread_stack) {
+                    Ok([row]) => row,
+                    Err(thread_stack) => Value::from_row_values(thread_stack, self)?,
                 };
                 rows.push(row);
             }
@@ -1210,3 +2811,397 @@ where
         self().arg_name()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{diagnostics, run, run_with};
+
+    #[test]
+    fn memo_keys() {
+        let env = Uiua::with_native_sys();
+        let func = |instr: Instr| Function::new(FunctionId::Temp, [instr], Signature::new(0, 1));
+        let calls = func(Instr::Prim(Primitive::Calls, 0));
+        assert!(memo_key(&calls, &[], &env).is_none());
+        let outer_local = func(Instr::GetLocal {
+            up: 1,
+            index: 0,
+            frame: None,
+            span: 0,
+        });
+        assert!(memo_key(&outer_local, &[], &env).is_none());
+        let own_local = func(Instr::GetLocal {
+            up: 0,
+            index: 0,
+            frame: None,
+            span: 0,
+        });
+        assert!(memo_key(&own_local, &[], &env).is_some());
+        // Keys change with the settings that change results
+        let eq = func(Instr::Prim(Primitive::Eq, 0));
+        let args = [Value::from(0.3), Value::from(0.1 + 0.2)];
+        let key = |env: Uiua| memo_key(&eq, &args, &env);
+        let exact = key(Uiua::with_native_sys());
+        assert_ne!(
+            exact,
+            key(Uiua::with_native_sys().with_comparison_tolerance(1e-9))
+        );
+        assert_ne!(
+            exact,
+            key(Uiua::with_native_sys().with_num_policy(NumPolicy::Error))
+        );
+        assert_eq!(exact, key(Uiua::with_native_sys()));
+    }
+
+    #[test]
+    fn array_row_errors() {
+        let error = |code: &str| run(code).unwrap_err().to_string();
+        // Errors point at the word that pushed the offending row
+        let err = error("[1_2 3 4]");
+        assert!(
+            err.starts_with("1:6: Cannot construct array: row 1"),
+            "{err}"
+        );
+        let err = error("F ← [⇡4 ⇡]\nF 3");
+        assert!(
+            err.starts_with("1:9: Cannot construct array: row 1"),
+            "{err}"
+        );
+        // A word can push several rows
+        let err = error("F ← [⊃(⇡4)(⇡)]\nF 3");
+        assert!(
+            err.starts_with("1:6: Cannot construct array: row 1"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn cells() {
+        let mut env = Uiua::with_native_sys();
+        let res = env.run_cell("a", "X ← 5\n&p \"hi\"\n1 2");
+        assert!(res.error.is_none());
+        assert_eq!(res.output, [CellOutput::Stdout("hi\n".into())]);
+        assert_eq!(res.pushed, [Value::from(2.0), Value::from(1.0)]);
+        let res = env.run_cell("b", "+X");
+        assert_eq!(res.popped, [Value::from(1.0)]);
+        assert_eq!(res.pushed, [Value::from(6.0)]);
+        let res = env.run_cell("c", "Y ← 3\n⊢[]");
+        assert!(res.error.is_some());
+        assert_eq!(env.run_cell("d", "Y").pushed.len(), 0);
+        assert_eq!(env.clone_stack_top(2), [Value::from(2.0), Value::from(6.0)]);
+    }
+
+    #[test]
+    fn capture_output() {
+        let mut env = Uiua::with_native_sys().capture_output();
+        env.load_str("&p \"hi\"\n&pf 1\n&s [1 2]\n&w \"oops\" 2\n⸮3")
+            .unwrap();
+        assert_eq!(env.captured_stdout(), "hi\n1[1 2]\n");
+        let output = env.take_captured_output();
+        assert!(matches!(
+            output.as_slice(),
+            [
+                CellOutput::Stdout(_),
+                CellOutput::Stderr(err),
+                CellOutput::Trace(_)
+            ] if err == "oops"
+        ));
+        assert!(env.captured_output().is_empty());
+        assert_eq!(env.take_stack(), [Value::from(3.0)]);
+    }
+
+    #[test]
+    fn inspect() {
+        let mut env = Uiua::with_native_sys().capture_output();
+        env.load_str("inspect△ [1 2 3]").unwrap();
+        assert_eq!(
+            env.take_captured_output(),
+            [CellOutput::Trace("┌╴1:1 △\n├╴[3]\n└╴╴╴╴╴╴\n".into())]
+        );
+        assert_eq!(
+            env.take_stack(),
+            [[1usize, 2, 3].into_iter().collect::<Value>()]
+        );
+    }
+
+    #[test]
+    fn output_order() {
+        use std::{any::Any, mem::take, sync::Mutex};
+        /// A backend that buffers stdout until it is flushed
+        #[derive(Default)]
+        struct Buffered {
+            stdout: Mutex<String>,
+            log: Mutex<Vec<String>>,
+        }
+        impl SysBackend for Buffered {
+            fn any(&self) -> &dyn Any {
+                self
+            }
+            fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+                self.stdout.lock().unwrap().push_str(s);
+                Ok(())
+            }
+            fn flush_stdout(&self) -> Result<(), String> {
+                let s = take(&mut *self.stdout.lock().unwrap());
+                if !s.is_empty() {
+                    self.log.lock().unwrap().push(format!("out {s}"));
+                }
+                Ok(())
+            }
+            fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+                self.log.lock().unwrap().push(format!("err {s}"));
+                Ok(())
+            }
+            fn print_str_trace(&self, _: &str) {
+                self.log.lock().unwrap().push("trace".into());
+            }
+        }
+        let mut env = Uiua::with_backend(Buffered::default());
+        env.load_str("&p 1\n&p 2\n&ep 3\n&pf 4\n&pf 5\n⸮6\n&p 7")
+            .unwrap();
+        let backend = env.backend.any().downcast_ref::<Buffered>().unwrap();
+        let mut log = backend.log.lock().unwrap().clone();
+        // A trace is printed in several pieces
+        log.dedup();
+        assert_eq!(
+            log,
+            [
+                "out 1\n2\n",
+                "err 3\n",
+                "out 4",
+                "out 5",
+                "trace",
+                "out 7\n"
+            ]
+        );
+    }
+
+    #[test]
+    fn check() {
+        let code = "\
+X ← +1 @a
+[A B] ← [X X]
+⍤\"fails\" 0
+---
+⍤\"fails\" =A B
+---";
+        assert!(Uiua::with_native_sys().load_str(code).is_err());
+        let mut env = Uiua::with_native_sys();
+        env.check_str(code).unwrap();
+        assert!(env.take_stack().is_empty());
+        assert!(Uiua::with_native_sys().check_str("Y").is_err());
+        assert!(Uiua::with_native_sys().check_str("Z ← 1\nZ Z").is_ok());
+        // Code macros run even when only checking, so they cannot do IO
+        let code = "Loud! ←^ ⊔⊢&p\"hi\"\nLoud!(+1) 2";
+        let mut env = Uiua::with_native_sys().capture_output();
+        let err = env.check_str(code).unwrap_err();
+        assert!(
+            err.to_string().contains("code macros run at compile time"),
+            "{err}"
+        );
+        assert!(Uiua::with_native_sys().load_str(code).is_err());
+    }
+
+    #[test]
+    fn num_policy() {
+        let run = |policy: NumPolicy, code: &str| {
+            let mut env = Uiua::with_native_sys().with_num_policy(policy);
+            let res = env.load_str(code).map(|_| ());
+            (res, diagnostics(&mut env))
+        };
+        let (res, warnings) = run(NumPolicy::Allow, "÷0 1");
+        assert!(res.is_ok() && warnings.is_empty());
+        let (res, warnings) = run(NumPolicy::Warn, "÷0 1\n√¯1\n+1 ∞");
+        assert!(res.is_ok());
+        assert_eq!(warnings, ["Division by zero", "√ produced NaN"]);
+        let (res, _) = run(NumPolicy::Error, "F ← ⁿ400\nF 10");
+        assert!(res.unwrap_err().to_string().contains("ⁿ produced infinity"));
+        let (res, _) = run(NumPolicy::Error, "÷ [1 2] [3 4]\n÷0 ∞");
+        assert!(res.is_ok());
+        assert_eq!("warn".parse(), Ok(NumPolicy::Warn));
+        for code in ["⊞÷ [0] [1]", "/÷ [0 1]", "\\÷ [0 1]", "≡/÷ [[0 1]]"] {
+            let (res, _) = run(NumPolicy::Error, code);
+            assert!(res.unwrap_err().to_string().contains("Division by zero"));
+        }
+        let (res, _) = run(
+            NumPolicy::Warn,
+            "# Experimental!\n+: unit \"s\" 1 unit \"m\" 2",
+        );
+        assert!(res.unwrap_err().to_string().contains("do not match"));
+    }
+
+    #[test]
+    fn stack_checks() {
+        let code = "F ← |1.1 eval \":\"\n[F 1 2]";
+        // Only the height change is checked by default
+        Uiua::with_native_sys().load_str(code).unwrap();
+        let mut env = Uiua::with_native_sys().with_stack_checks(true);
+        let message = env.load_str(code).unwrap_err().message();
+        assert_eq!(
+            message,
+            "2:2: `F` took 2 values from the stack, but its signature of |1.1 says it takes 1"
+        );
+        // Well-behaved functions pass
+        let mut env = Uiua::with_native_sys().with_stack_checks(true);
+        env.load_str("F ← |2.3 ,\n{F 1 2 ⊙(⍜⊢(+1)) 3 [1 2]}")
+            .unwrap();
+        let mut env = Uiua::with_native_sys().with_stack_checks(true);
+        let message = env
+            .load_str("F ← |1.1 eval \"+\"\nF 1 2")
+            .unwrap_err()
+            .message();
+        assert_eq!(
+        message,
+        "2:1: `F` modified the stack by -1 values, but its signature of |1.1 implies a change of 0"
+    );
+    }
+
+    #[test]
+    fn call_stack() {
+        let mut env = Uiua::with_native_sys().with_experimental(true);
+        env.load_str("F ← ⊂calls □\nG ← F\nG 1").unwrap();
+        use crate::boxed::Boxed;
+        let expected: Array<Boxed> = ["G", "F"]
+            .into_iter()
+            .map(|name| Boxed(name.into()))
+            .chain([Boxed(1.0.into())])
+            .collect();
+        assert_eq!(env.take_stack(), [Value::from(expected)]);
+        assert_eq!(env.call_depth(), 0);
+        assert!(env.call_stack().is_empty());
+    }
+
+    #[test]
+    fn native_functions() {
+        let mut env = Uiua::with_native_sys();
+        env.bind_native_function("Half", Signature::new(1, 1), |env| {
+            let n = env.pop(1)?.as_num(env, "Half expects a number")?;
+            env.push(n / 2.0);
+            Ok(())
+        });
+        // Modifiers can call the functions they are given, which can call back into them
+        env.bind_native_function("Twice!", Signature::new(1, 1), |env| {
+            let f = env.pop_function()?;
+            env.call(f.clone())?;
+            env.call(f)
+        });
+        env.load_str("Twice!(Twice!(+1)) Half 6").unwrap();
+        assert_eq!(env.take_stack(), [Value::from(7.0)]);
+        let err = env.load_str("Twice!(⊢) 5").unwrap_err();
+        assert!(err.to_string().contains("Twice!"), "{err}");
+    }
+
+    #[test]
+    fn superinstructions() {
+        // Constants, duplicate, and flip run as one instruction with a pervasive primitive after them,
+        // but errors are still reported at the first instruction
+        let message = Uiua::with_native_sys()
+            .load_str("+.")
+            .unwrap_err()
+            .message();
+        assert!(message.starts_with("1:2: "), "{message}");
+    }
+
+    #[test]
+    fn crash_recovery() {
+        use std::any::Any;
+        /// A backend with a bug
+        struct Panicky;
+        impl SysBackend for Panicky {
+            fn any(&self) -> &dyn Any {
+                self
+            }
+            fn print_str_stdout(&self, _: &str) -> Result<(), String> {
+                panic!("backend bug")
+            }
+        }
+        let mut env = Uiua::with_backend(Panicky);
+        let err = env.load_str("F ← (&p [1 2])\n[F 1]").unwrap_err();
+        assert!(
+            err.to_string().contains("The interpreter has crashed"),
+            "{err}"
+        );
+        env.take_stack();
+        env.load_str("[+1 2 ⍥(□)2 3]").unwrap();
+        assert_eq!(env.take_stack().len(), 1);
+    }
+
+    #[test]
+    fn deterministic() {
+        let run = || {
+            run_with(
+                Uiua::with_native_sys().deterministic(true),
+                "now\n[⚂ ⚂ ⚂]\n≡(⚂;)⇡3",
+            )
+            .unwrap()
+        };
+        let first = run();
+        assert_eq!(first, run());
+        assert_eq!(first[0], Value::from(0.0));
+    }
+
+    #[test]
+    fn binding_docs() {
+        let mut env = Uiua::with_native_sys();
+        env.load_str(
+            "\
+# Experimental!
+# Add 1
+# to a number
+#
+# Works on arrays too
+Inc ← +1
+X ← 5
+# Not documentation
+[1 2]
+Y ← 6
+Sqr ← &i \"example.ua\" \"Square\"
+H ← help Inc",
+        )
+        .unwrap();
+        let doc = "Add 1 to a number\n\nWorks on arrays too";
+        assert_eq!(env.binding_doc("Inc").as_deref(), Some(doc));
+        assert_eq!(env.binding_doc("X"), None);
+        assert_eq!(env.binding_doc("Y"), None);
+        assert_eq!(env.binding_doc("Sqr").as_deref(), Some("Square a number"));
+        assert_eq!(env.binding_doc("e").as_deref(), Some("Euler's constant"));
+        assert_eq!(env.binding_doc("Z"), None);
+        let help = env.all_values_is_scope()[&Ident::new("H")]
+            .as_string(&env, "")
+            .unwrap();
+        assert_eq!(help, doc);
+    }
+
+    #[test]
+    fn span_table() {
+        // Running the same code again reuses its spans
+        let mut env = Uiua::with_native_sys();
+        env.load_str("F ← +1\nF 2").unwrap();
+        let len = env.spans.lock().len();
+        env.load_str("F ← +1\nF 2").unwrap();
+        assert_eq!(env.spans.lock().len(), len);
+        // Spans at the same place in different code are kept apart
+        env.load_str("⊂1 2").unwrap();
+        let report = env.load_str("⊢1 2").unwrap_err().report().to_string();
+        assert!(report.contains("⊢1 2"), "{report}");
+    }
+
+    #[test]
+    fn sources() {
+        let dir = std::env::temp_dir().join(format!("uiua-sources-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lib = dir.join("lib.ua");
+        std::fs::write(&lib, "Sq ← ×.\nBad ← ⊢[]").unwrap();
+        let mut env = Uiua::with_native_sys();
+        let err = env
+            .load_str(&format!("Bad ← &i {:?} \"Bad\"\nBad", lib.display()))
+            .unwrap_err();
+        // Imported files are kept
+        let source = env.source(&lib).unwrap();
+        assert_eq!(&*source, "Sq ← ×.\nBad ← ⊢[]");
+        // Errors in them show the imported line
+        let report = err.report().to_string();
+        assert!(report.contains("Bad ← ⊢[]"), "{report}");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
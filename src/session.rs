@@ -0,0 +1,234 @@
+//! Saving and restoring the bindings and stack of an interpreter
+
+use std::{fs, io, path::Path};
+
+use crate::{ast::Item, parse::parse, Ident, Uiua, UiuaResult, Value};
+
+/// A binding made during a [`Session`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionBinding {
+    /// A binding to a value, which is saved as it is
+    Value(Ident, Value),
+    /// A binding to a function, which is saved as the code of the binding
+    Code(String),
+}
+
+/// The inputs, bindings, and final stack of a run of several inputs, like a REPL
+///
+/// When a session is restored, values are bound as they were saved instead of being computed again,
+/// so the IO done by the inputs is not repeated.
+/// Functions are compiled again from their code, which does not run it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    /// The inputs that ran, oldest first
+    pub history: Vec<String>,
+    /// The bindings that were made, in the order they were made
+    pub bindings: Vec<SessionBinding>,
+    /// The values left by the last input
+    pub stack: Vec<Value>,
+}
+
+const SESSION_FILE_HEADER: &str = concat!("uiua session ", env!("CARGO_PKG_VERSION"), "\n");
+
+impl Session {
+    /// Record an input that ran successfully in an interpreter, along with the values it left
+    pub fn record(&mut self, input: &str, env: &Uiua, stack: Vec<Value>) {
+        let (items, _, _) = parse(input, None);
+        let values = env.all_values_is_scope();
+        for item in items {
+            match item {
+                Item::Binding(binding) => {
+                    let name = binding.name.value;
+                    self.bindings.push(match values.get(&name) {
+                        Some(value) => SessionBinding::Value(name, value.clone()),
+                        // Only the binding itself is saved, so the rest of the input is not run again
+                        None => SessionBinding::Code(binding.span().as_str().into()),
+                    });
+                }
+                Item::Destructure(destructure) => {
+                    for name in destructure.names {
+                        if let Some(value) = values.get(&name.value) {
+                            (self.bindings).push(SessionBinding::Value(name.value, value.clone()));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.history.push(input.into());
+        self.stack = stack;
+    }
+    /// Make the bindings in an interpreter and push the final stack
+    pub fn restore(&self, env: &mut Uiua) -> UiuaResult {
+        for binding in &self.bindings {
+            match binding {
+                SessionBinding::Value(name, value) => env.bind_saved_value(*name, value.clone()),
+                SessionBinding::Code(code) => env.load_str(code)?,
+            }
+        }
+        for value in &self.stack {
+            env.push(value.clone());
+        }
+        Ok(())
+    }
+    /// Load a session saved with [`Session::save`]
+    ///
+    /// A file that does not exist gives an empty session.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Session::default()),
+            Err(e) => return Err(e),
+        };
+        (bytes.strip_prefix(SESSION_FILE_HEADER.as_bytes()))
+            .and_then(|mut bytes| Self::decode(&mut bytes))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{} is not a session file from this version of Uiua",
+                        path.display()
+                    ),
+                )
+            })
+    }
+    /// Save the session to a file
+    ///
+    /// Metadata like labels and units is not saved.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut bytes = SESSION_FILE_HEADER.as_bytes().to_vec();
+        bytes.extend((self.history.len() as u64).to_le_bytes());
+        for input in &self.history {
+            encode_str(input, &mut bytes);
+        }
+        bytes.extend((self.bindings.len() as u64).to_le_bytes());
+        for binding in &self.bindings {
+            match binding {
+                SessionBinding::Value(name, value) => {
+                    bytes.push(0);
+                    encode_str(name.as_str(), &mut bytes);
+                    value.encode(&mut bytes);
+                }
+                SessionBinding::Code(code) => {
+                    bytes.push(1);
+                    encode_str(code, &mut bytes);
+                }
+            }
+        }
+        bytes.extend((self.stack.len() as u64).to_le_bytes());
+        for value in &self.stack {
+            value.encode(&mut bytes);
+        }
+        fs::write(path, bytes)
+    }
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        let history = (0..decode_len(bytes)?)
+            .map(|_| decode_str(bytes))
+            .collect::<Option<_>>()?;
+        let bindings = (0..decode_len(bytes)?)
+            .map(|_| {
+                let (&tag, rest) = bytes.split_first()?;
+                *bytes = rest;
+                Some(match tag {
+                    0 => SessionBinding::Value(
+                        Ident::from(decode_str(bytes)?),
+                        Value::decode(bytes)?,
+                    ),
+                    1 => SessionBinding::Code(decode_str(bytes)?),
+                    _ => return None,
+                })
+            })
+            .collect::<Option<_>>()?;
+        let stack = (0..decode_len(bytes)?)
+            .map(|_| Value::decode(bytes))
+            .collect::<Option<_>>()?;
+        Some(Session {
+            history,
+            bindings,
+            stack,
+        })
+    }
+}
+
+fn encode_str(s: &str, bytes: &mut Vec<u8>) {
+    bytes.extend((s.len() as u64).to_le_bytes());
+    bytes.extend(s.as_bytes());
+}
+
+fn decode_len(bytes: &mut &[u8]) -> Option<usize> {
+    let (len, rest) = bytes.split_first_chunk::<8>()?;
+    *bytes = rest;
+    usize::try_from(u64::from_le_bytes(*len)).ok()
+}
+
+fn decode_str(bytes: &mut &[u8]) -> Option<String> {
+    let len = decode_len(bytes)?;
+    if len > bytes.len() {
+        return None;
+    }
+    let (s, rest) = bytes.split_at(len);
+    *bytes = rest;
+    String::from_utf8(s.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions() {
+        let mut env = Uiua::with_native_sys().capture_output();
+        let mut session = Session::default();
+        for input in ["X ← ⊃&p∘ 5", "F ← +X", "X ← 6", "[A B] ← [1 2]", "F A"] {
+            env.load_str(input).unwrap();
+            let stack = env.take_stack();
+            session.record(input, &env, stack);
+        }
+        assert_eq!(env.captured_stdout(), "5\n");
+        let path = std::env::temp_dir().join(format!("uiua-session-test-{}", std::process::id()));
+        session.save(&path).unwrap();
+        let loaded = Session::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(loaded, session);
+        assert_eq!(loaded.history.len(), 5);
+        // Restoring does not run the inputs again
+        let mut env = Uiua::with_native_sys().capture_output();
+        loaded.restore(&mut env).unwrap();
+        assert_eq!(env.captured_stdout(), "");
+        assert_eq!(env.take_stack(), [Value::from(6.0)]);
+        // Functions use the values that were bound when they were defined
+        env.load_str("[F 0 X B]").unwrap();
+        assert_eq!(
+            env.take_stack(),
+            [[5.0, 6.0, 2.0].into_iter().collect::<Value>()]
+        );
+        assert!(Session::load("not a session file".as_ref())
+            .unwrap()
+            .history
+            .is_empty());
+    }
+
+    #[test]
+    fn session_function_bindings() {
+        let mut env = Uiua::with_native_sys().capture_output();
+        let mut session = Session::default();
+        let input = "F ← +1\nG ← ×2\n&p \"side effect\"\n3";
+        env.load_str(input).unwrap();
+        let stack = env.take_stack();
+        session.record(input, &env, stack);
+        assert_eq!(
+            session.bindings,
+            [
+                SessionBinding::Code("F ← +1".into()),
+                SessionBinding::Code("G ← ×2".into())
+            ]
+        );
+        // Restoring compiles each binding once without running the rest of the input
+        let mut env = Uiua::with_native_sys().capture_output();
+        session.restore(&mut env).unwrap();
+        assert_eq!(env.captured_stdout(), "");
+        assert_eq!(env.take_stack(), [Value::from(3.0)]);
+        env.load_str("G F 1").unwrap();
+        assert_eq!(env.take_stack(), [Value::from(4.0)]);
+    }
+}
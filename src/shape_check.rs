@@ -0,0 +1,910 @@
+//! Gradual static checking of array shapes
+//!
+//! Shapes are propagated through compiled instructions for the primitives whose
+//! effects on shape are known. Anything else produces values of unknown shape,
+//! so only mismatches that are certain to happen at runtime are reported.
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    ast::{Annotation, DimPattern, ValuePattern, ValueType},
+    check::instrs_signature,
+    function::{Function, FunctionId, Instr, Signature},
+    primitive::PrimClass,
+    value::Value,
+    Ident, Primitive,
+};
+
+/// How deeply calls to functions are followed
+const MAX_DEPTH: usize = 8;
+
+/// A problem found by [`check_shapes`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ShapeProblem {
+    /// The span index of the instruction that will fail,
+    /// or `None` if the outputs do not match the annotation
+    pub span: Option<usize>,
+    pub message: String,
+}
+
+/// Check the shapes of values passing through some instructions
+///
+/// If the instructions are the body of an annotated binding,
+/// its arguments start with the shapes the annotation describes,
+/// and its outputs are checked against it.
+/// `annotated` maps the ids of the checks of annotated functions to their names and annotations.
+pub(crate) fn check_shapes(
+    instrs: &[Instr],
+    binding: Option<(&Ident, &Annotation)>,
+    annotated: &HashMap<u64, (Ident, Annotation)>,
+) -> Vec<ShapeProblem> {
+    let mut checker = Checker {
+        annotated,
+        stack: Vec::new(),
+        functions: Vec::new(),
+        arrays: Vec::new(),
+        inline_stack: Vec::new(),
+        under_stack: Vec::new(),
+        sym_names: Vec::new(),
+        depth: 0,
+        call_span: None,
+        problems: Vec::new(),
+    };
+    let (instrs, constants) = match instrs {
+        [Instr::PushFunc(f)] => (f.instrs.as_slice(), f.constants.as_slice()),
+        instrs => (instrs, [].as_slice()),
+    };
+    let mut dims = HashMap::new();
+    if let Some((_, annotation)) = binding {
+        for pattern in annotation.args.iter().rev() {
+            let value = checker.pattern_value(&pattern.value, &mut dims);
+            checker.stack.push(value);
+        }
+    }
+    if checker.instrs(instrs, constants).is_none() {
+        return checker.problems;
+    }
+    if let Some((name, annotation)) = binding {
+        if let Some(outputs) = &annotation.outputs {
+            let kind = if annotation.args.is_empty() && outputs.len() == 1 {
+                "Value"
+            } else {
+                "Output"
+            };
+            for (i, pattern) in outputs.iter().enumerate() {
+                let value = checker.peek(i);
+                if let Some(message) = checker.mismatch(&pattern.value, &value, &mut dims) {
+                    checker.problems.push(ShapeProblem {
+                        span: None,
+                        message: describe(kind, i, name, &message),
+                    });
+                }
+            }
+        }
+    }
+    checker.problems
+}
+
+fn describe(kind: &str, i: usize, name: &Ident, message: &str) -> String {
+    if kind == "Value" {
+        format!("{kind} of {name} {message}")
+    } else {
+        format!("{kind} {} of {name} {message}", i + 1)
+    }
+}
+
+/// The length of an axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dim {
+    Len(usize),
+    /// An unknown length, which is only known to be equal to itself
+    Sym(usize),
+}
+
+/// What is known about a value
+#[derive(Debug, Clone, Default)]
+struct Abstract {
+    ty: Option<ValueType>,
+    shape: Option<Vec<Dim>>,
+    /// The value itself, if it is a constant
+    value: Option<Value>,
+}
+
+impl Abstract {
+    fn from_value(value: &Value) -> Self {
+        let ty = match value {
+            Value::Num(_) | Value::Byte(_) => ValueType::Num,
+            Value::Char(_) => ValueType::Char,
+            Value::Box(_) => ValueType::Box,
+        };
+        Abstract {
+            ty: Some(ty),
+            shape: Some(value.shape().iter().map(|&n| Dim::Len(n)).collect()),
+            value: Some(value.clone()),
+        }
+    }
+    fn with_shape(ty: Option<ValueType>, shape: Option<Vec<Dim>>) -> Self {
+        Abstract {
+            ty,
+            shape,
+            value: None,
+        }
+    }
+    fn num(shape: Vec<Dim>) -> Self {
+        Abstract::with_shape(Some(ValueType::Num), Some(shape))
+    }
+    fn nats(&self) -> Option<Vec<usize>> {
+        let value = self.value.as_ref()?;
+        if value.rank() > 1 {
+            return None;
+        }
+        let nums: Vec<f64> = match value {
+            Value::Num(arr) => arr.data.iter().copied().collect(),
+            Value::Byte(arr) => arr.data.iter().map(|&n| n as f64).collect(),
+            _ => return None,
+        };
+        (nums.iter())
+            .map(|&n| (n >= 0.0 && n.fract() == 0.0).then_some(n as usize))
+            .collect()
+    }
+}
+
+/// Whether two axes are certain to have different lengths
+fn dims_differ(a: Dim, b: Dim) -> bool {
+    matches!((a, b), (Dim::Len(a), Dim::Len(b)) if a != b)
+}
+
+/// Whether two shapes are certain to be different
+fn shapes_differ(a: &[Dim], b: &[Dim]) -> bool {
+    a.len() != b.len() || a.iter().zip(b).any(|(&a, &b)| dims_differ(a, b))
+}
+
+/// Combine what is known about two axes that must be the same length
+fn merge_dim(a: Dim, b: Dim) -> Dim {
+    match (a, b) {
+        (Dim::Sym(_), Dim::Len(n)) => Dim::Len(n),
+        (a, _) => a,
+    }
+}
+
+fn merge_shapes(a: &[Dim], b: &[Dim]) -> Vec<Dim> {
+    a.iter().zip(b).map(|(&a, &b)| merge_dim(a, b)).collect()
+}
+
+fn type_name_plural(ty: ValueType) -> &'static str {
+    match ty {
+        ValueType::Num => "numbers",
+        ValueType::Char => "characters",
+        ValueType::Box => "boxes",
+    }
+}
+
+/// A function on the function stack
+#[derive(Clone)]
+enum Func {
+    Known(Arc<Function>),
+    /// A placeholder function, of which only the signature is known
+    Unknown(Signature),
+}
+
+impl Func {
+    fn function(&self) -> Arc<Function> {
+        match self {
+            Func::Known(f) => f.clone(),
+            Func::Unknown(sig) => Function::new(FunctionId::Temp, Vec::new(), *sig).into(),
+        }
+    }
+    /// The primitive if the function is just a single primitive
+    fn prim(&self) -> Option<Primitive> {
+        match self {
+            Func::Known(f) => match f.instrs.as_slice() {
+                [Instr::Prim(prim, _)] => Some(*prim),
+                _ => None,
+            },
+            Func::Unknown(_) => None,
+        }
+    }
+}
+
+struct Checker<'a> {
+    annotated: &'a HashMap<u64, (Ident, Annotation)>,
+    stack: Vec<Abstract>,
+    functions: Vec<Func>,
+    arrays: Vec<usize>,
+    inline_stack: Vec<Abstract>,
+    under_stack: Vec<Abstract>,
+    /// The names of unknown lengths that come from annotations, indexed by [`Dim::Sym`]
+    sym_names: Vec<Option<Ident>>,
+    depth: usize,
+    /// The span of the outermost call while following a call
+    call_span: Option<usize>,
+    problems: Vec<ShapeProblem>,
+}
+
+/// `None` means that checking cannot continue
+type Checked = Option<()>;
+
+impl<'a> Checker<'a> {
+    /// Check instructions, looking up the constants they push in a constant pool
+    fn instrs(&mut self, instrs: &[Instr], constants: &[Value]) -> Checked {
+        for instr in instrs {
+            self.instr(instr, constants)?;
+        }
+        Some(())
+    }
+    fn instr(&mut self, instr: &Instr, constants: &[Value]) -> Checked {
+        match instr {
+            Instr::Push(_) | Instr::PushConst(_) => {
+                let value = instr.as_constant(constants)?;
+                self.stack.push(Abstract::from_value(value))
+            }
+            Instr::BeginArray => self.arrays.push(self.stack.len()),
+            &Instr::EndArray { boxed, span, .. } => {
+                let bottom = self.arrays.pop()?;
+                let mut rows: Vec<Abstract> = self.stack.drain(bottom..).collect();
+                rows.reverse();
+                let value = if boxed {
+                    Abstract::with_shape(Some(ValueType::Box), Some(vec![Dim::Len(rows.len())]))
+                } else {
+                    self.array(rows, span)
+                };
+                self.stack.push(value);
+            }
+            Instr::Prim(prim, span) => self.prim(*prim, *span)?,
+            Instr::ImplPrim(prim, _) => {
+                for _ in 0..prim.modifier_args().unwrap_or(0) {
+                    self.pop_func()?;
+                }
+                self.unknown(prim.args() as usize, prim.outputs() as usize);
+            }
+            &Instr::Call(span) => {
+                let f = self.pop_func()?;
+                self.call(f, span)?;
+            }
+            Instr::PushFunc(f) => self.functions.push(Func::Known(f.clone())),
+            Instr::Switch { .. } => return None,
+            Instr::PushTempFunctions(_) | Instr::PopTempFunctions(_) => {}
+            Instr::GetTempFunction { sig, .. } => self.functions.push(Func::Unknown(*sig)),
+            Instr::Dynamic(f) => self.unknown(f.signature.args, f.signature.outputs),
+            &Instr::PushTempUnder { count, .. } => {
+                for _ in 0..count {
+                    let value = self.pop();
+                    self.under_stack.push(value);
+                }
+            }
+            &Instr::PopTempUnder { count, .. } => {
+                for _ in 0..count {
+                    let value = self.under_stack.pop().unwrap_or_default();
+                    self.stack.push(value);
+                }
+            }
+            &Instr::PushTempInline { count, .. } => {
+                for _ in 0..count {
+                    let value = self.pop();
+                    self.inline_stack.push(value);
+                }
+            }
+            &Instr::PopTempInline { count, .. } => {
+                for _ in 0..count {
+                    let value = self.inline_stack.pop().unwrap_or_default();
+                    self.stack.push(value);
+                }
+            }
+            &Instr::CopyTempInline { offset, count, .. } => {
+                let start = self.inline_stack.len().checked_sub(offset)?;
+                for i in 0..count {
+                    let value = self.inline_stack.get(start.checked_sub(i + 1)?)?.clone();
+                    self.stack.push(value);
+                }
+            }
+            &Instr::DropTempInline { count, .. } => {
+                let len = self.inline_stack.len().checked_sub(count)?;
+                self.inline_stack.truncate(len);
+            }
+            Instr::InitLocals { .. } => {}
+            Instr::SetLocal { .. } | Instr::SetGlobal { .. } => self.unknown(1, 0),
+            Instr::GetLocal { .. } | Instr::GetGlobal { .. } => self.unknown(0, 1),
+            &Instr::Unpack { count, .. } => {
+                let value = self.pop();
+                let row = match value.shape {
+                    Some(shape) if !shape.is_empty() => {
+                        Abstract::with_shape(value.ty, Some(shape[1..].to_vec()))
+                    }
+                    _ => Abstract::default(),
+                };
+                for _ in 0..count {
+                    self.stack.push(row.clone());
+                }
+            }
+        }
+        Some(())
+    }
+    fn prim(&mut self, prim: Primitive, span: usize) -> Checked {
+        use Primitive::*;
+        match prim {
+            Dup => {
+                let a = self.pop();
+                self.stack.push(a.clone());
+                self.stack.push(a);
+            }
+            Over => {
+                let a = self.pop();
+                let b = self.pop();
+                self.stack.push(b.clone());
+                self.stack.push(a);
+                self.stack.push(b);
+            }
+            Flip => {
+                let a = self.pop();
+                let b = self.pop();
+                self.stack.push(a);
+                self.stack.push(b);
+            }
+            Pop => {
+                self.pop();
+            }
+            Identity => {
+                let a = self.pop();
+                self.stack.push(a);
+            }
+            Dip => {
+                let f = self.pop_func()?;
+                let x = self.pop();
+                self.call(f, span)?;
+                self.stack.push(x);
+            }
+            Gap => {
+                let f = self.pop_func()?;
+                self.pop();
+                self.call(f, span)?;
+            }
+            prim if prim.class() == PrimClass::MonadicPervasive => {
+                let a = self.pop();
+                let ty = a.ty.filter(|&ty| ty == ValueType::Num);
+                self.stack.push(Abstract::with_shape(ty, a.shape));
+            }
+            prim if prim.class() == PrimClass::DyadicPervasive => {
+                let a = self.pop();
+                let b = self.pop();
+                let value = self.pervade(prim, a, b, span);
+                self.stack.push(value);
+            }
+            Len => {
+                self.pop();
+                self.stack.push(Abstract::num(Vec::new()));
+            }
+            Shape => {
+                let a = self.pop();
+                let len = match &a.shape {
+                    Some(shape) => Dim::Len(shape.len()),
+                    None => self.sym(),
+                };
+                self.stack.push(Abstract::num(vec![len]));
+            }
+            Reverse => {
+                let a = self.pop();
+                self.stack.push(Abstract::with_shape(a.ty, a.shape));
+            }
+            Transpose => {
+                let a = self.pop();
+                let shape = a.shape.map(|mut shape| {
+                    if !shape.is_empty() {
+                        shape.rotate_left(1);
+                    }
+                    shape
+                });
+                self.stack.push(Abstract::with_shape(a.ty, shape));
+            }
+            Deshape => {
+                let a = self.pop();
+                let len = (a.shape.as_ref())
+                    .and_then(|shape| {
+                        (shape.iter())
+                            .map(|dim| match dim {
+                                Dim::Len(n) => Some(*n),
+                                Dim::Sym(_) => None,
+                            })
+                            .product::<Option<usize>>()
+                    })
+                    .map_or_else(|| self.sym(), Dim::Len);
+                self.stack.push(Abstract::with_shape(a.ty, Some(vec![len])));
+            }
+            First => {
+                let a = self.pop();
+                let shape = a.shape.map(|shape| shape.get(1..).unwrap_or(&[]).to_vec());
+                self.stack.push(Abstract::with_shape(a.ty, shape));
+            }
+            Range => {
+                let a = self.pop();
+                let shape = match (a.nats(), &a.shape) {
+                    (Some(ns), Some(shape)) if shape.is_empty() => Some(vec![Dim::Len(ns[0])]),
+                    (Some(ns), _) => {
+                        let len = ns.len();
+                        Some(ns.into_iter().chain([len]).map(Dim::Len).collect())
+                    }
+                    (None, Some(shape)) if shape.is_empty() => Some(vec![self.sym()]),
+                    (None, _) => None,
+                };
+                self.stack
+                    .push(Abstract::with_shape(Some(ValueType::Num), shape));
+            }
+            Couple => {
+                let a = self.pop();
+                let b = self.pop();
+                let shape = match (&a.shape, &b.shape) {
+                    (Some(a_shape), Some(b_shape)) => {
+                        if shapes_differ(a_shape, b_shape) {
+                            self.problem(
+                                span,
+                                format!(
+                                    "Cannot couple arrays with shapes {} and {}",
+                                    self.format_shape(a_shape),
+                                    self.format_shape(b_shape)
+                                ),
+                            );
+                            return None;
+                        }
+                        let shape = merge_shapes(a_shape, b_shape);
+                        Some([Dim::Len(2)].into_iter().chain(shape).collect())
+                    }
+                    _ => None,
+                };
+                let ty = a.ty.filter(|_| a.ty == b.ty);
+                self.stack.push(Abstract::with_shape(ty, shape));
+            }
+            Join => {
+                let a = self.pop();
+                let b = self.pop();
+                let shape = match (&a.shape, &b.shape) {
+                    (Some(a_shape), Some(b_shape)) => Some(self.join(a_shape, b_shape, span)?),
+                    _ => None,
+                };
+                let ty = a.ty.filter(|_| a.ty == b.ty);
+                self.stack.push(Abstract::with_shape(ty, shape));
+            }
+            Reshape => {
+                let a = self.pop();
+                let b = self.pop();
+                let shape = match (a.nats(), &a.shape, &b.shape) {
+                    (Some(ns), Some(shape), Some(b_shape)) if shape.is_empty() => Some(
+                        [Dim::Len(ns[0])]
+                            .into_iter()
+                            .chain(b_shape.iter().copied())
+                            .collect(),
+                    ),
+                    (Some(ns), Some(shape), _) if !shape.is_empty() => {
+                        Some(ns.into_iter().map(Dim::Len).collect())
+                    }
+                    _ => None,
+                };
+                self.stack.push(Abstract::with_shape(b.ty, shape));
+            }
+            Reduce => {
+                let f = self.pop_func()?;
+                match f.prim().filter(|p| p.class() == PrimClass::DyadicPervasive) {
+                    Some(_) => {
+                        let a = self.pop();
+                        let shape = a.shape.map(|shape| shape.get(1..).unwrap_or(&[]).to_vec());
+                        let ty = a.ty.filter(|&ty| ty == ValueType::Num);
+                        self.stack.push(Abstract::with_shape(ty, shape));
+                    }
+                    None => self.modified(prim, span, vec![f])?,
+                }
+            }
+            Each | Rows => {
+                let f = self.pop_func()?;
+                match f
+                    .prim()
+                    .filter(|p| p.class() == PrimClass::MonadicPervasive)
+                {
+                    Some(_) => {
+                        let a = self.pop();
+                        let ty = a.ty.filter(|&ty| ty == ValueType::Num);
+                        self.stack.push(Abstract::with_shape(ty, a.shape));
+                    }
+                    None => self.modified(prim, span, vec![f])?,
+                }
+            }
+            Table | Cross => {
+                let f = self.pop_func()?;
+                let is_pervasive = f.prim().is_some_and(|p| p.class().is_pervasive());
+                match (is_pervasive, self.peek(0).shape, self.peek(1).shape) {
+                    (true, Some(a), Some(b)) if a.len() == 1 && b.len() == 1 => {
+                        self.pop();
+                        self.pop();
+                        self.stack
+                            .push(Abstract::with_shape(None, Some(vec![a[0], b[0]])));
+                    }
+                    _ => self.modified(prim, span, vec![f])?,
+                }
+            }
+            prim => {
+                let count = prim.modifier_args().unwrap_or(0) as usize;
+                let mut funcs = Vec::with_capacity(count);
+                for _ in 0..count {
+                    funcs.push(self.pop_func()?);
+                }
+                self.modified(prim, span, funcs)?;
+            }
+        }
+        Some(())
+    }
+    /// Handle a primitive whose effect on shapes is not known
+    ///
+    /// `funcs` are the function arguments, already popped, starting with the top.
+    fn modified(&mut self, prim: Primitive, span: usize, funcs: Vec<Func>) -> Checked {
+        if let Some((args, outputs)) = prim.args().zip(prim.outputs()) {
+            self.unknown(args as usize, outputs as usize);
+            return Some(());
+        }
+        let mut instrs: Vec<Instr> = (funcs.iter().rev())
+            .map(|f| Instr::PushFunc(f.function()))
+            .collect();
+        instrs.push(Instr::Prim(prim, span));
+        let sig = instrs_signature(&instrs).ok()?;
+        self.unknown(sig.args, sig.outputs);
+        Some(())
+    }
+    fn call(&mut self, f: Func, span: usize) -> Checked {
+        let f = match f {
+            Func::Known(f) => f,
+            Func::Unknown(sig) => {
+                self.unknown(sig.args, sig.outputs);
+                return Some(());
+            }
+        };
+        if let [Instr::Dynamic(dynamic)] = f.instrs.as_slice() {
+            if let Some((name, annotation)) = self.annotated.get(&dynamic.id) {
+                self.annotated_call(name, annotation, f.signature(), span);
+                return Some(());
+            }
+        }
+        if self.depth >= MAX_DEPTH {
+            let sig = f.signature();
+            self.unknown(sig.args, sig.outputs);
+            return Some(());
+        }
+        self.depth += 1;
+        let prev_span = self.call_span;
+        self.call_span = Some(prev_span.unwrap_or(span));
+        let res = self.instrs(&f.instrs, &f.constants);
+        self.call_span = prev_span;
+        self.depth -= 1;
+        res
+    }
+    /// Check the arguments of a call to an annotated function, and push the outputs it describes
+    fn annotated_call(
+        &mut self,
+        name: &Ident,
+        annotation: &Annotation,
+        sig: Signature,
+        span: usize,
+    ) {
+        let mut dims = HashMap::new();
+        for (i, pattern) in annotation.args.iter().enumerate() {
+            let value = self.peek(i);
+            if let Some(message) = self.mismatch(&pattern.value, &value, &mut dims) {
+                self.problem(span, describe("Argument", i, name, &message));
+            }
+        }
+        for _ in 0..sig.args {
+            self.pop();
+        }
+        match &annotation.outputs {
+            Some(outputs) => {
+                for pattern in outputs.iter().rev() {
+                    let value = self.pattern_value(&pattern.value, &mut dims);
+                    self.stack.push(value);
+                }
+            }
+            None => self.unknown(0, sig.outputs),
+        }
+    }
+    /// The values that a pattern describes
+    fn pattern_value(
+        &mut self,
+        pattern: &ValuePattern,
+        dims: &mut HashMap<Ident, Dim>,
+    ) -> Abstract {
+        let shape = (pattern.shape.as_ref())
+            .filter(|shape| !shape.contains(&DimPattern::Rest))
+            .map(|shape| {
+                (shape.iter())
+                    .map(|dim| match dim {
+                        DimPattern::Len(n) => Dim::Len(*n),
+                        DimPattern::Named(name) => match dims.get(name) {
+                            Some(&dim) => dim,
+                            None => {
+                                let dim = self.named_sym(*name);
+                                dims.insert(*name, dim);
+                                dim
+                            }
+                        },
+                        DimPattern::Any | DimPattern::Rest => self.sym(),
+                    })
+                    .collect()
+            });
+        Abstract::with_shape(pattern.ty, shape)
+    }
+    /// Describe how a value certainly does not match a pattern
+    fn mismatch(
+        &self,
+        pattern: &ValuePattern,
+        value: &Abstract,
+        dims: &mut HashMap<Ident, Dim>,
+    ) -> Option<String> {
+        if let (Some(expected), Some(ty)) = (pattern.ty, value.ty) {
+            if expected != ty {
+                return Some(format!(
+                    "must be {}, but it is {}",
+                    type_name_plural(expected),
+                    type_name_plural(ty)
+                ));
+            }
+        }
+        let (Some(dim_patterns), Some(shape)) = (&pattern.shape, &value.shape) else {
+            return None;
+        };
+        let mismatch = || {
+            let expected = ValuePattern {
+                ty: None,
+                shape: pattern.shape.clone(),
+            };
+            format!(
+                "must have shape {expected}, but it has shape {}",
+                self.format_shape(shape)
+            )
+        };
+        let rest = dim_patterns.iter().position(|dim| *dim == DimPattern::Rest);
+        let (front, back) = match rest {
+            Some(i) => (&dim_patterns[..i], &dim_patterns[i + 1..]),
+            None => (dim_patterns.as_slice(), &[][..]),
+        };
+        let fits = match rest {
+            Some(_) => shape.len() >= front.len() + back.len(),
+            None => shape.len() == front.len(),
+        };
+        if !fits {
+            return Some(mismatch());
+        }
+        let pairs = (front.iter().zip(shape)).chain(back.iter().rev().zip(shape.iter().rev()));
+        for (dim, &len) in pairs {
+            match dim {
+                DimPattern::Any | DimPattern::Rest => {}
+                &DimPattern::Len(n) => {
+                    if dims_differ(Dim::Len(n), len) {
+                        return Some(mismatch());
+                    }
+                }
+                DimPattern::Named(name) => match dims.get(name) {
+                    Some(&bound) if dims_differ(bound, len) => {
+                        let bound = self.format_dim(bound);
+                        return Some(format!("{}, and {name} is {bound}", mismatch()));
+                    }
+                    Some(_) => {}
+                    None => {
+                        dims.insert(*name, len);
+                    }
+                },
+            }
+        }
+        None
+    }
+    fn pervade(&mut self, prim: Primitive, a: Abstract, b: Abstract, span: usize) -> Abstract {
+        use Primitive::*;
+        let shape = match (&a.shape, &b.shape) {
+            (Some(a_shape), Some(b_shape)) => {
+                if a_shape
+                    .iter()
+                    .zip(b_shape)
+                    .any(|(&a, &b)| dims_differ(a, b))
+                {
+                    self.problem(
+                        span,
+                        format!(
+                            "Shapes {} and {} do not match",
+                            self.format_shape(a_shape),
+                            self.format_shape(b_shape)
+                        ),
+                    );
+                }
+                let (long, short) = if a_shape.len() >= b_shape.len() {
+                    (a_shape, b_shape)
+                } else {
+                    (b_shape, a_shape)
+                };
+                let mut shape = long.clone();
+                for (dim, &other) in shape.iter_mut().zip(short) {
+                    *dim = merge_dim(*dim, other);
+                }
+                Some(shape)
+            }
+            _ => None,
+        };
+        let ty = if matches!(prim, Eq | Ne | Lt | Le | Gt | Ge) {
+            Some(ValueType::Num)
+        } else {
+            a.ty.filter(|&ty| ty == ValueType::Num && b.ty == a.ty)
+        };
+        Abstract::with_shape(ty, shape)
+    }
+    /// The shape of an array of some rows
+    fn array(&mut self, rows: Vec<Abstract>, span: usize) -> Abstract {
+        let shapes: Option<Vec<&Vec<Dim>>> = rows.iter().map(|row| row.shape.as_ref()).collect();
+        let Some(shapes) = shapes else {
+            return Abstract::with_shape(None, Some(vec![Dim::Len(rows.len())]));
+        };
+        let mut row_shape = shapes
+            .first()
+            .map(|shape| shape.to_vec())
+            .unwrap_or_default();
+        for (i, shape) in shapes.iter().enumerate().skip(1) {
+            if shapes_differ(shape, shapes[0]) {
+                self.problem(
+                    span,
+                    format!(
+                        "Cannot construct array: row {i} has shape {} but row 0 has shape {}",
+                        self.format_shape(shape),
+                        self.format_shape(shapes[0])
+                    ),
+                );
+                return Abstract::default();
+            }
+            row_shape = merge_shapes(&row_shape, shape);
+        }
+        let ty = rows.first().and_then(|row| row.ty);
+        let ty = ty.filter(|_| rows.iter().all(|row| row.ty == ty));
+        let shape = [Dim::Len(rows.len())]
+            .into_iter()
+            .chain(row_shape)
+            .collect();
+        Abstract::with_shape(ty, Some(shape))
+    }
+    /// The shape of joining two arrays
+    fn join(&mut self, a: &[Dim], b: &[Dim], span: usize) -> Option<Vec<Dim>> {
+        let add = |a: Dim, b: Dim, checker: &mut Self| match (a, b) {
+            (Dim::Len(a), Dim::Len(b)) => Dim::Len(a + b),
+            _ => checker.sym(),
+        };
+        if a.len() == b.len() {
+            if a.is_empty() {
+                return Some(vec![Dim::Len(2)]);
+            }
+            if shapes_differ(&a[1..], &b[1..]) {
+                self.join_problem(a, b, span);
+                return None;
+            }
+            let len = add(a[0], b[0], self);
+            return Some(
+                [len]
+                    .into_iter()
+                    .chain(merge_shapes(&a[1..], &b[1..]))
+                    .collect(),
+            );
+        }
+        let (short, long) = if a.len() < b.len() { (a, b) } else { (b, a) };
+        if long.len() - short.len() > 1 {
+            self.problem(
+                span,
+                format!(
+                    "Cannot join rank {} array with rank {} array",
+                    a.len(),
+                    b.len()
+                ),
+            );
+            return None;
+        }
+        if shapes_differ(short, &long[1..]) {
+            self.join_problem(a, b, span);
+            return None;
+        }
+        let len = add(long[0], Dim::Len(1), self);
+        Some(
+            [len]
+                .into_iter()
+                .chain(merge_shapes(&long[1..], short))
+                .collect(),
+        )
+    }
+    fn join_problem(&mut self, a: &[Dim], b: &[Dim], span: usize) {
+        let message = format!(
+            "Cannot join arrays of shapes {} and {}",
+            self.format_shape(a),
+            self.format_shape(b)
+        );
+        self.problem(span, message);
+    }
+    fn problem(&mut self, span: usize, message: String) {
+        let problem = ShapeProblem {
+            span: Some(self.call_span.unwrap_or(span)),
+            message,
+        };
+        if !self.problems.contains(&problem) {
+            self.problems.push(problem);
+        }
+    }
+    /// Pop a value, which is unknown if it was on the stack before the checked code
+    fn pop(&mut self) -> Abstract {
+        let value = self.stack.pop().unwrap_or_default();
+        if let Some(bottom) = self.arrays.last_mut() {
+            *bottom = (*bottom).min(self.stack.len());
+        }
+        value
+    }
+    fn peek(&self, depth: usize) -> Abstract {
+        (self.stack.len().checked_sub(depth + 1))
+            .map(|i| self.stack[i].clone())
+            .unwrap_or_default()
+    }
+    fn pop_func(&mut self) -> Option<Func> {
+        self.functions.pop()
+    }
+    fn unknown(&mut self, args: usize, outputs: usize) {
+        for _ in 0..args {
+            self.pop();
+        }
+        for _ in 0..outputs {
+            self.stack.push(Abstract::default());
+        }
+    }
+    fn sym(&mut self) -> Dim {
+        self.sym_names.push(None);
+        Dim::Sym(self.sym_names.len() - 1)
+    }
+    fn named_sym(&mut self, name: Ident) -> Dim {
+        self.sym_names.push(Some(name));
+        Dim::Sym(self.sym_names.len() - 1)
+    }
+    fn format_dim(&self, dim: Dim) -> String {
+        match dim {
+            Dim::Len(n) => n.to_string(),
+            Dim::Sym(i) => match &self.sym_names[i] {
+                Some(name) => name.to_string(),
+                None => "_".into(),
+            },
+        }
+    }
+    fn format_shape(&self, shape: &[Dim]) -> String {
+        let dims: Vec<String> = shape.iter().map(|&dim| self.format_dim(dim)).collect();
+        format!("[{}]", dims.join(" × "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::diagnostics;
+    use crate::Uiua;
+
+    #[test]
+    fn shape_checks() {
+        let warnings = |code: &str| {
+            let mut env = Uiua::with_native_sys().with_shape_checks(true);
+            env.check_str(code).unwrap();
+            diagnostics(&mut env)
+        };
+        assert_eq!(
+            warnings("+ [1 2 3] [4 5]"),
+            ["Shapes [3] and [2] do not match"]
+        );
+        assert_eq!(
+            warnings("[⇡3 ⇡4]"),
+            ["Cannot construct array: row 1 has shape [4] but row 0 has shape [3]"]
+        );
+        assert_eq!(
+            warnings("Dot ← |[N] [N].[]| /+×\nDot ⇡3 ⇡2"),
+            ["Argument 2 of Dot must have shape [N], but it has shape [2], and N is 3"]
+        );
+        assert_eq!(
+            warnings("F ← |[N].[N]| ↯3"),
+            ["Output 1 of F must have shape [N], but it has shape [3 × N]"]
+        );
+        // Only mismatches that are certain are reported
+        assert!(warnings("F ← |[N] [M]| +\nF ⇡3 ⇡3").is_empty());
+        assert!(warnings("F ← |[N] [M]| +").is_empty());
+        assert!(warnings("⬚0+ [1 2 3] [4 5]").is_empty());
+        // Shapes are not checked by default
+        let mut env = Uiua::with_native_sys();
+        env.check_str("+ [1 2 3] [4 5]").unwrap();
+        assert!(env.take_diagnostics().is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+//! Capturing the stack after each word of some code runs
+
+use crate::{ast::Item, parse::parse, version, CodeSpan, Uiua, UiuaError, Value};
+
+/// Limits on how much of the stack a [`Snapshot`] keeps
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotLimits {
+    /// The most values kept from the top of the stack
+    pub values: usize,
+    /// The most elements a value can have and still be kept
+    pub elements: usize,
+}
+
+impl Default for SnapshotLimits {
+    fn default() -> Self {
+        SnapshotLimits {
+            values: 16,
+            elements: 1000,
+        }
+    }
+}
+
+/// The stack after a word of code runs
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// The code of the word
+    pub code: String,
+    pub span: CodeSpan,
+    /// The values on the top of the stack, with the top of the stack last
+    pub stack: Vec<SnapshotValue>,
+    /// How many values below these were left out
+    pub omitted: usize,
+}
+
+/// A value in a [`Snapshot`]
+#[derive(Debug, Clone)]
+pub enum SnapshotValue {
+    Value(Value),
+    /// The shape of a value that has too many elements to keep
+    TooBig(Vec<usize>),
+}
+
+/// The snapshots taken while running some code
+#[derive(Debug, Clone, Default)]
+pub struct Snapshots {
+    /// The snapshots, in the order the words ran
+    pub steps: Vec<Snapshot>,
+    /// The error that stopped the code, if there was one
+    ///
+    /// The snapshots before it are still kept.
+    pub error: Option<UiuaError>,
+}
+
+impl Uiua {
+    /// Run some code, taking a snapshot of the stack after each word in each line runs
+    ///
+    /// Bindings are run without snapshots.
+    /// The words of a line run one at a time, so they can not be optimized together,
+    /// but they do the same thing as they would otherwise.
+    /// The stack is left as the code leaves it.
+    pub fn snapshots(&mut self, input: &str, limits: SnapshotLimits) -> Snapshots {
+        let mut snapshots = Snapshots::default();
+        if let Err(error) = self.snapshots_impl(input, limits, &mut snapshots.steps) {
+            snapshots.error = Some(error);
+        }
+        snapshots
+    }
+    fn snapshots_impl(
+        &mut self,
+        input: &str,
+        limits: SnapshotLimits,
+        steps: &mut Vec<Snapshot>,
+    ) -> Result<(), UiuaError> {
+        let (items, errors, _) = parse(input, None);
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+        self.experimental = self.experimental || version::declares_experimental(&items);
+        self.execution_start = instant::now();
+        for item in items {
+            let Item::Words(words) = item else {
+                self.items(vec![item], false)?;
+                continue;
+            };
+            for word in words.into_iter().rev().filter(|word| word.value.is_code()) {
+                let span = word.span.clone();
+                let instrs = self.compile_words(vec![word], true)?;
+                self.exec_global_instrs(instrs)?;
+                steps.push(self.snapshot(span, limits));
+            }
+        }
+        Ok(())
+    }
+    fn snapshot(&self, span: CodeSpan, limits: SnapshotLimits) -> Snapshot {
+        let stack = &self.stack;
+        let omitted = stack.len().saturating_sub(limits.values);
+        let stack = (stack[omitted..].iter())
+            .map(|val| {
+                if val.shape().iter().product::<usize>() > limits.elements {
+                    SnapshotValue::TooBig(val.shape().to_vec())
+                } else {
+                    SnapshotValue::Value(val.clone())
+                }
+            })
+            .collect();
+        Snapshot {
+            code: span.as_str().into(),
+            span,
+            stack,
+            omitted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots() {
+        let mut env = Uiua::with_native_sys();
+        let limits = SnapshotLimits {
+            values: 2,
+            elements: 3,
+        };
+        let snapshots = env.snapshots("F ← ×2\nF /+ ⇡ 3 ⇡10 5", limits);
+        assert!(snapshots.error.is_none());
+        let codes: Vec<&str> = snapshots.steps.iter().map(|s| &*s.code).collect();
+        assert_eq!(codes, ["5", "10", "⇡", "3", "⇡", "/+", "F"]);
+        let second = &snapshots.steps[2];
+        assert_eq!(second.omitted, 0);
+        assert!(
+            matches!(&second.stack[..], [SnapshotValue::Value(_), SnapshotValue::TooBig(shape)] if shape == &[10])
+        );
+        let last = &snapshots.steps[6];
+        assert_eq!(last.omitted, 1);
+        assert!(matches!(&last.stack[1], SnapshotValue::Value(v) if v.show() == "6"));
+        // Snapshots before an error are kept
+        let snapshots = Uiua::with_native_sys().snapshots("+ ⊂ 1", limits);
+        assert_eq!(snapshots.steps.len(), 1);
+        assert!(snapshots.error.is_some());
+    }
+}
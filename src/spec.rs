@@ -0,0 +1,245 @@
+//! Executable specification files
+//!
+//! A spec file is a `.ua` file made of cases separated by blank lines.
+//! Each case is some code followed by comments that record what running it should do:
+//! - `#> ` lines are the values left on the stack, from bottom to top, as [`Value::snapshot`](crate::Value::snapshot) shows them
+//! - `#| ` lines are the text printed to stdout, without trailing whitespace
+//! - `#! ` lines are the message of the error the code gives
+//!
+//! ```text
+//! # Range
+//! ⇡5
+//! #> [0 1 2 3 4]
+//!
+//! &p "hi" 5
+//! #| hi
+//! #> 5
+//! ```
+//!
+//! Each case runs in its own deterministic runtime with a [`NullSys`] backend,
+//! so cases cannot do IO, do not depend on each other, and give the same results every run.
+//! If the first line of the file is `# Experimental!`, every case can use experimental primitives.
+//!
+//! `uiua spec` checks spec files, and `uiua spec --update` rewrites their expectations.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{CellOutput, NullSys, Uiua, UiuaError, UiuaResult};
+
+const STACK: &str = "#>";
+const STDOUT: &str = "#|";
+const ERROR: &str = "#!";
+
+fn is_expectation(line: &str) -> bool {
+    [STACK, STDOUT, ERROR]
+        .iter()
+        .any(|prefix| line.trim_start().starts_with(prefix))
+}
+
+/// A parsed spec file
+#[derive(Debug, Clone)]
+pub struct SpecFile {
+    pub path: PathBuf,
+    pub cases: Vec<SpecCase>,
+    /// Whether the cases can use experimental primitives
+    pub experimental: bool,
+}
+
+/// A single case in a spec file
+#[derive(Debug, Clone)]
+pub struct SpecCase {
+    /// The line the case starts on
+    pub line: usize,
+    /// The lines of code, including any comments that are not expectations
+    pub code: Vec<String>,
+    /// The expectation lines
+    pub expected: Vec<String>,
+}
+
+/// A case whose code did not do what was expected
+#[derive(Debug, Clone)]
+pub struct SpecFailure {
+    pub path: PathBuf,
+    pub line: usize,
+    pub code: String,
+    pub expected: Vec<String>,
+    pub actual: Vec<String>,
+}
+
+impl fmt::Display for SpecFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}:{}", self.path.display(), self.line)?;
+        for line in self.code.lines() {
+            writeln!(f, "  {line}")?;
+        }
+        writeln!(f, "expected:")?;
+        for line in &self.expected {
+            writeln!(f, "  {line}")?;
+        }
+        writeln!(f, "actual:")?;
+        for line in &self.actual {
+            writeln!(f, "  {line}")?;
+        }
+        Ok(())
+    }
+}
+
+impl SpecCase {
+    /// The code to run
+    pub fn source(&self) -> String {
+        self.code.join("\n")
+    }
+    /// Run the case and get the expectation lines that describe what it did
+    pub fn run(&self, experimental: bool) -> Vec<String> {
+        let mut env = Uiua::with_backend(NullSys)
+            .deterministic(true)
+            .with_experimental(experimental)
+            .capture_output();
+        let res = env.load_str(&self.source());
+        let mut lines = Vec::new();
+        for output in env.take_captured_output() {
+            if let CellOutput::Stdout(text) = output {
+                let text = text.strip_suffix('\n').unwrap_or(&text);
+                lines.extend((text.split('\n')).map(|line| expectation(STDOUT, line.trim_end())));
+            }
+        }
+        match res {
+            Ok(()) => {
+                for value in env.take_stack() {
+                    lines.extend(
+                        value
+                            .snapshot()
+                            .lines()
+                            .map(|line| expectation(STACK, line)),
+                    );
+                }
+            }
+            Err(error) => {
+                let message = error.bare_message();
+                lines.extend(message.lines().map(|line| expectation(ERROR, line)));
+            }
+        }
+        lines
+    }
+}
+
+fn expectation(prefix: &str, line: &str) -> String {
+    if line.is_empty() {
+        prefix.into()
+    } else {
+        format!("{prefix} {line}")
+    }
+}
+
+impl SpecFile {
+    /// Parse the text of a spec file
+    pub fn parse(path: impl Into<PathBuf>, text: &str) -> Self {
+        let mut cases = Vec::new();
+        let mut case: Option<SpecCase> = None;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                cases.extend(case.take());
+                continue;
+            }
+            let case = case.get_or_insert_with(|| SpecCase {
+                line: i + 1,
+                code: Vec::new(),
+                expected: Vec::new(),
+            });
+            if is_expectation(line) {
+                case.expected.push(line.trim_start().into());
+            } else {
+                case.code.push(line.into());
+            }
+        }
+        cases.extend(case);
+        SpecFile {
+            path: path.into(),
+            cases,
+            experimental: text.lines().next().map(str::trim) == Some("# Experimental!"),
+        }
+    }
+    /// Load a spec file
+    pub fn load(path: impl AsRef<Path>) -> UiuaResult<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| UiuaError::Load(path.into(), e.into()))?;
+        Ok(Self::parse(path, &text))
+    }
+    /// Run every case and get the ones that did not do what was expected
+    pub fn check(&self) -> Vec<SpecFailure> {
+        let mut failures = Vec::new();
+        for case in &self.cases {
+            let actual = case.run(self.experimental);
+            if actual != case.expected {
+                failures.push(SpecFailure {
+                    path: self.path.clone(),
+                    line: case.line,
+                    code: case.source(),
+                    expected: case.expected.clone(),
+                    actual,
+                });
+            }
+        }
+        failures
+    }
+    /// Run every case and get the text of the file with the expectations replaced by what the code did
+    pub fn updated(&self) -> String {
+        let mut text = String::new();
+        for (i, case) in self.cases.iter().enumerate() {
+            if i > 0 {
+                text.push('\n');
+            }
+            for line in case.code.iter().chain(&case.run(self.experimental)) {
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+/// Find the spec files at a path
+///
+/// If the path is a directory, all the `.ua` files in it and its subdirectories are found.
+pub fn spec_files(path: &Path) -> UiuaResult<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.into()]);
+    }
+    let mut files = Vec::new();
+    let entries = fs::read_dir(path).map_err(|e| UiuaError::Load(path.into(), e.into()))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| UiuaError::Load(path.into(), e.into()))?;
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(spec_files(&path)?);
+        } else if path.extension().is_some_and(|ext| ext == "ua") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_corpus() {
+        let mut failures = Vec::new();
+        for path in spec_files("tests/spec".as_ref()).unwrap() {
+            failures.extend(SpecFile::load(path).unwrap().check());
+        }
+        if !failures.is_empty() {
+            let failures: Vec<String> = failures.iter().map(ToString::to_string).collect();
+            panic!(
+                "Spec cases failed. Run `uiua spec --update` if the new behavior is right.\n\n{}",
+                failures.join("\n")
+            );
+        }
+    }
+}
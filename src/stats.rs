@@ -0,0 +1,209 @@
+//! Statistics about the code of a program
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ast::{walk_binding, walk_word, Binding, Visitor, Word},
+    lex::{CodeSpan, Loc, Sp},
+    parse::parse,
+    Ident, Primitive, Uiua, UiuaError, UiuaResult,
+};
+
+/// Statistics about the code of a file or project
+#[derive(Debug, Clone, Default)]
+pub struct ProgramStats {
+    /// How many times each primitive is used, including as a modifier
+    pub primitives: BTreeMap<Primitive, usize>,
+    /// The size of each binding
+    pub bindings: Vec<BindingStats>,
+    /// The array literal with the highest rank, if there are any
+    ///
+    /// Literals that are inside other literals are not counted separately.
+    pub max_rank_literal: Option<(usize, CodeSpan)>,
+    /// Bindings of imports that are never referenced
+    pub unused_imports: Vec<Sp<Ident>>,
+}
+
+/// The size of a binding
+#[derive(Debug, Clone)]
+pub struct BindingStats {
+    pub name: Sp<Ident>,
+    /// The number of words in the binding's code, including the words in its functions and arrays
+    pub words: usize,
+}
+
+impl ProgramStats {
+    /// The primitives used, with the most used first
+    pub fn primitives_by_count(&self) -> Vec<(Primitive, usize)> {
+        let mut prims: Vec<(Primitive, usize)> =
+            self.primitives.iter().map(|(p, n)| (*p, *n)).collect();
+        prims.sort_by(|(_, a), (_, b)| b.cmp(a));
+        prims
+    }
+    /// Combine the statistics of another file into these ones
+    pub fn merge(&mut self, other: Self) {
+        for (prim, count) in other.primitives {
+            *self.primitives.entry(prim).or_default() += count;
+        }
+        self.bindings.extend(other.bindings);
+        if let Some((rank, span)) = other.max_rank_literal {
+            if !matches!(&self.max_rank_literal, Some((max, _)) if *max >= rank) {
+                self.max_rank_literal = Some((rank, span));
+            }
+        }
+        self.unused_imports.extend(other.unused_imports);
+    }
+}
+
+/// Get statistics about some code
+///
+/// The code is checked but not run, so the files it imports are not loaded.
+pub fn stats(input: &str, path: Option<&Path>) -> UiuaResult<ProgramStats> {
+    let (items, errors, _) = parse(input, path);
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+    let mut counter = Counter::default();
+    counter.visit_items(&items);
+    let mut stats = counter.stats;
+    // Imports are unused if their bindings are never referenced
+    let mut env = Uiua::with_native_sys();
+    env.binding_refs = Some(Vec::new());
+    env.check_impl(input, path)?;
+    let refs = env.binding_refs.take().unwrap_or_default();
+    let used: HashSet<usize> = (refs.iter().filter(|r| !r.definition))
+        .map(|r| r.index)
+        .collect();
+    let defined: Vec<(Loc, usize)> = (refs.iter().filter(|r| r.definition))
+        .map(|r| (r.span.start, r.index))
+        .collect();
+    stats.unused_imports = (counter.imports.into_iter())
+        .filter(|name| {
+            !(defined.iter())
+                .filter(|(loc, _)| *loc == name.span.start)
+                .any(|(_, index)| used.contains(index))
+        })
+        .collect();
+    Ok(stats)
+}
+
+/// Get statistics about some files, combined
+pub fn project_stats<P: AsRef<Path>>(paths: &[P]) -> UiuaResult<ProgramStats> {
+    let mut project = ProgramStats::default();
+    for path in paths {
+        let path = path.as_ref();
+        let input =
+            fs::read_to_string(path).map_err(|e| UiuaError::Load(PathBuf::from(path), e.into()))?;
+        project.merge(stats(&input, Some(path))?);
+    }
+    Ok(project)
+}
+
+#[derive(Default)]
+struct Counter {
+    stats: ProgramStats,
+    /// The number of words in the binding being visited
+    words: Option<usize>,
+    /// Whether the word being visited is inside an array literal
+    in_literal: bool,
+    /// Bindings whose code imports something
+    imports: Vec<Sp<Ident>>,
+}
+
+impl Visitor for Counter {
+    fn visit_binding(&mut self, binding: &Binding) {
+        let outer = self.words.replace(0);
+        walk_binding(self, binding);
+        let words = self.words.take().unwrap_or(0);
+        self.words = outer.map(|outer| outer + words);
+        self.stats.bindings.push(BindingStats {
+            name: binding.name.clone(),
+            words,
+        });
+        if (binding.words.iter())
+            .any(|word| matches!(word.value, Word::Primitive(Primitive::Sys(op)) if op.is_import()))
+        {
+            self.imports.push(binding.name.clone());
+        }
+    }
+    fn visit_word(&mut self, word: &Sp<Word>) {
+        if let Some(words) = &mut self.words {
+            if word.value.is_code() {
+                *words += 1;
+            }
+        }
+        let is_literal = matches!(word.value, Word::Array(_));
+        if is_literal && !self.in_literal {
+            let rank = literal_rank(&word.value);
+            if !matches!(&self.stats.max_rank_literal, Some((max, _)) if *max >= rank) {
+                self.stats.max_rank_literal = Some((rank, word.span.clone()));
+            }
+        }
+        let outer = self.in_literal;
+        self.in_literal |= is_literal;
+        walk_word(self, word);
+        self.in_literal = outer;
+    }
+    fn visit_primitive(&mut self, prim: Primitive, _: &CodeSpan) {
+        *self.stats.primitives.entry(prim).or_default() += 1;
+    }
+}
+
+/// The rank of an array literal, as far as can be known without running it
+fn literal_rank(word: &Word) -> usize {
+    let max_rank = |words: &[Sp<Word>]| {
+        (words.iter())
+            .filter(|word| word.value.is_code())
+            .map(|word| literal_rank(&word.value))
+            .max()
+            .unwrap_or(0)
+    };
+    match word {
+        Word::String(_) | Word::FormatString(_) | Word::MultilineString(_) => 1,
+        Word::Strand(items) => 1 + max_rank(items),
+        Word::Array(arr) if arr.constant => 1,
+        Word::Array(arr) => {
+            1 + arr
+                .lines
+                .iter()
+                .map(|line| max_rank(line))
+                .max()
+                .unwrap_or(0)
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats() {
+        let code = "\
+Unused ← &i \"lib.ua\"
+Lib ← &i \"lib.ua\"
+Sq ← ×.
+Lib \"Foo\"
+[[1_2 3_4] [5_6 7_8]]
+Sq ⊂[1 2] 3";
+        let stats = super::stats(code, None).unwrap();
+        assert_eq!(stats.primitives[&Primitive::Mul], 1);
+        assert_eq!(stats.primitives[&Primitive::Dup], 1);
+        assert_eq!(stats.primitives_by_count()[0].1, 2);
+        let sizes: Vec<(&str, usize)> = (stats.bindings.iter())
+            .map(|b| (&*b.name.value, b.words))
+            .collect();
+        assert_eq!(sizes, [("Unused", 2), ("Lib", 2), ("Sq", 2)]);
+        let (rank, span) = stats.max_rank_literal.unwrap();
+        assert_eq!((rank, span.start.line), (3, 5));
+        let unused: Vec<&str> = (stats.unused_imports.iter())
+            .map(|name| &*name.value)
+            .collect();
+        assert_eq!(unused, ["Unused"]);
+    }
+}
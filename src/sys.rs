@@ -1,8 +1,8 @@
 use std::{
     any::Any,
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap},
     io::{stderr, stdin, Cursor, Read, Write},
-    sync::OnceLock,
+    sync::{Arc, OnceLock},
     time::Duration,
 };
 
@@ -21,15 +21,18 @@ use crate::{
     function::Signature,
     primitive::PrimDoc,
     value::Value,
-    Uiua, UiuaError, UiuaResult,
+    CellOutput, Ident, Uiua, UiuaError, UiuaResult,
 };
 
 pub fn example_ua<T>(f: impl FnOnce(&mut String) -> T) -> T {
     static EXAMPLE_UA: Lazy<Mutex<String>> = Lazy::new(|| {
         Mutex::new(
             "\
+# Square a number
 Square ← ×.
+# Double a number
 Double ← +.
+# Add 1 to a number
 Increment ← +1"
                 .into(),
         )
@@ -132,6 +135,10 @@ sys_op! {
     (1(0), Prin, StdIO, "&pf", "print and flush"),
     /// Print a value to stdout followed by a newline
     (1(0), Print, StdIO, "&p", "print with newline"),
+    /// Print a value to stderr followed by a newline
+    ///
+    /// This is useful for keeping messages about a program's progress or problems separate from its output.
+    (1(0), PrintErr, StdIO, "&ep", "print to stderr"),
     /// Read a line from stdin
     ///
     /// The normal output is a string.
@@ -148,6 +155,26 @@ sys_op! {
     /// Expects a boolean.
     /// If enabled, the terminal will not echo characters or wait for a newline.
     (1(0), RawMode, Env, "&raw", "set raw mode"),
+    /// Get the next input event, or an empty list if there is none
+    ///
+    /// Events are lists of numbers. The first number is the kind of event.
+    /// - `[0 key]` - A key was pressed. `key` is the code point of its character, or a negative number for keys that are not characters:
+    ///   `¯1` up, `¯2` down, `¯3` left, `¯4` right, `¯5` home, `¯6` end, `¯7` page up, `¯8` page down, `¯9` insert, and `¯10` minus n for function key n
+    /// - `[1 x y]` - The mouse moved
+    /// - `[2 x y button]` - A mouse button was pressed. `button` is `0` for left, `1` for right, and `2` for middle
+    /// - `[3 x y button]` - A mouse button was released
+    /// - `[4 width height]` - The terminal or window was resized
+    /// - `[5]` - The window was closed. See [&wf].
+    ///
+    /// In the terminal, key and mouse events are only reported in raw mode. See [&raw].
+    /// ex: &raw 1
+    ///   : ⍢(&inp &sl 0.05;|=0⧻) &inp
+    ///   : &raw 0
+    (0, PollEvent, Env, "&inp", "input - poll"),
+    /// Wait for the next input event
+    ///
+    /// See [&inp] for how events are encoded.
+    (0, WaitEvent, Env, "&inw", "input - wait"),
     /// Get the command line arguments
     ///
     /// The first element will always be the name of your script
@@ -173,6 +200,20 @@ sys_op! {
     /// On the web, this example will hang for 1 second.
     /// ex: ⚂ &sl 1
     (1(0), Sleep, Misc, "&sl", "sleep"),
+    /// Call a function every n seconds until it returns 0
+    ///
+    /// The function may take the number of times it has been called, starting at 0.
+    /// It runs in a copy of the environment, so it cannot change the stack.
+    /// ex: &ev(&p "tick" <3) 0.1
+    /// Time spent in the function counts toward the time between calls.
+    /// On the web, this example will hang until the function returns 0.
+    (1(0)[1], Every, Misc, "&ev", "every"),
+    /// Call an operation that the environment provides
+    ///
+    /// The first argument is the name of the operation. The second is its argument, which it replaces with its result.
+    /// Programs that embed Uiua can use this to give Uiua code access to things that are specific to them, like the state of a game or a sensor's reading.
+    /// The native interpreter has no such operations.
+    (2, Ext, Misc, "&ext", "extension"),
     /// Read at most n bytes from a stream
     (2, ReadStr, Stream, "&rs", "read to string"),
     /// Read at most n bytes from a stream
@@ -194,6 +235,14 @@ sys_op! {
     ///   : Square ← ex "Square"
     ///   : Square Double 5
     (2, Import, Filesystem, "&i", "import"),
+    /// Import an item from a file without letting the file's code do IO
+    ///
+    /// This works like [&i], but code from the file cannot use any other system functions, even when it is called from code that can.
+    /// This is a way to use a file you don't fully trust.
+    /// ex: Sqr ← &ip "example.ua" "Square"
+    ///   : Sqr 5
+    /// Files that the file imports are also imported this way.
+    (2, ImportPure, Filesystem, "&ip", "import pure"),
     /// Invoke a path with the system's default program
     (1(1), Invoke, Command, "&invk", "invoke"),
     /// Close a stream by its handle
@@ -286,6 +335,16 @@ sys_op! {
     ///
     /// See also: [&ime]
     (1(0), ImShow, Images, "&ims", "image - show"),
+    /// Show an image as a frame in a window
+    ///
+    /// The first frame opens the window, and later frames replace the one before.
+    /// Images are the same as for [&ims].
+    /// While the window is open, [&inp] and [&inw] get key and mouse events from it.
+    /// When the window is closed, they get a `[5]` event, and the next frame opens a new window.
+    ///
+    /// This is only available in the native interpreter when it is built with the `window` feature.
+    /// ex: &ev(<100 ⊙(&wf ÷2+1○÷10+⊞+.⇡100).) ÷60 1
+    (1(0), WindowFrame, Images, "&wf", "window - frame"),
     /// Decode a gif from a byte array
     ///
     /// Returns a framerate in seconds and a rank 4 array of RGBA frames.
@@ -439,6 +498,108 @@ impl From<Handle> for Value {
     }
 }
 
+/// When the default [`SysBackend::clock`] started
+static CLOCK_START: Lazy<instant::Instant> = Lazy::new(instant::Instant::now);
+
+/// An input event, like a key press or a mouse click
+///
+/// See [`SysOp::PollEvent`] for how events are encoded as arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    Key(Key),
+    MouseMove {
+        x: usize,
+        y: usize,
+    },
+    MousePress {
+        x: usize,
+        y: usize,
+        button: MouseButton,
+    },
+    MouseRelease {
+        x: usize,
+        y: usize,
+        button: MouseButton,
+    },
+    Resize {
+        width: usize,
+        height: usize,
+    },
+    Close,
+}
+
+/// A key on the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// A key that types a character, including enter, tab, backspace, and escape
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    /// A function key
+    F(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+impl InputEvent {
+    /// Encode the event as a list of numbers
+    pub fn encode(&self) -> Vec<f64> {
+        let mouse = |kind: f64, x: usize, y: usize| vec![kind, x as f64, y as f64];
+        match *self {
+            InputEvent::Key(key) => vec![0.0, key.code()],
+            InputEvent::MouseMove { x, y } => mouse(1.0, x, y),
+            InputEvent::MousePress { x, y, button } => {
+                let mut encoded = mouse(2.0, x, y);
+                encoded.push(button as u8 as f64);
+                encoded
+            }
+            InputEvent::MouseRelease { x, y, button } => {
+                let mut encoded = mouse(3.0, x, y);
+                encoded.push(button as u8 as f64);
+                encoded
+            }
+            InputEvent::Resize { width, height } => vec![4.0, width as f64, height as f64],
+            InputEvent::Close => vec![5.0],
+        }
+    }
+}
+
+impl Key {
+    /// The code of the key used in encoded events
+    pub fn code(&self) -> f64 {
+        match *self {
+            Key::Char(c) => c as u32 as f64,
+            Key::Up => -1.0,
+            Key::Down => -2.0,
+            Key::Left => -3.0,
+            Key::Right => -4.0,
+            Key::Home => -5.0,
+            Key::End => -6.0,
+            Key::PageUp => -7.0,
+            Key::PageDown => -8.0,
+            Key::Insert => -9.0,
+            Key::F(n) => -10.0 - n as f64,
+        }
+    }
+}
+
+/// A function called by [`SysBackend::schedule`]
+///
+/// It returns whether to keep calling it.
+pub type ScheduleFn = Box<dyn FnMut() -> Result<bool, String> + Send>;
+
 pub type AudioStreamFn = Box<dyn FnMut(&[f64]) -> UiuaResult<Vec<[f64; 2]>> + Send>;
 
 #[allow(unused_variables)]
@@ -446,12 +607,23 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     fn any(&self) -> &dyn Any;
     /// Save a color-formatted version of an error message for later printing
     fn save_error_color(&self, error: &UiuaError) {}
+    /// Print to stdout
+    ///
+    /// The text may be buffered until [`SysBackend::flush_stdout`] is called.
+    /// The interpreter flushes stdout before anything is printed to stderr or traced,
+    /// before reading from stdin, and when a program finishes,
+    /// so output from all three appears in the order it was printed.
     fn print_str_stdout(&self, s: &str) -> Result<(), String> {
         Err("Printing to stdout is not supported in this environment".into())
     }
+    /// Write any text that [`SysBackend::print_str_stdout`] has buffered
+    fn flush_stdout(&self) -> Result<(), String> {
+        Ok(())
+    }
     fn print_str_stderr(&self, s: &str) -> Result<(), String> {
         Err("Printing to stderr is not supported in this environment".into())
     }
+    /// Print the output of [`trace`](crate::Primitive::Trace) and [`dump`](crate::Primitive::Dump)
     fn print_str_trace(&self, s: &str) {
         eprint!("{s}");
         _ = stderr().flush();
@@ -465,6 +637,14 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     fn set_raw_mode(&self, raw_mode: bool) -> Result<(), String> {
         Err("Setting raw mode is not supported in this environment".into())
     }
+    /// Get the next input event if there is one, without waiting
+    fn poll_event(&self) -> Result<Option<InputEvent>, String> {
+        Err("Input events are not supported in this environment".into())
+    }
+    /// Wait for the next input event
+    fn wait_event(&self) -> Result<InputEvent, String> {
+        Err("Input events are not supported in this environment".into())
+    }
     fn var(&self, name: &str) -> Option<String> {
         None
     }
@@ -524,12 +704,53 @@ pub trait SysBackend: Any + Send + Sync + 'static {
         self.close(handle)?;
         Ok(())
     }
+    /// Report how far along a long-running operation is
+    ///
+    /// `op` is the name of the operation, like `rise` or `&fras`, and `done` is the fraction of it that is done, from 0 to 1.
+    /// Operations that cannot count their steps only report 0 when they start and 1 when they finish.
+    ///
+    /// Return `false` to cancel the operation, which makes the program fail with an interruption error.
+    fn progress(&self, op: &str, done: f64) -> bool {
+        true
+    }
     fn sleep(&self, seconds: f64) -> Result<(), String> {
         Err("Sleeping is not supported in this environment".into())
     }
+    /// Call a function every `interval` seconds until it returns `false` or fails
+    ///
+    /// The default implementation calls it in a loop and [`sleep`](SysBackend::sleep)s between calls.
+    /// Backends that should not block, like on the web, can call it from a timer instead.
+    fn schedule(&self, interval: f64, mut f: ScheduleFn) -> Result<(), String> {
+        loop {
+            let start = self.clock();
+            if !f()? {
+                return Ok(());
+            }
+            let remaining = interval - (self.clock() - start);
+            if remaining > 0.0 {
+                self.sleep(remaining)?;
+            }
+        }
+    }
+    /// Get the current time in seconds since the Unix epoch
+    fn now(&self) -> f64 {
+        instant::now() / 1000.0
+    }
+    /// Get the time in seconds from a monotonic clock with an arbitrary start
+    ///
+    /// Used to time code, so it should be as precise as possible.
+    fn clock(&self) -> f64 {
+        instant::Instant::now()
+            .duration_since(*CLOCK_START)
+            .as_secs_f64()
+    }
     fn show_image(&self, image: DynamicImage) -> Result<(), String> {
         Err("Showing images not supported in this environment".into())
     }
+    /// Show an image as a frame in a window, opening the window if it is not open
+    fn show_frame(&self, image: DynamicImage) -> Result<(), String> {
+        Err("Windows are not supported in this environment".into())
+    }
     fn show_gif(&self, gif_bytes: Vec<u8>) -> Result<(), String> {
         Err("Showing gifs not supported in this environment".into())
     }
@@ -593,34 +814,96 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
         Err("Making HTTPS requests is not supported in this environment".into())
     }
+    /// Run a system function in place of the interpreter
+    ///
+    /// The backend can pop the function's arguments from `env`, push its outputs,
+    /// and make errors at the right span with [`Uiua::error`].
+    /// It must take and leave as many values as the function's signature says.
+    ///
+    /// Return `Ok(false)` to let the interpreter run it with the other methods of this trait.
+    fn run_sys_op(&self, op: SysOp, env: &mut Uiua) -> UiuaResult<bool> {
+        Ok(false)
+    }
+    /// Run an operation called with [`&ext`](SysOp::Ext)
+    ///
+    /// The operation's argument is on top of `env`'s stack.
+    /// It should be popped and replaced with the operation's result.
+    ///
+    /// Return `Ok(false)` if the backend has no operation with this name.
+    fn ext(&self, name: &str, env: &mut Uiua) -> UiuaResult<bool> {
+        Ok(false)
+    }
+}
+
+/// A backend that supports no IO at all
+///
+/// Useful for running code whose speed or output should not depend on the environment.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullSys;
+
+impl SysBackend for NullSys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn print_str_trace(&self, _: &str) {}
 }
 
 impl SysOp {
+    /// Whether this is one of the ways to import from a file
+    pub fn is_import(&self) -> bool {
+        matches!(self, SysOp::Import | SysOp::ImportPure)
+    }
     pub(crate) fn run(&self, env: &mut Uiua) -> UiuaResult {
+        // Functions called on proxy rows must not do IO, because there are no real rows
+        if env.proxy_depth > 0 {
+            return Err(env.error(format!(
+                "{} cannot be called on an empty array's proxy rows",
+                self.name()
+            )));
+        }
+        if let Some(id) = &env.running_macro {
+            return Err(env.error(format!(
+                "{} cannot be used by code macro {id}, because code macros run at compile time",
+                self.name()
+            )));
+        }
+        // Files imported as pure can still import other files, which will also be pure
+        if !self.is_import() {
+            if let Some(path) = env.pure_import_on_stack() {
+                return Err(env.error(format!(
+                    "{} cannot be used by code from {}, which was imported with {}",
+                    self.name(),
+                    path.display(),
+                    SysOp::ImportPure.name()
+                )));
+            }
+        }
+        let backend = env.backend.clone();
+        if backend.run_sys_op(*self, env)? {
+            return Ok(());
+        }
         match self {
             SysOp::Show => {
                 let s = env.pop(1)?.show();
-                env.backend.print_str_stdout(&s).map_err(|e| env.error(e))?;
-                env.backend
-                    .print_str_stdout("\n")
-                    .map_err(|e| env.error(e))?;
+                env.print_str_stdout(&s)?;
+                env.print_str_stdout("\n")?;
             }
             SysOp::Prin => {
                 let val = env.pop(1)?;
-                env.backend
-                    .print_str_stdout(&val.to_string())
-                    .map_err(|e| env.error(e))?;
+                env.print_str_stdout(&val.to_string())?;
+                env.flush_stdout()?;
             }
             SysOp::Print => {
                 let val = env.pop(1)?;
-                env.backend
-                    .print_str_stdout(&val.to_string())
-                    .map_err(|e| env.error(e))?;
-                env.backend
-                    .print_str_stdout("\n")
-                    .map_err(|e| env.error(e))?;
+                env.print_str_stdout(&val.to_string())?;
+                env.print_str_stdout("\n")?;
+            }
+            SysOp::PrintErr => {
+                let val = env.pop(1)?;
+                env.print_str_stderr(&format!("{val}\n"))?;
             }
             SysOp::ScanLine => {
+                env.flush_stdout()?;
                 if let Some(line) = env.backend.scan_line_stdin().map_err(|e| env.error(e))? {
                     env.push(line);
                 } else {
@@ -637,6 +920,15 @@ impl SysOp {
                     .set_raw_mode(raw_mode)
                     .map_err(|e| env.error(e))?;
             }
+            SysOp::PollEvent => {
+                let event = env.backend.poll_event().map_err(|e| env.error(e))?;
+                let encoded = event.map(|event| event.encode()).unwrap_or_default();
+                env.push(Array::<f64>::from_iter(encoded));
+            }
+            SysOp::WaitEvent => {
+                let event = env.backend.wait_event().map_err(|e| env.error(e))?;
+                env.push(Array::<f64>::from_iter(event.encode()));
+            }
             SysOp::Args => {
                 let mut args = Vec::new();
                 args.push(env.file_path().to_string_lossy().into_owned());
@@ -789,14 +1081,8 @@ impl SysOp {
                     Value::Box(_) => return Err(env.error("Cannot write function array to file")),
                 };
                 match handle {
-                    Handle::STDOUT => env
-                        .backend
-                        .print_str_stdout(&String::from_utf8_lossy(&bytes))
-                        .map_err(|e| env.error(e))?,
-                    Handle::STDERR => env
-                        .backend
-                        .print_str_stderr(&String::from_utf8_lossy(&bytes))
-                        .map_err(|e| env.error(e))?,
+                    Handle::STDOUT => env.print_str_stdout(&String::from_utf8_lossy(&bytes))?,
+                    Handle::STDERR => env.print_str_stderr(&String::from_utf8_lossy(&bytes))?,
                     Handle::STDIN => return Err(env.error("Cannot write to stdin")),
                     _ => env
                         .backend
@@ -806,6 +1092,7 @@ impl SysOp {
             }
             SysOp::FReadAllStr => {
                 let path = env.pop(1)?.as_string(env, "Path must be a string")?;
+                env.report_progress(self.name(), 0.0)?;
                 let bytes = env
                     .backend
                     .file_read_all(&path)
@@ -817,11 +1104,13 @@ impl SysOp {
                         }
                     })
                     .map_err(|e| env.error(e))?;
+                env.report_progress(self.name(), 1.0)?;
                 let s = String::from_utf8(bytes).map_err(|e| env.error(e))?;
                 env.push(s);
             }
             SysOp::FReadAllBytes => {
                 let path = env.pop(1)?.as_string(env, "Path must be a string")?;
+                env.report_progress(self.name(), 0.0)?;
                 let bytes = env
                     .backend
                     .file_read_all(&path)
@@ -833,6 +1122,7 @@ impl SysOp {
                         }
                     })
                     .map_err(|e| env.error(e))?;
+                env.report_progress(self.name(), 1.0)?;
                 let bytes = bytes.into_iter().map(Into::into);
                 env.push(Array::<u8>::from_iter(bytes));
             }
@@ -873,9 +1163,10 @@ impl SysOp {
                 let is_file = env.backend.is_file(&path).map_err(|e| env.error(e))?;
                 env.push(is_file);
             }
-            SysOp::Import => {
+            SysOp::Import | SysOp::ImportPure => {
                 let path = env.pop(1)?.as_string(env, "Import path must be a string")?;
                 let item = env.pop(2)?.as_string(env, "Item name must be a string")?;
+                env.report_progress(self.name(), 0.0)?;
                 let input = String::from_utf8(
                     env.backend
                         .file_read_all(&path)
@@ -889,7 +1180,8 @@ impl SysOp {
                         .map_err(|e| env.error(e))?,
                 )
                 .map_err(|e| env.error(format!("Failed to read file: {e}")))?;
-                env.import(&input, path.as_ref(), &item)?;
+                env.report_progress(self.name(), 1.0)?;
+                env.import(&input, path.as_ref(), &item, *self == SysOp::ImportPure)?;
             }
             SysOp::Invoke => {
                 let path = env.pop(1)?.as_string(env, "Invoke path must be a string")?;
@@ -949,9 +1241,20 @@ impl SysOp {
                 env.push(Array::<u8>::from(bytes.as_slice()));
             }
             SysOp::ImShow => {
+                let value = env.pop(1)?;
+                if env.output_capturing() {
+                    let bytes = value_to_image_bytes(&value, ImageOutputFormat::Png)
+                        .map_err(|e| env.error(e))?;
+                    env.capture(CellOutput::Image(bytes));
+                } else {
+                    let image = value_to_image(&value).map_err(|e| env.error(e))?;
+                    env.backend.show_image(image).map_err(|e| env.error(e))?;
+                }
+            }
+            SysOp::WindowFrame => {
                 let value = env.pop(1)?;
                 let image = value_to_image(&value).map_err(|e| env.error(e))?;
-                env.backend.show_image(image).map_err(|e| env.error(e))?;
+                env.backend.show_frame(image).map_err(|e| env.error(e))?;
             }
             SysOp::GifDecode => {
                 let bytes = env
@@ -971,7 +1274,9 @@ impl SysOp {
                 let delay = env.pop(1)?.as_num(env, "Delay must be a number")?;
                 let value = env.pop(2)?;
                 let bytes = value_to_gif_bytes(&value, delay).map_err(|e| env.error(e))?;
-                env.backend.show_gif(bytes).map_err(|e| env.error(e))?;
+                if !env.capture(CellOutput::Gif(bytes.clone())) {
+                    env.backend.show_gif(bytes).map_err(|e| env.error(e))?;
+                }
             }
             SysOp::AudioDecode => {
                 let bytes = match env.pop(1)? {
@@ -1014,7 +1319,9 @@ impl SysOp {
                 let value = env.pop(1)?;
                 let bytes = value_to_wav_bytes(&value, env.backend.audio_sample_rate())
                     .map_err(|e| env.error(e))?;
-                env.backend.play_audio(bytes).map_err(|e| env.error(e))?;
+                if !env.capture(CellOutput::Audio(bytes.clone())) {
+                    env.backend.play_audio(bytes).map_err(|e| env.error(e))?;
+                }
             }
             SysOp::AudioSampleRate => {
                 let sample_rate = env.backend.audio_sample_rate();
@@ -1054,6 +1361,51 @@ impl SysOp {
                     return Err(env.error(e));
                 }
             }
+            SysOp::Every => {
+                let f = env.pop_function()?;
+                let interval = env
+                    .pop(1)?
+                    .as_num(env, "Interval must be a number")?
+                    .max(0.0);
+                let sig = f.signature();
+                if sig.args > 1 || sig.outputs != 1 {
+                    return Err(env.error(format!(
+                        "&ev's function's signature must be {} or {}, but it is {sig}",
+                        Signature::new(0, 1),
+                        Signature::new(1, 1),
+                    )));
+                }
+                // The backend only sees the message of an error in the function,
+                // so the error itself is kept here to be returned
+                let error = Arc::new(Mutex::new(None));
+                let f_error = error.clone();
+                let mut every_env = env.clone();
+                let mut count = 0;
+                let res = env.backend.schedule(
+                    interval,
+                    Box::new(move || {
+                        if sig.args == 1 {
+                            every_env.push(count);
+                        }
+                        count += 1;
+                        (every_env.call(f.clone()))
+                            .and_then(|_| every_env.pop("&ev's function result"))
+                            .and_then(|keep_going| {
+                                keep_going
+                                    .as_bool(&every_env, "&ev's function must return a boolean")
+                            })
+                            .map_err(|e| {
+                                let message = e.to_string();
+                                *f_error.lock() = Some(e);
+                                message
+                            })
+                    }),
+                );
+                if let Some(e) = error.lock().take() {
+                    return Err(e);
+                }
+                res.map_err(|e| env.error(e))?;
+            }
             SysOp::Sleep => {
                 let seconds = env
                     .pop(1)?
@@ -1140,6 +1492,22 @@ impl SysOp {
                     .map_err(|e| env.error(e))?;
                 env.push(res);
             }
+            SysOp::Ext => {
+                let name = (env.pop(1)?).as_string(env, "Operation name must be a string")?;
+                let height = env.stack_size();
+                if let Some(op) =
+                    (Ident::get(&name).and_then(|name| env.ext_ops.get(&name))).cloned()
+                {
+                    op(env)?;
+                } else if !backend.ext(&name, env)? {
+                    return Err(env.error(format!("There is no operation named {name:?}")));
+                }
+                if env.stack_size() != height {
+                    return Err(env.error(format!(
+                        "Operation {name:?} must replace its argument with one value"
+                    )));
+                }
+            }
             SysOp::Close => {
                 let handle = env
                     .pop(1)?
@@ -1508,13 +1876,13 @@ pub fn value_to_gif_bytes(value: &Value, frame_rate: f64) -> Result<Vec<u8>, Str
     }
     let mut reduction = 1;
     let mut bytes = Cursor::new(Vec::new());
-    let mut all_colors = HashSet::new();
+    let mut all_colors = BTreeSet::new();
     for frame in &frames {
         for pixel in frame.pixels() {
             all_colors.insert(pixel.0);
         }
     }
-    let mut used_colors = HashSet::new();
+    let mut used_colors = BTreeSet::new();
     let used_colors = 'colors: loop {
         used_colors.clear();
         let adder = reduction - 1;
@@ -1572,3 +1940,118 @@ pub fn gif_bytes_to_value(bytes: &[u8]) -> Result<(f64, Value), gif::DecodingErr
     num.compress();
     Ok((frame_rate, num))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::run_with;
+
+    #[test]
+    fn pure_imports() {
+        let dir = std::env::temp_dir().join(format!("uiua-pure-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lib = dir.join("lib.ua");
+        let loud = dir.join("loud.ua");
+        let wrapper = dir.join("wrapper.ua");
+        std::fs::write(&lib, "Sq ← ×.\nLoud ← (&p \"hi\" ×.)").unwrap();
+        std::fs::write(&loud, "&p \"loading\"\nX ← 5").unwrap();
+        std::fs::write(&wrapper, format!("Loud ← &i {:?} \"Loud\"", lib.display())).unwrap();
+        let run = |code: String| run_with(Uiua::with_native_sys().capture_output(), &code);
+        let stack = run(format!("Sq ← &ip {:?} \"Sq\"\nSq 3", lib.display())).unwrap();
+        assert_eq!(stack, [Value::from(9.0)]);
+        for (path, item) in [(&lib, "Loud"), (&loud, "X"), (&wrapper, "Loud")] {
+            let err = run(format!("F ← &ip {:?} {item:?}\nF 3", path.display())).unwrap_err();
+            assert!(
+                err.to_string().contains("cannot be used by code from"),
+                "{err}"
+            );
+        }
+        let stack = run(format!("Loud ← &i {:?} \"Loud\"\nLoud 3", lib.display())).unwrap();
+        assert_eq!(stack, [Value::from(9.0)]);
+        // A file imported both as pure and not can still do IO when it is not imported as pure
+        let stack = run(format!(
+            "F ← &ip {:?} \"Loud\"\nLoud ← &i {:?} \"Loud\"\nLoud 3",
+            wrapper.display(),
+            lib.display()
+        ))
+        .unwrap();
+        assert_eq!(stack, [Value::from(9.0)]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn backend_ops() {
+        use std::any::Any;
+
+        struct Ops;
+        impl SysBackend for Ops {
+            fn any(&self) -> &dyn Any {
+                self
+            }
+            fn run_sys_op(&self, op: SysOp, env: &mut Uiua) -> UiuaResult<bool> {
+                if op != SysOp::Var {
+                    return Ok(false);
+                }
+                let name = env.pop(1)?.as_string(env, "Name must be a string")?;
+                env.push(name.to_uppercase());
+                Ok(true)
+            }
+            fn ext(&self, name: &str, env: &mut Uiua) -> UiuaResult<bool> {
+                match name {
+                    "half" => {
+                        let n = env.pop(1)?.as_num(env, "Expected a number")?;
+                        env.push(n / 2.0);
+                    }
+                    "lose" => _ = env.pop(1)?,
+                    _ => return Ok(false),
+                }
+                Ok(true)
+            }
+        }
+
+        let mut env = Uiua::with_backend(Ops);
+        env.load_str("&var \"home\"\n&ext \"half\" 5").unwrap();
+        assert_eq!(env.take_stack(), [Value::from("HOME"), Value::from(2.5)]);
+        let err = env.load_str("&ext \"double\" 5").unwrap_err();
+        assert!(err.to_string().contains("no operation named"), "{err}");
+        // Operations added to the interpreter come before the backend's
+        let mut env = Uiua::with_backend(Ops).with_ext("double", |env| {
+            let n = env.pop(1)?.as_num(env, "Expected a number")?;
+            env.push(n * 2.0);
+            Ok(())
+        });
+        env.load_str("&ext \"double\" &ext \"half\" 5").unwrap();
+        assert_eq!(env.take_stack(), [Value::from(5.0)]);
+        let err = env.load_str("&ext \"lose\" 5").unwrap_err();
+        assert!(err.to_string().contains("must replace"), "{err}");
+    }
+
+    #[test]
+    fn input_events() {
+        use std::{any::Any, sync::Mutex};
+
+        struct Events(Mutex<Vec<InputEvent>>);
+        impl SysBackend for Events {
+            fn any(&self) -> &dyn Any {
+                self
+            }
+            fn poll_event(&self) -> Result<Option<InputEvent>, String> {
+                Ok(self.0.lock().unwrap().pop())
+            }
+        }
+
+        let mut env = Uiua::with_backend(Events(Mutex::new(vec![
+            InputEvent::MousePress {
+                x: 3,
+                y: 4,
+                button: MouseButton::Right,
+            },
+            InputEvent::Key(Key::Up),
+            InputEvent::Key(Key::Char('a')),
+        ])));
+        env.load_str("[□&inp □&inp □&inp □&inp]").unwrap();
+        let mut expected = Uiua::with_native_sys();
+        expected.load_str("{[] [2 3 4 1] [0 ¯1] [0 97]}").unwrap();
+        assert_eq!(env.take_stack(), expected.take_stack());
+    }
+}
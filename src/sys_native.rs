@@ -12,7 +12,9 @@ use std::{
     time::Duration,
 };
 
-use crate::{Handle, SysBackend, UiuaError};
+use crate::{Handle, InputEvent, SysBackend, UiuaError};
+#[cfg(any(feature = "raw_mode", feature = "window"))]
+use crate::{Key, MouseButton};
 use bufreaderwriter::seq::BufReaderWriterSeq;
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
@@ -105,8 +107,10 @@ impl SysBackend for NativeSys {
     }
     fn print_str_stdout(&self, s: &str) -> Result<(), String> {
         let mut stdout = stdout().lock();
-        stdout.write_all(s.as_bytes()).map_err(|e| e.to_string())?;
-        stdout.flush().map_err(|e| e.to_string())
+        stdout.write_all(s.as_bytes()).map_err(|e| e.to_string())
+    }
+    fn flush_stdout(&self) -> Result<(), String> {
+        stdout().flush().map_err(|e| e.to_string())
     }
     fn print_str_stderr(&self, s: &str) -> Result<(), String> {
         let mut stderr = stderr().lock();
@@ -138,13 +142,57 @@ impl SysBackend for NativeSys {
     }
     #[cfg(feature = "raw_mode")]
     fn set_raw_mode(&self, raw_mode: bool) -> Result<(), String> {
+        use crossterm::{event::*, execute, terminal::*};
+        // Mouse events are reported in raw mode too
         if raw_mode {
-            crossterm::terminal::enable_raw_mode()
+            enable_raw_mode().and_then(|_| execute!(stdout(), EnableMouseCapture))
         } else {
-            crossterm::terminal::disable_raw_mode()
+            execute!(stdout(), DisableMouseCapture).and_then(|_| disable_raw_mode())
         }
         .map_err(|e| e.to_string())
     }
+    fn poll_event(&self) -> Result<Option<InputEvent>, String> {
+        // Events come from the window while it is open
+        #[cfg(feature = "window")]
+        if window::is_open() {
+            return Ok(window::poll_event());
+        }
+        #[cfg(feature = "raw_mode")]
+        while crossterm::event::poll(Duration::ZERO).map_err(|e| e.to_string())? {
+            let event = crossterm::event::read().map_err(|e| e.to_string())?;
+            if let Some(event) = input_event(event) {
+                return Ok(Some(event));
+            }
+        }
+        #[cfg(feature = "raw_mode")]
+        return Ok(None);
+        #[cfg(not(feature = "raw_mode"))]
+        Err("Input events are not supported in this environment".into())
+    }
+    fn wait_event(&self) -> Result<InputEvent, String> {
+        #[cfg(feature = "window")]
+        if window::is_open() {
+            loop {
+                if let Some(event) = window::poll_event() {
+                    return Ok(event);
+                }
+                sleep(Duration::from_millis(10));
+            }
+        }
+        #[cfg(feature = "raw_mode")]
+        loop {
+            let event = crossterm::event::read().map_err(|e| e.to_string())?;
+            if let Some(event) = input_event(event) {
+                return Ok(event);
+            }
+        }
+        #[cfg(not(feature = "raw_mode"))]
+        Err("Input events are not supported in this environment".into())
+    }
+    #[cfg(feature = "window")]
+    fn show_frame(&self, image: image::DynamicImage) -> Result<(), String> {
+        window::show_frame(image)
+    }
     fn var(&self, name: &str) -> Option<String> {
         env::var(name).ok()
     }
@@ -601,3 +649,216 @@ fn check_http(mut request: String, hostname: &str) -> Result<String, String> {
 
     Ok(request)
 }
+
+/// Convert a terminal event to an input event, if it is one that is reported
+#[cfg(feature = "raw_mode")]
+fn input_event(event: crossterm::event::Event) -> Option<InputEvent> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, MouseEventKind};
+    Some(match event {
+        Event::Key(key) if key.kind != KeyEventKind::Release => InputEvent::Key(match key.code {
+            KeyCode::Char(c) => Key::Char(c),
+            KeyCode::Enter => Key::Char('\n'),
+            KeyCode::Tab => Key::Char('\t'),
+            KeyCode::Backspace => Key::Char('\u{8}'),
+            KeyCode::Esc => Key::Char('\u{1b}'),
+            KeyCode::Delete => Key::Char('\u{7f}'),
+            KeyCode::Up => Key::Up,
+            KeyCode::Down => Key::Down,
+            KeyCode::Left => Key::Left,
+            KeyCode::Right => Key::Right,
+            KeyCode::Home => Key::Home,
+            KeyCode::End => Key::End,
+            KeyCode::PageUp => Key::PageUp,
+            KeyCode::PageDown => Key::PageDown,
+            KeyCode::Insert => Key::Insert,
+            KeyCode::F(n) => Key::F(n),
+            _ => return None,
+        }),
+        Event::Mouse(mouse) => {
+            let (x, y) = (mouse.column as usize, mouse.row as usize);
+            let button = |button| match button {
+                event::MouseButton::Left => MouseButton::Left,
+                event::MouseButton::Right => MouseButton::Right,
+                event::MouseButton::Middle => MouseButton::Middle,
+            };
+            match mouse.kind {
+                MouseEventKind::Moved | MouseEventKind::Drag(_) => InputEvent::MouseMove { x, y },
+                MouseEventKind::Down(b) => InputEvent::MousePress {
+                    x,
+                    y,
+                    button: button(b),
+                },
+                MouseEventKind::Up(b) => InputEvent::MouseRelease {
+                    x,
+                    y,
+                    button: button(b),
+                },
+                _ => return None,
+            }
+        }
+        Event::Resize(width, height) => InputEvent::Resize {
+            width: width as usize,
+            height: height as usize,
+        },
+        _ => return None,
+    })
+}
+
+/// A window that shows frames drawn with [`SysOp::WindowFrame`](crate::SysOp::WindowFrame)
+#[cfg(feature = "window")]
+mod window {
+    use std::{cell::RefCell, collections::VecDeque};
+
+    use minifb::{KeyRepeat, MouseMode, Window, WindowOptions};
+
+    use super::*;
+
+    struct WindowState {
+        window: Window,
+        /// Events that have happened but not been polled
+        events: VecDeque<InputEvent>,
+        size: (usize, usize),
+        mouse: Option<(usize, usize)>,
+        buttons: [bool; 3],
+        closed: bool,
+    }
+
+    thread_local! {
+        // Windows cannot be sent between threads on every platform
+        static WINDOW: RefCell<Option<WindowState>> = RefCell::new(None);
+    }
+
+    /// Whether there is a window, including one that was closed but whose close event has not been polled
+    pub fn is_open() -> bool {
+        WINDOW.with(|state| state.borrow().is_some())
+    }
+
+    pub fn show_frame(image: image::DynamicImage) -> Result<(), String> {
+        let image = image.into_rgb8();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let buffer: Vec<u32> = (image.pixels())
+            .map(|p| u32::from_be_bytes([0, p[0], p[1], p[2]]))
+            .collect();
+        WINDOW.with(|state| {
+            let mut state = state.borrow_mut();
+            if state.as_ref().map_or(true, |state| state.closed) {
+                let options = WindowOptions {
+                    resize: true,
+                    ..Default::default()
+                };
+                let window =
+                    Window::new("Uiua", width, height, options).map_err(|e| e.to_string())?;
+                *state = Some(WindowState {
+                    window,
+                    events: VecDeque::new(),
+                    size: (width, height),
+                    mouse: None,
+                    buttons: [false; 3],
+                    closed: false,
+                });
+            }
+            let state = state.as_mut().unwrap();
+            (state.window)
+                .update_with_buffer(&buffer, width, height)
+                .map_err(|e| e.to_string())?;
+            state.collect_events();
+            Ok(())
+        })
+    }
+
+    pub fn poll_event() -> Option<InputEvent> {
+        WINDOW.with(|state| {
+            let mut state = state.borrow_mut();
+            let window = state.as_mut()?;
+            if !window.closed {
+                window.window.update();
+                window.collect_events();
+            }
+            let event = window.events.pop_front();
+            if event == Some(InputEvent::Close) {
+                *state = None;
+            }
+            event
+        })
+    }
+
+    impl WindowState {
+        fn collect_events(&mut self) {
+            if !self.window.is_open() {
+                if !self.closed {
+                    self.closed = true;
+                    self.events.push_back(InputEvent::Close);
+                }
+                return;
+            }
+            for key in self.window.get_keys_pressed(KeyRepeat::Yes) {
+                if let Some(key) = window_key(key) {
+                    self.events.push_back(InputEvent::Key(key));
+                }
+            }
+            let size = self.window.get_size();
+            if size != self.size {
+                self.size = size;
+                let (width, height) = size;
+                (self.events).push_back(InputEvent::Resize { width, height });
+            }
+            let Some((x, y)) = self.window.get_mouse_pos(MouseMode::Discard) else {
+                return;
+            };
+            let (x, y) = (x as usize, y as usize);
+            if self.mouse != Some((x, y)) {
+                self.mouse = Some((x, y));
+                self.events.push_back(InputEvent::MouseMove { x, y });
+            }
+            let buttons = [
+                (minifb::MouseButton::Left, MouseButton::Left),
+                (minifb::MouseButton::Right, MouseButton::Right),
+                (minifb::MouseButton::Middle, MouseButton::Middle),
+            ];
+            for (i, (window_button, button)) in buttons.into_iter().enumerate() {
+                let down = self.window.get_mouse_down(window_button);
+                if down != self.buttons[i] {
+                    self.buttons[i] = down;
+                    self.events.push_back(if down {
+                        InputEvent::MousePress { x, y, button }
+                    } else {
+                        InputEvent::MouseRelease { x, y, button }
+                    });
+                }
+            }
+        }
+    }
+
+    /// Letter keys are reported as lowercase
+    fn window_key(key: minifb::Key) -> Option<Key> {
+        use minifb::Key as K;
+        Some(match key {
+            K::Up => Key::Up,
+            K::Down => Key::Down,
+            K::Left => Key::Left,
+            K::Right => Key::Right,
+            K::Home => Key::Home,
+            K::End => Key::End,
+            K::PageUp => Key::PageUp,
+            K::PageDown => Key::PageDown,
+            K::Insert => Key::Insert,
+            K::Enter => Key::Char('\n'),
+            K::Tab => Key::Char('\t'),
+            K::Space => Key::Char(' '),
+            K::Backspace => Key::Char('\u{8}'),
+            K::Escape => Key::Char('\u{1b}'),
+            K::Delete => Key::Char('\u{7f}'),
+            key => {
+                // Letters are named by themselves, digits like `Key0`, and function keys like `F1`
+                let name = format!("{key:?}");
+                let name = name.strip_prefix("Key").unwrap_or(&name);
+                let mut chars = name.chars();
+                match (chars.next()?, chars.as_str()) {
+                    (c, "") if c.is_ascii_alphanumeric() => Key::Char(c.to_ascii_lowercase()),
+                    ('F', n) => Key::F(n.parse().ok()?),
+                    _ => return None,
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,22 @@
+//! Helpers shared by the unit tests
+
+use crate::{Uiua, UiuaResult, Value};
+
+/// Run some code with experimental features enabled and return the stack
+pub fn run(code: &str) -> UiuaResult<Vec<Value>> {
+    run_with(Uiua::with_native_sys(), code)
+}
+
+/// Run some code in the given environment with experimental features enabled and return the stack
+pub fn run_with(env: Uiua, code: &str) -> UiuaResult<Vec<Value>> {
+    let mut env = env.with_experimental(true);
+    env.load_str(code)?;
+    Ok(env.take_stack())
+}
+
+/// Take the messages of the diagnostics an environment has collected
+pub fn diagnostics(env: &mut Uiua) -> Vec<String> {
+    (env.take_diagnostics().into_iter())
+        .map(|diag| diag.message)
+        .collect()
+}
@@ -0,0 +1,176 @@
+//! Units of measure for numbers
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    ops::{Div, Mul},
+};
+
+/// A unit of measure, like meters or meters per second
+///
+/// A unit is a product of named base units, each raised to a whole power.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Unit {
+    /// The power of each base unit, which is never 0
+    powers: BTreeMap<String, i32>,
+}
+
+impl Unit {
+    /// Parse a unit like `m`, `m/s`, or `kg*m/s^2`
+    ///
+    /// Base units are multiplied with `*` or a space, or divided with `/`.
+    /// A `/` only divides by the base unit right after it, so `m/s/s` and `m/s^2` are the same.
+    /// Each base unit can be raised to a whole power with `^`. `1` can be used for a unit with no base units, as in `1/s`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut unit = Unit::default();
+        let mut rest = s.trim();
+        let mut divide = false;
+        while !rest.is_empty() {
+            let end = rest.find(['*', '/', ' ']).unwrap_or(rest.len());
+            let (term, after) = rest.split_at(end);
+            if !term.is_empty() {
+                let (name, power) = match term.split_once('^') {
+                    Some((name, power)) => {
+                        let power: i32 = power
+                            .parse()
+                            .map_err(|_| format!("`{power}` is not a whole power in unit `{s}`"))?;
+                        (name, power)
+                    }
+                    None => (term, 1),
+                };
+                if name.is_empty()
+                    || (name.starts_with(|c: char| c.is_ascii_digit()) && name != "1")
+                {
+                    return Err(format!("`{term}` is not a base unit in unit `{s}`"));
+                }
+                if name != "1" {
+                    unit.multiply(name, if divide { -power } else { power });
+                }
+                divide = false;
+            }
+            let mut chars = after.chars();
+            match chars.next() {
+                Some('/') if divide => return Err(format!("Unit `{s}` divides twice in a row")),
+                Some('/') => divide = true,
+                _ => {}
+            }
+            rest = chars.as_str();
+        }
+        if divide {
+            return Err(format!("Unit `{s}` ends with a division"));
+        }
+        Ok(unit)
+    }
+    /// Whether the unit has no base units
+    pub fn is_dimensionless(&self) -> bool {
+        self.powers.is_empty()
+    }
+    /// The base units and their powers, in alphabetical order
+    pub fn powers(&self) -> impl Iterator<Item = (&str, i32)> {
+        self.powers
+            .iter()
+            .map(|(name, power)| (name.as_str(), *power))
+    }
+    /// Take a root of the unit
+    ///
+    /// Returns `None` if a power is not divisible by `n`.
+    pub fn root(&self, n: i32) -> Option<Self> {
+        let mut powers = BTreeMap::new();
+        for (name, power) in &self.powers {
+            if power % n != 0 {
+                return None;
+            }
+            powers.insert(name.clone(), power / n);
+        }
+        Some(Unit { powers })
+    }
+    fn multiply(&mut self, name: &str, power: i32) {
+        let entry = self.powers.entry(name.into()).or_default();
+        *entry += power;
+        if *entry == 0 {
+            self.powers.remove(name);
+        }
+    }
+}
+
+impl Mul for &Unit {
+    type Output = Unit;
+    fn mul(self, other: &Unit) -> Unit {
+        let mut unit = self.clone();
+        for (name, power) in other.powers() {
+            unit.multiply(name, power);
+        }
+        unit
+    }
+}
+
+impl Div for &Unit {
+    type Output = Unit;
+    fn div(self, other: &Unit) -> Unit {
+        let mut unit = self.clone();
+        for (name, power) in other.powers() {
+            unit.multiply(name, -power);
+        }
+        unit
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write = |f: &mut fmt::Formatter, name: &str, power: i32| {
+            write!(f, "{name}")?;
+            if power != 1 {
+                write!(f, "^{power}")?;
+            }
+            Ok(())
+        };
+        let mut numerator = self.powers().filter(|(_, power)| *power > 0).peekable();
+        if numerator.peek().is_none() {
+            write!(f, "1")?;
+        }
+        for (i, (name, power)) in numerator.enumerate() {
+            if i > 0 {
+                write!(f, "*")?;
+            }
+            write(f, name, power)?;
+        }
+        for (name, power) in self.powers().filter(|(_, power)| *power < 0) {
+            write!(f, "/")?;
+            write(f, name, -power)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::run;
+
+    #[test]
+    fn units() {
+        let unit = |code: &str| {
+            let stack = run(code).unwrap();
+            stack[0].unit().map(ToString::to_string)
+        };
+        assert_eq!(Unit::parse("kg m/s/s").unwrap().to_string(), "kg*m/s^2");
+        assert_eq!(Unit::parse("m^2/m").unwrap().to_string(), "m");
+        assert!(Unit::parse("m/").is_err());
+        assert!(Unit::parse("m^x").is_err());
+        assert_eq!(unit("÷ unit \"s\" 2 unit \"m\" 10").as_deref(), Some("m/s"));
+        assert_eq!(unit("× unit \"m/s\" 2 unit \"s\" 10").as_deref(), Some("m"));
+        assert_eq!(unit("+1 unit \"m\" 10").as_deref(), Some("m"));
+        assert_eq!(unit("< unit \"m\" 1 unit \"m\" 10"), None);
+        assert_eq!(unit("√ unit \"m^2\" 4").as_deref(), Some("m"));
+        assert_eq!(run("unit \"m/s\" [1 2]").unwrap()[0].show(), "[1 2] m/s");
+        // Rows and elements keep their array's unit
+        assert_eq!(unit("∵(+1) unit \"m\" [1 2]").as_deref(), Some("m"));
+        assert_eq!(unit("≡(+1) unit \"m\" [1 2]").as_deref(), Some("m"));
+        assert_eq!(unit("/× unit \"m\" [2 3]").as_deref(), Some("m^2"));
+        assert_eq!(unit("⊞+ unit \"m\" [1 2] [3 4]").as_deref(), Some("m"));
+        // Memoized results are not shared between values with different units
+        let memo = "F ← memo(×2)\nF unit \"m\" 3\nF unit \"s\" 3";
+        assert_eq!(unit(memo).as_deref(), Some("m"));
+        assert_eq!(unit(&format!("{memo}\n:")).as_deref(), Some("s"));
+    }
+}
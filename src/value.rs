@@ -3,7 +3,7 @@ use std::{
     cmp::Ordering,
     fmt,
     hash::{Hash, Hasher},
-    mem::take,
+    mem::{size_of, take},
 };
 
 use ecow::EcoVec;
@@ -14,7 +14,7 @@ use crate::{
     boxed::Boxed,
     cowslice::CowSlice,
     grid_fmt::GridFmt,
-    Uiua, UiuaResult,
+    Uiua, UiuaResult, Unit,
 };
 
 #[derive(Clone)]
@@ -31,6 +31,33 @@ impl Default for Value {
     }
 }
 
+impl Value {
+    /// Get a value of the same type with default elements and the given shape
+    ///
+    /// This stands in for the rows or elements of an array that has none.
+    pub(crate) fn proxy(&self, shape: Shape) -> Self {
+        let len: usize = shape.iter().product();
+        match self {
+            Value::Num(_) => Array::new(shape, vec![0.0; len].as_slice()).into(),
+            Value::Byte(_) => Array::new(shape, vec![0u8; len].as_slice()).into(),
+            Value::Char(_) => Array::new(shape, vec![' '; len].as_slice()).into(),
+            Value::Box(_) => {
+                Array::new(shape, vec![Boxed(Value::default()); len].as_slice()).into()
+            }
+        }
+    }
+    /// Turn a proxy result into an empty array whose rows or elements have its shape
+    pub(crate) fn into_empty(self, mut shape: Shape) -> Self {
+        shape.extend_from_slice(self.shape());
+        match self {
+            Value::Num(_) => Array::<f64>::new(shape, []).into(),
+            Value::Byte(_) => Array::<u8>::new(shape, []).into(),
+            Value::Char(_) => Array::<char>::new(shape, []).into(),
+            Value::Box(_) => Array::<Boxed>::new(shape, []).into(),
+        }
+    }
+}
+
 impl fmt::Debug for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -105,12 +132,21 @@ impl Value {
         }
     }
     pub fn into_flat_values(self) -> Box<dyn Iterator<Item = Self>> {
-        match self {
+        let unit = self.unit().cloned();
+        let values: Box<dyn Iterator<Item = Self>> = match self {
             Self::Num(array) => Box::new(array.data.into_iter().map(Value::from)),
             Self::Byte(array) => Box::new(array.data.into_iter().map(Value::from)),
             Self::Char(array) => Box::new(array.data.into_iter().map(Value::from)),
             Self::Box(array) => Box::new(array.data.into_iter().map(Value::from)),
+        };
+        // Every element has the value's unit
+        if unit.is_none() {
+            return values;
         }
+        Box::new(values.map(move |mut val| {
+            val.set_unit(unit.clone());
+            val
+        }))
     }
     pub fn type_name(&self) -> &'static str {
         match self {
@@ -126,6 +162,41 @@ impl Value {
             Self::Box(_) => "boxes",
         }
     }
+    /// Get the name of how the value's elements are stored
+    ///
+    /// Unlike [`Value::type_name`], this distinguishes numbers stored as bytes.
+    /// How many boxes deep the value's nesting goes
+    pub(crate) fn box_depth(&self) -> usize {
+        match self {
+            Value::Box(arr) => 1 + arr.data.iter().map(|b| b.0.box_depth()).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+    pub fn datatype_name(&self) -> &'static str {
+        match self {
+            Self::Num(_) => "number",
+            Self::Byte(_) => "byte",
+            Self::Char(_) => "character",
+            Self::Box(_) => "box",
+        }
+    }
+    /// Get the approximate number of bytes used by the value's shape and elements, including those of boxed values
+    pub fn memory_size(&self) -> usize {
+        fn size<T>(arr: &Array<T>) -> usize {
+            arr.shape.len() * size_of::<usize>() + arr.data.len() * size_of::<T>()
+        }
+        match self {
+            Self::Num(array) => size(array),
+            Self::Byte(array) => size(array),
+            Self::Char(array) => size(array),
+            Self::Box(array) => {
+                size(array)
+                    + (array.data.iter())
+                        .map(|bx| bx.as_value().memory_size())
+                        .sum::<usize>()
+            }
+        }
+    }
     pub fn shape(&self) -> &[usize] {
         self.generic_ref_shallow(Array::shape, Array::shape, Array::shape, Array::shape)
     }
@@ -328,13 +399,104 @@ impl Value {
         }
     }
     /// Get the pretty-printed string representation of the value
+    ///
+    /// A labeled value is shown after its label, and a value with named axes after their names.
+    /// A value's unit is shown after it, or before it if it is shown on several lines.
     pub fn show(&self) -> String {
-        match self {
+        let grid = match self {
             Self::Num(array) => array.grid_string(),
             Self::Byte(array) => array.grid_string(),
             Self::Char(array) => array.grid_string(),
             Self::Box(array) => array.grid_string(),
+        };
+        let axes = self.axes().map(|axes| {
+            let names: Vec<&str> = (axes.iter())
+                .map(|name| name.as_deref().unwrap_or("_"))
+                .collect();
+            names.join(" × ")
+        });
+        let header = match (self.label(), axes) {
+            (Some(label), Some(axes)) => Some(format!("{label} ({axes})")),
+            (Some(label), None) => Some(label.into()),
+            (None, Some(axes)) => Some(format!("({axes})")),
+            (None, None) => None,
+        };
+        match (header, self.unit()) {
+            (Some(header), Some(unit)) if grid.contains('\n') => {
+                format!("{header} {unit}:\n{grid}")
+            }
+            (None, Some(unit)) if grid.contains('\n') => format!("{unit}:\n{grid}"),
+            (Some(header), _) if grid.contains('\n') => format!("{header}:\n{grid}"),
+            (Some(header), Some(unit)) => format!("{header}: {grid} {unit}"),
+            (Some(header), None) => format!("{header}: {grid}"),
+            (None, Some(unit)) => format!("{grid} {unit}"),
+            (None, None) => grid,
+        }
+    }
+    /// Get the metadata attached to the value
+    pub fn meta(&self) -> Option<&ArrayMeta> {
+        match self {
+            Self::Num(array) => array.meta(),
+            Self::Byte(array) => array.meta(),
+            Self::Char(array) => array.meta(),
+            Self::Box(array) => array.meta(),
+        }
+    }
+    /// Change the metadata attached to the value
+    pub fn meta_mut(&mut self, f: impl FnOnce(&mut ArrayMeta)) {
+        match self {
+            Self::Num(array) => array.meta_mut(f),
+            Self::Byte(array) => array.meta_mut(f),
+            Self::Char(array) => array.meta_mut(f),
+            Self::Box(array) => array.meta_mut(f),
+        }
+    }
+    /// Get the value's label
+    pub fn label(&self) -> Option<&str> {
+        self.meta()?.label.as_deref()
+    }
+    /// Set or remove the value's label
+    pub fn set_label(&mut self, label: Option<String>) {
+        self.meta_mut(|meta| meta.label = label);
+    }
+    /// Get the value's unit of measure
+    pub fn unit(&self) -> Option<&Unit> {
+        self.meta()?.unit.as_ref()
+    }
+    /// Set or remove the value's unit of measure
+    ///
+    /// A unit with no base units removes the unit.
+    pub fn set_unit(&mut self, unit: Option<Unit>) {
+        let unit = unit.filter(|unit| !unit.is_dimensionless());
+        self.meta_mut(|meta| meta.unit = unit);
+    }
+    /// Get the names of the value's axes, if any are named
+    pub fn axes(&self) -> Option<&[Option<String>]> {
+        match self {
+            Self::Num(array) => array.axes(),
+            Self::Byte(array) => array.axes(),
+            Self::Char(array) => array.axes(),
+            Self::Box(array) => array.axes(),
+        }
+    }
+    /// Set or remove the names of the value's axes
+    ///
+    /// The names are ignored if there are not as many as the value's rank.
+    pub fn set_axes(&mut self, axes: Vec<Option<String>>) {
+        self.meta_mut(|meta| meta.axes = axes);
+    }
+    /// Get the pretty-printed string representation of the value without trailing whitespace on its lines
+    ///
+    /// This is the canonical form used to compare how values are shown in tests.
+    pub fn snapshot(&self) -> String {
+        let mut snapshot = String::new();
+        for (i, line) in self.show().lines().enumerate() {
+            if i > 0 {
+                snapshot.push('\n');
+            }
+            snapshot.push_str(line.trim_end());
         }
+        snapshot
     }
     pub fn as_indices(&self, env: &Uiua, requirement: &'static str) -> UiuaResult<Vec<isize>> {
         self.as_number_list(env, requirement, |f| f % 1.0 == 0.0, |f| f as isize)
@@ -687,7 +849,10 @@ impl Value {
                 for n in take(&mut nums.data) {
                     bytes.push(n as u8);
                 }
-                *self = (take(&mut nums.shape), bytes).into();
+                let meta = nums.meta.take();
+                let mut bytes = Array::new(take(&mut nums.shape), bytes);
+                bytes.meta = meta;
+                *self = bytes.into();
             }
         }
     }
@@ -1139,3 +1304,106 @@ impl ValueBuilder {
         self.value.unwrap_or_default()
     }
 }
+
+impl Value {
+    /// Encode the value in a compact binary format
+    ///
+    /// This is used to persist memoized results and sessions between runs.
+    pub(crate) fn encode(&self, bytes: &mut Vec<u8>) {
+        fn shape(shape: &[usize], bytes: &mut Vec<u8>) {
+            bytes.extend((shape.len() as u64).to_le_bytes());
+            for &dim in shape {
+                bytes.extend((dim as u64).to_le_bytes());
+            }
+        }
+        match self {
+            Value::Num(arr) => {
+                bytes.push(0);
+                shape(&arr.shape, bytes);
+                for n in &arr.data {
+                    bytes.extend(n.to_le_bytes());
+                }
+            }
+            Value::Byte(arr) => {
+                bytes.push(1);
+                shape(&arr.shape, bytes);
+                bytes.extend(arr.data.iter());
+            }
+            Value::Char(arr) => {
+                bytes.push(2);
+                shape(&arr.shape, bytes);
+                for &c in &arr.data {
+                    bytes.extend((c as u32).to_le_bytes());
+                }
+            }
+            Value::Box(arr) => {
+                bytes.push(3);
+                shape(&arr.shape, bytes);
+                for b in &arr.data {
+                    b.0.encode(bytes);
+                }
+            }
+        }
+    }
+    /// Decode a value encoded with [`Value::encode`]
+    ///
+    /// Returns `None` if the bytes are malformed.
+    pub(crate) fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        fn take<const N: usize>(bytes: &mut &[u8]) -> Option<[u8; N]> {
+            if bytes.len() < N {
+                return None;
+            }
+            let (head, tail) = bytes.split_at(N);
+            *bytes = tail;
+            head.try_into().ok()
+        }
+        fn uint(bytes: &mut &[u8]) -> Option<usize> {
+            usize::try_from(u64::from_le_bytes(take(bytes)?)).ok()
+        }
+        let [tag] = take(bytes)?;
+        let rank = uint(bytes)?;
+        let mut shape = Shape::new();
+        for _ in 0..rank.min(bytes.len()) {
+            shape.push(uint(bytes)?);
+        }
+        if shape.len() != rank {
+            return None;
+        }
+        let len = shape
+            .iter()
+            .try_fold(1usize, |acc, &d| acc.checked_mul(d))?;
+        if len > bytes.len() {
+            return None;
+        }
+        Some(match tag {
+            0 => {
+                let data: Option<CowSlice<f64>> = (0..len)
+                    .map(|_| take(bytes).map(f64::from_le_bytes))
+                    .collect();
+                Array::new(shape, data?).into()
+            }
+            1 => Array::new(shape, CowSlice::from(take_slice(bytes, len)?)).into(),
+            2 => {
+                let data: Option<CowSlice<char>> = (0..len)
+                    .map(|_| take(bytes).map(u32::from_le_bytes).and_then(char::from_u32))
+                    .collect();
+                Array::new(shape, data?).into()
+            }
+            3 => {
+                let data: Option<CowSlice<Boxed>> =
+                    (0..len).map(|_| Value::decode(bytes).map(Boxed)).collect();
+                Array::new(shape, data?).into()
+            }
+            _ => return None,
+        })
+    }
+}
+
+fn take_slice<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if bytes.len() < len {
+        return None;
+    }
+    let (head, tail) = bytes.split_at(len);
+    *bytes = tail;
+    Some(head)
+}
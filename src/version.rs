@@ -0,0 +1,152 @@
+//! Language versions and experimental features
+//!
+//! A file can declare the version of Uiua it was written for with a comment like `# Uiua 0.0.17`
+//! on its first line. Spellings of primitives that were current in that version are then
+//! accepted without deprecation warnings.
+//!
+//! Unstable primitives can only be used in files that opt in with an `# Experimental!` comment
+//! before any code.
+
+use std::fmt;
+
+use crate::{
+    ast::{Item, Word},
+    lex::Sp,
+    UiuaResult,
+};
+
+/// A version of the Uiua language
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl Version {
+    pub const fn new(major: u16, minor: u16, patch: u16) -> Self {
+        Version {
+            major,
+            minor,
+            patch,
+        }
+    }
+    /// The version of this interpreter
+    pub fn current() -> Self {
+        Version::parse(env!("CARGO_PKG_VERSION")).expect("crate version is valid")
+    }
+    /// Parse a version like `0.0.17`
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.split('.').map(|part| part.parse::<u16>().ok());
+        let version = Version::new(parts.next()??, parts.next()??, parts.next()??);
+        parts.next().is_none().then_some(version)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Get the version text of a version declaration comment
+pub(crate) fn pragma_text(comment: &str) -> Option<&str> {
+    let mut words = comment.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (Some("Uiua"), Some(version), None)
+            if version.starts_with(|c: char| c.is_ascii_digit()) =>
+        {
+            Some(version)
+        }
+        _ => None,
+    }
+}
+
+/// Whether a comment enables experimental features
+pub(crate) fn is_experimental_pragma(comment: &str) -> bool {
+    comment.trim() == "Experimental!"
+}
+
+/// Whether a file enables experimental features in one of the comments before its code
+pub(crate) fn declares_experimental(items: &[Item]) -> bool {
+    for item in items {
+        match item {
+            Item::Words(words) => match words.as_slice() {
+                [Sp {
+                    value: Word::Comment(comment),
+                    ..
+                }] => {
+                    if is_experimental_pragma(comment) {
+                        return true;
+                    }
+                }
+                _ => return false,
+            },
+            Item::ExtraNewlines(_) => {}
+            _ => return false,
+        }
+    }
+    false
+}
+
+/// Get the version that a file declares on its first line, if any
+pub(crate) fn declared(items: &[Item]) -> UiuaResult<Option<Version>> {
+    let Some(Item::Words(words)) = items.first() else {
+        return Ok(None);
+    };
+    let [Sp {
+        value: Word::Comment(comment),
+        span,
+    }] = words.as_slice()
+    else {
+        return Ok(None);
+    };
+    let Some(text) = pragma_text(comment).filter(|_| span.start.line == 1) else {
+        return Ok(None);
+    };
+    let Some(version) = Version::parse(text) else {
+        return Err(span
+            .clone()
+            .sp(format!("`{text}` is not a valid Uiua version"))
+            .into());
+    };
+    let current = Version::current();
+    if version > current {
+        return Err(span
+            .clone()
+            .sp(format!(
+                "This file was written for Uiua {version}, \
+                but this interpreter only supports up to {current}"
+            ))
+            .into());
+    }
+    Ok(Some(version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::run;
+    use crate::Uiua;
+
+    #[test]
+    fn language_version() {
+        let warnings = |code: &str| {
+            let mut env = Uiua::with_native_sys();
+            env.load_str(code).unwrap();
+            env.take_diagnostics().len()
+        };
+        assert_eq!(warnings("⌂ [3 1 2]"), 1);
+        assert_eq!(warnings("# Uiua 0.0.17\n⌂ [3 1 2]"), 1);
+        assert_eq!(warnings("# Uiua 0.0.16\n⌂ [3 1 2]"), 0);
+        // Only the first line declares a version
+        assert_eq!(warnings("\n# Uiua 0.0.16\n⌂ [3 1 2]"), 1);
+        let error = |code: &str| run(code).unwrap_err().to_string();
+        assert!(error("# Uiua 99.0.0").contains("written for Uiua 99.0.0"));
+        assert!(error("# Uiua 0.0").contains("not a valid Uiua version"));
+        let mut env = Uiua::with_native_sys();
+        env.load_str("# Uiua 0.0.23\nF ← +1").unwrap();
+        assert_eq!(env.binding_doc("F"), None);
+        assert_eq!(Version::parse("0.0.17"), Some(Version::new(0, 0, 17)));
+    }
+}
@@ -0,0 +1,123 @@
+//! Algebraic laws of primitives, checked on random arrays
+//!
+//! The compiler and the inverse system assume these laws hold.
+//! If one of them breaks, an optimization or an inverse is no longer correct.
+
+use std::ops::Range;
+
+use proptest::prelude::*;
+use uiua::{Uiua, UiuaResult, Value};
+
+/// Run some code and get the stack
+fn run(code: &str) -> UiuaResult<Vec<Value>> {
+    let mut env = Uiua::with_native_sys();
+    env.load_str(code)?;
+    Ok(env.take_stack())
+}
+
+/// Check that two pieces of code give the same stack, or both give an error
+fn same(a: &str, b: &str) -> Result<(), TestCaseError> {
+    match (run(a), run(b)) {
+        (Ok(a_stack), Ok(b_stack)) => {
+            prop_assert_eq!(a_stack, b_stack, "{} and {} differ", a, b);
+        }
+        (Err(_), Err(_)) => {}
+        (a_res, b_res) => {
+            prop_assert!(
+                false,
+                "{} gave {:?} but {} gave {:?}",
+                a,
+                a_res.map_err(|e| e.to_string()),
+                b,
+                b_res.map_err(|e| e.to_string())
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Uiua code for an array of up to rank 3
+fn array() -> impl Strategy<Value = String> {
+    array_with_rank(0..4)
+}
+
+/// Uiua code for an array with a rank in some range
+fn array_with_rank(ranks: Range<usize>) -> impl Strategy<Value = String> {
+    let number = prop_oneof![-9i32..10, Just(100), Just(-100)].prop_map(|n| n as f64);
+    let number = prop_oneof![number.clone(), number.prop_map(|n| n / 2.0)];
+    let numbers = prop::collection::vec(number, 0..4);
+    let chars = prop::collection::vec(prop::char::range('a', 'e'), 0..4);
+    // The data and the value to fill the shape with if there is not enough of it
+    let data = prop_oneof![
+        numbers.prop_map(|ns| {
+            let items: Vec<String> = ns
+                .into_iter()
+                .map(|n| n.to_string().replace('-', "¯"))
+                .collect();
+            (format!("[{}]", items.join(" ")), "0")
+        }),
+        chars.prop_map(|cs| (format!("{:?}", cs.into_iter().collect::<String>()), "@ ")),
+    ];
+    let shape = prop::collection::vec(0usize..4, ranks);
+    (shape, data).prop_map(|(shape, (data, fill))| {
+        let shape: Vec<String> = shape.iter().map(usize::to_string).collect();
+        match shape.len() {
+            0 => format!("⊢♭⬚{fill}↯1 {data}"),
+            1 => format!("⬚{fill}↯{} {data}", shape[0]),
+            _ => format!("⬚{fill}↯{} {data}", shape.join("_")),
+        }
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn reverse_is_an_involution(x in array()) {
+        same(&format!("⇌⇌{x}"), &x)?;
+    }
+
+    #[test]
+    fn transpose_round_trips(x in array()) {
+        same(&format!("⍘⍉⍉{x}"), &x)?;
+        same(&format!("⍉⍘⍉{x}"), &x)?;
+        same(&format!("⍥⍉⧻△.{x}"), &x)?;
+    }
+
+    #[test]
+    fn sort_is_idempotent(x in array_with_rank(1..4)) {
+        same(&format!("⊏⍏.⊏⍏.{x}"), &format!("⊏⍏.{x}"))?;
+        same(&format!("⍏⊏⍏.{x}"), &format!("⇡⧻{x}"))?;
+        same(&format!("⊏⍖.⊏⍖.{x}"), &format!("⊏⍖.{x}"))?;
+    }
+
+    #[test]
+    fn inverses_undo(x in array()) {
+        for f in ["⇌", "⍉", "□", "∘"] {
+            same(&format!("⍘{f}{f}{x}"), &x)?;
+            same(&format!("⍜{f}∘{x}"), &x)?;
+        }
+    }
+
+    // The compiler replaces these sequences of primitives with faster implementations.
+    // Putting identity between them keeps them from being replaced.
+    #[test]
+    fn fused_primitives_match(x in array()) {
+        for (fused, unfused) in [
+            ("⊢⍏", "⊢∘⍏"),
+            ("⊢⍖", "⊢∘⍖"),
+            ("⊢⇌⍏", "⊢∘⇌⍏"),
+            ("⊢⇌⍖", "⊢∘⇌⍖"),
+            ("⊢⇌", "⊢∘⇌"),
+        ] {
+            same(&format!("{fused}{x}"), &format!("{unfused}{x}"))?;
+        }
+    }
+
+    #[test]
+    fn cosine_matches_shifted_sine(n in -100.0..100.0f64) {
+        let n = n.to_string().replace('-', "¯");
+        let close = run(&format!("<1e-9 ⌵-○∘+η {n} ○+η {n}")).unwrap();
+        prop_assert_eq!(close, [Value::from(1.0)]);
+    }
+}